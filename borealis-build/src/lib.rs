@@ -0,0 +1,77 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// Name of the file generated by generate_embedded_resources() in OUT_DIR,
+// included by borealis_rs::include_resources!()
+pub const EMBEDDED_RESOURCES_FILE: &str = "borealis_resources.rs";
+
+/**
+ * To be called from the build script of an app: generates the table of every file
+ * in the given directory in OUT_DIR, to be embedded in the binary with include_resources!().
+ */
+pub fn generate_embedded_resources(resources_dir: &Path) -> std::io::Result<()> {
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR").expect("OUT_DIR is only set in build scripts"));
+    write_embedded_resources(resources_dir, &out_dir.join(EMBEDDED_RESOURCES_FILE))?;
+
+    println!("cargo:rerun-if-changed={}", resources_dir.display());
+    Ok(())
+}
+
+/**
+ * Writes the table of every file in the given directory to the given file,
+ * as a slice of (relative path, content) pairs.
+ */
+fn write_embedded_resources(resources_dir: &Path, out_file: &Path) -> std::io::Result<()> {
+    let mut files = vec![];
+    collect_files(resources_dir, resources_dir, &mut files)?;
+    files.sort();
+
+    let mut out = fs::File::create(out_file)?;
+    writeln!(out, "&[")?;
+    for (relative, absolute) in &files {
+        writeln!(out, "    ({:?}, include_bytes!({:?}) as &[u8]),", relative, absolute)?;
+    }
+    writeln!(out, "]")?;
+
+    Ok(())
+}
+
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<(String, String)>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            collect_files(root, &path, files)?;
+        } else {
+            let relative = path.strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/");
+            let absolute = fs::canonicalize(&path)?.to_string_lossy().to_string();
+            files.push((relative, absolute));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env::temp_dir;
+    use super::*;
+
+    #[test]
+    fn writes_every_file_with_its_relative_path() {
+        let root = temp_dir().join("borealis-build-resources");
+        fs::create_dir_all(root.join("xml")).unwrap();
+        fs::write(root.join("xml/main.xml"), "<brls:Box/>").unwrap();
+        fs::write(root.join("logo.png"), [1, 2, 3]).unwrap();
+
+        let out_file = temp_dir().join("borealis-build-resources.rs");
+        write_embedded_resources(&root, &out_file).unwrap();
+        let table = fs::read_to_string(&out_file).unwrap();
+
+        let logo = table.find("(\"logo.png\", include_bytes!(").unwrap();
+        let main = table.find("(\"xml/main.xml\", include_bytes!(").unwrap();
+        assert!(logo < main, "files must be sorted by path");
+        assert!(table.starts_with("&[\n") && table.ends_with("]\n"));
+    }
+}
@@ -33,7 +33,7 @@ fn main() {
 
     println!("cargo:rerun-if-changed=build.rs");
 
-    let mut file = File::create(&dest.join("gl_bindings.rs")).unwrap();
+    let mut file = File::create(dest.join("gl_bindings.rs")).unwrap();
     Registry::new(Api::Gles2, (3, 0), Profile::Core, Fallbacks::All, [])
         .write_bindings(StructGenerator, &mut file)
         .unwrap();
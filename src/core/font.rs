@@ -1,30 +1,292 @@
 use std::collections::HashMap;
+use std::ffi::CString;
+use std::path::{Path, PathBuf};
 
-pub type FontStash = HashMap<String, i64>;
+use nanovg::Context;
+
+use crate::core::system_font::{discover_font, load_fallback_chain, load_system_font, FontScript};
+
+pub const FONT_INVALID: i32 = -1;
+pub const FONT_REGULAR: &str = "regular"; // regular Latin font
+pub const FONT_KOREAN_REGULAR: &str = "korean"; // regular Korean font
+pub const FONT_MATERIAL_ICONS: &str = "material"; // Material icons font
+pub const FONT_SWITCH_ICONS: &str = "switch"; // Switch icons font (see the HOS shared symbols font for an example)
 
 const USER_FONT_PATH: &str = "font/font.ttf";
 const USER_ICON_PATH: &str = "font/font.ttf";
+const USER_KOREAN_FONT_PATH: &str = "font/korean.ttf";
+const MATERIAL_ICONS_PATH: &str = "material/MaterialIcons-Regular.ttf";
+
+/// Locale of the application, ordering the fallback fonts (see Application::get_locale())
+const DEFAULT_LOCALE: &str = "CN";
+
+/// Handles of the fonts loaded in the nanovg context, by name
+///
+/// Fonts loaded from memory are not copied by nanovg: the context reads the buffers
+/// kept by the stash until it is deleted, so the stash must outlive the context.
+/// Owners of both drop the context first (declare it before the stash).
+#[derive(Default)]
+pub struct FontStash {
+    handles: HashMap<String, i32>,
+    /// Buffers of the fonts loaded from memory, never freed before the stash
+    buffers: Vec<Vec<u8>>,
+    buffers_by_font: HashMap<String, usize>,
+}
+
+impl FontStash {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, font_name: &str, handle: i32) {
+        self.handles.insert(font_name.into(), handle);
+    }
+
+    /**
+     * Returns the nanovg handle of the given font, if it has been loaded.
+     */
+    pub fn get(&self, font_name: &str) -> Option<i32> {
+        self.handles.get(font_name).copied()
+    }
+
+    pub fn contains(&self, font_name: &str) -> bool {
+        self.handles.contains_key(font_name)
+    }
+
+    /**
+     * Keeps the given font buffer alive as long as the stash, under the given name.
+     * The buffer stays available to the CPU side text processing (shaping, metrics).
+     */
+    pub fn keep_buffer(&mut self, font_name: &str, buffer: Vec<u8>) {
+        self.buffers.push(buffer);
+        self.buffers_by_font.insert(font_name.into(), self.buffers.len() - 1);
+    }
+
+    /**
+     * Returns the TTF data of the given font, if it was loaded from memory.
+     */
+    pub fn get_font_data(&self, font_name: &str) -> Option<&[u8]> {
+        self.buffers_by_font.get(font_name).map(|index| self.buffers[*index].as_slice())
+    }
+
+    /**
+     * Loads a font file into the nanovg context and registers it under the given name.
+     * Returns the nanovg handle of the font.
+     */
+    pub fn load_font_from_file(
+        &mut self,
+        vg: &Context,
+        font_name: &str,
+        file_path: &str,
+    ) -> anyhow::Result<i32> {
+        if !Path::new(file_path).is_file() {
+            anyhow::bail!("font file \"{}\" does not exist", file_path);
+        }
+
+        let name = CString::new(font_name)?;
+        let path = CString::new(file_path)?;
+
+        let handle = unsafe { nanovg_sys::nvgCreateFont(vg.raw(), name.as_ptr(), path.as_ptr()) };
+
+        if handle == FONT_INVALID {
+            anyhow::bail!("nanovg could not load font file \"{}\"", file_path);
+        }
+
+        self.insert(font_name, handle);
+        Ok(handle)
+    }
+
+    /**
+     * Loads a font from a memory buffer into the nanovg context and registers it under the given name.
+     * The stash keeps ownership of the buffer.
+     * Returns the nanovg handle of the font.
+     */
+    pub fn load_font_from_memory(
+        &mut self,
+        vg: &Context,
+        font_name: &str,
+        mut data: Vec<u8>,
+    ) -> anyhow::Result<i32> {
+        if data.is_empty() {
+            anyhow::bail!("font buffer for \"{}\" is empty", font_name);
+        }
+
+        let name = CString::new(font_name)?;
+
+        let handle = unsafe {
+            nanovg_sys::nvgCreateFontMem(
+                vg.raw(),
+                name.as_ptr(),
+                data.as_mut_ptr(),
+                data.len() as i32,
+                0, // the buffer is owned and freed by the stash
+            )
+        };
+
+        if handle == FONT_INVALID {
+            anyhow::bail!("nanovg could not load font \"{}\" from memory", font_name);
+        }
+
+        // Moving the buffer into the stash keeps its heap data where nanovg points
+        self.keep_buffer(font_name, data);
+        self.insert(font_name, handle);
+        Ok(handle)
+    }
+}
+
+/**
+ * Returns the path of the given file shipped next to the binary, so that fonts
+ * are found whatever the working directory. Falls back to the path relative
+ * to the working directory if the binary location is unknown.
+ */
+pub fn get_bundled_path(path: &str) -> PathBuf {
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join(path)))
+        .unwrap_or_else(|| PathBuf::from(path))
+}
 
 /// Platform interface to load fonts from disk or other sources (system / shared font...)
 pub trait FontLoader {
     /**
      * Called once on init to load every font in the font stash.
      *
-     * The implementation must use the Application::loadFont and
-     * Application::loadFontFromMemory methods to load as much as possible
+     * The implementation must use the FontStash::load_font_from_file and
+     * FontStash::load_font_from_memory methods to load as much as possible
      * of the "built-in" fonts defined in the FONT_* constants above.
+     *
+     * A missing font must not be fatal: log it and carry on.
      */
-    fn load_fonts();
+    fn load_fonts(&self, vg: &Context, font_stash: &mut FontStash);
 
     /**
      * Convenience method to load a font from a file path
      * with some more logging.
      */
-    fn load_font_from_file(font_name: &str, file_path: &str) -> anyhow::Result<()>;
+    fn load_font_from_file(
+        &self,
+        vg: &Context,
+        font_stash: &mut FontStash,
+        font_name: &str,
+        file_path: &str,
+    ) -> anyhow::Result<()> {
+        match font_stash.load_font_from_file(vg, font_name, file_path) {
+            Ok(handle) => {
+                info!("Loaded font \"{}\" from {} (handle {})", font_name, file_path, handle);
+                Ok(())
+            }
+            Err(err) => {
+                warn!("Could not load font \"{}\": {}", font_name, err);
+                Err(err)
+            }
+        }
+    }
 
     /**
      * Can be called internally to load the Material icons font from resources.
-     * Returns true if the operation succeeds.
+     * Returns Ok if the operation succeeds.
+     */
+    fn load_material_from_resources(
+        &self,
+        vg: &Context,
+        font_stash: &mut FontStash,
+    ) -> anyhow::Result<()> {
+        self.load_font_from_file(vg, font_stash, FONT_MATERIAL_ICONS, &get_bundled_path(MATERIAL_ICONS_PATH).to_string_lossy())
+    }
+}
+
+/// Font loader of the winit platform, reading user fonts from files next to the binary
+/// and completing them with the fonts installed on the system
+pub struct WinitFontLoader {
+    locale: String,
+}
+
+impl Default for WinitFontLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WinitFontLoader {
+    /**
+     * Creates a loader ordering the system fallback fonts for the locale of the application.
      */
-    fn load_material_from_resources() -> anyhow::Result<()>;
+    pub fn new() -> Self {
+        Self::with_locale(DEFAULT_LOCALE)
+    }
+
+    /**
+     * Creates a loader ordering the system fallback fonts for the given locale.
+     */
+    pub fn with_locale(locale: &str) -> Self {
+        WinitFontLoader {
+            locale: locale.into(),
+        }
+    }
+
+    /**
+     * Loads the system font discovered for the given script under the given name.
+     */
+    fn load_system_font(
+        &self,
+        vg: &Context,
+        font_stash: &mut FontStash,
+        font_name: &str,
+        script: FontScript,
+    ) -> anyhow::Result<()> {
+        let path = match discover_font(script) {
+            Some(path) => path,
+            None => anyhow::bail!("no system font found for {:?}", script),
+        };
+
+        match load_system_font(vg, font_stash, font_name, script, &path) {
+            Ok(_) => Ok(()),
+            Err(err) => {
+                warn!("Could not load system font {:?} for {:?}: {}", path, script, err);
+                Err(err)
+            }
+        }
+    }
+}
+
+impl FontLoader for WinitFontLoader {
+    fn load_fonts(&self, vg: &Context, font_stash: &mut FontStash) {
+        let user_font_path = get_bundled_path(USER_FONT_PATH);
+        let user_font_path = user_font_path.to_string_lossy();
+
+        // Regular
+        // Try to use user-provided font first, fallback to the system sans-serif font
+        if self
+            .load_font_from_file(vg, font_stash, FONT_REGULAR, &user_font_path)
+            .is_err()
+            && self
+                .load_system_font(vg, font_stash, FONT_REGULAR, FontScript::Latin)
+                .is_err()
+        {
+            error!(
+                "No regular font found, text will not be displayed (expected at \"{}\" or installed on the system)",
+                user_font_path
+            );
+        }
+
+        // Korean
+        if self
+            .load_font_from_file(vg, font_stash, FONT_KOREAN_REGULAR, &get_bundled_path(USER_KOREAN_FONT_PATH).to_string_lossy())
+            .is_err()
+        {
+            let _ = self.load_system_font(vg, font_stash, FONT_KOREAN_REGULAR, FontScript::Korean);
+        }
+
+        // CJK fallbacks, so that every script renders in the same label
+        if font_stash.contains(FONT_REGULAR) {
+            load_fallback_chain(vg, font_stash, FONT_REGULAR, &self.locale);
+        }
+
+        // Switch icons
+        // Only supports user-provided font
+        let _ = self.load_font_from_file(vg, font_stash, FONT_SWITCH_ICONS, &get_bundled_path(USER_ICON_PATH).to_string_lossy());
+
+        // Material icons
+        let _ = self.load_material_from_resources(vg, font_stash);
+    }
 }
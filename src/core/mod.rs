@@ -6,6 +6,7 @@ pub mod application;
 pub mod font;
 pub mod frame_context;
 pub mod platform;
+pub mod system_font;
 pub mod view;
 
 use nanovg::{Color, Context, PathOptions};
@@ -23,6 +24,7 @@ use winit::platform::unix;
 use winit::platform::unix::WindowBuilderExtUnix;
 use winit::window::{Window, WindowBuilder};
 
+use crate::core::font::{FontLoader, FontStash, WinitFontLoader};
 use crate::core::view::View;
 use glutin::config::{Config, ConfigSurfaceTypes, ConfigTemplate, ConfigTemplateBuilder};
 use glutin::context::{ContextApi, ContextAttributesBuilder};
@@ -198,21 +200,19 @@ pub fn main() {
             }
 
             Event::WindowEvent { event, .. } => match event {
-                WindowEvent::Resized(size) => {
-                    if size.width != 0 && size.height != 0 {
-                        // Some platforms like EGL require resizing GL surface to update the size
-                        // Notable platforms here are Wayland and macOS, other don't require it
-                        // and the function is no-op, but it's wise to resize it for portability
-                        // reasons.
-                        if let Some((gl_context, gl_window)) = &state {
-                            gl_window.surface.resize(
-                                gl_context,
-                                NonZeroU32::new(size.width).unwrap(),
-                                NonZeroU32::new(size.height).unwrap(),
-                            );
-                            let renderer = renderer.as_ref().unwrap();
-                            renderer.resize(size.width as i32, size.height as i32);
-                        }
+                WindowEvent::Resized(size) if size.width != 0 && size.height != 0 => {
+                    // Some platforms like EGL require resizing GL surface to update the size
+                    // Notable platforms here are Wayland and macOS, other don't require it
+                    // and the function is no-op, but it's wise to resize it for portability
+                    // reasons.
+                    if let Some((gl_context, gl_window)) = &state {
+                        gl_window.surface.resize(
+                            gl_context,
+                            NonZeroU32::new(size.width).unwrap(),
+                            NonZeroU32::new(size.height).unwrap(),
+                        );
+                        let renderer = renderer.as_ref().unwrap();
+                        renderer.resize(size.width as i32, size.height as i32);
                     }
                 }
                 WindowEvent::CloseRequested => {
@@ -324,7 +324,8 @@ pub struct Renderer {
     vao: gl::types::GLuint,
     vbo: gl::types::GLuint,
     gl: gl::Gl,
-    context: Context,
+    context: Context, // dropped before the font stash, see FontStash
+    font_stash: FontStash,
     views: Vec<View>,
 }
 
@@ -343,6 +344,10 @@ impl Renderer {
                 .build()
                 .expect("glfw: unable to init nanovg");
 
+            // Load fonts
+            let mut font_stash = FontStash::new();
+            WinitFontLoader::new().load_fonts(&context, &mut font_stash);
+
             if let Some(renderer) = get_gl_string(&gl, gl::RENDERER) {
                 println!("Running on {}", renderer.to_string_lossy());
             }
@@ -383,8 +388,8 @@ impl Renderer {
                 gl::STATIC_DRAW,
             );
 
-            let pos_attrib = gl.GetAttribLocation(program, b"position\0".as_ptr() as *const _);
-            let color_attrib = gl.GetAttribLocation(program, b"color\0".as_ptr() as *const _);
+            let pos_attrib = gl.GetAttribLocation(program, c"position".as_ptr());
+            let color_attrib = gl.GetAttribLocation(program, c"color".as_ptr());
             gl.VertexAttribPointer(
                 pos_attrib as gl::types::GLuint,
                 2,
@@ -410,6 +415,7 @@ impl Renderer {
                 vbo,
                 gl,
                 context,
+                font_stash,
                 views: vec![
                     View::new(0.0, 0.0, 50.0, 50.0),
                     View::new(0.0, 80.0, 10.0, 10.0),
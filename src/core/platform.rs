@@ -77,7 +77,7 @@ pub trait Platform {
     where
         F: Fn() -> bool,
     {
-        return run_loop_impl();
+        run_loop_impl()
     }
 
     fn get_video_context();
@@ -0,0 +1,469 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use nanovg::Context;
+
+use crate::core::font::{FontStash, FONT_INVALID};
+
+/// Directories scanned for fonts when fontconfig is not available
+const SYSTEM_FONT_DIRS: &[&str] = &[
+    "/usr/share/fonts",
+    "/usr/local/share/fonts",
+    "~/.local/share/fonts",
+    "~/.fonts",
+];
+
+/// Maximum depth of the font directories scan
+const MAX_SCAN_DEPTH: u32 = 6;
+
+/// Scripts a system font can be discovered for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FontScript {
+    Latin,
+    SimplifiedChinese,
+    TraditionalChinese,
+    Japanese,
+    Korean,
+}
+
+impl FontScript {
+    /**
+     * Returns the fontconfig language tag of the script.
+     */
+    pub fn get_fontconfig_lang(&self) -> &'static str {
+        match self {
+            FontScript::Latin => "en",
+            FontScript::SimplifiedChinese => "zh-cn",
+            FontScript::TraditionalChinese => "zh-tw",
+            FontScript::Japanese => "ja",
+            FontScript::Korean => "ko",
+        }
+    }
+
+    /**
+     * Returns the region tags ending the family names of the faces made for the script
+     * in font collections ("Noto Sans CJK SC", "Source Han Sans K").
+     */
+    fn get_face_tags(&self) -> &'static [&'static str] {
+        match self {
+            FontScript::Latin => &[],
+            FontScript::SimplifiedChinese => &["SC", "CN"],
+            FontScript::TraditionalChinese => &["TC", "HK", "TW"],
+            FontScript::Japanese => &["JP", "J"],
+            FontScript::Korean => &["KR", "K"],
+        }
+    }
+
+    /**
+     * Returns the name the discovered font is registered under in the font stash.
+     */
+    pub fn get_font_name(&self) -> String {
+        format!("system/{}", self.get_fontconfig_lang())
+    }
+
+    /**
+     * File name fragments of well-known fonts for the script, by order of preference.
+     * Used when scanning the font directories without fontconfig.
+     */
+    fn get_known_file_names(&self) -> &'static [&'static str] {
+        match self {
+            FontScript::Latin => &["notosans-regular", "dejavusans.", "liberationsans-regular", "ubuntu-r"],
+            FontScript::SimplifiedChinese => &[
+                "notosanscjksc-regular",
+                "notosanssc-regular",
+                "sourcehansanssc-regular",
+                "wqy-microhei",
+                "wqy-zenhei",
+                "droidsansfallback",
+                "notosanscjk-regular",
+            ],
+            FontScript::TraditionalChinese => &[
+                "notosanscjktc-regular",
+                "notosanstc-regular",
+                "sourcehansanstc-regular",
+                "bsmi00lp",
+                "uming",
+                "notosanscjk-regular",
+            ],
+            FontScript::Japanese => &[
+                "notosanscjkjp-regular",
+                "notosansjp-regular",
+                "sourcehansansjp-regular",
+                "ipagp",
+                "takaopgothic",
+                "notosanscjk-regular",
+            ],
+            FontScript::Korean => &[
+                "notosanscjkkr-regular",
+                "notosanskr-regular",
+                "sourcehansanskr-regular",
+                "nanumgothic",
+                "undotum",
+                "notosanscjk-regular",
+            ],
+        }
+    }
+}
+
+/**
+ * Returns the fallback scripts to chain after the regular font for the given locale
+ * ("zh-Hant", "ja_JP.UTF-8", or a region alone like "TW"), by order of preference.
+ * Every CJK script is chained so that mixed text renders in one label,
+ * the locale only decides which glyph variants win for the shared Han characters.
+ */
+pub fn get_fallback_chain(locale: &str) -> Vec<FontScript> {
+    use FontScript::*;
+
+    let locale = locale.split(['.', '@']).next().unwrap_or_default().to_lowercase();
+    let mut subtags = locale.split(['-', '_']);
+    let language = subtags.next().unwrap_or_default();
+    let subtags: Vec<&str> = subtags.collect();
+
+    let traditional = ["hant", "tw", "hk", "mo"].iter().any(|tag| subtags.contains(tag));
+
+    match language {
+        "zh" if traditional => vec![TraditionalChinese, SimplifiedChinese, Japanese, Korean],
+        "tw" | "hk" | "mo" => vec![TraditionalChinese, SimplifiedChinese, Japanese, Korean],
+        "ja" | "jp" => vec![Japanese, SimplifiedChinese, TraditionalChinese, Korean],
+        "ko" | "kr" => vec![Korean, SimplifiedChinese, Japanese],
+        _ => vec![SimplifiedChinese, TraditionalChinese, Japanese, Korean],
+    }
+}
+
+/**
+ * Finds an installed font covering the given script.
+ * Asks fontconfig first, then scans the system font directories.
+ */
+pub fn discover_font(script: FontScript) -> Option<PathBuf> {
+    discover_with_fontconfig(script).or_else(|| discover_in_directories(script))
+}
+
+/**
+ * Uses fc-match to get the preferred sans-serif font for the script language,
+ * making sure it actually covers the language with fc-list (fc-match always returns
+ * a font, even if it has none of the required glyphs).
+ */
+fn discover_with_fontconfig(script: FontScript) -> Option<PathBuf> {
+    let lang = script.get_fontconfig_lang();
+
+    let covering: Vec<String> = run_command("fc-list", &[&format!(":lang={}", lang), "file"])?
+        .lines()
+        .map(|line| line.trim().trim_end_matches(':').to_string())
+        .filter(|line| !line.is_empty())
+        .collect();
+
+    if covering.is_empty() {
+        return None;
+    }
+
+    if let Some(matched) = run_command("fc-match", &["-f", "%{file}", &format!("sans-serif:lang={}", lang)]) {
+        let matched = matched.trim();
+        if covering.iter().any(|file| file == matched) {
+            return Some(PathBuf::from(matched));
+        }
+    }
+
+    // Fall back to the best known font in the covering list
+    let covering: Vec<PathBuf> = covering.into_iter().map(PathBuf::from).collect();
+    pick_known_font(script, &covering).or_else(|| covering.first().cloned())
+}
+
+fn discover_in_directories(script: FontScript) -> Option<PathBuf> {
+    let mut files = vec![];
+
+    for dir in SYSTEM_FONT_DIRS {
+        scan_font_dir(&expand_home(dir), 0, &mut files);
+    }
+
+    pick_known_font(script, &files)
+}
+
+/**
+ * Returns the first file matching the known font names of the script, by order of preference.
+ */
+fn pick_known_font(script: FontScript, files: &[PathBuf]) -> Option<PathBuf> {
+    for known in script.get_known_file_names() {
+        let found = files.iter().find(|file| {
+            file.file_name()
+                .map(|name| name.to_string_lossy().to_lowercase().contains(known))
+                .unwrap_or(false)
+        });
+
+        if let Some(found) = found {
+            return Some(found.clone());
+        }
+    }
+
+    None
+}
+
+fn scan_font_dir(dir: &Path, depth: u32, files: &mut Vec<PathBuf>) {
+    if depth > MAX_SCAN_DEPTH {
+        return;
+    }
+
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        if path.is_dir() {
+            scan_font_dir(&path, depth + 1, files);
+            continue;
+        }
+
+        let is_font = path
+            .extension()
+            .map(|ext| matches!(ext.to_string_lossy().to_lowercase().as_str(), "ttf" | "otf" | "ttc"))
+            .unwrap_or(false);
+
+        if is_font {
+            files.push(path);
+        }
+    }
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn run_command(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    String::from_utf8(output.stdout).ok()
+}
+
+/**
+ * Returns the index of the face made for the script in the given font collection (.ttc),
+ * found by the region tag ending its family name. Returns 0 for single fonts,
+ * and for collections without a face made for the script.
+ */
+pub fn pick_collection_face(script: FontScript, data: &[u8]) -> u32 {
+    let count = ttf_parser::fonts_in_collection(data).unwrap_or(0);
+
+    (0..count)
+        .find(|index| {
+            let face = match ttf_parser::Face::parse(data, *index) {
+                Ok(face) => face,
+                Err(_) => return false,
+            };
+
+            face.names()
+                .into_iter()
+                .filter(|name| name.name_id == ttf_parser::name_id::FAMILY || name.name_id == ttf_parser::name_id::TYPOGRAPHIC_FAMILY)
+                .filter_map(|name| name.to_string())
+                .any(|family| family.split_whitespace().any(|word| script.get_face_tags().contains(&word)))
+        })
+        .unwrap_or(0)
+}
+
+/**
+ * Rebuilds a single font from the given face of a font collection, nanovg only loading
+ * the first face of a file. The table directory of the face is copied to the start of the data:
+ * table offsets are relative to the start of the file in collections, the tables stay in place.
+ *
+ * Returns None if the data is not a collection, or if the directory does not fit before the tables.
+ */
+pub fn extract_collection_face(data: &[u8], index: u32) -> Option<Vec<u8>> {
+    if data.get(0..4)? != b"ttcf" || index >= read_u32(data, 8)? {
+        return None;
+    }
+
+    let offset = read_u32(data, 12 + 4 * index as usize)? as usize;
+    let tables = read_u16(data, offset + 4)? as usize;
+    let directory = data.get(offset..offset + 12 + 16 * tables)?;
+
+    let first_table = (0..tables).filter_map(|table| read_u32(directory, 12 + 16 * table + 8)).min()? as usize;
+    if first_table < directory.len() {
+        return None;
+    }
+
+    let mut font = data.to_vec();
+    font[..directory.len()].copy_from_slice(directory);
+    Some(font)
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+/**
+ * Loads the given system font file in the font stash under the given name.
+ * For font collections, the face made for the script is loaded instead of the first one.
+ *
+ * Returns the nanovg handle of the font.
+ */
+pub fn load_system_font(
+    vg: &Context,
+    font_stash: &mut FontStash,
+    font_name: &str,
+    script: FontScript,
+    path: &Path,
+) -> anyhow::Result<i32> {
+    let data = std::fs::read(path)?;
+    let face = pick_collection_face(script, &data);
+
+    let data = match extract_collection_face(&data, face) {
+        Some(font) => font,
+        None if face == 0 => data,
+        None => anyhow::bail!("could not extract face {} of {:?}", face, path),
+    };
+
+    let handle = font_stash.load_font_from_memory(vg, font_name, data)?;
+    info!("Loaded face {} of {:?} for {:?} as \"{}\"", face, path, script, font_name);
+    Ok(handle)
+}
+
+/**
+ * Discovers the fonts of the fallback chain of the given locale, loads them
+ * in the font stash and chains them after the given base font with nvgAddFallbackFontId.
+ *
+ * Returns the number of fallback fonts added.
+ */
+pub fn load_fallback_chain(vg: &Context, font_stash: &mut FontStash, base_font: &str, locale: &str) -> usize {
+    let base = match font_stash.get(base_font) {
+        Some(handle) => handle,
+        None => {
+            warn!("Cannot build the fallback chain of \"{}\": font is not loaded", base_font);
+            return 0;
+        }
+    };
+
+    let mut chained = 0;
+    let mut chained_files = HashSet::new();
+
+    for script in get_fallback_chain(locale) {
+        let path = match discover_font(script) {
+            Some(path) => path,
+            None => {
+                warn!("No system font found for {:?}, its characters will not be displayed", script);
+                continue;
+            }
+        };
+
+        // Collections such as Noto Sans CJK cover every script from the same file: the face
+        // of the first script of the chain is loaded, the other faces would add no glyph
+        if !chained_files.insert(path.clone()) {
+            continue;
+        }
+
+        let font_name = script.get_font_name();
+        let handle = match font_stash.get(&font_name) {
+            Some(handle) => handle,
+            None => match load_system_font(vg, font_stash, &font_name, script, &path) {
+                Ok(handle) => handle,
+                Err(err) => {
+                    warn!("Could not load system font {:?} for {:?}: {}", path, script, err);
+                    continue;
+                }
+            },
+        };
+
+        if handle == FONT_INVALID || handle == base {
+            continue;
+        }
+
+        let added = unsafe { nanovg_sys::nvgAddFallbackFontId(vg.raw(), base, handle) };
+        if added != 0 {
+            info!("Chained {:?} font {:?} after \"{}\"", script, path, base_font);
+            chained += 1;
+        }
+    }
+
+    chained
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use FontScript::*;
+
+    static FONT: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fonts/DejaVuSans.ttf"));
+
+    // A collection of two faces sharing the tables of the test font
+    fn make_collection() -> Vec<u8> {
+        let header = 12 + 4 * 2;
+        let tables = read_u16(FONT, 4).unwrap() as usize;
+        let directory = 12 + 16 * tables;
+
+        let mut collection = b"ttcf".to_vec();
+        collection.extend(0x0001_0000u32.to_be_bytes());
+        collection.extend(2u32.to_be_bytes());
+        collection.extend((header as u32).to_be_bytes());
+        collection.extend((header as u32).to_be_bytes());
+
+        // The tables move after the collection header
+        let mut font = FONT.to_vec();
+        for table in 0..tables {
+            let record = 12 + 16 * table + 8;
+            let offset = read_u32(&font, record).unwrap() + header as u32;
+            font[record..record + 4].copy_from_slice(&offset.to_be_bytes());
+        }
+
+        collection.extend(&font[..directory]);
+        collection.extend(&FONT[directory..]);
+        collection
+    }
+
+    #[test]
+    fn orders_the_fallback_chain_after_the_locale() {
+        assert_eq!(get_fallback_chain("zh-Hans")[0], SimplifiedChinese);
+        assert_eq!(get_fallback_chain("zh_CN.UTF-8")[0], SimplifiedChinese);
+        assert_eq!(get_fallback_chain("zh-Hant")[0], TraditionalChinese);
+        assert_eq!(get_fallback_chain("zh_TW")[0], TraditionalChinese);
+        assert_eq!(get_fallback_chain("HK")[0], TraditionalChinese);
+        assert_eq!(get_fallback_chain("CN")[0], SimplifiedChinese);
+        assert_eq!(get_fallback_chain("ja_JP.UTF-8")[0], Japanese);
+        assert_eq!(get_fallback_chain("ko-KR"), vec![Korean, SimplifiedChinese, Japanese]);
+        assert_eq!(get_fallback_chain("en-US")[0], SimplifiedChinese);
+        assert_eq!(get_fallback_chain("fr").len(), 4);
+    }
+
+    #[test]
+    fn picks_the_preferred_known_font() {
+        let files: Vec<PathBuf> = [
+            "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+            "/usr/share/fonts/wqy/wqy-microhei.ttc",
+            "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+        ].iter().map(PathBuf::from).collect();
+
+        // Noto Sans CJK comes after the dedicated fonts for Simplified Chinese, first for Korean
+        assert_eq!(pick_known_font(SimplifiedChinese, &files), Some(files[1].clone()));
+        assert_eq!(pick_known_font(Korean, &files), Some(files[2].clone()));
+        assert_eq!(pick_known_font(Latin, &files), Some(files[0].clone()));
+        assert_eq!(pick_known_font(Latin, &files[1..]), None);
+    }
+
+    #[test]
+    fn extracts_the_faces_of_collections() {
+        let collection = make_collection();
+        assert_eq!(ttf_parser::fonts_in_collection(&collection), Some(2));
+
+        // Single fonts are not collections, collections without a matching face use the first one
+        assert_eq!(extract_collection_face(FONT, 0), None);
+        assert_eq!(pick_collection_face(Korean, FONT), 0);
+        assert_eq!(pick_collection_face(Korean, &collection), 0);
+        assert_eq!(extract_collection_face(&collection, 2), None);
+
+        let font = extract_collection_face(&collection, 1).unwrap();
+        let face = ttf_parser::Face::parse(&font, 0).unwrap();
+        let original = ttf_parser::Face::parse(FONT, 0).unwrap();
+        assert_eq!(face.number_of_glyphs(), original.number_of_glyphs());
+        assert_eq!(face.glyph_index('A'), original.glyph_index('A'));
+    }
+}
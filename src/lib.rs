@@ -1,3 +1,5 @@
 #[macro_use]
 extern crate log;
 pub mod core;
+#[path = "lib0/mod.rs"]
+pub mod lib;
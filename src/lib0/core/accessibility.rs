@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+use nanovg::Color;
+use once_cell::sync::Lazy;
+use crate::lib::core::config::ConfigFile;
+use crate::lib::core::style::{get_style_mut, metrics, MetricKey, Style};
+use crate::lib::core::theme::{get_theme, has_theme, register_theme, ThemeVariant};
+
+const ACCESSIBILITY_SETTINGS_FILE: &str = "accessibility.cfg";
+
+// Name of the high contrast theme variant
+pub const HIGH_CONTRAST_VARIANT: &str = "high_contrast";
+
+pub const MIN_FONT_SCALE: f32 = 0.75;
+pub const MAX_FONT_SCALE: f32 = 2.0;
+
+// Metrics multiplied by the font scale
+const SCALED_FONT_METRICS: &[MetricKey] = &[
+    metrics::LABEL_DEFAULT_FONT_SIZE,
+    metrics::APPLET_FRAME_HEADER_TITLE_FONT_SIZE,
+    metrics::SIDEBAR_ITEM_FONT_SIZE,
+    metrics::HEADER_FONT_SIZE,
+    metrics::BUTTON_TEXT_SIZE,
+    // Containers sized after their text
+    metrics::SIDEBAR_ITEM_HEIGHT,
+    metrics::APPLET_FRAME_HEADER_HEIGHT,
+    metrics::APPLET_FRAME_FOOTER_HEIGHT,
+    metrics::HEADER_RECTANGLE_HEIGHT,
+];
+
+// Read from the animations code, every frame
+static REDUCED_MOTION: AtomicBool = AtomicBool::new(false);
+
+// Base and scaled values of the scaled metrics, to scale again from the base
+static SCALED_METRICS: Lazy<RwLock<HashMap<MetricKey, (f32, f32)>>> = Lazy::new(|| {
+    RwLock::new(HashMap::new())
+});
+
+/**
+ * Returns true if animations must be skipped: Animatable steps are then instant
+ * and activity transitions have no duration.
+ */
+pub fn is_reduced_motion() -> bool {
+    REDUCED_MOTION.load(Ordering::Relaxed)
+}
+
+// Accessibility preferences of the user, persisted in the config directory
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessibilitySettings {
+    high_contrast: bool,
+    font_scale: f32,
+    reduced_motion: bool,
+}
+
+impl Default for AccessibilitySettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AccessibilitySettings {
+    pub fn new() -> Self {
+        Self {
+            high_contrast: false,
+            font_scale: 1.0,
+            reduced_motion: false,
+        }
+    }
+
+    /**
+     * Loads the settings saved by a previous run, or returns the default
+     * settings if there are none.
+     */
+    pub fn load() -> Self {
+        let config = ConfigFile::open(ACCESSIBILITY_SETTINGS_FILE);
+        let mut settings = Self::new();
+
+        settings.high_contrast = config.get_bool("high_contrast", settings.high_contrast);
+        settings.font_scale = clamp_font_scale(config.get_float("font_scale", settings.font_scale));
+        settings.reduced_motion = config.get_bool("reduced_motion", settings.reduced_motion);
+
+        settings
+    }
+
+    /**
+     * Saves the settings to disk so that they are restored on next launch.
+     * Returns true if the operation succeeds.
+     */
+    pub fn save(&self) -> bool {
+        let mut config = ConfigFile::open(ACCESSIBILITY_SETTINGS_FILE);
+
+        config.set_bool("high_contrast", self.high_contrast);
+        config.set_float("font_scale", self.font_scale);
+        config.set_bool("reduced_motion", self.reduced_motion);
+
+        config.save()
+    }
+
+    pub fn set_high_contrast(&mut self, enabled: bool) {
+        self.high_contrast = enabled;
+    }
+
+    pub fn is_high_contrast(&self) -> bool {
+        self.high_contrast
+    }
+
+    /**
+     * Sets the multiplier of the font sizes, between MIN_FONT_SCALE and MAX_FONT_SCALE.
+     */
+    pub fn set_font_scale(&mut self, scale: f32) {
+        self.font_scale = clamp_font_scale(scale);
+    }
+
+    pub fn get_font_scale(&self) -> f32 {
+        self.font_scale
+    }
+
+    pub fn set_reduced_motion(&mut self, enabled: bool) {
+        self.reduced_motion = enabled;
+    }
+
+    pub fn is_reduced_motion(&self) -> bool {
+        self.reduced_motion
+    }
+
+    /**
+     * Applies the font scale to the style metrics and the reduced motion flag to the animations.
+     * The high contrast variant is applied by the Application, as it replaces the theme variant.
+     */
+    pub fn apply(&self) {
+        REDUCED_MOTION.store(self.reduced_motion, Ordering::Relaxed);
+
+        let mut style = get_style_mut();
+        let mut scaled = SCALED_METRICS.write().unwrap();
+
+        for key in SCALED_FONT_METRICS {
+            scale_metric(&mut style, &mut scaled, *key, self.font_scale);
+        }
+    }
+}
+
+/**
+ * Scales the given metric from its base value. A metric changed in the style since
+ * it was last scaled (new style loaded by the app) has its new value as base.
+ */
+fn scale_metric(style: &mut Style, scaled: &mut HashMap<MetricKey, (f32, f32)>, key: MetricKey, scale: f32) {
+    let current = style.get(key);
+    let base = match scaled.get(&key) {
+        Some((base, value)) if *value == current => *base,
+        _ => current,
+    };
+
+    let value = (base * scale).round();
+    style.add_metric(key.name(), value);
+    scaled.insert(key, (base, value));
+}
+
+fn clamp_font_scale(scale: f32) -> f32 {
+    scale.clamp(MIN_FONT_SCALE, MAX_FONT_SCALE)
+}
+
+/**
+ * Returns the high contrast theme variant, registering its theme on first use.
+ * The theme starts from the dark theme: white text on black, yellow focus highlight
+ * and fully opaque borders. Apps can register their own theme under this variant beforehand.
+ */
+pub fn get_high_contrast_variant() -> ThemeVariant {
+    let variant = ThemeVariant::Custom(HIGH_CONTRAST_VARIANT.into());
+
+    if has_theme(&variant) {
+        return variant;
+    }
+
+    let mut theme = match get_theme(&ThemeVariant::Dark) {
+        Some(theme) => theme,
+        None => return variant,
+    };
+
+    let black = Color::from_rgb(0, 0, 0);
+    let white = Color::from_rgb(255, 255, 255);
+    let yellow = Color::from_rgb(255, 214, 0);
+
+    for (name, color) in [
+        ("brls/background", black),
+        ("brls/text", white),
+        ("brls/backdrop", Color::from_rgba(0, 0, 0, 230)),
+        ("brls/click_pulse", Color::from_rgba(255, 214, 0, 64)),
+        ("brls/highlight/background", black),
+        ("brls/highlight/color1", yellow),
+        ("brls/highlight/color2", yellow),
+        ("brls/applet_frame/separator", white),
+        ("brls/sidebar/background", black),
+        ("brls/sidebar/active_item", yellow),
+        ("brls/sidebar/separator", white),
+        ("brls/header/border", white),
+        ("brls/header/rectangle", yellow),
+        ("brls/header/subtitle", white),
+        ("brls/button/primary_enabled_background", yellow),
+        ("brls/button/primary_disabled_background", Color::from_rgb(90, 90, 90)),
+        ("brls/button/primary_enabled_text", black),
+        ("brls/button/primary_disabled_text", white),
+        ("brls/button/default_enabled_background", black),
+        ("brls/button/default_disabled_background", black),
+        ("brls/button/default_enabled_text", white),
+        ("brls/button/default_disabled_text", Color::from_rgb(170, 170, 170)),
+        ("brls/button/highlight_enabled_text", yellow),
+        ("brls/button/highlight_disabled_text", Color::from_rgb(170, 170, 170)),
+        ("brls/button/enabled_border_color", white),
+        ("brls/button/disabled_border_color", Color::from_rgb(170, 170, 170)),
+    ] {
+        theme.add_color(name, color);
+    }
+
+    register_theme(&variant, theme);
+    variant
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lib::core::style::StyleValues;
+    use super::*;
+
+    #[test]
+    fn scales_metrics_from_their_base_value() {
+        let key = metrics::LABEL_DEFAULT_FONT_SIZE;
+        let mut style = Style::new(StyleValues::new(vec![(key.name(), 20.0)]));
+        let mut scaled = HashMap::new();
+
+        scale_metric(&mut style, &mut scaled, key, 1.5);
+        assert_eq!(style.get(key), 30.0);
+        scale_metric(&mut style, &mut scaled, key, 2.0);
+        assert_eq!(style.get(key), 40.0);
+
+        // A new value in the style becomes the base
+        style.add_metric(key.name(), 10.0);
+        scale_metric(&mut style, &mut scaled, key, 2.0);
+        assert_eq!(style.get(key), 20.0);
+        scale_metric(&mut style, &mut scaled, key, 1.0);
+        assert_eq!(style.get(key), 10.0);
+    }
+}
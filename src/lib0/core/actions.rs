@@ -2,13 +2,14 @@ use std::string::String;
 use std::option::Option;
 use std::boxed::Box;
 use crate::lib::core::audio::Sound;
+use crate::lib::core::icons::{get_button_icon, Icon};
 use crate::lib::core::input::ControllerButton;
 use crate::lib::core::view::View;
 
 pub type ActionListener = Box<dyn FnMut(&mut dyn View) -> bool>;
 pub type ActionIdentifier = i32;
 
-const ACTION_NONE: ActionIdentifier = -1;
+pub const ACTION_NONE: ActionIdentifier = -1;
 
 pub struct Action {
     pub button: ControllerButton,
@@ -21,7 +22,7 @@ pub struct Action {
 }
 
 impl Action {
-    pub(crate) fn new(
+    pub fn new(
         button: ControllerButton,
         identifier: ActionIdentifier,
         hint_text: &str,
@@ -41,7 +42,14 @@ impl Action {
         }
     }
 
-    fn operator_eq(&self, other: &ControllerButton) -> bool {
+    /**
+     * Returns the icon of the action button, displayed before the hint text in the hints footer.
+     */
+    pub fn get_hint_icon(&self) -> Option<Icon> {
+        get_button_icon(&self.button)
+    }
+
+    pub fn operator_eq(&self, other: &ControllerButton) -> bool {
         self.button == *other
     }
 }
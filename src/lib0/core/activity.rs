@@ -1,14 +1,16 @@
-use std::cell::RefCell;
-use std::rc::Rc;
+use crate::lib::core::accessibility::is_reduced_motion;
 use crate::lib::core::actions::{ActionIdentifier, ActionListener};
 use crate::lib::core::audio::Sound;
 use crate::lib::core::input::ControllerButton;
-use crate::lib::core::base_view::{TransitionAnimation, BaseView};
-use crate::lib::core::view::View;
+use crate::lib::core::music::MusicTrack;
+use crate::lib::core::base_view::TransitionAnimation;
+use crate::lib::core::style::{get_style, metrics};
+use crate::lib::core::view_tree::{ViewId, ViewTree};
 
 // An activity is a "screen" of your app in which the library adds
 // the UI components. The app is made of a stack of activities, each activity
-// containing a views tree.
+// containing a views tree. The views are owned by the view tree
+// of the application, activities hold the handle of their content view.
 pub trait Activity {
 
     /**
@@ -20,12 +22,13 @@ pub trait Activity {
      * As such, you should override createContentView() if you want
      * to use XML in your activity.
      */
-    fn set_content_view(&mut self,  view: Rc<RefCell<Option<Box<dyn View>>>>);
+    fn set_content_view(&mut self, view: ViewId);
 
     /**
-     * Called when the activity is created. Should return the activity content view, if any.
+     * Called when the activity is created. Should add the activity content view to the given tree,
+     * as a root, and return its handle, if any.
      *
-     * Returning nullptr means the content is not known when the activity is created.
+     * Returning None means the content is not known when the activity is created.
      *
      * You can use View::createFromXMLFile(), View::createFromXMLResource() and View::createFromXMLString() to load
      * a view from respectively an XML file path, an XML name in the resources/xml romfs directory and an XML string.
@@ -37,7 +40,7 @@ pub trait Activity {
      * The onContentAvailable() method will be called once the content has been created, so that
      * you can get the references to the activity views (by id).
      */
-    fn create_content_view(&self) ->  Rc<RefCell<Option<Box<dyn View>>>>;
+    fn create_content_view(&self, tree: &mut ViewTree) -> Option<ViewId>;
 
     /**
      * Called when the content view is created, so that
@@ -45,13 +48,26 @@ pub trait Activity {
      */
     fn on_content_available(&self);
 
-    fn get_content_view(&self) ->  Rc<RefCell<Option<Box<dyn View>>>>;
+    fn get_content_view(&self) -> Option<ViewId>;
 
     /**
-     * Returns the view with the corresponding id, or nullptr
+     * Returns the view with the corresponding id, or None
      * if it hasn't been found in the activity.
      */
-    fn get_view(&self, id: &str) -> Rc<RefCell<Option<Box<dyn View>>>>;
+    fn get_view(&self, tree: &ViewTree, id: &str) -> Option<ViewId> {
+        tree.find_view(self.get_content_view()?, id)
+    }
+
+    /**
+     * Returns the background music track of this activity, if any.
+     *
+     * The track starts when the activity is pushed, crossfading with the track
+     * of the previous activity, and the previous track resumes when the activity is popped.
+     * Returning None keeps the music of the activity below in the stack.
+     */
+    fn get_background_music(&self) -> Option<MusicTrack> {
+        None
+    }
 
     /**
      * Resizes the activity to fit the window. Called when the activity
@@ -68,10 +84,17 @@ pub trait Activity {
 
     /**
      * Returns the duration of the activity show / hide animation.
+     * Transitions are instant when the reduced motion accessibility setting is enabled.
      */
     fn get_show_animation_duration(&self, animation: TransitionAnimation) -> f32 {
-        // Implementation for getting the show animation duration
-        unimplemented!()
+        if is_reduced_motion() {
+            return 0.0;
+        }
+
+        match animation {
+            TransitionAnimation::SlideLeft | TransitionAnimation::SlideRight => get_style().get(metrics::ANIMATIONS_SHOW_SLIDE),
+            TransitionAnimation::Fade => get_style().get(metrics::ANIMATIONS_SHOW),
+        }
     }
 
     /**
@@ -83,12 +106,12 @@ pub trait Activity {
         unimplemented!()
     }
 
-    fn will_appear(&self, reset_state: bool) {
+    fn will_appear(&self, _reset_state: bool) {
         // Implementation for the willAppear method
         unimplemented!()
     }
 
-    fn will_disappear(&self, reset_state: bool) {
+    fn will_disappear(&self, _reset_state: bool) {
         // Implementation for the willDisappear method
         unimplemented!()
     }
@@ -96,7 +119,7 @@ pub trait Activity {
     /**
      * If set to true, will force the activity to be translucent.
      */
-    fn set_in_fade_animation(&self, translucent: bool) {
+    fn set_in_fade_animation(&self, _translucent: bool) {
         // Implementation for setting in fade animation
         unimplemented!()
     }
@@ -104,7 +127,7 @@ pub trait Activity {
     /**
      * Shows the activity with a fade in animation, or no animation at all.
      */
-    fn show(&self, cb: fn(), animate: bool, animation_duration: f32) {
+    fn show(&self, _cb: fn(), _animate: bool, _animation_duration: f32) {
         // Implementation for showing the activity
         unimplemented!()
     }
@@ -112,7 +135,7 @@ pub trait Activity {
     /**
      * Hides the activity with a fade in animation, or no animation at all.
      */
-    fn hide(&self, cb: Box<dyn Fn()>, animate: bool, animation_duration: f32) {
+    fn hide(&self, _cb: Box<dyn Fn()>, _animate: bool, _animation_duration: f32) {
         // Implementation for hiding the activity
         unimplemented!()
     }
@@ -138,11 +161,11 @@ pub trait Activity {
      */
     fn register_action(
         &self,
-        hint_text: &str,
-        button: ControllerButton,
-        action_listener: ActionListener,
-        hidden: bool,
-        sound: Sound,
+        _hint_text: &str,
+        _button: ControllerButton,
+        _action_listener: ActionListener,
+        _hidden: bool,
+        _sound: Sound,
     ) -> ActionIdentifier {
         // Implementation for registering an action
         unimplemented!()
@@ -153,7 +176,7 @@ pub trait Activity {
      *
      * Must be called after the content view is set.
      */
-    fn unregister_action(&self, identifier: ActionIdentifier) {
+    fn unregister_action(&self, _identifier: ActionIdentifier) {
         // Implementation for unregistering an action
         unimplemented!()
     }
@@ -166,7 +189,7 @@ pub trait Activity {
      * Returns the identifier for the action, so it can be unregistered later on. Returns ACTION_NONE if the
      * action was not registered.
      */
-    fn register_exit_action(&self, button: ControllerButton) -> ActionIdentifier {
+    fn register_exit_action(&self, _button: ControllerButton) -> ActionIdentifier {
         // Implementation for registering an exit action
         unimplemented!()
     }
@@ -176,12 +199,14 @@ pub trait Activity {
         unimplemented!()
     }
 
-    fn get_default_focus(&self) -> Option<Box<dyn View>> {
-        // Implementation for getting the default focus
-        unimplemented!()
+    /**
+     * Returns the view to focus when the activity is shown, see View::get_default_focus().
+     */
+    fn get_default_focus(&self, tree: &ViewTree) -> Option<ViewId> {
+        tree.get(self.get_content_view()?)?.get_default_focus(tree)
     }
 
-    fn set_alpha(&self, alpha: f32) {
+    fn set_alpha(&self, _alpha: f32) {
         // Implementation for setting alpha
         unimplemented!()
     }
@@ -1,6 +1,8 @@
+use std::f32::consts::PI;
 use std::sync::{Arc, Mutex};
 use once_cell::sync::Lazy;
-use crate::lib::core::time::{empty_end_fn, empty_fn, FiniteTicking, get_cpu_time_msec, Ticking, TickingEndCallback, TickingGenericCallback, TickingTickCallback, Timestamp};
+use crate::lib::core::accessibility::is_reduced_motion;
+use crate::lib::core::time::{empty_end_fn, empty_fn, FiniteTicking, get_cpu_time_msec, Ticking, TickingEndCallback, TickingTickCallback, Timestamp};
 
 // An animatable is a float which value can be animated from an initial value to a target value,
 // during a given amount of time. An easing function can also be specified.
@@ -18,57 +20,164 @@ pub struct Animatable {
     running: bool,
     end_callback: TickingEndCallback,
     tick_callback: TickingTickCallback,
-    callback: TickingGenericCallback,
-    period: Timestamp,
     progress: Timestamp,
     current_value: f32,
+    initial_value: f32,
+    start_value: f32,
+    steps: Vec<AnimationStep>,
+    current_step: usize,
+    ignore_reduced_motion: bool,
+}
+
+// Easing functions shared by every animation of the library,
+// mapping a linear progress between 0.0 and 1.0 to an eased progress
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EasingFunction
+{
+    Linear,
+    QuadraticIn,
+    QuadraticOut,
+    QuadraticInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    SinusoidalIn,
+    SinusoidalOut,
+    SinusoidalInOut,
+    ExponentialIn,
+    ExponentialOut,
+    ExponentialInOut,
+}
+
+impl EasingFunction {
+    /**
+     * Applies the easing function to the given progress, between 0.0 and 1.0.
+     */
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+
+        match self {
+            EasingFunction::Linear => t,
+            EasingFunction::QuadraticIn => t * t,
+            EasingFunction::QuadraticOut => -t * (t - 2.0),
+            EasingFunction::QuadraticInOut => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    -2.0 * t * t + 4.0 * t - 1.0
+                }
+            }
+            EasingFunction::CubicIn => t * t * t,
+            EasingFunction::CubicOut => {
+                let f = t - 1.0;
+                f * f * f + 1.0
+            }
+            EasingFunction::CubicInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    let f = 2.0 * t - 2.0;
+                    0.5 * f * f * f + 1.0
+                }
+            }
+            EasingFunction::SinusoidalIn => 1.0 - (t * PI / 2.0).cos(),
+            EasingFunction::SinusoidalOut => (t * PI / 2.0).sin(),
+            EasingFunction::SinusoidalInOut => 0.5 * (1.0 - (t * PI).cos()),
+            EasingFunction::ExponentialIn => {
+                if t == 0.0 { 0.0 } else { 2f32.powf(10.0 * (t - 1.0)) }
+            }
+            EasingFunction::ExponentialOut => {
+                if t == 1.0 { 1.0 } else { 1.0 - 2f32.powf(-10.0 * t) }
+            }
+            EasingFunction::ExponentialInOut => {
+                if t == 0.0 || t == 1.0 {
+                    t
+                } else if t < 0.5 {
+                    0.5 * 2f32.powf(20.0 * t - 10.0)
+                } else {
+                    1.0 - 0.5 * 2f32.powf(-20.0 * t + 10.0)
+                }
+            }
+        }
+    }
+}
+
+// One step of an Animatable, from the previous step value (or the initial value) to the target value
+struct AnimationStep {
+    target_value: f32,
+    duration: Timestamp,
+    easing: EasingFunction,
 }
 
 impl Ticking for Animatable {
     fn set_end_callback(&mut self, end_callback: TickingEndCallback) {
-        todo!()
+        self.end_callback = end_callback;
     }
 
     fn set_tick_callback(&mut self, tick_callback: TickingTickCallback) {
-        todo!()
+        self.tick_callback = tick_callback;
     }
 
     fn get_end_callback(&mut self) -> TickingEndCallback {
-        todo!()
+        self.end_callback
     }
 
     fn get_tick_callback(&mut self) -> TickingTickCallback {
-        todo!()
+        self.tick_callback
     }
 
     fn is_running(&self) -> bool {
-        todo!()
+        self.running
     }
 
     fn set_running(&mut self, running: bool) {
-        todo!()
+        self.running = running;
     }
 
     fn on_update(&mut self, delta: Timestamp) -> bool {
-        todo!()
+        self.progress += delta;
+
+        // Skip every step that has been completed since the last update
+        while self.current_step < self.steps.len() && self.progress >= self.steps[self.current_step].duration {
+            self.progress -= self.steps[self.current_step].duration;
+            self.start_value = self.steps[self.current_step].target_value;
+            self.current_value = self.start_value;
+            self.current_step += 1;
+        }
+
+        if self.current_step >= self.steps.len() {
+            self.progress = 0;
+            return false;
+        }
+
+        let step = &self.steps[self.current_step];
+        let t = self.progress as f32 / step.duration as f32;
+        self.current_value = self.start_value + (step.target_value - self.start_value) * step.easing.apply(t);
+
+        true
     }
 
     fn on_start(&mut self) {
-        todo!()
+        self.progress = 0;
     }
 
     fn on_stop(&self) {
-        todo!()
+
     }
 }
 
 impl FiniteTicking for Animatable {
     fn on_rewind(&mut self) {
-        todo!()
+        self.progress = 0;
+        self.current_step = 0;
+        self.start_value = self.initial_value;
+        self.current_value = self.initial_value;
     }
 
     fn on_reset(&mut self) {
-        todo!()
+        self.progress = 0;
+        self.current_step = 0;
+        self.steps.clear();
     }
 }
 
@@ -82,10 +191,13 @@ impl Animatable {
            running: false,
            end_callback: empty_end_fn,
            tick_callback: empty_fn,
-           callback: empty_fn,
-           period: 0,
            progress: 0,
-           current_value: 0.0,
+           current_value: value,
+           initial_value: value,
+           start_value: value,
+           steps: vec![],
+           current_step: 0,
+           ignore_reduced_motion: false,
        }
     }
 
@@ -102,28 +214,86 @@ impl Animatable {
      * If an animation was already ongoing for that animatable, its end callback
      * will be called.
      */
-    fn reset_with_value(&mut self, initial_value: f32) {
+    pub fn reset_with_value(&mut self, initial_value: f32) {
+        self.reset();
         self.current_value = initial_value;
+        self.initial_value = initial_value;
+        self.start_value = initial_value;
     }
 
     /**
      * Adds an animation step to the target value, lasting the specified duration in milliseconds.
      *
      * An animation can have multiple steps. Target value can be greater and lower than the previous step (it can go forwards or backwards).
-     * Easing function is optional, default is EasingFunction::linear.
+     * Use EasingFunction::Linear for no easing.
      *
      * Duration is int32_t due to internal limitations, so a step cannot last for longer than 2 147 483 647ms.
      * The sum of the duration of all steps cannot exceed 71582min.
+     *
+     * Steps are instant when the reduced motion accessibility setting is enabled.
      */
-    fn add_step(&self, target_value: f32, duration: i32) {
+    pub fn add_step(&mut self, target_value: f32, duration: i32, easing: EasingFunction) {
+        let duration = if is_reduced_motion() && !self.ignore_reduced_motion { 0 } else { duration };
 
+        self.steps.push(AnimationStep {
+            target_value,
+            duration: duration.max(1) as Timestamp,
+            easing,
+        });
+    }
+
+    /**
+     * Keeps the step durations when the reduced motion accessibility setting is enabled,
+     * for animatables that do not move anything on screen (audio fades...).
+     */
+    pub fn set_ignore_reduced_motion(&mut self, ignore: bool) {
+        self.ignore_reduced_motion = ignore;
+    }
+
+    /**
+     * Starts the animation without registering it in a TickManager.
+     * The owner of the animatable is then responsible for calling update() every frame.
+     */
+    pub fn start_self_updated(&mut self) {
+        if self.running {
+            return;
+        }
+
+        self.set_running(true);
+        self.on_start();
+    }
+
+    /**
+     * Advances the animation by the given delta in ms, executing the
+     * tick callback, and the end callback once all steps are done.
+     *
+     * To be called every frame by the owner of the animatable while it's running.
+     */
+    pub fn update(&mut self, delta: Timestamp) {
+        if !self.running {
+            return;
+        }
+
+        let run = self.on_update(delta);
+        (self.tick_callback)();
+
+        if !run {
+            self.set_stop(true);
+        }
     }
 
     /**
      * Returns the progress of the animation between 0.0f and 1.0f.
      */
-    fn get_progress(&self) {
+    pub fn get_progress(&self) -> f32 {
+        let total: Timestamp = self.steps.iter().map(|step| step.duration).sum();
+
+        if total == 0 {
+            return 1.0;
+        }
 
+        let done: Timestamp = self.steps.iter().take(self.current_step).map(|step| step.duration).sum();
+        ((done + self.progress) as f32 / total as f32).min(1.0)
     }
 }
 
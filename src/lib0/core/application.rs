@@ -1,28 +1,36 @@
-use std::cell::RefCell;
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
 use std::ffi::c_float;
-use std::mem::swap;
+use std::fs;
+use std::path::Path;
 use std::rc::Rc;
-use glfw::Key::N;
-use log::{debug, error, info, warn};
-use nanovg_sys::NVGcontext;
-use rust_i18n::t;
+use log::{debug, error, info};
 use crate::lib::core::actions::ActionIdentifier;
+use crate::lib::core::accessibility::{get_high_contrast_variant, AccessibilitySettings};
 use crate::lib::core::activity::Activity;
-use crate::lib::core::audio::{AudioPlayer, Sound};
-use crate::lib::core::font::{FONT_INVALID, FontStash};
-use crate::lib::core::frame_context::FrameContext;
+use crate::lib::core::animation::{Animatable, EasingFunction};
+use crate::lib::core::audio::{AudioPlayer, AudioSettings, Sound, SoundCategory};
+use crate::lib::core::font::{set_layout_font_stash, FONT_INVALID, FontStash};
+use crate::lib::core::frame_context::{DrawLayer, FrameContext, FrameStats};
 use crate::lib::core::input::{ControllerButton, ControllerState};
-use crate::lib::core::input::ControllerButton::{ButtonA, ButtonBack, ButtonStart};
+use crate::lib::core::music::MusicPlayer;
 use crate::lib::core::platform::Platform;
-use crate::lib::core::theme::{get_dark_theme, get_light_theme, Theme, ThemeVariant};
-use crate::lib::core::time::{get_cpu_time_usec, Timestamp};
-use crate::lib::core::base_view::{FocusDirection, TransitionAnimation};
+use crate::lib::core::resources::mount_default_directory;
+use crate::lib::core::event::Event;
+use crate::lib::core::style::validate_style;
+use crate::lib::core::theme::{colors, get_theme, validate_themes, Theme, ThemeVariant};
+use crate::lib::core::time::{get_cpu_time_msec, get_cpu_time_usec, Ticking, Timestamp};
+use crate::lib::core::base_view::{FocusDirection, TransitionAnimation, Visibility};
+use crate::lib::core::config::get_config_dir;
+use crate::lib::core::debug::{dump_view_tree, load_wireframe_mode_from_env, toggle_wireframe_mode, VIEW_TREE_DUMP_FILE};
 use crate::lib::core::r#box::{BoxView, Padding};
 use crate::lib::core::view::{GenericEvent, View, VoidEvent};
+use crate::lib::core::view_tree::{ViewId, ViewTree};
+use crate::lib::core::xml::{create_from_xml_string, load_xml_resource};
 use crate::lib::platforms::platform::GlfwPlatform;
 use crate::lib::views::applet_frame::AppletFrame;
 use crate::lib::views::button::Button;
+use crate::lib::views::grid::Grid;
 use crate::lib::views::header::Header;
 use crate::lib::views::image::Image;
 use crate::lib::views::label::Label;
@@ -45,16 +53,33 @@ pub struct Application {
     content_width: u32,
     content_height: u32,
     old_controller_state: ControllerState,
+    button_press_time: Timestamp,
+    repeating_button_timer: i32,
     block_inputs_tokens: i32,
-    current_focus: Rc<RefCell<Option<Box<dyn View>>>>,
-    repetition_old_focus: Rc<RefCell<Option<Box<dyn View>>>>,
-    activities_stack: VecDeque<Rc<RefCell<Box<dyn Activity>>>>,
-    focus_stack: VecDeque<Rc<RefCell<Box<dyn View>>>>,
-    audio_player: Rc<RefCell<Option<Box<dyn AudioPlayer>>>>,
-    font_stash: Option<FontStash>,
+    view_tree: ViewTree, // views of every activity
+    current_focus: Option<ViewId>,
+    repetition_old_focus: Option<ViewId>,
+    activities_stack: VecDeque<Box<dyn Activity>>,
+    focus_stack: VecDeque<ViewId>,
+    audio_settings: AudioSettings,
+    music_player: MusicPlayer,
+    first_frame: bool,
+    last_frame_time: Timestamp,
+    last_frame_stats: Cell<FrameStats>,
+    font_stash: Rc<RefCell<FontStash>>, // filled by the font loader of the platform
     window_scale: f32,
 
+    theme_variant: ThemeVariant,
+    theme: Theme,
+    theme_transition: Option<(Theme, Theme)>, // from, to
+    theme_fade: Animatable,
+    theme_changed_event: ThemeChangedEvent,
+    follow_system_theme: bool,
+    system_theme_variant: ThemeVariant, // restored when leaving high contrast
+    accessibility_settings: AccessibilitySettings,
+
     global_quit_enabled: bool,
+    #[allow(dead_code)] // read back once the global quit action is registered
     global_quit_identifier: ActionIdentifier,
 
     common_footer: String,
@@ -68,37 +93,94 @@ pub struct Application {
 static BUTTON_REPEAT_DELAY: i32 = 15;
 static BUTTON_REPEAT_CADENCY: i32 = 5;
 
-type XMLViewCreator = fn() -> Rc<RefCell<Box<dyn View>>>;
+type XMLViewCreator = fn() -> Box<dyn View>;
+
+pub type ThemeChangedEvent = Event<ThemeVariant>;
+
+// Default duration of the crossfade between two theme variants, in ms
+const THEME_CROSSFADE_DURATION: i32 = 300;
 
 impl Application {
     pub fn new(title: &str, width: u32, height: u32) -> Self {
-        Application {
+        // Resources next to the executable override the ones mounted by the app, embedded ones included
+        mount_default_directory();
+
+        let platform: Rc<RefCell<Box<dyn Platform>>> = Rc::new(RefCell::new(Box::new(GlfwPlatform::new(title, width,  height))));
+        let theme_variant = platform.borrow().get_theme_variant();
+        let theme = get_theme(&theme_variant)
+            .or_else(|| get_theme(&ThemeVariant::Light))
+            .expect("the light theme is always registered");
+        load_wireframe_mode_from_env();
+
+        // The fonts are also used to measure text during layout
+        let font_loader = platform.borrow_mut().get_font_loader();
+        let font_stash = font_loader.borrow().get_font_stash();
+        set_layout_font_stash(Some(Rc::clone(&font_stash)));
+
+        let mut application = Application {
             quit_requested: false,
-            platform: Rc::new(RefCell::new(Box::new(GlfwPlatform::new(title, width,  height)))),
+            platform,
             title: title.into(),
             window_width: width,
             window_height: height,
             content_width: 0,
             content_height: 0,
             old_controller_state: ControllerState::new(),
+            button_press_time: 0,
+            repeating_button_timer: 0,
             block_inputs_tokens: 0,
-            current_focus: Rc::new(RefCell::new(None)),
-            repetition_old_focus: Rc::new(RefCell::new(None)),
+            view_tree: ViewTree::new(),
+            current_focus: None,
+            repetition_old_focus: None,
             activities_stack:  VecDeque::new(),
             focus_stack: VecDeque::new(),
-            audio_player: Rc::new(RefCell::new(None)),
-            font_stash: None,
+            audio_settings: AudioSettings::load(),
+            music_player: MusicPlayer::new(),
+            first_frame: true,
+            last_frame_time: 0,
+            last_frame_stats: Cell::new(FrameStats::default()),
+            font_stash,
             window_scale: 1f32,
+            theme_variant: theme_variant.clone(),
+            theme,
+            theme_transition: None,
+            theme_fade: Animatable::new(1.0),
+            theme_changed_event: ThemeChangedEvent::new(),
+            follow_system_theme: true,
+            system_theme_variant: theme_variant.clone(),
+            accessibility_settings: AccessibilitySettings::load(),
             global_quit_enabled: false,
             global_quit_identifier: 0,
             common_footer: "".to_string(),
             global_focus_change_event: GenericEvent::new(),
             global_hints_update_event: VoidEvent::new(),
             xml_views_register: Default::default(),
+        };
+
+        // Load the fonts, with the system fallbacks ordered after the locale of the application
+        font_loader.borrow().load_fonts(application.get_locale());
+
+        application.on_window_resized(width, height);
+        application.register_built_in_xml_views();
+
+        // Restore the audio settings saved by a previous run
+        application.get_audio_player().borrow().apply_settings(&application.audio_settings);
+        application.music_player.set_gain(application.audio_settings.get_effective_gain(SoundCategory::App));
+
+        // Restore the accessibility settings
+        application.accessibility_settings.apply();
+        if application.accessibility_settings.is_high_contrast() {
+            application.set_theme_variant(get_high_contrast_variant(), false);
         }
+
+        application
     }
 
     pub fn main_loop(&mut self) -> bool {
+        // First iteration: the app had the time to declare its own style and theme keys
+        if self.first_frame {
+            self.validate_style();
+        }
 
         // Main loop callback
         if !self.platform.borrow_mut().main_loop_iteration() || self.quit_requested {
@@ -107,19 +189,17 @@ impl Application {
         }
 
         // Input
-        let input_manager = self.platform.borrow_mut().get_input_manager().borrow_mut();
+        let input_manager = self.platform.borrow_mut().get_input_manager();
+        let input_manager = input_manager.borrow_mut();
         let controller_state = input_manager.get_controller_state();
 
         // Trigger controller events
         let mut any_button_pressed = false;
-        let mut repeating = false;
-        let mut button_press_time: Timestamp = 0;
-        let mut repeating_button_timer: i32 = 0;
 
         for i in 0..controller_state.buttons.len() {
             if controller_state.buttons[i] {
                 any_button_pressed = true;
-                repeating = repeating_button_timer > BUTTON_REPEAT_DELAY && repeating_button_timer % BUTTON_REPEAT_CADENCY == 0;
+                let repeating = self.repeating_button_timer > BUTTON_REPEAT_DELAY && self.repeating_button_timer % BUTTON_REPEAT_CADENCY == 0;
 
                 if !self.old_controller_state.buttons[i] || repeating {
                     // self.on_controller_button_pressed(i, repeating);
@@ -127,20 +207,56 @@ impl Application {
             }
 
             if controller_state.buttons[i] != self.old_controller_state.buttons[i] {
-                repeating_button_timer = 0;
-                button_press_time = 0;
+                self.repeating_button_timer = 0;
+                self.button_press_time = 0;
             }
         }
 
-        if any_button_pressed && get_cpu_time_usec() - button_press_time > 1000 {
-            button_press_time = get_cpu_time_usec();
-            repeating_button_timer +=1; // Increased once every ~1ms
+        if any_button_pressed && get_cpu_time_usec() - self.button_press_time > 1000 {
+            self.button_press_time = get_cpu_time_usec();
+            self.repeating_button_timer +=1; // Increased once every ~1ms
         }
 
         self.old_controller_state = controller_state;
 
         // Animations
+        let current_time = get_cpu_time_msec();
+        let delta = match self.first_frame {
+            true => 0,
+            false => current_time - self.last_frame_time,
+        };
+        self.last_frame_time = current_time;
+        self.first_frame = false;
+
         self.update_highlight_animation();
+        self.music_player.update(delta);
+        self.update_theme_transition(delta);
+        self.update_views(delta);
+        self.validate_focus();
+
+        // Follow the system theme
+        let system_variant = self.platform.borrow_mut().poll_theme_variant_change();
+        if let Some(variant) = system_variant {
+            self.system_theme_variant = variant.clone();
+
+            if self.follow_system_theme && !self.accessibility_settings.is_high_contrast() {
+                self.set_theme_variant(variant, true);
+            }
+        }
+        // Debug wireframe hotkey
+        if self.platform.borrow_mut().poll_wireframe_hotkey() {
+            info!("Wireframe mode: {:?}", toggle_wireframe_mode());
+        }
+
+        // Debug view tree dump hotkey
+        if self.platform.borrow_mut().poll_view_tree_dump_hotkey() {
+            let path = get_config_dir().join(VIEW_TREE_DUMP_FILE);
+
+            if self.dump_view_tree_to_file(&path) {
+                info!("View tree dumped to {:?}", path);
+            }
+        }
+
         // Ticking::updateTickings();
 
         // Render
@@ -148,6 +264,98 @@ impl Application {
         true
     }
 
+    pub fn get_view_tree(&self) -> &ViewTree {
+        &self.view_tree
+    }
+
+    pub fn get_view_tree_mut(&mut self) -> &mut ViewTree {
+        &mut self.view_tree
+    }
+
+    /**
+     * Dispatches an event to the focused view of the tree, then to its parents
+     * until one of them consumes it, see ViewTree::dispatch().
+     * Returns the view that consumed the event.
+     */
+    pub fn dispatch_to_focused_view<F: FnMut(&mut dyn View, &mut ViewTree) -> bool>(&mut self, f: F) -> Option<ViewId> {
+        let focused = self.get_current_focus()?;
+        self.view_tree.dispatch(focused, f)
+    }
+
+    /**
+     * Returns the state of the activity stack as JSON, bottom activity first,
+     * with the view tree of each activity (see View::to_json()) and the focused view.
+     */
+    pub fn dump_view_tree(&self) -> serde_json::Value {
+        let content_views: Vec<Option<ViewId>> = self.activities_stack.iter()
+            .map(|activity| activity.get_content_view())
+            .collect();
+
+        let mut dump = dump_view_tree(&self.view_tree, &content_views, self.get_current_focus());
+        dump["window"] = serde_json::json!({
+            "width": self.window_width,
+            "height": self.window_height,
+            "scale": self.window_scale,
+        });
+        dump
+    }
+
+    /**
+     * Writes dump_view_tree() to the given file, to attach it to a bug report.
+     * Returns true if the operation succeeds.
+     */
+    pub fn dump_view_tree_to_file(&self, path: &Path) -> bool {
+        if let Some(parent) = path.parent() {
+            if let Err(err) = fs::create_dir_all(parent) {
+                error!("Could not create directory {:?}: {}", parent, err);
+                return false;
+            }
+        }
+
+        let content = match serde_json::to_string_pretty(&self.dump_view_tree()) {
+            Ok(content) => content,
+            Err(err) => {
+                error!("Could not serialize the view tree: {}", err);
+                return false;
+            }
+        };
+
+        match fs::write(path, content) {
+            Ok(_) => true,
+            Err(err) => {
+                error!("Could not write the view tree to {:?}: {}", path, err);
+                false
+            }
+        }
+    }
+
+    /**
+     * Advances the animations of the views of every activity in the stack.
+     */
+    fn update_views(&mut self, delta: Timestamp) {
+        for activity in &self.activities_stack {
+            if let Some(content_view) = activity.get_content_view() {
+                self.view_tree.update(content_view, delta);
+            }
+        }
+    }
+
+    /**
+     * Checks that every built-in and app-required style metric and theme color is defined,
+     * reporting all the missing ones at once. Done automatically before the first frame.
+     * Returns true if nothing is missing.
+     */
+    pub fn validate_style(&self) -> bool {
+        let mut missing = validate_style();
+        missing.extend(validate_themes());
+
+        if !missing.is_empty() {
+            error!("Invalid style or theme, {} keys are missing:\n{}", missing.len(), missing.join("\n"));
+        }
+
+        missing.is_empty()
+    }
+
     pub fn get_platform(&self) -> Rc<RefCell<Box<dyn Platform>>> {
         Rc::clone(&self.platform)
     }
@@ -156,69 +364,158 @@ impl Application {
         self.platform.borrow_mut().get_audio_player()
     }
 
+    /**
+     * Returns the player of the activities background music, to customize the crossfade.
+     */
+    pub fn get_music_player(&mut self) -> &mut MusicPlayer {
+        &mut self.music_player
+    }
+
+    pub fn get_audio_settings(&self) -> &AudioSettings {
+        &self.audio_settings
+    }
+
+    /**
+     * Sets the master volume, between 0.0 and 1.0.
+     * The setting is saved and applied immediately.
+     */
+    pub fn set_master_volume(&mut self, volume: f32) {
+        self.audio_settings.set_master_volume(volume);
+        self.on_audio_settings_changed(true);
+    }
+
+    /**
+     * Mutes or unmutes every sound.
+     * The setting is saved and applied immediately.
+     */
+    pub fn set_muted(&mut self, muted: bool) {
+        self.audio_settings.set_muted(muted);
+        self.on_audio_settings_changed(true);
+    }
+
+    /**
+     * Sets the gain of the given sound category, between 0.0 and 1.0.
+     * The setting is saved and applied immediately.
+     */
+    pub fn set_sound_category_gain(&mut self, category: SoundCategory, gain: f32) {
+        self.audio_settings.set_category_gain(category, gain);
+        self.on_audio_settings_changed(true);
+    }
+
+    /**
+     * Enables or disables ducking of UI sounds while app audio plays,
+     * with the given gain applied to ducked sounds.
+     */
+    pub fn set_ui_sounds_ducking(&mut self, enabled: bool, gain: f32) {
+        self.audio_settings.set_ducking(enabled, gain);
+        self.on_audio_settings_changed(true);
+    }
+
+    /**
+     * Must be called by the app when it starts or stops playing its own audio,
+     * so that UI sounds can be ducked.
+     */
+    pub fn set_app_audio_playing(&mut self, playing: bool) {
+        self.audio_settings.set_app_audio_playing(playing);
+        self.on_audio_settings_changed(false);
+    }
+
+    pub fn get_accessibility_settings(&self) -> &AccessibilitySettings {
+        &self.accessibility_settings
+    }
+
+    /**
+     * Switches to the high contrast theme variant, or back to the system variant.
+     * The setting is saved and applied immediately.
+     */
+    pub fn set_high_contrast(&mut self, enabled: bool) {
+        self.accessibility_settings.set_high_contrast(enabled);
+        self.accessibility_settings.save();
+
+        let variant = match enabled {
+            true => get_high_contrast_variant(),
+            false => self.system_theme_variant.clone(),
+        };
+        self.set_theme_variant(variant, false);
+    }
+
+    /**
+     * Sets the multiplier applied to every font size of the style.
+     * The setting is saved and applied immediately, views must be laid out again.
+     */
+    pub fn set_font_scale(&mut self, scale: f32) {
+        self.accessibility_settings.set_font_scale(scale);
+        self.accessibility_settings.save();
+        self.accessibility_settings.apply();
+        self.invalidate_views();
+    }
+
+    // Marks every view of every activity to be measured and laid out again at the next frame
+    fn invalidate_views(&mut self) {
+        let content_views: Vec<ViewId> = self.activities_stack.iter()
+            .filter_map(|activity| activity.get_content_view())
+            .collect();
+
+        for content_view in content_views {
+            for id in self.view_tree.descendants(content_view) {
+                if let Some(view) = self.view_tree.get_mut(id) {
+                    view.get_base_view_mut().invalidate();
+                }
+            }
+        }
+    }
+
+    /**
+     * Skips the animations and activity transitions.
+     * The setting is saved and applied immediately.
+     */
+    pub fn set_reduced_motion(&mut self, enabled: bool) {
+        self.accessibility_settings.set_reduced_motion(enabled);
+        self.accessibility_settings.save();
+        self.accessibility_settings.apply();
+    }
+
+    fn on_audio_settings_changed(&mut self, save: bool) {
+        self.get_audio_player().borrow().apply_settings(&self.audio_settings);
+        self.music_player.set_gain(self.audio_settings.get_effective_gain(SoundCategory::App));
+
+        if save {
+            self.audio_settings.save();
+        }
+    }
+
     pub fn quit(&mut self) {
         self.quit_requested = true;
     }
 
     pub fn navigate(&mut self, direction: FocusDirection) {
-        // if let Some(mut current_focus) = &self.current_focus {
-        //     let mut next_focus: Option<T>  = None;
-        //
-        //     // Handle custom navigation routes
-        //     // By View ptr
-        //     if current_focus.has_custom_navigation_route_by_ptr(direction) {
-        //         next_focus = current_focus.get_custom_navigation_route_ptr(direction);
-        //
-        //         if next_focus.is_none() {
-        //             warn!("Tried to follow a navigation route that leads to a None view! (from=\"{}\", direction={})", current_focus.describe(), direction as i32);
-        //         }
-        //     }
-        //     // By ID
-        //     else if current_focus.has_custom_navigation_route_by_id(direction) {
-        //         if let Some(id) = current_focus.get_custom_navigation_route_id(direction) {
-        //             next_focus = current_focus.get_nearest_view(&id);
-        //
-        //             if next_focus.is_none() {
-        //                 warn!("Tried to follow a navigation route that leads to an unknown view ID! (from=\"{}\", direction={}, targetId=\"{}\")", current_focus.describe(), direction as i32, id);
-        //             }
-        //         }
-        //     }
-        //     // Do nothing if current focus doesn't have a parent
-        //     // (in which case there is nothing to traverse)
-        //     else if let Some(mut current_focus) = current_focus.get_parent() {
-        //         // Get next view to focus by traversing the views tree upwards
-        //         next_focus = current_focus.get_next_focus(direction, &current_focus);
-        //
-        //         while next_focus.is_none() {
-        //             if !current_focus.has_parent() || !current_focus.get_parent().unwrap().has_parent() {
-        //                 // stop when we reach the root of the tree
-        //                 break;
-        //             }
-        //
-        //             current_focus = current_focus.get_parent().unwrap();
-        //             next_focus = current_focus.get_parent().unwrap().get_next_focus(direction, &current_focus);
-        //         }
-        //     }
-        //
-        //     // No view to focus at the end of the traversal: wiggle and return
-        //     if next_focus.is_none() {
-        //         if let Some(mut current_focus) = &self.current_focus.get_mut() {
-        //             current_focus.shake_highlight(direction);
-        //         }
-        //         if let Some(audio_player) = &self.audio_player.get_mut() {
-        //             audio_player.play(Sound::SoundFocusError);
-        //         }
-        //         return;
-        //     }
-        //
-        //     // Otherwise play sound and give it focus
-        //     if let Some(audio_player) = &self.audio_player.get_mut() {
-        //         if let Some(focus_sound) = next_focus.as_ref().unwrap().get_focus_sound() {
-        //             audio_player.play(*focus_sound);
-        //         }
-        //     }
-        //     self.give_focus(next_focus.unwrap());
-        // }
+        let current_focus = match self.get_current_focus() {
+            Some(current_focus) => current_focus,
+            None => return,
+        };
+
+        // Get next view to focus by traversing the views tree upwards,
+        // up to the content view of the activity
+        let mut child = current_focus;
+        let mut next_focus = None;
+
+        for parent in self.view_tree.ancestors(current_focus) {
+            next_focus = self.view_tree.get(parent).and_then(|parent| parent.get_next_focus(&self.view_tree, direction, child));
+
+            if next_focus.is_some() {
+                break;
+            }
+
+            child = parent;
+        }
+
+        // No view to focus at the end of the traversal
+        match next_focus {
+            Some(next_focus) => self.give_focus(Some(next_focus)),
+            None => {
+                self.get_audio_player().borrow().play(Sound::SoundFocusError);
+            }
+        }
     }
 
 
@@ -227,12 +524,11 @@ impl Application {
             debug!("{:?} button press blocked (tokens={})", button, self.block_inputs_tokens);
         }
 
-        // let mut repetition_old_focus = self.repetition_old_focus.borrow_mut();
-        // if repeating && repetition_old_focus == self.current_focus.borrow_mut() {
-        //     return;
-        // }
+        if repeating && self.repetition_old_focus == self.current_focus {
+            return;
+        }
 
-        self.repetition_old_focus = Rc::clone(&self.current_focus);
+        self.repetition_old_focus = self.current_focus;
 
         // Actions
         if self.handle_action(button) {
@@ -272,11 +568,14 @@ impl Application {
     }
 
 
-    pub fn get_current_focus(&mut self) -> Rc<RefCell<Option<Box<dyn View>>>> {
-        Rc::clone(&self.current_focus)
+    /**
+     * Returns the focused view, None if it was removed from the tree since it got the focus.
+     */
+    pub fn get_current_focus(&self) -> Option<ViewId> {
+        self.current_focus.filter(|id| self.view_tree.contains(*id))
     }
 
-    pub fn handle_action(&mut self, button: ControllerButton) -> bool {
+    pub fn handle_action(&mut self, _button: ControllerButton) -> bool {
         // if self.activities_stack.is_empty() {
         //     return false;
         // }
@@ -324,52 +623,74 @@ impl Application {
         true
     }
 
-    pub fn frame(&self) {
-        todo!();
-        let video_context = self.platform.borrow_mut().get_video_context().borrow_mut();
-        let mut frame_context = FrameContext::new(
-            self.get_nvg_context(),
-            (self.window_width / self.window_height) as f32,
-            &self.font_stash.unwrap(),
+    pub fn frame(&mut self) {
+        let mut views_to_draw = Vec::new();
+
+        // Draw all activities in the stack
+        // until we find one that's not translucent
+        for activity in self.activities_stack.iter().rev() {
+            if let Some(content_view) = activity.get_content_view() {
+                views_to_draw.push(content_view);
+            }
+
+            if !activity.is_translucent() {
+                break;
+            }
+        }
+
+        // Layout pass, only going through the views changed since the last frame
+        for view in &views_to_draw {
+            self.view_tree.layout(*view, self.content_width as f32, self.content_height as f32);
+        }
+
+        let vg = self.get_nvg_context();
+        let video_context = self.platform.borrow_mut().get_video_context();
+        let font_stash = self.font_stash.borrow();
+        let frame_context = FrameContext::new(
+            Rc::clone(&vg),
+            self.window_width as f32 / self.window_height as f32,
+            &font_stash,
             self.get_theme(),
+            &self.view_tree,
         );
 
         // Begin frame and clear
-        let background_color = frame_context.theme.get_color("brls/background").unwrap();
-        video_context.begin_frame();
-        video_context.clear(background_color.clone());
+        let background_color = frame_context.theme.get(colors::BACKGROUND);
+        video_context.borrow().begin_frame();
+        video_context.borrow().clear(background_color);
 
         unsafe {
-            nanovg_sys::nvgBeginFrame(self.get_nvg_context().borrow_mut().raw(), self.window_width as c_float, self.window_height as c_float, frame_context.pixel_ratio);
-            nanovg_sys::nvgScale(self.get_nvg_context().borrow_mut().raw(), self.window_scale, self.window_scale);
+            nanovg_sys::nvgBeginFrame(vg.borrow().raw(), self.window_width as c_float, self.window_height as c_float, frame_context.pixel_ratio);
+            nanovg_sys::nvgScale(vg.borrow().raw(), self.window_scale, self.window_scale);
         }
 
-        let mut views_to_draw: Vec<Rc<RefCell<Option<Box<dyn View>>>>> = Vec::new();
-
-        // Draw all activities in the stack
-        // until we find one that's not translucent
-        for i in 0..self.activities_stack.len() {
-            let activity = &self.activities_stack[self.activities_stack.len() - 1 - i];
+        // Draw pass, bottom activity first, then the detached views of all of them on top,
+        // then the debug wireframes
+        for layer in [DrawLayer::Content, DrawLayer::Overlay, DrawLayer::Debug] {
+            let layer_context = frame_context.with_layer(layer);
 
-            views_to_draw.push(activity.borrow_mut().get_content_view());
-
-            if !activity.borrow().is_translucent() {
-                break;
+            for view in views_to_draw.iter().rev().filter_map(|view| self.view_tree.get(*view)) {
+                view.frame(&layer_context);
             }
         }
 
-        // for i in 0..views_to_draw.len() {
-        //     let view = views_to_draw.get(views_to_draw.len() - 1 - i).unwrap();
-        //     view.borrow_mut().unwrap().frame(&mut frame_context);
-        // }
+        self.last_frame_stats.set(frame_context.get_stats());
 
         // End frame
         unsafe {
-            nanovg_sys::nvgResetTransform(self.get_nvg_context().borrow_mut().raw());
-            nanovg_sys::nvgEndFrame(self.get_nvg_context().borrow_mut().raw());
+            nanovg_sys::nvgResetTransform(vg.borrow().raw());
+            nanovg_sys::nvgEndFrame(vg.borrow().raw());
         }
 
-        self.platform.borrow_mut().get_video_context().borrow_mut().end_frame();
+        video_context.borrow().end_frame();
+    }
+
+    /**
+     * Returns the counters of the last drawn frame: views drawn and views culled
+     * by their parent for being out of its viewport.
+     */
+    pub fn get_last_frame_stats(&self) -> FrameStats {
+        self.last_frame_stats.get()
     }
 
     pub fn exit(&mut self) {
@@ -377,7 +698,7 @@ impl Application {
         self.clear();
     }
 
-    pub fn set_display_framerate(&self, enabled: bool) {
+    pub fn set_display_framerate(&self, _enabled: bool) {
         // To be implemented
     }
 
@@ -385,7 +706,7 @@ impl Application {
         // To be implemented (call setDisplayFramerate)
     }
 
-    pub fn register_fps_toggle_action(&self, activity: &mut dyn Activity) {
+    pub fn register_fps_toggle_action(&self, _activity: &mut dyn Activity) {
         // activity.register_action("FPS", ButtonBack, , true)
     }
 
@@ -403,92 +724,122 @@ impl Application {
         // }
     }
 
-    pub fn set_global_fps_toggle(&mut self, enabled: bool) {
+    pub fn set_global_fps_toggle(&mut self, _enabled: bool) {
 
     }
 
-    pub fn notify(&mut self, text: &str) {
+    pub fn notify(&mut self, _text: &str) {
         // To be implemented
     }
 
-    pub fn give_focus(&mut self, view: Option<Box<dyn View>>) {
-        // let old_focus = self.current_focus.take();
-        // let new_focus = view.map(|v| v.get_default_focus());
-        //
-        // if old_focus.as_ref() != new_focus.as_ref() {
-        //     if let Some(old) = old_focus.as_deref() {
-        //         old.on_focus_lost();
-        //     }
-        //
-        //     self.current_focus = Rc::new(RefCell::new(new_focus));
-        //     self.global_focus_change_event.fire(&Rc::clone(&self.current_focus));
-        //
-        //     if let Some(new) = new_focus.as_deref() {
-        //         new.on_focus_gained();
-        //         println!("Giving focus to {}", new.describe());
-        //     }
-        // }
+    /**
+     * Gives the focus to the default focus of the given view (see View::get_default_focus()),
+     * or removes the focus. The views losing and getting the focus are notified,
+     * along with the parents of the focused view.
+     */
+    pub fn give_focus(&mut self, view: Option<ViewId>) {
+        let old_focus = self.get_current_focus();
+        let new_focus = view.and_then(|view| self.view_tree.get(view)?.get_default_focus(&self.view_tree));
+
+        if old_focus == new_focus {
+            return;
+        }
+
+        if let Some(old_view) = old_focus.and_then(|old_focus| self.view_tree.get_mut(old_focus)) {
+            old_view.on_focus_lost();
+        }
+
+        self.current_focus = new_focus;
+        self.global_focus_change_event.fire(&new_focus);
+
+        if let Some(new_focus) = new_focus {
+            let ancestors: Vec<ViewId> = self.view_tree.ancestors(new_focus).collect();
+            let mut child = new_focus;
+
+            for parent in ancestors {
+                if let Some(parent_view) = self.view_tree.get_mut(parent) {
+                    parent_view.on_child_focus_gained(child);
+                }
+
+                child = parent;
+            }
+
+            if let Some(new_view) = self.view_tree.get_mut(new_focus) {
+                new_view.on_focus_gained();
+                debug!("Giving focus to {}", new_view.describe());
+            }
+        }
     }
 
-    pub fn pop_activity(&mut self, animation: TransitionAnimation, cb: fn()) {
-        // if self.activities_stack.len() <= 1 {
-        //     // never pop the first activity
-        //     return;
-        // }
-        //
-        // self.block_inputs();
-        //
-        // let last = self.activities_stack.pop().unwrap();
-        // last.will_disappear(true);
-        // last.set_in_fade_animation(true);
-        //
-        // let wait = animation == TransitionAnimation::Fade;
-        //
-        // // Hide animation (and show previous activity, if any)
-        // last.hide(
-        //     move || {
-        //         last.set_in_fade_animation(false);
-        //         // No need to manually delete in Rust
-        //
-        //         // Animate the old activity once the new one
-        //         // has ended its animation
-        //         if !self.activities_stack.is_empty() && wait {
-        //             let new_last = self.activities_stack.last().unwrap();
-        //
-        //             if new_last.is_hidden() {
-        //                 new_last.will_appear(false);
-        //                 new_last.show(cb, true, new_last.get_show_animation_duration(animation));
-        //             } else {
-        //                 cb();
-        //             }
-        //         }
-        //
-        //         self.unblock_inputs();
-        //     },
-        //     true,
-        //     last.get_show_animation_duration(animation),
-        // );
-        //
-        // // Animate the old activity immediately
-        // if !wait && !self.activities_stack.is_empty() {
-        //     let to_show : Box<dyn Activity>= self.activities_stack.last().unwrap();
-        //     to_show.will_appear(false);
-        //     to_show.show(cb.clone(), true, to_show.get_show_animation_duration(animation));
-        // }
-        //
-        // // Focus
-        // if let Some(new_focus) = self.focus_stack.pop() {
-        //     println!("Giving focus to {}, and removing it from the focus stack", new_focus.describe());
-        //     self.give_focus(new_focus);
-        // }
+    /**
+     * Moves the focus away from the focused view once it can't be focused anymore:
+     * collapsed, hidden or gone, itself or one of its parents. The focus goes to the
+     * default focus of the nearest parent still having one (a neighbour in grids).
+     */
+    fn validate_focus(&mut self) {
+        let focus = match self.get_current_focus() {
+            Some(focus) => focus,
+            None => return,
+        };
+
+        let tree = &self.view_tree;
+        let focusable = tree.get(focus).is_some_and(|view| view.get_base_view().is_focusable());
+        let hidden_parent = tree.ancestors(focus)
+            .filter(|parent| tree.get(*parent).is_some_and(|view| {
+                let view = view.get_base_view();
+                view.get_visibility() != Visibility::Visible || view.is_collapsed()
+            }))
+            .last();
+
+        let lost = match hidden_parent {
+            Some(parent) => parent,
+            None if !focusable => focus,
+            None => return,
+        };
+
+        let new_focus = tree.ancestors(lost).find_map(|parent| tree.get(parent)?.get_default_focus(tree));
+        self.give_focus(new_focus);
+    }
+
+    pub fn pop_activity(&mut self, _animation: TransitionAnimation, cb: fn()) {
+        if self.activities_stack.len() <= 1 {
+            // never pop the first activity
+            return;
+        }
+
+        let last = match self.activities_stack.pop_back() {
+            Some(last) => last,
+            None => return,
+        };
+
+        last.will_disappear(true);
+        if let Some(content_view) = last.get_content_view() {
+            self.view_tree.remove(content_view);
+        }
+
+        // Resume the music of the activity below, now on top of the stack
+        let audio_player = self.get_audio_player();
+        self.music_player.on_activity_popped(audio_player.borrow().as_ref());
+
+        if let Some(new_last) = self.activities_stack.back() {
+            new_last.will_appear(false);
+        }
+
+        // Focus
+        if let Some(new_focus) = self.focus_stack.pop_back() {
+            self.give_focus(Some(new_focus));
+        }
+
+        cb();
     }
 
-    pub fn push_activity(&mut self, mut activity_rc: Rc<RefCell<Box<dyn Activity>>>, animation: TransitionAnimation) {
+    pub fn push_activity(&mut self, mut activity: Box<dyn Activity>, _animation: TransitionAnimation) {
         self.block_inputs();
 
-        let mut activity = activity_rc.borrow_mut();
         // Create the activity content view
-        let content_view = activity.create_content_view();
+        if let Some(content_view) = activity.create_content_view(&mut self.view_tree) {
+            activity.set_content_view(content_view);
+        }
         activity.on_content_available();
         //
         // // Call hide() on the previous activity in the stack if no
@@ -543,17 +894,20 @@ impl Application {
         // }
 
         // Focus
-        // if let Some(current_focus) = self.current_focus.get_mut() {
-        //     println!("Pushing {} to the focus stack", current_focus.describe());
-        //     self.focus_stack.push_back(Rc::new(RefCell::new(self.current_focus.borrow_mut().unwrap())));
-        // }
+        if let Some(current_focus) = self.get_current_focus() {
+            self.focus_stack.push_back(current_focus);
+        }
+
+        // Start the activity background music, if any
+        let audio_player = self.get_audio_player();
+        self.music_player.on_activity_pushed(activity.get_background_music(), audio_player.borrow().as_ref());
 
         // Layout and prepare activity
         activity.will_appear(true);
-        self.give_focus(activity.get_default_focus());
+        self.give_focus(activity.get_default_focus(&self.view_tree));
 
         // And push it
-        self.activities_stack.push_back(Rc::clone(&activity_rc));
+        self.activities_stack.push_back(activity);
     }
 
     pub fn update_highlight_animation(&self) {
@@ -566,26 +920,124 @@ impl Application {
         //     activity.borrow_mut().will_appear(true);
         // }
 
-        self.activities_stack.clear();
+        for activity in self.activities_stack.drain(..) {
+            if let Some(content_view) = activity.get_content_view() {
+                self.view_tree.remove(content_view);
+            }
+        }
+
+        self.current_focus = None;
+        self.focus_stack.clear();
     }
 
+    /**
+     * Returns the theme used to draw the current frame, in the middle of the crossfade
+     * if the variant is being switched.
+     */
     pub fn get_theme(&self) -> &Theme {
-        if self.get_theme_variant() == ThemeVariant::Light {
-            get_light_theme()
+        &self.theme
+    }
+
+    pub fn get_theme_variant(&self) -> ThemeVariant {
+        self.theme_variant.clone()
+    }
+
+    /**
+     * Switches to the given theme variant at runtime, either light, dark or a custom variant
+     * registered with register_theme(). Every view is notified through View::on_theme_changed()
+     * and the theme changed event, the next frame is drawn with the new theme.
+     *
+     * If crossfade is true, the colors are blended from the old theme to the new one.
+     * Returns false if the variant is not registered.
+     */
+    pub fn set_theme_variant(&mut self, variant: ThemeVariant, crossfade: bool) -> bool {
+        let target = match get_theme(&variant) {
+            Some(theme) => theme,
+            None => return false,
+        };
+
+        if variant == self.theme_variant && self.theme_transition.is_none() {
+            return true;
+        }
+
+        info!("Switching to theme variant \"{}\"", variant.get_name());
+        self.theme_variant = variant;
+
+        if crossfade {
+            self.theme_transition = Some((self.theme.clone(), target));
+            self.theme_fade.reset_with_value(0.0);
+            self.theme_fade.add_step(1.0, THEME_CROSSFADE_DURATION, EasingFunction::QuadraticInOut);
+            self.theme_fade.start_self_updated();
         } else {
-            get_dark_theme()
+            self.theme_transition = None;
+            self.theme = target;
         }
+
+        self.notify_theme_changed();
+        true
     }
 
-    pub fn get_theme_variant(&self) -> ThemeVariant {
-        self.platform.borrow().get_theme_variant()
+    /**
+     * Sets whether the app switches theme variant when the system one changes (enabled by default).
+     * Apps offering their own theme setting should disable it.
+     */
+    pub fn set_follow_system_theme(&mut self, follow: bool) {
+        self.follow_system_theme = follow;
+    }
+
+    /**
+     * Reloads the theme of the current variant, after it has been modified
+     * (theme file loaded, custom theme registered again...).
+     */
+    pub fn refresh_theme(&mut self) {
+        if let Some(theme) = get_theme(&self.theme_variant) {
+            self.theme_transition = None;
+            self.theme = theme;
+            self.notify_theme_changed();
+        }
+    }
+
+    /**
+     * Returns the event fired every time the theme variant changes.
+     */
+    pub fn get_theme_changed_event(&mut self) -> &mut ThemeChangedEvent {
+        &mut self.theme_changed_event
+    }
+
+    fn notify_theme_changed(&self) {
+        let views = self.activities_stack.iter()
+            .filter_map(|activity| activity.get_content_view())
+            .flat_map(|content_view| self.view_tree.descendants(content_view));
+
+        for view in views {
+            if let Some(view) = self.view_tree.get(view) {
+                view.on_theme_changed(&self.theme_variant);
+            }
+        }
+
+        self.theme_changed_event.fire(&self.theme_variant);
+    }
+
+    fn update_theme_transition(&mut self, delta: i64) {
+        let (from, to) = match &self.theme_transition {
+            Some(transition) => transition,
+            None => return,
+        };
+
+        self.theme_fade.update(delta);
+        from.blend_into(to, self.theme_fade.get_value(), &mut self.theme);
+
+        if !self.theme_fade.is_running() {
+            self.theme = to.clone();
+            self.theme_transition = None;
+        }
     }
 
     pub fn get_locale(&self) -> &str {
         "CN"
     }
 
-    fn load_font_from_file(&mut self, font_name: &str, file_path: &str) -> bool {
+    pub fn load_font_from_file(&mut self, font_name: &str, file_path: &str) -> bool {
         let handle = unsafe {
             nanovg_sys::nvgCreateFont(
                 self.get_nvg_context().borrow().raw(), // Adjust the type as needed
@@ -599,11 +1051,11 @@ impl Application {
             return false;
         }
 
-        // self.font_stash.unwrap().insert(font_name, handle);
+        self.font_stash.borrow_mut().insert(font_name, handle);
         true
     }
 
-    pub fn crash(&self, text: &str) {
+    pub fn crash(&self, _text: &str) {
         // To be implemented
     }
 
@@ -620,8 +1072,7 @@ impl Application {
     }
 
     pub fn get_nvg_context(&self) -> Rc<RefCell<nanovg::Context>> {
-        todo!()
-        // self.platform.borrow_mut().get_video_context().get_mut().get_nvg_context()
+        self.platform.borrow_mut().get_video_context().borrow_mut().get_nvg_context()
     }
 
     pub fn set_common_footer(&mut self, common_footer: &str) {
@@ -639,7 +1090,7 @@ impl Application {
         // Rescale UI
         self.window_scale = width as f32 / ORIGINAL_WINDOW_WIDTH as f32;
 
-        let content_height: f32 = (height as f32 / (self.window_scale * ORIGINAL_WINDOW_HEIGHT as f32) * ORIGINAL_WINDOW_HEIGHT as f32) as f32;
+        let content_height: f32 = height as f32 / (self.window_scale * ORIGINAL_WINDOW_HEIGHT as f32) * ORIGINAL_WINDOW_HEIGHT as f32 ;
 
         self.content_width = ORIGINAL_WINDOW_WIDTH;
         self.content_height = content_height as u32;
@@ -656,17 +1107,15 @@ impl Application {
         self.title.as_str()
     }
 
-    pub fn get_global_focus_change_event<'a>(&self) -> &'a GenericEvent {
-        todo!()
-        //&self.global_focus_change_event
+    pub fn get_global_focus_change_event(&self) -> &GenericEvent {
+        &self.global_focus_change_event
     }
 
-    pub fn get_global_hints_update_event<'a>(&self) -> &'a VoidEvent {
-        todo!()
-        //&self.global_hints_update_event
+    pub fn get_global_hints_update_event(&self) -> &VoidEvent {
+        &self.global_hints_update_event
     }
 
-    pub fn get_font(&mut self, name: &str) {
+    pub fn get_font(&mut self, _name: &str) {
         todo!()
     }
 
@@ -691,9 +1140,28 @@ impl Application {
         self.register_xml_view("brls:Image", Image::create);
         self.register_xml_view("brls:Padding", Padding::create);
         self.register_xml_view("brls:Button", Button::create);
+        self.register_xml_view("brls:Grid", Grid::create);
     }
 
     pub fn register_xml_view(&mut self, name: &str, creator: XMLViewCreator) {
         self.xml_views_register.insert(name.into(), creator);
     }
+
+    /**
+     * Creates the views of the given XML string in the view tree, with the registered views.
+     * The root view is not attached, add it to a parent or use it as an activity content view.
+     * Returns None if the XML is invalid or uses an unregistered view.
+     */
+    pub fn create_from_xml_string(&mut self, xml: &str) -> Option<ViewId> {
+        let register = &self.xml_views_register;
+        create_from_xml_string(&mut self.view_tree, xml, |name| register.get(name).map(|creator| creator()))
+    }
+
+    /**
+     * Creates the views of the given XML resource ("xml/activity.xml"), see create_from_xml_string().
+     */
+    pub fn create_from_xml_resource(&mut self, name: &str) -> Option<ViewId> {
+        let xml = load_xml_resource(name)?;
+        self.create_from_xml_string(&xml)
+    }
 }
\ No newline at end of file
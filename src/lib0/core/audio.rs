@@ -1,5 +1,8 @@
 use strum::{EnumCount, IntoEnumIterator};
 use strum_macros::{EnumCount as EnumCountMacro, EnumIter};
+use crate::lib::core::config::ConfigFile;
+use crate::lib::core::music::{FileMusicStream, MusicStream};
+
 #[derive(Debug, Clone, EnumCountMacro, EnumIter)]
 pub enum Sound
 {
@@ -13,6 +16,204 @@ pub enum Sound
     SoundClickSidebar, // played when a sidebar item is clicked
 }
 
+// Gain category of a sound, each category has its own volume in the audio settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumCountMacro, EnumIter)]
+pub enum SoundCategory
+{
+    UiFeedback = 0, // focus changes and clicks
+    UiError, // wiggles and disabled clicks, kept separate so that errors stay audible
+    Notification, // notifications and honks
+    App, // sounds played by the app itself (media, games...)
+}
+
+impl Sound {
+    /**
+     * Returns the gain category of the sound.
+     */
+    pub fn get_category(&self) -> SoundCategory {
+        match self {
+            Sound::SoundNone => SoundCategory::UiFeedback,
+            Sound::SoundFocusChange => SoundCategory::UiFeedback,
+            Sound::SoundFocusError => SoundCategory::UiError,
+            Sound::SoundClick => SoundCategory::UiFeedback,
+            Sound::SoundFocusSidebar => SoundCategory::UiFeedback,
+            Sound::SoundClickError => SoundCategory::UiError,
+            Sound::SoundHonk => SoundCategory::Notification,
+            Sound::SoundClickSidebar => SoundCategory::UiFeedback,
+        }
+    }
+}
+
+impl SoundCategory {
+    /**
+     * Returns the gain of the category when nothing was saved, also used in place of invalid gains.
+     */
+    pub fn get_default_gain(&self) -> f32 {
+        match self {
+            SoundCategory::UiFeedback => DEFAULT_CATEGORY_GAIN,
+            SoundCategory::UiError => DEFAULT_CATEGORY_GAIN,
+            SoundCategory::Notification => DEFAULT_CATEGORY_GAIN,
+            SoundCategory::App => DEFAULT_CATEGORY_GAIN,
+        }
+    }
+}
+
+const AUDIO_SETTINGS_FILE: &str = "audio.cfg";
+
+// Default gain applied to UI sounds while app audio is playing
+const DEFAULT_DUCKING_GAIN: f32 = 0.3;
+
+const DEFAULT_MASTER_VOLUME: f32 = 1.0;
+const DEFAULT_CATEGORY_GAIN: f32 = 1.0;
+
+// Volume settings shared by every AudioPlayer implementation
+// All values are gains between 0.0 and 1.0
+#[derive(Debug, Clone)]
+pub struct AudioSettings {
+    master_volume: f32,
+    muted: bool,
+    category_gains: [f32; SoundCategory::COUNT],
+    ducking_enabled: bool,
+    ducking_gain: f32,
+    app_audio_playing: bool,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AudioSettings {
+    pub fn new() -> Self {
+        Self {
+            master_volume: DEFAULT_MASTER_VOLUME,
+            muted: false,
+            category_gains: SoundCategory::iter()
+                .map(|category| category.get_default_gain())
+                .collect::<Vec<f32>>()
+                .try_into()
+                .expect("one gain per category"),
+            ducking_enabled: true,
+            ducking_gain: DEFAULT_DUCKING_GAIN,
+            app_audio_playing: false,
+        }
+    }
+
+    /**
+     * Loads the settings saved by a previous run, or returns the default
+     * settings if there are none.
+     */
+    pub fn load() -> Self {
+        let config = ConfigFile::open(AUDIO_SETTINGS_FILE);
+        let mut settings = Self::new();
+
+        settings.master_volume = clamp_gain(config.get_float("master_volume", settings.master_volume), DEFAULT_MASTER_VOLUME);
+        settings.muted = config.get_bool("muted", settings.muted);
+        settings.ducking_enabled = config.get_bool("ducking_enabled", settings.ducking_enabled);
+        settings.ducking_gain = clamp_gain(config.get_float("ducking_gain", settings.ducking_gain), DEFAULT_DUCKING_GAIN);
+
+        for category in SoundCategory::iter() {
+            let key = get_category_key(category);
+            let default_gain = category.get_default_gain();
+            settings.category_gains[category as usize] = clamp_gain(config.get_float(&key, default_gain), default_gain);
+        }
+
+        settings
+    }
+
+    /**
+     * Saves the settings to disk so that they are restored on next launch.
+     * Returns true if the operation succeeds.
+     */
+    pub fn save(&self) -> bool {
+        let mut config = ConfigFile::open(AUDIO_SETTINGS_FILE);
+
+        config.set_float("master_volume", self.master_volume);
+        config.set_bool("muted", self.muted);
+        config.set_bool("ducking_enabled", self.ducking_enabled);
+        config.set_float("ducking_gain", self.ducking_gain);
+
+        for category in SoundCategory::iter() {
+            config.set_float(&get_category_key(category), self.category_gains[category as usize]);
+        }
+
+        config.save()
+    }
+
+    pub fn set_master_volume(&mut self, volume: f32) {
+        self.master_volume = clamp_gain(volume, DEFAULT_MASTER_VOLUME);
+    }
+
+    pub fn get_master_volume(&self) -> f32 {
+        self.master_volume
+    }
+
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    pub fn set_category_gain(&mut self, category: SoundCategory, gain: f32) {
+        self.category_gains[category as usize] = clamp_gain(gain, category.get_default_gain());
+    }
+
+    pub fn get_category_gain(&self, category: SoundCategory) -> f32 {
+        self.category_gains[category as usize]
+    }
+
+    /**
+     * Enables or disables UI sounds ducking, and sets the gain
+     * applied to UI sounds while app audio is playing.
+     */
+    pub fn set_ducking(&mut self, enabled: bool, gain: f32) {
+        self.ducking_enabled = enabled;
+        self.ducking_gain = clamp_gain(gain, DEFAULT_DUCKING_GAIN);
+    }
+
+    /**
+     * Tells the settings whether app audio is currently playing, so that UI sounds
+     * can be ducked. This is runtime state and is not persisted.
+     */
+    pub fn set_app_audio_playing(&mut self, playing: bool) {
+        self.app_audio_playing = playing;
+    }
+
+    /**
+     * Returns the final gain to apply when playing a sound of the given category:
+     * master volume * category gain * ducking. Only UI feedback sounds are ducked,
+     * errors stay audible over the app audio.
+     */
+    pub fn get_effective_gain(&self, category: SoundCategory) -> f32 {
+        if self.muted {
+            return 0.0;
+        }
+
+        let mut gain = self.master_volume * self.category_gains[category as usize];
+
+        if category == SoundCategory::UiFeedback && self.ducking_enabled && self.app_audio_playing {
+            gain *= self.ducking_gain;
+        }
+
+        gain
+    }
+}
+
+// Clamps the gain between 0.0 and 1.0, NaN (from a corrupt config file) giving the default gain
+fn clamp_gain(gain: f32, default_gain: f32) -> f32 {
+    if gain.is_nan() {
+        return default_gain;
+    }
+    gain.clamp(0.0, 1.0)
+}
+
+fn get_category_key(category: SoundCategory) -> String {
+    format!("category_gain/{:?}", category)
+}
+
 // Platform agnostic Audio player
 // Each platform's AudioPlayer is responsible for managing the enum Sound -> internal representation map
 pub trait AudioPlayer {
@@ -34,6 +235,23 @@ pub trait AudioPlayer {
      * Returns a boolean indicating if the sound has been played or not.
      */
     fn play(&self, sound: Sound) -> bool;
+
+    /**
+     * Called every time the audio settings change (volume, mute, category gains, ducking).
+     *
+     * The AudioPlayer must apply the new settings immediately, including on sounds that
+     * are currently playing, by using AudioSettings::get_effective_gain() for each sound category.
+     */
+    fn apply_settings(&self, settings: &AudioSettings);
+
+    /**
+     * Opens a music file as a stream, decoded progressively from disk
+     * instead of being loaded whole. The stream is created paused.
+     *
+     * Returns None if the platform does not support music streaming
+     * or if the file could not be opened.
+     */
+    fn open_music_stream(&self, path: &str, looping: bool) -> Option<Box<dyn MusicStream>>;
 }
 
 // An AudioPlayer that does nothing
@@ -41,6 +259,12 @@ pub struct NullAudioPlayer {
 
 }
 
+impl Default for NullAudioPlayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl NullAudioPlayer {
     pub fn new() -> Self {
         Self {
@@ -50,11 +274,73 @@ impl NullAudioPlayer {
 }
 
 impl AudioPlayer for NullAudioPlayer {
-    fn load(&self, sound: Sound) -> bool {
+    fn load(&self, _sound: Sound) -> bool {
         false
     }
 
-    fn play(&self, sound: Sound) -> bool {
+    fn play(&self, _sound: Sound) -> bool {
         false
     }
-}
\ No newline at end of file
+
+    fn apply_settings(&self, _settings: &AudioSettings) {
+
+    }
+
+    /**
+     * Streams the file like a real backend, its chunks being dropped instead of decoded.
+     */
+    fn open_music_stream(&self, path: &str, looping: bool) -> Option<Box<dyn MusicStream>> {
+        let stream = FileMusicStream::open(path, looping, Box::new(|chunk, _volume| chunk.len()))?;
+        Some(Box::new(stream))
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn effective_gain_ducks_only_the_ui_feedback() {
+        let mut settings = AudioSettings::new();
+        settings.set_master_volume(0.5);
+        settings.set_category_gain(SoundCategory::UiFeedback, 0.8);
+        settings.set_category_gain(SoundCategory::App, 2.0);
+        settings.set_ducking(true, 0.25);
+
+        assert_eq!(settings.get_effective_gain(SoundCategory::UiFeedback), 0.4);
+        assert_eq!(settings.get_effective_gain(SoundCategory::App), 0.5);
+
+        // App audio playing: feedback is ducked, errors and the app itself are not
+        settings.set_app_audio_playing(true);
+        assert_eq!(settings.get_effective_gain(SoundCategory::UiFeedback), 0.1);
+        assert_eq!(settings.get_effective_gain(SoundCategory::UiError), 0.5);
+        assert_eq!(settings.get_effective_gain(SoundCategory::Notification), 0.5);
+        assert_eq!(settings.get_effective_gain(SoundCategory::App), 0.5);
+
+        settings.set_ducking(false, 0.25);
+        assert_eq!(settings.get_effective_gain(SoundCategory::UiFeedback), 0.4);
+
+        settings.set_muted(true);
+        assert_eq!(settings.get_effective_gain(SoundCategory::UiError), 0.0);
+    }
+
+    #[test]
+    fn gains_are_clamped() {
+        let mut settings = AudioSettings::new();
+        settings.set_master_volume(-1.0);
+        assert_eq!(settings.get_master_volume(), 0.0);
+        settings.set_category_gain(SoundCategory::Notification, f32::NAN);
+        assert_eq!(settings.get_category_gain(SoundCategory::Notification), 1.0);
+    }
+
+    #[test]
+    fn nan_gains_fall_back_to_their_default() {
+        let mut settings = AudioSettings::new();
+
+        settings.set_category_gain(SoundCategory::UiError, 0.2);
+        settings.set_category_gain(SoundCategory::UiError, f32::NAN);
+        assert_eq!(settings.get_category_gain(SoundCategory::UiError), SoundCategory::UiError.get_default_gain());
+
+        settings.set_ducking(true, f32::NAN);
+        assert_eq!(settings.ducking_gain, DEFAULT_DUCKING_GAIN, "a NaN ducking gain must not play UI sounds at full volume");
+    }
+}
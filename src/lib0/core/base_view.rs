@@ -1,27 +1,23 @@
-use std::cell::RefCell;
-use std::collections::{HashMap, HashSet};
-use std::mem::swap;
-use std::rc::Rc;
-use glfw::Action;
-use crate::lib::core::animation::{Animatable, get_highlight_animation};
-use nanovg::Context as NVGcontext;
-use nanovg::Solidity::Hole;
-use nanovg_sys::{nvgRGBA, nvgBeginPath, nvgFill, nvgFillColor, nvgRect, nvgPathWinding, nvgRoundedRect, nvgBoxGradient, nvgFillPaint, nvgLinearGradient, nvgRadialGradient, nvgStrokeColor, nvgStrokeWidth, nvgStroke, nvgStrokePaint, nvgRestore, nvgMoveTo, nvgLineTo, nvgRGB};
-use ordered_float::OrderedFloat;
-use rand::Rng;
-use rust_i18n::t;
-use crate::lib::core::frame_context::FrameContext;
-use crate::lib::core::style::{Style, STYLE};
-use crate::lib::core::theme::Theme;
-use crate::lib::core::time::{get_cpu_time_msec, Timestamp};
-use yoga::{Align, Edge, Node as YGNode, Node, style, StyleUnit};
-use yoga::prelude::*;
-use yoga::StyleUnit::{Auto, Percent, UndefinedValue};
-use crate::lib::core::{audio, util};
-use crate::lib::core::actions::{ActionIdentifier, ActionListener};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use log::error;
+use crate::lib::core::actions::{Action, ActionIdentifier, ActionListener};
+use crate::lib::core::animation::{Animatable, EasingFunction};
+use nanovg_sys::{nvgBeginPath, nvgFill, nvgFillColor, nvgFillPaint, nvgLinearGradient, nvgRect, nvgRoundedRect, nvgStrokeColor, nvgStrokeWidth, nvgStroke, nvgMoveTo, nvgLineTo, nvgRGB, nvgRGBAf, NVGcolor};
+use serde_json::json;
+use crate::lib::core::flex::{Dimension, Display, Edges, FlexNode};
+use crate::lib::core::r#box::Direction;
+use crate::lib::core::frame_context::{DrawLayer, FrameContext};
+use crate::lib::core::style::{get_style, metrics};
+use crate::lib::core::theme::{colors, Theme, ThemeValues};
+use crate::lib::core::color::parse_hex;
+use crate::lib::core::debug::{get_wireframe_mode, WireframeMode};
+use crate::lib::core::time::{Ticking, Timestamp};
+use crate::lib::core::audio;
 use crate::lib::core::audio::Sound;
 use crate::lib::core::input::ControllerButton;
-use crate::lib::core::view::GenericEvent;
+use crate::lib::core::view::{GenericEvent, VoidEvent};
+use crate::lib::core::view_tree::ViewId;
 
 pub static TRANSPARENT: nanovg::Color = nanovg::Color::from_rgba(0, 0, 0, 0);
 
@@ -35,6 +31,20 @@ pub enum FocusDirection
     Right
 }
 
+impl FocusDirection {
+    /**
+     * Returns the direction with left and right swapped, to go from a direction
+     * on screen to a direction in the layout of a right to left view.
+     */
+    pub fn mirrored(self) -> Self {
+        match self {
+            FocusDirection::Left => FocusDirection::Right,
+            FocusDirection::Right => FocusDirection::Left,
+            direction => direction,
+        }
+    }
+}
+
 // View background
 pub enum ViewBackground
 {
@@ -44,6 +54,7 @@ pub enum ViewBackground
     ShapeColor,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum AlignSelf
 {
     Auto,
@@ -57,6 +68,7 @@ pub enum AlignSelf
 }
 
 // View visibility
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Visibility
 {
     Visible, // the view is visible
@@ -65,6 +77,7 @@ pub enum Visibility
 }
 
 // Position attribute behavior
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum PositionType
 {
     Relative, // position attributes offset the view from the base layout
@@ -98,7 +111,22 @@ pub type ColorAttributeHandler = fn(nanovg::Color);
 pub type BoolAttributeHandler = fn(bool);
 pub type FilePathAttributeHandler = fn(String);
 
-const AUTO: f32 = f32::NAN;
+pub const AUTO: f32 = f32::NAN;
+
+// Duration of the collapse and expand animations, in ms
+const COLLAPSE_ANIMATION_DURATION: i32 = 200;
+
+fn to_nvg_color(color: nanovg::Color) -> NVGcolor {
+    unsafe { nvgRGBAf(color.red(), color.green(), color.blue(), color.alpha()) }
+}
+
+fn to_dimension(value: f32) -> Dimension {
+    if value.is_nan() {
+        Dimension::Auto
+    } else {
+        Dimension::Points(value)
+    }
+}
 
 // Superclass for all the other views
 // Lifecycle of a view is :
@@ -109,6 +137,10 @@ const AUTO: f32 = f32::NAN;
 //
 // willAppear and willDisappear can be called zero or multiple times
 // before deletion (in case of a TabLayout for instance)
+//
+// The highlight, XML attributes, lines and shadow fields are not read yet:
+// they wait for the drawing and inflation code still to be ported.
+#[allow(dead_code)]
 pub struct BaseView {
     background: ViewBackground,
     highlight_alpha: Animatable,
@@ -133,7 +165,7 @@ pub struct BaseView {
     translation_y: f32,
     wireframe_enabled: bool,
 
-    actions: Vec<crate::lib::core::actions::Action>,
+    actions: Vec<Action>,
 
     /**
      * Parent user data, typically the index of the view
@@ -142,6 +174,7 @@ pub struct BaseView {
     parent_userdata: bool,
 
     culled: bool, // will be culled by the parent Box, if any
+    in_viewport: bool, // false when culled by the parent, the view is then neither drawn nor ticked
 
     bound_documents: Vec<String>,
 
@@ -154,6 +187,7 @@ pub struct BaseView {
     file_path_attributes: HashMap<String, FilePathAttributeHandler>,
 
     known_attributes: HashSet<String>,
+    xml_attributes: BTreeMap<String, String>, // applied ones, by name
 
     maximum_allowed_xml_elements: u32,
 
@@ -173,29 +207,40 @@ pub struct BaseView {
     show_shadow: bool,
 
     custom_focus_by_id: HashMap<FocusDirection, String>,
-    custom_focus_by_ptr: HashMap<FocusDirection, Rc<RefCell<BaseView>>>,
+    custom_focus_by_ptr: HashMap<FocusDirection, ViewId>,
 
     collapse_state: Animatable,
+    collapsed: bool, // collapsed or collapsing
+    collapse_full_height: Option<f32>, // height of the view when expanded, None if collapsed before its first layout
+    collapse_style: Option<(Dimension, Dimension, Dimension)>, // height, min and max height to restore once expanded
 
     focused: bool,
 
     focus_event: GenericEvent,
+    layout_event: VoidEvent,
 
-    yg_node: YGNode,
+    flex_node: FlexNode,
 
     id: String,
+    view_id: Option<ViewId>, // handle in the view tree, once inserted
 
     alpha: Animatable,
 }
 
+impl Default for BaseView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl BaseView {
 
     pub fn new() -> Self {
-        let mut s = Self {
+        let s = Self {
             background: ViewBackground::None,
             highlight_alpha: Animatable::new(0.0),
             highlight_padding: 0.0,
-            highlight_corner_radius: STYLE.get_metric("brls/highlight/corner_radius").unwrap().clone(),
+            highlight_corner_radius: get_style().get(metrics::HIGHLIGHT_CORNER_RADIUS),
             click_alpha: Animatable::new(0.0),
             highlight_shaking: false,
             highlight_shake_start: 0,
@@ -216,7 +261,8 @@ impl BaseView {
             wireframe_enabled: false,
             actions: vec![],
             parent_userdata: false,
-            culled: false,
+            culled: true,
+            in_viewport: true,
             bound_documents: vec![],
             auto_attributes: Default::default(),
             percentage_attributes: Default::default(),
@@ -226,6 +272,7 @@ impl BaseView {
             bool_attributes: Default::default(),
             file_path_attributes: Default::default(),
             known_attributes: Default::default(),
+            xml_attributes: Default::default(),
             maximum_allowed_xml_elements: 0,
             line_color: TRANSPARENT,
             line_top: 0.0,
@@ -242,80 +289,274 @@ impl BaseView {
             custom_focus_by_id: Default::default(),
             custom_focus_by_ptr: Default::default(),
             collapse_state: Animatable::new(1.0),
+            collapsed: false,
+            collapse_full_height: None,
+            collapse_style: None,
             focused: false,
             focus_event: GenericEvent::new(),
-            yg_node: yoga::Node::new(),
+            layout_event: VoidEvent::new(),
+            flex_node: FlexNode::new(),
             id: "".to_string(),
+            view_id: None,
             alpha: Animatable::new(1.0),
         };
 
-        // style!(s.yg_node, Width(Auto), Height(Auto));
         // s.register_common_attributes();
         s
     }
 
-    // fn draw_background(&mut self, vg: &mut nanovg::Context, ctx: &mut FrameContext, style: Style) {
-    //     let x = self.get_x();
-    //     let y = self.get_y();
-    //     let width = self.get_width();
-    //     let height = self.get_height();
-    //
-    //     let theme = ctx.theme;
-    //
-    //     match self.background {
-    //         ViewBackground::None => {}
-    //         ViewBackground::Sidebar => {
-    //             let backdrop_height = *style.get_metric("brls/sidebar/border_height").unwrap();
-    //             let sidebar_color = *theme.get_color("brls/sidebar/background").unwrap();
-    //
-    //             unsafe {
-    //                 // Solid color
-    //                 nvgBeginPath(vg.into());
-    //                 nvgFillColor(vg.into(), self.a_color(sidebar_color).into());
-    //                 nvgRect(vg.into(), x, y + backdrop_height, width, height - backdrop_height * 2);
-    //                 nvgFill(vg.into());
-    //
-    //                 //Borders gradient
-    //                 // Top
-    //                 let top_gradient = nvgLinearGradient(vg.into(), x, y + backdrop_height, x, y, self.a_color(sidebar_color).into(), TRANSPARENT.into());
-    //                 nvgBeginPath(vg.into());
-    //                 nvgFillPaint(vg.into(), top_gradient);
-    //                 nvgRect(vg.into(), x, y, width, backdrop_height);
-    //                 nvgFill(vg.into());
-    //
-    //                 // Bottom
-    //                 let bottom_gradient = nvgLinearGradient(vg.into(), x, y + height - backdrop_height, x, y + height, self.a_color(sidebar_color).into(), TRANSPARENT.into());
-    //                 nvgBeginPath(vg.into());
-    //                 nvgFillPaint(vg.into(), bottom_gradient);
-    //                 nvgRect(vg.into(), x, y + height - backdrop_height, width, backdrop_height);
-    //                 nvgFill(vg.into());
-    //             }
-    //         }
-    //         ViewBackground::Backdrop => {
-    //             unsafe {
-    //                 nvgFillColor(vg.into(), self.a_color(*theme.get_color("brls/backdrop").unwrap()).into());
-    //                 nvgBeginPath(vg.into());
-    //                 nvgRect(vg.into(), x, y, width, height);
-    //                 nvgFill(vg.into());
-    //             }
-    //         }
-    //         ViewBackground::ShapeColor => {
-    //             unsafe {
-    //                 nvgFillColor(vg.into(), self.a_color(self.background_color).into());
-    //                 nvgBeginPath(vg.into());
-    //
-    //                 if self.corner_radius > 0.0 {
-    //                     nvgRoundedRect(vg.into(), x, y, width, height, self.corner_radius);
-    //                 } else {
-    //                     nvgRect(vg.into(), x, y, width, height);
-    //                 }
-    //
-    //                 nvgFill(vg.into());
-    //             }
-    //         }
-    //     }
-    // }
-    //
+    /**
+     * Forces this view and its children to use the colors of the given theme.
+     * The theme can be partial: only the colors it defines are overridden, the other
+     * ones are inherited from the parent. Overrides of nested views are merged top-down.
+     */
+    pub fn override_theme(&mut self, theme: Option<Theme>) {
+        self.theme_override = theme;
+    }
+
+    pub fn get_theme_override(&self) -> Option<&Theme> {
+        self.theme_override.as_ref()
+    }
+
+    /**
+     * Overrides a single color for this view and its children, see override_theme().
+     */
+    pub fn override_theme_color(&mut self, name: &str, color: nanovg::Color) {
+        self.theme_override
+            .get_or_insert_with(|| Theme::new(ThemeValues::new(vec![])))
+            .add_color(name, color);
+    }
+
+    /**
+     * Runs the given drawing closure with the frame context of this view:
+     * the context of the parent with the theme override of this view applied on top.
+     * Children drawn from the closure inherit the override.
+     */
+    pub fn with_frame_context<F: FnOnce(&FrameContext)>(&self, ctx: &FrameContext, f: F) {
+        match self.resolve_theme(ctx.theme) {
+            Cow::Owned(theme) => f(&ctx.with_theme(&theme)),
+            Cow::Borrowed(_) => f(ctx),
+        }
+    }
+
+    /**
+     * Returns the theme this view draws with, given the theme of its parent.
+     */
+    pub fn resolve_theme<'a>(&self, parent_theme: &'a Theme) -> Cow<'a, Theme> {
+        match &self.theme_override {
+            Some(overrides) => Cow::Owned(parent_theme.overlay(overrides)),
+            None => Cow::Borrowed(parent_theme),
+        }
+    }
+
+    /**
+     * Applies the XML attributes handled by every view.
+     * Returns false if the attribute is unknown.
+     */
+    pub fn apply_xml_attribute(&mut self, name: &str, value: &str) -> bool {
+        let applied = match name {
+            "id" => {
+                self.set_id(value);
+                true
+            }
+            "themeOverride" => self.apply_theme_override_xml_attribute(value),
+            "direction" => match value {
+                "inherit" => {
+                    self.set_direction(Direction::Inherit);
+                    true
+                }
+                "ltr" => {
+                    self.set_direction(Direction::LeftToRight);
+                    true
+                }
+                "rtl" => {
+                    self.set_direction(Direction::RightToLeft);
+                    true
+                }
+                _ => false,
+            },
+            "backgroundColor" | "borderColor" => match parse_hex(value) {
+                Some(color) if name == "backgroundColor" => {
+                    self.set_background_color(color);
+                    true
+                }
+                Some(color) => {
+                    self.set_border_color(color);
+                    true
+                }
+                None => false,
+            },
+            "borderThickness" | "cornerRadius" => match value.parse::<f32>() {
+                Ok(size) if name == "borderThickness" => {
+                    self.set_border_thickness(size);
+                    true
+                }
+                Ok(size) => {
+                    self.set_corner_radius(size);
+                    true
+                }
+                Err(_) => false,
+            },
+            _ => false,
+        };
+
+        if applied {
+            self.record_xml_attribute(name, value);
+        }
+
+        applied
+    }
+
+    /**
+     * Remembers an XML attribute applied to the view, for the view tree dumps.
+     * Views handling their own attributes call it for them.
+     */
+    pub(crate) fn record_xml_attribute(&mut self, name: &str, value: &str) {
+        self.xml_attributes.insert(name.into(), value.into());
+    }
+
+    /**
+     * Returns the state of the view as JSON: type, id, frame, box model, visibility,
+     * focus, actions and applied XML attributes. Containers add their children.
+     */
+    pub fn to_json(&self, view_type: &str) -> serde_json::Value {
+        let layout = self.flex_node.get_layout();
+        let edges = |edges: &Edges<f32>| json!({
+            "top": edges.top,
+            "right": edges.right,
+            "bottom": edges.bottom,
+            "left": edges.left,
+        });
+
+        let actions: Vec<serde_json::Value> = self.actions.iter()
+            .map(|action| json!({
+                "button": format!("{:?}", action.button),
+                "identifier": action.identifier,
+                "hint": action.hint_text,
+                "available": action.available,
+                "hidden": action.hidden,
+            }))
+            .collect();
+
+        json!({
+            "type": view_type,
+            "id": self.id,
+            "frame": {
+                "x": layout.x,
+                "y": layout.y,
+                "width": layout.width,
+                "height": layout.height,
+            },
+            "padding": edges(&layout.padding),
+            "margin": edges(&layout.margin),
+            "visibility": format!("{:?}", self.visibility),
+            "collapsed": self.collapsed,
+            "detached": self.detached,
+            "inViewport": self.in_viewport,
+            "rightToLeft": self.is_right_to_left(),
+            "focusable": self.is_focusable(),
+            "focused": self.focused,
+            "actions": actions,
+            "attributes": self.xml_attributes,
+        })
+    }
+
+    /**
+     * Parses the "themeOverride" XML attribute, a list of colors:
+     * themeOverride="brls/background: #A01E1E; brls/text: #FFFFFF"
+     */
+    fn apply_theme_override_xml_attribute(&mut self, value: &str) -> bool {
+        for entry in value.split(';').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let color = entry.split_once(':')
+                .and_then(|(name, color)| parse_hex(color).map(|color| (name.trim(), color)));
+
+            match color {
+                Some((name, color)) => self.override_theme_color(name, color),
+                None => {
+                    error!("Invalid themeOverride entry \"{}\", expected \"name: #RRGGBB\"", entry);
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+
+    /**
+     * Draws the background of the view in its frame, see set_background().
+     */
+    fn draw_background(&self, ctx: &FrameContext) {
+        let (width, height) = (self.get_width(), self.get_height());
+        let vg = ctx.vg.borrow();
+        let vg = vg.raw();
+
+        unsafe {
+            match self.background {
+                ViewBackground::None => {}
+                ViewBackground::Sidebar => {
+                    let backdrop_height = get_style().get(metrics::SIDEBAR_BORDER_HEIGHT);
+                    let color = to_nvg_color(ctx.theme.get(colors::SIDEBAR_BACKGROUND));
+                    let transparent = to_nvg_color(TRANSPARENT);
+
+                    // Solid color
+                    nvgBeginPath(vg);
+                    nvgFillColor(vg, color);
+                    nvgRect(vg, 0.0, backdrop_height, width, height - backdrop_height * 2.0);
+                    nvgFill(vg);
+
+                    // Borders gradient, top then bottom
+                    nvgBeginPath(vg);
+                    nvgFillPaint(vg, nvgLinearGradient(vg, 0.0, backdrop_height, 0.0, 0.0, color, transparent));
+                    nvgRect(vg, 0.0, 0.0, width, backdrop_height);
+                    nvgFill(vg);
+
+                    nvgBeginPath(vg);
+                    nvgFillPaint(vg, nvgLinearGradient(vg, 0.0, height - backdrop_height, 0.0, height, color, transparent));
+                    nvgRect(vg, 0.0, height - backdrop_height, width, backdrop_height);
+                    nvgFill(vg);
+                }
+                ViewBackground::Backdrop => {
+                    nvgBeginPath(vg);
+                    nvgFillColor(vg, to_nvg_color(ctx.theme.get(colors::BACKDROP)));
+                    nvgRect(vg, 0.0, 0.0, width, height);
+                    nvgFill(vg);
+                }
+                ViewBackground::ShapeColor => {
+                    nvgBeginPath(vg);
+                    nvgFillColor(vg, to_nvg_color(self.background_color));
+
+                    if self.corner_radius > 0.0 {
+                        nvgRoundedRect(vg, 0.0, 0.0, width, height, self.corner_radius);
+                    } else {
+                        nvgRect(vg, 0.0, 0.0, width, height);
+                    }
+
+                    nvgFill(vg);
+                }
+            }
+        }
+    }
+
+    /**
+     * Draws the border of the view shape, if it has a thickness.
+     */
+    fn draw_border(&self, ctx: &FrameContext) {
+        if self.border_thickness <= 0.0 {
+            return;
+        }
+
+        let vg = ctx.vg.borrow();
+        let vg = vg.raw();
+
+        unsafe {
+            nvgBeginPath(vg);
+            nvgStrokeColor(vg, to_nvg_color(self.border_color));
+            nvgStrokeWidth(vg, self.border_thickness);
+            nvgRoundedRect(vg, 0.0, 0.0, self.get_width(), self.get_height(), self.corner_radius);
+            nvgStroke(vg);
+        }
+    }
+
     // fn draw_shadow(&mut self, vg: &mut NVGcontext, ctx: &mut FrameContext, style: Style, x: f32, y: f32, width: f32, height: f32) {
     //     let mut shadow_width = 0.0f32;
     //     let mut shadow_feather = 0.0f32;
@@ -357,16 +598,6 @@ impl BaseView {
     //     }
     // }
     //
-    // fn draw_border(&mut self, vg: &mut NVGcontext, ctx: &mut FrameContext, style: Style, x: f32, y: f32, width: f32, height: f32) {
-    //     unsafe {
-    //         nvgBeginPath(vg.into());
-    //         nvgStrokeColor(vg.into(), self.border_color.into());
-    //         nvgStrokeWidth(vg.into(), self.border_thickness.into());
-    //         nvgRoundedRect(vg.into(), x, y, width, height, self.corner_radius);
-    //         nvgStroke(vg.into());
-    //     }
-    // }
-    //
     // fn draw_highlight(&mut self, vg: &mut NVGcontext, theme: Theme, alpha: f32, style: Style, background: bool) {
     //     unsafe {
     //         nanovg_sys::nvgSave(vg.into());
@@ -518,99 +749,98 @@ impl BaseView {
     //         nvgFill(vg.into());
     //     }
     // }
-    //
-    // fn draw_wireframe(&mut self, ctx: &mut FrameContext, x: f32, y: f32, width: f32, height: f32) {
-    //     unsafe {
-    //         nvgStrokeWidth(ctx.vg.into(), 1.0);
-    //
-    //         // Outline
-    //         nvgBeginPath(ctx.vg.into());
-    //         nvgStrokeColor(ctx.vg.into(), nvgRGB(0, 0, 255));
-    //         nvgRect(ctx.vg.into(), x, y, width, height);
-    //         nvgStroke(ctx.vg.into());
-    //
-    //         if self.has_parent()
-    //         {
-    //             // Diagonals
-    //             nvgFillColor(ctx.vg.into(), nvgRGB(0, 0, 255));
-    //
-    //             nvgBeginPath(ctx.vg.into());
-    //             nvgMoveTo(ctx.vg.into(), x, y);
-    //             nvgLineTo(ctx.vg.into(), x + width, y + height);
-    //             nvgFill(ctx.vg.into());
-    //
-    //             nvgBeginPath(ctx.vg.into());
-    //             nvgMoveTo(ctx.vg.into(), x + width, y);
-    //             nvgLineTo(ctx.vg.into(), x, y + height);
-    //             nvgFill(ctx.vg.into());
-    //         }
-    //
-    //         // Padding
-    //         nvgBeginPath(ctx.vg.into());
-    //         nvgStrokeColor(ctx.vg.into(), nvgRGB(0, 255, 0));
-    //
-    //         // Retrieve padding values
-    //         let padding_top = self.yg_node.get_layout_padding_top();
-    //         let padding_left = self.yg_node.get_layout_padding_left();
-    //         let padding_bottom = self.yg_node.get_layout_padding_bottom();
-    //         let padding_right = self.yg_node.get_layout_padding_right();
-    //
-    //         // Top
-    //         if padding_top > 0.0 {
-    //             nvgRect(ctx.vg.into(), x, y, width, padding_top);
-    //         }
-    //
-    //         // Left
-    //         if padding_left > 0.0 {
-    //             nvgRect(ctx.vg.into(), x, y, padding_left, height);
-    //         }
-    //
-    //         // Bottom
-    //         if padding_bottom > 0.0 {
-    //             nvgRect(ctx.vg.into(), x, y + height - padding_bottom, width, padding_bottom);
-    //         }
-    //
-    //         // Right
-    //         if padding_right > 0.0 {
-    //             nvgRect(ctx.vg.into(), x + width - padding_right, y, padding_right, height);
-    //         }
-    //
-    //         nvgStroke(ctx.vg.into());
-    //
-    //         // Margins
-    //         nvgBeginPath(ctx.vg.into());
-    //         nvgStrokeColor(ctx.vg.into(), nvgRGB(255, 0, 0));
-    //
-    //         // Retrieve margin values
-    //         let margin_top = self.yg_node.get_layout_margin_top();
-    //         let margin_left = self.yg_node.get_layout_margin_left();
-    //         let margin_bottom = self.yg_node.get_layout_margin_bottom();
-    //         let margin_right = self.yg_node.get_layout_margin_right();
-    //
-    //         // Top
-    //         if margin_top > 0.0 {
-    //             nvgRect(ctx.vg.into(), x - margin_left, y - margin_top, width + margin_left + margin_right, margin_top);
-    //         }
-    //
-    //         // Left
-    //         if margin_left > 0.0 {
-    //             nvgRect(ctx.vg.into(), x - margin_left, y - margin_top, margin_left, height + margin_top + margin_bottom);
-    //         }
-    //
-    //         // Bottom
-    //         if margin_bottom > 0.0 {
-    //             nvgRect(ctx.vg.into(), x - margin_left, y + height, width + margin_left + margin_right, margin_bottom);
-    //         }
-    //
-    //         // Right
-    //         if margin_right > 0.0 {
-    //             nvgRect(ctx.vg.into(), x + width, y - margin_top, margin_right, height + margin_top + margin_bottom);
-    //         }
-    //
-    //         nvgStroke(ctx.vg.into());
-    //     }
-    // }
-    //
+
+    /**
+     * Draws the box model of the view: bounds in blue with diagonals
+     * for leaf views, padding in green and margins in red.
+     */
+    fn draw_wireframe(&self, ctx: &FrameContext, x: f32, y: f32, width: f32, height: f32, leaf: bool) {
+        let layout = self.flex_node.get_layout();
+        let padding = layout.padding;
+        let margin = layout.margin;
+        let vg = ctx.vg.borrow();
+        let vg = vg.raw();
+
+        unsafe {
+            nvgStrokeWidth(vg, 1.0);
+
+            // Outline
+            nvgBeginPath(vg);
+            nvgStrokeColor(vg, nvgRGB(0, 0, 255));
+            nvgRect(vg, x, y, width, height);
+
+            if leaf {
+                // Diagonals
+                nvgMoveTo(vg, x, y);
+                nvgLineTo(vg, x + width, y + height);
+                nvgMoveTo(vg, x + width, y);
+                nvgLineTo(vg, x, y + height);
+            }
+
+            nvgStroke(vg);
+
+            // Padding
+            nvgBeginPath(vg);
+            nvgStrokeColor(vg, nvgRGB(0, 255, 0));
+
+            if padding.top > 0.0 {
+                nvgRect(vg, x, y, width, padding.top);
+            }
+
+            if padding.left > 0.0 {
+                nvgRect(vg, x, y, padding.left, height);
+            }
+
+            if padding.bottom > 0.0 {
+                nvgRect(vg, x, y + height - padding.bottom, width, padding.bottom);
+            }
+
+            if padding.right > 0.0 {
+                nvgRect(vg, x + width - padding.right, y, padding.right, height);
+            }
+
+            nvgStroke(vg);
+
+            // Margins
+            nvgBeginPath(vg);
+            nvgStrokeColor(vg, nvgRGB(255, 0, 0));
+
+            if margin.top > 0.0 {
+                nvgRect(vg, x - margin.left, y - margin.top, width + margin.left + margin.right, margin.top);
+            }
+
+            if margin.left > 0.0 {
+                nvgRect(vg, x - margin.left, y - margin.top, margin.left, height + margin.top + margin.bottom);
+            }
+
+            if margin.bottom > 0.0 {
+                nvgRect(vg, x - margin.left, y + height, width + margin.left + margin.right, margin.bottom);
+            }
+
+            if margin.right > 0.0 {
+                nvgRect(vg, x + width, y - margin.top, margin.right, height + margin.top + margin.bottom);
+            }
+
+            nvgStroke(vg);
+        }
+    }
+
+    /**
+     * Draws the wireframe of the view on the debug layer if enabled for the view, or globally
+     * for all the views or the focused one (see set_wireframe_mode()). Containers are not leaves.
+     */
+    pub(crate) fn draw_debug(&self, ctx: &FrameContext, leaf: bool) {
+        let shown = self.wireframe_enabled || match get_wireframe_mode() {
+            WireframeMode::Off => false,
+            WireframeMode::All => true,
+            WireframeMode::Focused => self.focused,
+        };
+
+        if shown && self.visibility == Visibility::Visible {
+            self.draw_wireframe(ctx, 0.0, 0.0, self.get_width(), self.get_height(), leaf);
+        }
+    }
+
     // fn draw_line(&mut self, ctx: &mut FrameContext, x: f32, y: f32, width: f32, height: f32) {
     //     // Don't setup and draw empty nvg path if there is no line to draw
     //     if self.line_top <= 0.0 && self.line_right <= 0.0 && self.line_bottom <= 0.0 && self.line_left <= 0.0 {
@@ -676,11 +906,11 @@ impl BaseView {
     // fn animate_hint() -> bool {
     //     false
     // }
-    //
-    // fn set_background(&mut self, background: ViewBackground) {
-    //     self.background = background;
-    // }
-    //
+
+    pub fn set_background(&mut self, background: ViewBackground) {
+        self.background = background;
+    }
+
     // pub(crate) fn shake_highlight(&mut self, direction: FocusDirection) {
     //     self.highlight_shaking = true;
     //     self.highlight_shake_start = get_cpu_time_msec();
@@ -692,9 +922,9 @@ impl BaseView {
     //     return if self.detached {
     //         self.detached_origin_x + self.translation_x
     //     } else if self.has_parent() {
-    //         self.get_parent().unwrap().get_x() + self.yg_node.get_layout_left() + self.translation_x
+    //         self.get_parent().unwrap().get_x() + self.flex_node.get_layout().x + self.translation_x
     //     } else {
-    //         self.yg_node.get_layout_left() + self.translation_x
+    //         self.flex_node.get_layout().x + self.translation_x
     //     }
     // }
     //
@@ -702,52 +932,23 @@ impl BaseView {
     //     return if self.detached {
     //         self.detached_origin_y + self.translation_y
     //     } else if self.has_parent() {
-    //         self.get_parent().unwrap().get_y() + self.yg_node.get_layout_top() + self.translation_y
-    //     } else {
-    //         self.yg_node.get_layout_top() + self.translation_y
-    //     }
-    // }
-    //
-    // fn get_width(&self) -> f32 {
-    //     self.yg_node.get_layout_width()
-    // }
-    //
-    // fn get_height(&self) -> f32 {
-    //     self.yg_node.get_layout_height()
-    // }
-    //
-    // fn get_height_include_collapse(&self) -> f32 {
-    //     self.get_height() * self.collapse_state.get_value()
-    // }
-    //
-    // /**
-    //  * Triggers a layout of the whole view tree. Must be called
-    //  * after a yoga node property is changed.
-    //  *
-    //  * Only methods that change yoga nodes properties should
-    //  * call this method.
-    //  */
-    // fn invalidate(&mut self) {
-    //     if self.yg_node.get_measure().is_some() {
-    //         self.yg_node.mark_dirty();
-    //     }
-    //
-    //     if let Some(parent) = self.get_parent() {
-    //         if !self.detached() {
-    //             parent.invalidate();
-    //         }
+    //         self.get_parent().unwrap().get_y() + self.flex_node.get_layout().y + self.translation_y
     //     } else {
-    //         self.yg_node.calculate_layout(yoga::Undefined, yoga::Undefined, yoga::Direction::LTR);
+    //         self.flex_node.get_layout().y + self.translation_y
     //     }
     // }
-    //
-    // /**
-    //  * Called when a layout pass ends on that view.
-    //  */
-    // fn on_layout(&self) {
-    //     // Placeholder; replace with your implementation
-    // }
-    //
+
+    /**
+     * Returns the height of the view shrunk by the collapse animation, ahead of the layout
+     * which only catches up at the next frame.
+     */
+    pub fn get_height_include_collapse(&self) -> f32 {
+        match self.collapse_style {
+            Some(_) => self.collapse_full_height.unwrap_or(0.0) * self.collapse_state.get_value(),
+            None => self.get_height(),
+        }
+    }
+
     // /**
     //  * Returns the view with the corresponding id in the view or its children,
     //  * or nullptr if it hasn't been found.
@@ -762,522 +963,420 @@ impl BaseView {
     //         None
     //     }
     // }
-    //
-    // // -----------------------------------------------------------
-    // // Flex layout properties
-    // // -----------------------------------------------------------
-    //
-    // /**
-    //  * Sets the preferred width of the view. Use brls::View::AUTO
-    //  * to have the layout automatically resize the view.
-    //  *
-    //  * If set to anything else than AUTO, the view is guaranteed
-    //  * to never shrink below the given width.
-    //  */
-    // fn set_width(&mut self, width: f32) {
-    //     self.yg_node.set_min_width(StyleUnit::Percent(OrderedFloat::from(0)));
-    //
-    //     if width == AUTO {
-    //         self.yg_node.set_width(StyleUnit::Auto);
-    //         self.yg_node.set_min_width(StyleUnit::UndefinedValue);
-    //     } else {
-    //         self.yg_node.set_width(StyleUnit::Point(OrderedFloat::from(width)));
-    //         self.yg_node.set_min_width(StyleUnit::Point(OrderedFloat::from(width)));
-    //     }
-    //
-    //     self.invalidate();
-    // }
-    //
-    // /**
-    //  * Sets the preferred height of the view. Use brls::View::AUTO
-    //  * to have the layout automatically resize the view.
-    //  *
-    //  * If set to anything else than AUTO, the view is guaranteed
-    //  * to never shrink below the given height.
-    //  */
-    // fn set_height(&mut self, height: f32) {
-    //     self.yg_node.set_min_height(StyleUnit::Percent(OrderedFloat::from(0)));
-    //
-    //     if height == AUTO {
-    //         self.yg_node.set_height(StyleUnit::Auto);
-    //         self.yg_node.set_min_height(StyleUnit::UndefinedValue);
-    //     } else {
-    //         self.yg_node.set_height(StyleUnit::Point(OrderedFloat::from(height)));
-    //         self.yg_node.set_min_height(StyleUnit::Point(OrderedFloat::from(height)));
-    //     }
-    //
-    //     self.invalidate();
-    // }
-    //
-    // /**
-    //  * Shortcut to setWidth + setHeight.
-    //  *
-    //  * Only does one layout pass instead of two when using the two methods separately.
-    //  */
-    // pub fn set_dimensions(&mut self, width: f32, height: f32) {
-    //     self.yg_node.set_min_width(StyleUnit::Point(OrderedFloat::from(width)));
-    //     self.yg_node.set_min_height(StyleUnit::Point(OrderedFloat::from(height)));
-    //
-    //     if width == AUTO {
-    //         self.yg_node.set_width(StyleUnit::Auto);
-    //         self.yg_node.set_min_width(StyleUnit::UndefinedValue);
-    //     } else {
-    //         self.yg_node.set_width(StyleUnit::Point(OrderedFloat::from(width)));
-    //         self.yg_node.set_min_width(StyleUnit::Point(OrderedFloat::from(width)));
-    //     }
-    //
-    //     if height == AUTO {
-    //         self.yg_node.set_height(StyleUnit::Auto);
-    //         self.yg_node.set_min_height(StyleUnit::UndefinedValue);
-    //     } else {
-    //         self.yg_node.set_height(StyleUnit::Point(OrderedFloat::from(height)));
-    //         self.yg_node.set_min_height(StyleUnit::Point(OrderedFloat::from(height)));
-    //     }
-    //
-    //     self.invalidate();
-    // }
-    //
-    // /**
-    //  * Sets the preferred width of the view in percentage of
-    //  * the parent view width. Between 0.0f and 100.0f.
-    //  */
-    // fn set_width_percentage(&mut self, percentage: f32) {
-    //     self.yg_node.set_width(StyleUnit::Percent(OrderedFloat::from(percentage)));
-    //     self.yg_node.set_min_width(StyleUnit::Percent(OrderedFloat::from(percentage)));
-    //     self.invalidate();
-    // }
-    //
-    // /**
-    //  * Sets the preferred height of the view in percentage of
-    //  * the parent view height. Between 0.0f and 100.0f.
-    //  */
-    // fn set_height_percentage(&mut self, percentage: f32) {
-    //     self.yg_node.set_height(StyleUnit::Percent(OrderedFloat::from(percentage)));
-    //     self.yg_node.set_min_height(StyleUnit::Percent(OrderedFloat::from(percentage)));
-    //     self.invalidate();
-    // }
-    //
-    // /**
-    //  * Sets the maximum width of the view, in pixels.
-    //  *
-    //  * This constraint is stronger than the grow factor: the view
-    //  * is guaranteed to never be larger than the given max width.
-    //  *
-    //  * Use View::AUTO to disable the max width constraint.
-    //  */
-    // fn set_max_width(&mut self, max_width: f32) {
-    //     if max_width == AUTO {
-    //         self.yg_node.set_max_width(StyleUnit::UndefinedValue);
-    //     } else {
-    //         self.yg_node.set_max_width(StyleUnit::Point(OrderedFloat::from(max_width)));
-    //     }
-    //
-    //     self.invalidate();
-    // }
-    //
-    // /**
-    //  * Sets the maximum height of the view, in pixels.
-    //  *
-    //  * This constraint is stronger than the grow factor: the view
-    //  * is guaranteed to never be larger than the given max height.
-    //  *
-    //  * Use View::AUTO to disable the max height constraint.
-    //  */
-    // fn set_max_height(&mut self, max_height: f32) {
-    //     if max_height == AUTO {
-    //         self.yg_node.set_max_height(StyleUnit::UndefinedValue);
-    //     } else {
-    //         self.yg_node.set_max_height(StyleUnit::Point(OrderedFloat::from(max_height)));
-    //     }
-    //
-    //     self.invalidate();
-    // }
-    //
-    // /**
-    //  * Sets the maximum width of the view, in parent width percentage.
-    //  *
-    //  * This constraint is stronger than the grow factor: the view
-    //  * is guaranteed to never be larger than the given max width.
-    //  *
-    //  * Use View::AUTO to disable the max width constraint.
-    //  */
-    // fn set_max_width_percentage(&mut self, percentage: f32) {
-    //     self.yg_node.set_max_width(StyleUnit::Percent(OrderedFloat::from(percentage)));
-    //     self.invalidate();
-    // }
-    //
-    // /**
-    //  * Sets the maximum height of the view, in pixels.
-    //  *
-    //  * This constraint is stronger than the grow factor: the view
-    //  * is guaranteed to never be larger than the given max height.
-    //  *
-    //  * Use View::AUTO to disable the max height constraint.
-    //  */
-    // fn set_max_height_percentage(&mut self, max_height: f32) {
-    //     self.yg_node.set_max_height(StyleUnit::Percent(OrderedFloat::from(max_height)));
-    //     self.invalidate();
-    // }
-    //
-    // /**
-    //  * Sets the grow factor of the view, aka the percentage
-    //  * of remaining space to give this view, in the containing box axis.
-    //  * Opposite of shrink.
-    //  * Default is 0.0f;
-    //  */
-    // fn set_grow(&mut self, grow: f32) {
-    //     self.yg_node.set_flex_grow(grow);
-    //     self.invalidate();
-    // }
-    //
-    // /**
-    //  * Sets the shrink factor of the view, aka the percentage of space
-    //  * the view is allowed to shrink for if there is not enough space for everyone
-    //  * in the contaning box axis. Opposite of grow.
-    //  * Default is 1.0f;
-    //  */
-    // fn set_shrink(&mut self, shrink: f32) {
-    //     self.yg_node.set_flex_shrink(shrink);
-    //     self.invalidate();
-    // }
-    //
-    // /**
-    //  * Sets the margin of the view, aka the space that separates
-    //  * this view and the surrounding ones in all 4 directions.
-    //  *
-    //  * Use brls::View::AUTO to have the layout automatically select the
-    //  * margin.
-    //  *
-    //  * Only works with views that have parents - top level views that are pushed
-    //  * on the stack don't have parents.
-    //  *
-    //  * Only does one layout pass instead of four when using the four methods separately.
-    //  */
-    // fn set_margins(&mut self, top: f32, right: f32, bottom: f32, left: f32) {
-    //     if top == AUTO {
-    //         self.yg_node.set_margin(Edge::Top, StyleUnit::Auto);
-    //     } else {
-    //         self.yg_node.set_margin(Edge::Top, StyleUnit::Point(OrderedFloat::from(top)));
-    //     }
-    //
-    //     if left == AUTO {
-    //         self.yg_node.set_margin(Edge::Left, StyleUnit::Auto);
-    //     } else {
-    //         self.yg_node.set_margin(Edge::Left, StyleUnit::Point(OrderedFloat::from(left)));
-    //     }
-    //
-    //     if bottom == AUTO {
-    //         self.yg_node.set_margin(Edge::Bottom, StyleUnit::Auto);
-    //     } else {
-    //         self.yg_node.set_margin(Edge::Bottom, StyleUnit::Point(OrderedFloat::from(bottom)));
-    //     }
-    //
-    //     if right == AUTO {
-    //         self.yg_node.set_margin(Edge::Right, StyleUnit::Auto);
-    //     } else {
-    //         self.yg_node.set_margin(Edge::Right, StyleUnit::Point(OrderedFloat::from(right)));
-    //     }
-    //
-    //     self.invalidate();
-    // }
-    //
-    // /**
-    //  * Sets the top margin of the view, aka the space that separates
-    //  * this view and the surrounding ones.
-    //  *
-    //  * Only works with views that have parents - top level views that are pushed
-    //  * on the stack don't have parents.
-    //  *
-    //  * Use brls::View::AUTO to have the layout automatically select the
-    //  * margin.
-    //  */
-    // fn set_margin_top(&mut self, top: f32) {
-    //     if top == AUTO {
-    //         self.yg_node.set_margin(Edge::Top, StyleUnit::Auto);
-    //     } else {
-    //         self.yg_node.set_margin(Edge::Top, StyleUnit::Point(OrderedFloat::from(top)));
-    //     }
-    //
-    //     self.invalidate();
-    // }
-    //
-    // /**
-    //  * Sets the right margin of the view, aka the space that separates
-    //  * this view and the surrounding ones.
-    //  *
-    //  * Only works with views that have parents - top level views that are pushed
-    //  * on the stack don't have parents.
-    //  *
-    //  * Use brls::View::AUTO to have the layout automatically select the
-    //  * margin.
-    //  */
-    // fn set_margin_right(&mut self, right: f32) {
-    //     if right == AUTO {
-    //         self.yg_node.set_margin(Edge::Right, StyleUnit::Auto);
-    //     } else {
-    //         self.yg_node.set_margin(Edge::Right, StyleUnit::Point(OrderedFloat::from(right)));
-    //     }
-    //
-    //     self.invalidate();
-    // }
-    //
-    // fn get_margin_right(&mut self) -> f32 {
-    //     self.yg_node.get_style().margin(Edge::Right).unwrap_or_default()
-    // }
-    //
-    // fn get_margin_left(&mut self) -> f32 {
-    //     self.yg_node.get_style().margin(Edge::Left).unwrap_or_default()
-    // }
-    //
-    // /**
-    //  * Sets the bottom margin of the view, aka the space that separates
-    //  * this view and the surrounding ones.
-    //  *
-    //  * Only works with views that have parents - top level views that are pushed
-    //  * on the stack don't have parents.
-    //  *
-    //  * Use brls::View::AUTO to have the layout automatically select the
-    //  * margin.
-    //  */
-    // fn set_margin_bottom(&mut self, bottom: f32) {
-    //     if bottom == AUTO {
-    //         self.yg_node.set_margin(Edge::Bottom, StyleUnit::Auto);
-    //     } else {
-    //         self.yg_node.set_margin(Edge::Bottom, StyleUnit::Point(OrderedFloat::from(bottom)));
-    //     }
-    //
-    //     self.invalidate();
-    // }
-    //
-    // /**
-    //  * Sets the right margin of the view, aka the space that separates
-    //  * this view and the surrounding ones.
-    //  *
-    //  * Only works with views that have parents - top level views that are pushed
-    //  * on the stack don't have parents.
-    //  *
-    //  * Use brls::View::AUTO to have the layout automatically select the
-    //  * margin.
-    //  */
-    // fn set_margin_left(&mut self, left: f32) {
-    //     if left == AUTO {
-    //         self.yg_node.set_margin(Edge::Left, StyleUnit::Auto);
-    //     } else {
-    //         self.yg_node.set_margin(Edge::Left, StyleUnit::Point(OrderedFloat::from(left)));
-    //     }
-    //
-    //     self.invalidate();
-    // }
-    //
-    // /**
-    //  * Sets the visibility of the view.
-    //  */
-    // fn set_visibility(&mut self, visibility: Visibility) {
-    //     // Only change YG properties and invalidate if going from or to GONE
-    //     if (self.visibility == Visibility::Gone && visibility != Visibility::Gone) || (self.visibility != Visibility::Gone && visibility == Visibility::Gone)
-    //     {
-    //         if visibility == Visibility::Gone {
-    //             self.yg_node.set_display(yoga::Display::None);
-    //         } else {
-    //             self.yg_node.set_display(yoga::Display::Flex);
-    //         }
-    //
-    //         self.invalidate();
-    //     }
-    //
-    //     self.visibility = visibility;
-    //
-    //     if visibility == Visibility::Visible {
-    //         self.will_appear();
-    //     }
-    //     else {
-    //         self.will_disappear();
-    //     }
-    // }
-    //
-    // /**
-    //  * Sets the top position of the view, in pixels.
-    //  *
-    //  * The behavior of this attribute changes depending on the
-    //  * position type of the view.
-    //  *
-    //  * If relative, it will simply offset the view by the given amount.
-    //  *
-    //  * If absolute, it will behave like the "display: absolute;" CSS property
-    //  * and move the view freely in its parent. Use 0 to snap to the parent top edge.
-    //  * Absolute positioning ignores padding.
-    //  *
-    //  * Use View::AUTO to disable (not the same as 0).
-    //  */
-    // fn set_position_top(&mut self, pos: f32) {
-    //     if pos == AUTO {
-    //         self.yg_node.set_position(Edge::Top, StyleUnit::UndefinedValue);
-    //     } else {
-    //         self.yg_node.set_position(Edge::Top, StyleUnit::Point(OrderedFloat::from(pos)));
-    //     }
-    //
-    //     self.invalidate();
-    // }
-    //
-    // /**
-    //  * Sets the right position of the view, in pixels.
-    //  *
-    //  * The behavior of this attribute changes depending on the
-    //  * position type of the view.
-    //  *
-    //  * If relative, it will simply offset the view by the given amount.
-    //  *
-    //  * If absolute, it will behave like the "display: absolute;" CSS property
-    //  * and move the view freely in its parent. Use 0 to snap to the parent right edge.
-    //  * Absolute positioning ignores padding.
-    //  *
-    //  * Use View::AUTO to disable (not the same as 0).
-    //  */
-    // fn set_position_right(&mut self, pos: f32) {
-    //     if pos == AUTO {
-    //         self.yg_node.set_position(Edge::Right, StyleUnit::UndefinedValue);
-    //     } else {
-    //         self.yg_node.set_position(Edge::Right, StyleUnit::Point(OrderedFloat::from(pos)));
-    //     }
-    //
-    //     self.invalidate();
-    // }
-    //
-    // /**
-    //  * Sets the bottom position of the view, in pixels.
-    //  *
-    //  * The behavior of this attribute changes depending on the
-    //  * position type of the view.
-    //  *
-    //  * If relative, it will simply offset the view by the given amount.
-    //  *
-    //  * If absolute, it will behave like the "display: absolute;" CSS property
-    //  * and move the view freely in its parent. Use 0 to snap to the parent bottom edge.
-    //  * Absolute positioning ignores padding.
-    //  *
-    //  * Use View::AUTO to disable (not the same as 0).
-    //  */
-    // fn set_position_bottom(&mut self, pos: f32) {
-    //     if pos == AUTO {
-    //         self.yg_node.set_position(Edge::Bottom, StyleUnit::UndefinedValue);
-    //     } else {
-    //         self.yg_node.set_position(Edge::Bottom, StyleUnit::Point(OrderedFloat::from(pos)));
-    //     }
-    //
-    //     self.invalidate();
-    // }
-    //
-    // /**
-    //  * Sets the left position of the view, in pixels.
-    //  *
-    //  * The behavior of this attribute changes depending on the
-    //  * position type of the view.
-    //  *
-    //  * If relative, it will simply offset the view by the given amount.
-    //  *
-    //  * If absolute, it will behave like the "display: absolute;" CSS property
-    //  * and move the view freely in its parent. Use 0 to snap to the parent left edge.
-    //  * Absolute positioning ignores padding.
-    //  *
-    //  * Use View::AUTO to disable (not the same as 0).
-    //  */
-    // fn set_position_left(&mut self, pos: f32) {
-    //     if pos == AUTO {
-    //         self.yg_node.set_position(Edge::Left, StyleUnit::UndefinedValue);
-    //     } else {
-    //         self.yg_node.set_position(Edge::Left, StyleUnit::Point(OrderedFloat::from(pos)));
-    //     }
-    //
-    //     self.invalidate();
-    // }
-    //
-    // /**
-    //  * Sets the top position of the view, in percents.
-    //  *
-    //  * The behavior of this attribute changes depending on the
-    //  * position type of the view.
-    //  */
-    // fn set_position_top_percentage(&mut self, percentage: f32) {
-    //     self.yg_node.set_position(Edge::Top, StyleUnit::Percent(OrderedFloat::from(percentage)));
-    //     self.invalidate();
-    // }
-    //
-    // /**
-    //  * Sets the right position of the view, in percents.
-    //  *
-    //  * The behavior of this attribute changes depending on the
-    //  * position type of the view.
-    //  */
-    // fn set_position_right_percentage(&mut self, percentage: f32) {
-    //     self.yg_node.set_position(Edge::Right, StyleUnit::Percent(OrderedFloat::from(percentage)));
-    //     self.invalidate();
-    // }
-    //
-    // /**
-    //  * Sets the bottom position of the view, in percents.
-    //  *
-    //  * The behavior of this attribute changes depending on the
-    //  * position type of the view.
-    //  */
-    // fn set_position_bottom_percentage(&mut self, percentage: f32) {
-    //     self.yg_node.set_position(Edge::Bottom, StyleUnit::Percent(OrderedFloat::from(percentage)));
-    //     self.invalidate();
-    // }
-    //
-    // /**
-    //  * Sets the left position of the view, in percents.
-    //  *
-    //  * The behavior of this attribute changes depending on the
-    //  * position type of the view.
-    //  */
-    // fn set_position_left_percentage(&mut self, percentage: f32) {
-    //     self.yg_node.set_position(Edge::Left, StyleUnit::Percent(OrderedFloat::from(percentage)));
-    //     self.invalidate();
-    // }
-    //
-    // /**
-    //  * Sets the id of the view.
-    //  */
-    // fn set_id(&mut self, id: &str) {
-    //     self.id = id.parse().unwrap();
-    // }
-    //
-    // /**
-    //  * Overrides align items of the parent box.
-    //  *
-    //  * Default is AUTO.
-    //  */
-    // fn set_align_self(&mut self, align: AlignSelf) {
-    //     match align {
-    //         AlignSelf::Auto => {
-    //             self.yg_node.set_align_self(Align::Auto);
-    //         }
-    //         AlignSelf::FlexStart => {
-    //             self.yg_node.set_align_self(Align::FlexStart);
-    //         }
-    //         AlignSelf::Center => {
-    //             self.yg_node.set_align_self(Align::Center);
-    //         }
-    //         AlignSelf::FlexEnd => {
-    //             self.yg_node.set_align_self(Align::FlexEnd);
-    //         }
-    //         AlignSelf::Stretch => {
-    //             self.yg_node.set_align_self(Align::Stretch);
-    //         }
-    //         AlignSelf::Baseline => {
-    //             self.yg_node.set_align_self(Align::Baseline);
-    //         }
-    //         AlignSelf::SpaceBetween => {
-    //             self.yg_node.set_align_self(Align::SpaceBetween);
-    //         }
-    //         AlignSelf::SpaceAround => {
-    //             self.yg_node.set_align_self(Align::SpaceAround);
-    //         }
-    //     }
-    //
-    //     self.invalidate();
-    // }
-    //
+
+    // -----------------------------------------------------------
+    // Flex layout properties
+    // -----------------------------------------------------------
+
+    pub fn get_flex_node(&self) -> &FlexNode {
+        &self.flex_node
+    }
+
+    pub fn get_flex_node_mut(&mut self) -> &mut FlexNode {
+        &mut self.flex_node
+    }
+
+    pub fn get_width(&self) -> f32 {
+        self.flex_node.get_layout().width
+    }
+
+    pub fn get_height(&self) -> f32 {
+        self.flex_node.get_layout().height
+    }
+
+    /**
+     * Marks the view to be laid out again at the next frame, along with its parents.
+     * Layout properties setters do it already, views must call it when their content
+     * size changes (text of a label...).
+     */
+    pub fn invalidate(&mut self) {
+        self.flex_node.mark_dirty();
+    }
+
+    /**
+     * Called after a layout pass that changed the frame of the view.
+     */
+    pub fn on_layout(&mut self) {
+        self.flex_node.mark_layout_seen();
+        self.layout_event.fire(&());
+    }
+
+    /**
+     * Returns the event fired when a layout pass changed the frame of the view.
+     */
+    pub fn get_layout_event(&mut self) -> &mut VoidEvent {
+        &mut self.layout_event
+    }
+
+    /**
+     * Sets the preferred width of the view. Use brls::View::AUTO
+     * to have the layout automatically resize the view.
+     *
+     * If set to anything else than AUTO, the view is guaranteed
+     * to never shrink below the given width.
+     */
+    pub fn set_width(&mut self, width: f32) {
+        let style = self.flex_node.get_style_mut();
+        style.width = to_dimension(width);
+        style.min_width = to_dimension(width);
+    }
+
+    /**
+     * Sets the preferred height of the view. Use brls::View::AUTO
+     * to have the layout automatically resize the view.
+     *
+     * If set to anything else than AUTO, the view is guaranteed
+     * to never shrink below the given height.
+     */
+    pub fn set_height(&mut self, height: f32) {
+        let style = self.flex_node.get_style_mut();
+        style.height = to_dimension(height);
+        style.min_height = to_dimension(height);
+    }
+
+    /**
+     * Shortcut to setWidth + setHeight.
+     */
+    pub fn set_dimensions(&mut self, width: f32, height: f32) {
+        self.set_width(width);
+        self.set_height(height);
+    }
+
+    /**
+     * Sets the preferred width of the view in percentage of
+     * the parent view width. Between 0.0f and 100.0f.
+     */
+    pub fn set_width_percentage(&mut self, percentage: f32) {
+        let style = self.flex_node.get_style_mut();
+        style.width = Dimension::Percent(percentage);
+        style.min_width = Dimension::Percent(percentage);
+    }
+
+    /**
+     * Sets the preferred height of the view in percentage of
+     * the parent view height. Between 0.0f and 100.0f.
+     */
+    pub fn set_height_percentage(&mut self, percentage: f32) {
+        let style = self.flex_node.get_style_mut();
+        style.height = Dimension::Percent(percentage);
+        style.min_height = Dimension::Percent(percentage);
+    }
+
+    /**
+     * Sets the maximum width of the view, in pixels.
+     *
+     * This constraint is stronger than the grow factor: the view
+     * is guaranteed to never be larger than the given max width.
+     *
+     * Use View::AUTO to disable the max width constraint.
+     */
+    pub fn set_max_width(&mut self, max_width: f32) {
+        self.flex_node.get_style_mut().max_width = to_dimension(max_width);
+    }
+
+    /**
+     * Sets the maximum height of the view, in pixels.
+     *
+     * This constraint is stronger than the grow factor: the view
+     * is guaranteed to never be larger than the given max height.
+     *
+     * Use View::AUTO to disable the max height constraint.
+     */
+    pub fn set_max_height(&mut self, max_height: f32) {
+        self.flex_node.get_style_mut().max_height = to_dimension(max_height);
+    }
+
+    /**
+     * Sets the maximum width of the view, in parent width percentage.
+     */
+    pub fn set_max_width_percentage(&mut self, percentage: f32) {
+        self.flex_node.get_style_mut().max_width = Dimension::Percent(percentage);
+    }
+
+    /**
+     * Sets the maximum height of the view, in parent height percentage.
+     */
+    pub fn set_max_height_percentage(&mut self, percentage: f32) {
+        self.flex_node.get_style_mut().max_height = Dimension::Percent(percentage);
+    }
+
+    /**
+     * Sets the grow factor of the view, aka the percentage
+     * of remaining space to give this view, in the containing box axis.
+     * Opposite of shrink.
+     * Default is 0.0f;
+     */
+    pub fn set_grow(&mut self, grow: f32) {
+        self.flex_node.get_style_mut().grow = grow;
+    }
+
+    /**
+     * Sets the shrink factor of the view, aka the percentage of space
+     * the view is allowed to shrink for if there is not enough space for everyone
+     * in the contaning box axis. Opposite of grow.
+     * Default is 1.0f;
+     */
+    pub fn set_shrink(&mut self, shrink: f32) {
+        self.flex_node.get_style_mut().shrink = shrink;
+    }
+
+    /**
+     * Sets the margin of the view, aka the space that separates
+     * this view and the surrounding ones in all 4 directions.
+     *
+     * Use brls::View::AUTO to have the layout automatically select the
+     * margin.
+     *
+     * Only works with views that have parents - top level views that are pushed
+     * on the stack don't have parents.
+     */
+    pub fn set_margins(&mut self, top: f32, right: f32, bottom: f32, left: f32) {
+        self.flex_node.get_style_mut().margin = Edges {
+            top: to_dimension(top),
+            right: to_dimension(right),
+            bottom: to_dimension(bottom),
+            left: to_dimension(left),
+        };
+    }
+
+    /**
+     * Sets the top margin of the view, see set_margins().
+     */
+    pub fn set_margin_top(&mut self, top: f32) {
+        self.flex_node.get_style_mut().margin.top = to_dimension(top);
+    }
+
+    /**
+     * Sets the right margin of the view, see set_margins().
+     */
+    pub fn set_margin_right(&mut self, right: f32) {
+        self.flex_node.get_style_mut().margin.right = to_dimension(right);
+    }
+
+    /**
+     * Sets the bottom margin of the view, see set_margins().
+     */
+    pub fn set_margin_bottom(&mut self, bottom: f32) {
+        self.flex_node.get_style_mut().margin.bottom = to_dimension(bottom);
+    }
+
+    /**
+     * Sets the left margin of the view, see set_margins().
+     */
+    pub fn set_margin_left(&mut self, left: f32) {
+        self.flex_node.get_style_mut().margin.left = to_dimension(left);
+    }
+
+    pub fn get_margin_right(&self) -> f32 {
+        self.flex_node.get_layout().margin.right
+    }
+
+    pub fn get_margin_left(&self) -> f32 {
+        self.flex_node.get_layout().margin.left
+    }
+
+    /**
+     * Overrides align items of the parent box.
+     *
+     * Default is AUTO.
+     */
+    pub fn set_align_self(&mut self, align: AlignSelf) {
+        self.flex_node.get_style_mut().align_self = align;
+    }
+
+    /**
+     * Sets the layout direction of the view and its children. Right to left mirrors
+     * rows, margins, padding, positions, and left and right focus navigation.
+     * Inherit (the default) takes the direction of the parent, or the one of the locale
+     * at the root, see set_layout_direction().
+     */
+    pub fn set_direction(&mut self, direction: Direction) {
+        self.flex_node.get_style_mut().direction = direction;
+    }
+
+    pub fn is_right_to_left(&self) -> bool {
+        self.flex_node.is_right_to_left()
+    }
+
+    /**
+     * Returns the direction in the layout of the view for a direction on screen.
+     */
+    pub fn get_layout_focus_direction(&self, direction: FocusDirection) -> FocusDirection {
+        match self.is_right_to_left() {
+            true => direction.mirrored(),
+            false => direction,
+        }
+    }
+
+    /**
+     * Sets the visibility of the view.
+     *
+     * Invisible views are not drawn but keep their space, gone views
+     * are removed from the layout. Neither can be focused.
+     */
+    pub fn set_visibility(&mut self, visibility: Visibility) {
+        let display = match visibility {
+            Visibility::Gone => Display::None,
+            _ => Display::Flex,
+        };
+
+        // Only change the layout when going from or to Gone
+        if self.flex_node.get_style().display != display {
+            self.flex_node.get_style_mut().display = display;
+        }
+
+        self.visibility = visibility;
+    }
+
+    pub fn get_visibility(&self) -> Visibility {
+        self.visibility
+    }
+
+    /**
+     * Sets the position type of the view, deciding how the position
+     * attributes (see set_position_top()) are applied.
+     *
+     * Absolute views are taken out of the layout of their parent:
+     * their siblings are laid out as if they were not there.
+     */
+    pub fn set_position_type(&mut self, position_type: PositionType) {
+        self.flex_node.get_style_mut().position_type = position_type;
+    }
+
+    /**
+     * Sets the top position of the view, in pixels.
+     *
+     * The behavior of this attribute changes depending on the
+     * position type of the view.
+     *
+     * If relative, it will simply offset the view by the given amount.
+     *
+     * If absolute, it will behave like the "display: absolute;" CSS property
+     * and move the view freely in its parent. Use 0 to snap to the parent top edge.
+     * Absolute positioning ignores padding.
+     *
+     * Use View::AUTO to disable (not the same as 0).
+     */
+    pub fn set_position_top(&mut self, pos: f32) {
+        self.flex_node.get_style_mut().position.top = to_dimension(pos);
+    }
+
+    /**
+     * Sets the right position of the view, in pixels.
+     *
+     * The behavior of this attribute changes depending on the
+     * position type of the view.
+     *
+     * If relative, it will simply offset the view by the given amount.
+     *
+     * If absolute, it will behave like the "display: absolute;" CSS property
+     * and move the view freely in its parent. Use 0 to snap to the parent right edge.
+     * Absolute positioning ignores padding.
+     *
+     * Use View::AUTO to disable (not the same as 0).
+     */
+    pub fn set_position_right(&mut self, pos: f32) {
+        self.flex_node.get_style_mut().position.right = to_dimension(pos);
+    }
+
+    /**
+     * Sets the bottom position of the view, in pixels.
+     *
+     * The behavior of this attribute changes depending on the
+     * position type of the view.
+     *
+     * If relative, it will simply offset the view by the given amount.
+     *
+     * If absolute, it will behave like the "display: absolute;" CSS property
+     * and move the view freely in its parent. Use 0 to snap to the parent bottom edge.
+     * Absolute positioning ignores padding.
+     *
+     * Use View::AUTO to disable (not the same as 0).
+     */
+    pub fn set_position_bottom(&mut self, pos: f32) {
+        self.flex_node.get_style_mut().position.bottom = to_dimension(pos);
+    }
+
+    /**
+     * Sets the left position of the view, in pixels.
+     *
+     * The behavior of this attribute changes depending on the
+     * position type of the view.
+     *
+     * If relative, it will simply offset the view by the given amount.
+     *
+     * If absolute, it will behave like the "display: absolute;" CSS property
+     * and move the view freely in its parent. Use 0 to snap to the parent left edge.
+     * Absolute positioning ignores padding.
+     *
+     * Use View::AUTO to disable (not the same as 0).
+     */
+    pub fn set_position_left(&mut self, pos: f32) {
+        self.flex_node.get_style_mut().position.left = to_dimension(pos);
+    }
+
+    /**
+     * Sets the top position of the view, in percents.
+     *
+     * The behavior of this attribute changes depending on the
+     * position type of the view.
+     */
+    pub fn set_position_top_percentage(&mut self, percentage: f32) {
+        self.flex_node.get_style_mut().position.top = Dimension::Percent(percentage);
+    }
+
+    /**
+     * Sets the right position of the view, in percents.
+     *
+     * The behavior of this attribute changes depending on the
+     * position type of the view.
+     */
+    pub fn set_position_right_percentage(&mut self, percentage: f32) {
+        self.flex_node.get_style_mut().position.right = Dimension::Percent(percentage);
+    }
+
+    /**
+     * Sets the bottom position of the view, in percents.
+     *
+     * The behavior of this attribute changes depending on the
+     * position type of the view.
+     */
+    pub fn set_position_bottom_percentage(&mut self, percentage: f32) {
+        self.flex_node.get_style_mut().position.bottom = Dimension::Percent(percentage);
+    }
+
+    /**
+     * Sets the left position of the view, in percents.
+     *
+     * The behavior of this attribute changes depending on the
+     * position type of the view.
+     */
+    pub fn set_position_left_percentage(&mut self, percentage: f32) {
+        self.flex_node.get_style_mut().position.left = Dimension::Percent(percentage);
+    }
+
+    /**
+     * Sets the id of the view.
+     */
+    pub fn set_id(&mut self, id: &str) {
+        self.id = id.into();
+    }
+
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
+    /**
+     * Returns the handle of the view in its tree, None if it was not inserted in a tree.
+     */
+    pub fn get_view_id(&self) -> Option<ViewId> {
+        self.view_id
+    }
+
+    pub(crate) fn set_view_id(&mut self, view_id: ViewId) {
+        self.view_id = Some(view_id);
+    }
+
     // // -----------------------------------------------------------
     // // Styling and view shape properties
     // // -----------------------------------------------------------
@@ -1331,41 +1430,41 @@ impl BaseView {
     // fn set_line_left(&mut self, thickness: f32) {
     //     self.line_left = thickness;
     // }
-    //
-    // /**
-    //  * Sets the view shape background color.
-    //  */
-    // fn set_background_color(&mut self, color: nanovg::Color) {
-    //     self.background_color = color;
-    //     self.set_background(ViewBackground::ShapeColor);
-    // }
-    //
-    // /**
-    //  * Sets the view shape border color.
-    //  */
-    // fn set_border_color(&mut self, color: nanovg::Color) {
-    //     self.border_color = color;
-    // }
-    //
-    // /**
-    //  * Sets the view shape border thickness.
-    //  */
-    // fn set_border_thickness(&mut self, thickness: f32) {
-    //     self.border_thickness = thickness;
-    // }
-    //
-    // fn get_border_thickness(&mut self) -> f32 {
-    //     self.border_thickness
-    // }
-    //
-    // /**
-    //  * Sets the view shape corner radius.
-    //  * 0 means no rounded corners.
-    //  */
-    // fn set_corner_radius(&mut self, radius: f32) {
-    //     self.corner_radius = radius;
-    // }
-    //
+
+    /**
+     * Sets the view shape background color.
+     */
+    pub fn set_background_color(&mut self, color: nanovg::Color) {
+        self.background_color = color;
+        self.set_background(ViewBackground::ShapeColor);
+    }
+
+    /**
+     * Sets the view shape border color.
+     */
+    pub fn set_border_color(&mut self, color: nanovg::Color) {
+        self.border_color = color;
+    }
+
+    /**
+     * Sets the view shape border thickness.
+     */
+    pub fn set_border_thickness(&mut self, thickness: f32) {
+        self.border_thickness = thickness;
+    }
+
+    pub fn get_border_thickness(&self) -> f32 {
+        self.border_thickness
+    }
+
+    /**
+     * Sets the view shape corner radius.
+     * 0 means no rounded corners.
+     */
+    pub fn set_corner_radius(&mut self, radius: f32) {
+        self.corner_radius = radius;
+    }
+
     // /**
     //  * Sets the view shape shadow type.
     //  * Default is NONE.
@@ -1434,21 +1533,24 @@ impl BaseView {
     // fn set_in_fade_animation(&mut self, translucent: bool) {
     //     self.in_fade_animation = translucent;
     // }
-    //
-    // /**
-    //  * Sets the view to be focusable.
-    //  *
-    //  * Required to be able to use actions that need
-    //  * focus on that view (such as an A press).
-    //  */
-    // fn set_focusable(&mut self, focusable: bool) {
-    //     self.focusable = focusable;
-    // }
-    //
-    // fn is_focusable(&self) -> bool {
-    //     self.focusable
-    // }
-    //
+
+    /**
+     * Sets the view to be focusable.
+     *
+     * Required to be able to use actions that need
+     * focus on that view (such as an A press).
+     */
+    pub fn set_focusable(&mut self, focusable: bool) {
+        self.focusable = focusable;
+    }
+
+    /**
+     * Returns true if the view can get the focus: focusable, visible and not collapsed.
+     */
+    pub fn is_focusable(&self) -> bool {
+        self.focusable && self.visibility == Visibility::Visible && !self.collapsed
+    }
+
     // /**
     //  * Sets the sound to play when this view gets focused.
     //  */
@@ -1459,33 +1561,68 @@ impl BaseView {
     // pub(crate) fn get_focus_sound(&mut self) -> audio::Sound {
     //     self.focus_sound.clone()
     // }
-    //
-    // /**
-    //  * Sets the detached flag to true.
-    //  * This action is irreversible.
-    //  *
-    //  * A detached view will, as the name suggests, not be
-    //  * attached to their parent Yoga node. That means that invalidation
-    //  * and layout need to be taken care of manually by the parent.
-    //  *
-    //  * detach() must be called before adding the view to the parent.
-    //  */
-    // fn detach(&mut self) {
-    //     self.detached = true;
-    // }
-    //
-    // fn is_detached(&self) -> bool {
-    //     self.detached
-    // }
-    //
-    // /**
-    //  * Sets the position of the view, if detached.
-    //  */
-    // fn set_detached_position(&mut self, x: f32, y: f32) {
-    //     self.detached_origin_x = x;
-    //     self.detached_origin_y = y;
-    // }
-    //
+
+    /**
+     * Sets the detached flag to true.
+     * This action is irreversible.
+     *
+     * A detached view will, as the name suggests, not be
+     * laid out by its parent: it gets the size of its content and
+     * is drawn on the overlay layer, above its siblings, at the
+     * position given by set_detached_position().
+     *
+     * detach() must be called before adding the view to the parent.
+     */
+    pub fn detach(&mut self) {
+        self.detached = true;
+        self.flex_node.set_detached(true);
+    }
+
+    pub fn is_detached(&self) -> bool {
+        self.detached
+    }
+
+    /**
+     * Sets the position of the view, if detached.
+     */
+    pub fn set_detached_position(&mut self, x: f32, y: f32) {
+        self.detached_origin_x = x;
+        self.detached_origin_y = y;
+    }
+
+    /**
+     * Returns the position of the view on the overlay layer, if detached.
+     */
+    pub fn get_detached_position(&self) -> (f32, f32) {
+        (self.detached_origin_x, self.detached_origin_y)
+    }
+
+    /**
+     * Returns true if the view is drawn on the given pass of the frame: detached views
+     * are drawn with their children on the overlay pass, the others on the content pass.
+     * The debug layer only has the wireframes.
+     */
+    pub fn is_drawn_on_layer(&self, layer: DrawLayer) -> bool {
+        match layer {
+            DrawLayer::Content => !self.detached,
+            DrawLayer::Overlay => self.detached,
+            DrawLayer::Debug => false,
+        }
+    }
+
+    /**
+     * Returns the layer the view is drawn with by its parent on the given pass of the frame,
+     * None if it is skipped: detached views are skipped on the content pass and drawn with
+     * their whole subtree on the overlay pass, with the content layer.
+     */
+    pub fn get_frame_layer(&self, pass: DrawLayer) -> Option<DrawLayer> {
+        match (pass, self.detached) {
+            (DrawLayer::Content, true) => None,
+            (DrawLayer::Overlay, true) => Some(DrawLayer::Content),
+            (pass, _) => Some(pass),
+        }
+    }
+
     // fn set_parent(&self, parent: &mut Box<BaseView>) {
     //     self.parent_userdata;
     // }
@@ -1504,66 +1641,49 @@ impl BaseView {
     //     // Placeholder; replace with your implementation
     // }
     //
-    // /**
-    //  * Registers an action with the given parameters. The listener will be fired when the user presses
-    //  * the key when the view is focused.
-    //  *
-    //  * The listener should return true if the action was consumed, false otherwise.
-    //  * The sound will only be played if the listener returned true.
-    //  *
-    //  * A hidden action will not show up in the bottom-right hints.
-    //  *
-    //  * Returns the identifier for the action, so it can be unregistered later on. Returns ACTION_NONE if the
-    //  * action was not registered.
-    //  */
-    // fn register_action(
-    //     &mut self,
-    //     hint_text: &str,
-    //     button: ControllerButton,
-    //     action_listener: ActionListener,
-    //     hidden: bool,
-    //     sound: Sound,
-    // ) -> ActionIdentifier {
-    //     let next_indentifier: ActionIdentifier = match self.actions.len() == 0  {
-    //         true => {1}
-    //         false => {
-    //             self.actions.last().unwrap().identifier + 1
-    //         }
-    //     };
-    //
-    //     let mut found = false;
-    //
-    //     for action in self.actions {
-    //         if action.identifier == next_indentifier {
-    //             found = true;
-    //             break;
-    //         }
-    //     }
-    //
-    //     if !found {
-    //         self.actions.push(crate::lib::core::actions::Action::new(
-    //             button,
-    //             next_indentifier,
-    //             hint_text,
-    //             true,
-    //             hidden,
-    //             sound,
-    //             action_listener,
-    //         ));
-    //     }
-    //
-    //     next_indentifier
-    // }
-    //
-    // /**
-    //  * Unregisters an action with the given identifier.
-    //  */
-    // fn unregister_action(&mut self, identifier: ActionIdentifier) {
-    //     self.actions.retain(|action| {
-    //         action.identifier != identifier
-    //     });
-    // }
-    //
+    /**
+     * Registers an action with the given parameters. The listener will be fired when the user presses
+     * the key when the view is focused.
+     *
+     * The listener should return true if the action was consumed, false otherwise.
+     * The sound will only be played if the listener returned true.
+     *
+     * A hidden action will not show up in the bottom-right hints.
+     *
+     * Returns the identifier for the action, so it can be unregistered later on.
+     */
+    pub fn register_action(
+        &mut self,
+        hint_text: &str,
+        button: ControllerButton,
+        action_listener: ActionListener,
+        hidden: bool,
+        sound: Sound,
+    ) -> ActionIdentifier {
+        let identifier = self.actions.last().map_or(1, |action| action.identifier + 1);
+
+        self.actions.push(Action::new(
+            button,
+            identifier,
+            hint_text,
+            true,
+            hidden,
+            sound,
+            action_listener,
+        ));
+
+        identifier
+    }
+
+    /**
+     * Unregisters an action with the given identifier.
+     */
+    pub fn unregister_action(&mut self, identifier: ActionIdentifier) {
+        self.actions.retain(|action| {
+            action.identifier != identifier
+        });
+    }
+
     // // Shortcut to register a generic "A OK" click action
     // fn register_click_action(&mut self, action_listener: ActionListener) {
     //     self.register_action("brls/hints/ok", ControllerButton::ButtonA, action_listener, false, Sound::SoundClick);
@@ -1590,24 +1710,35 @@ impl BaseView {
     // fn play_click_animation(&mut self, reverse: bool) {
     //     self.reset_click_animation();
     // }
-    //
-    // fn get_yg_node(&self) -> &Node {
-    //     &self.yg_node
-    // }
-    //
-    // pub(crate) fn get_actions(&self) -> &Vec<crate::lib::core::actions::Action> {
-    //     &self.actions
-    // }
-    //
-    // /**
-    //  * Called each frame
-    //  * Do not override it to draw your view,
-    //  * override draw() instead
-    //  */
-    // pub(crate) fn frame(&self, ctx: &FrameContext) {
-    //     // Placeholder; replace with your implementation
-    // }
-    //
+
+    pub(crate) fn get_actions(&self) -> &Vec<Action> {
+        &self.actions
+    }
+
+    /**
+     * Called each frame
+     * Draws the background and the border of the view, then its content with the given function
+     * (see View::draw()), all with the theme override of the view applied.
+     */
+    pub(crate) fn frame<F: FnOnce(&FrameContext)>(&self, ctx: &FrameContext, draw: F) {
+        if ctx.layer == DrawLayer::Debug {
+            self.draw_debug(ctx, true);
+            return;
+        }
+
+        // Detached views are given the content layer on the overlay pass, see get_frame_layer()
+        if ctx.layer != DrawLayer::Content || self.visibility != Visibility::Visible {
+            return;
+        }
+
+        self.with_frame_context(ctx, |ctx| {
+            ctx.count_drawn_view();
+            self.draw_background(ctx);
+            draw(ctx);
+            self.draw_border(ctx);
+        });
+    }
+
     // /**
     //  * Called by frame() to draw the view onscreen.
     //  * Views should not draw outside of their bounds (they
@@ -1669,31 +1800,105 @@ impl BaseView {
     //     // Placeholder; replace with your implementation
     //     0.0
     // }
-    //
-    // /**
-    //  * Hides the view in a collapse animation
-    //  */
-    // fn collapse(&self, animated: bool) {
-    //     // Placeholder; replace with your implementation
-    // }
-    //
-    // fn is_collapsed(&self) -> bool {
-    //     // Placeholder; replace with your implementation
-    //     false
-    // }
-    //
+
+    /**
+     * Hides the view in a collapse animation: its height goes down to 0,
+     * the views after it moving up at each frame. The focus leaves collapsed views.
+     *
+     * A view without fixed height collapsed before its first layout has no height to animate,
+     * it is collapsed and later expanded without animation.
+     */
+    pub fn collapse(&mut self, animated: bool) {
+        if self.collapsed {
+            return;
+        }
+
+        if self.collapse_style.is_none() {
+            let style = self.flex_node.get_style();
+            self.collapse_style = Some((style.height, style.min_height, style.max_height));
+            self.collapse_full_height = match style.height {
+                Dimension::Points(height) => Some(height),
+                _ if self.flex_node.get_layout().height > 0.0 => Some(self.get_height()),
+                _ => None,
+            };
+        }
+
+        self.collapsed = true;
+        self.animate_collapse(0.0, animated && self.collapse_full_height.is_some());
+    }
+
+    pub fn is_collapsed(&self) -> bool {
+        self.collapsed
+    }
+
     // fn set_alpha(&self, alpha: f32) {
     //     // Placeholder; replace with your implementation
     // }
-    //
-    // /**
-    //  * Shows the view in a expand animation (opposite
-    //  * of collapse)
-    //  */
-    // fn expand(&self, animated: bool) {
-    //     // Placeholder; replace with your implementation
-    // }
-    //
+
+    /**
+     * Shows the view in a expand animation (opposite
+     * of collapse)
+     */
+    pub fn expand(&mut self, animated: bool) {
+        if !self.collapsed {
+            return;
+        }
+
+        self.collapsed = false;
+        self.animate_collapse(1.0, animated && self.collapse_full_height.is_some());
+    }
+
+    fn animate_collapse(&mut self, target: f32, animated: bool) {
+        let value = self.collapse_state.get_value();
+
+        if animated {
+            self.collapse_state.reset_with_value(value);
+            self.collapse_state.add_step(target, COLLAPSE_ANIMATION_DURATION, EasingFunction::QuadraticOut);
+            self.collapse_state.start_self_updated();
+        } else {
+            self.collapse_state.reset_with_value(target);
+        }
+
+        self.apply_collapse();
+    }
+
+    /**
+     * Sizes the view after the collapse animation, and gives it its
+     * own height back once fully expanded.
+     */
+    fn apply_collapse(&mut self) {
+        let (height, min_height, max_height) = match self.collapse_style {
+            Some(collapse_style) => collapse_style,
+            None => return,
+        };
+
+        let expanded = !self.collapsed && !self.collapse_state.is_running();
+        let collapse_height = Dimension::Points(self.get_height_include_collapse());
+        let style = self.flex_node.get_style_mut();
+
+        if expanded {
+            style.height = height;
+            style.min_height = min_height;
+            style.max_height = max_height;
+            self.collapse_style = None;
+        } else {
+            style.height = collapse_height;
+            style.min_height = collapse_height;
+            style.max_height = collapse_height;
+        }
+    }
+
+    /**
+     * Advances the animations of the view by the given delta in ms.
+     * Called every frame by the parent of the view, before the layout pass.
+     */
+    pub fn update(&mut self, delta: Timestamp) {
+        if self.collapse_state.is_running() {
+            self.collapse_state.update(delta);
+            self.apply_collapse();
+        }
+    }
+
     // /**
     //  * Hides the view with a fade out animation.
     //  */
@@ -1724,12 +1929,11 @@ impl BaseView {
     //     // Placeholder; replace with your implementation
     //     false
     // }
-    //
-    // fn is_focused(&self) -> bool {
-    //     // Placeholder; replace with your implementation
-    //     false
-    // }
-    //
+
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
     // /**
     //  * Returns the default view to focus when focusing this view
     //  * Typically the view itself or one of its children.
@@ -1801,21 +2005,21 @@ impl BaseView {
     //     // Placeholder; replace with your implementation
     //     None
     // }
-    //
-    // /**
-    //  * Fired when focus is gained.
-    //  */
-    // fn on_focus_gained(&self) {
-    //     // Placeholder; replace with your implementation
-    // }
-    //
-    // /**
-    //  * Fired when focus is lost.
-    //  */
-    // fn on_focus_lost(&self) {
-    //     // Placeholder; replace with your implementation
-    // }
-    //
+
+    /**
+     * Fired when focus is gained, see View::on_focus_gained().
+     */
+    pub fn on_focus_gained(&mut self) {
+        self.focused = true;
+    }
+
+    /**
+     * Fired when focus is lost, see View::on_focus_lost().
+     */
+    pub fn on_focus_lost(&mut self) {
+        self.focused = false;
+    }
+
     // // Fired when focus is gained on this view's parent, or the parent of the parent...
     // fn on_parent_focus_gained(&mut self, focused_view: &BaseView) {
     //     // Implement based on your requirements
@@ -1841,22 +2045,37 @@ impl BaseView {
     // fn get_alpha(&self) -> f32 {
     //     self.alpha.get_value()
     // }
-    //
-    // // Forces this view and its children to use the specified theme
-    // fn override_theme(&mut self, new_theme: Rc<RefCell<Theme>>) {
-    //     // Implement based on your requirements
-    // }
-    //
-    // // Enable/disable culling for that view
-    // fn set_culled(&mut self, culled: bool) {
-    //     self.culled = culled;
-    // }
-    //
-    // // Check if view is culled
-    // fn is_culled(&self) -> bool {
-    //     self.culled
-    // }
-    //
+
+    // Enable/disable culling for that view
+    pub fn set_culled(&mut self, culled: bool) {
+        self.culled = culled;
+
+        if !culled {
+            self.in_viewport = true;
+        }
+    }
+
+    // Check if view is culled
+    pub fn is_culled(&self) -> bool {
+        self.culled
+    }
+
+    /**
+     * Called by the parent when the view enters or leaves its viewport,
+     * ignored if culling is disabled for the view.
+     */
+    pub(crate) fn set_in_viewport(&mut self, in_viewport: bool) {
+        self.in_viewport = in_viewport || !self.culled;
+    }
+
+    /**
+     * Returns false if the view is out of the viewport of its parent. It is then not drawn,
+     * and its tickings (label scrolling, spinners...) should be paused until it comes back.
+     */
+    pub fn is_in_viewport(&self) -> bool {
+        self.in_viewport
+    }
+
     // // Set the Y translation of this view
     // fn set_translation_y(&mut self, translate_y: f32) {
     //     self.translation_y = translate_y;
@@ -1866,17 +2085,17 @@ impl BaseView {
     // fn set_translation_x(&mut self, translate_x: f32) {
     //     self.translation_x = translate_x;
     // }
-    //
-    // // Enable/disable wireframe mode
-    // fn set_wireframe_enabled(&mut self, wireframe: bool) {
-    //     self.wireframe_enabled = wireframe;
-    // }
-    //
-    // // Check if wireframe mode is enabled
-    // fn is_wireframe_enabled(&self) -> bool {
-    //     self.wireframe_enabled
-    // }
-    //
+
+    // Enable/disable wireframe mode, whatever the global wireframe mode
+    pub fn set_wireframe_enabled(&mut self, wireframe: bool) {
+        self.wireframe_enabled = wireframe;
+    }
+
+    // Check if wireframe mode is enabled
+    pub fn is_wireframe_enabled(&self) -> bool {
+        self.wireframe_enabled
+    }
+
     // // Resolves the value of the given XML attribute string
     // fn get_string_xml_attribute_value(value: &str) -> String {
     //     // Implement based on your requirements
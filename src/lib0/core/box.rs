@@ -1,9 +1,11 @@
-use std::cell::RefCell;
-use std::rc::Rc;
-use crate::lib::core::base_view::{BaseView, FocusDirection};
-use crate::lib::core::frame_context::FrameContext;
+use std::mem::take;
+use crate::lib::core::base_view::{BaseView, FocusDirection, Visibility};
+use crate::lib::core::flex::{Edges, FlexLayout, FlexNode, FlexWrap};
+use crate::lib::core::frame_context::{DrawLayer, FrameContext};
 use crate::lib::core::view::View;
+use crate::lib::core::view_tree::{ViewId, ViewTree};
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum JustifyContent
 {
     FlexStart,
@@ -14,6 +16,7 @@ pub enum JustifyContent
     SpaceEvenly,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum AlignItems
 {
     Auto,
@@ -26,12 +29,14 @@ pub enum AlignItems
     SpaceAround,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Axis
 {
     Row,
     Column,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Direction
 {
     Inherit,
@@ -40,94 +45,605 @@ pub enum Direction
 }
 
 // Generic FlexBox layout
+//
+// The children are the children of the box in its view tree.
 pub struct BoxView {
     view: BaseView,
     axis: Axis,
-    children: Vec<Box<BaseView>>
+    laid_out_size: Option<(f32, f32)>, // size given to the last layout, when the box is the root
 }
 
 impl BoxView {
-    pub(crate) fn create() -> Rc<RefCell<Box<dyn View>>> {
-        todo!()
+    pub fn new(axis: Axis) -> Self {
+        let mut view = BaseView::new();
+        view.get_flex_node_mut().get_style_mut().axis = axis;
+
+        Self {
+            view,
+            axis,
+            laid_out_size: None,
+        }
+    }
+
+    pub(crate) fn create() -> Box<dyn View> {
+        Box::new(BoxView::new(Axis::Row))
+    }
+
+    pub fn set_axis(&mut self, axis: Axis) {
+        self.axis = axis;
+        self.view.get_flex_node_mut().get_style_mut().axis = axis;
+    }
+
+    pub fn get_axis(&self) -> Axis {
+        self.axis
+    }
+
+    /**
+     * Sets the padding of the box, aka the internal space between its boundaries and its children.
+     */
+    pub fn set_padding(&mut self, top: f32, right: f32, bottom: f32, left: f32) {
+        self.view.get_flex_node_mut().get_style_mut().padding = Edges {
+            top,
+            right,
+            bottom,
+            left,
+        };
+    }
+
+    pub fn set_justify_content(&mut self, justify: JustifyContent) {
+        self.view.get_flex_node_mut().get_style_mut().justify_content = justify;
+    }
+
+    pub fn set_align_items(&mut self, align: AlignItems) {
+        self.view.get_flex_node_mut().get_style_mut().align_items = align;
+    }
+
+    /**
+     * Sets how wrapped lines are distributed in the cross axis when wrapping is enabled.
+     */
+    pub fn set_align_content(&mut self, align: AlignItems) {
+        self.view.get_flex_node_mut().get_style_mut().align_content = align;
+    }
+
+    /**
+     * Allows the children to go on a new line when the box axis is full.
+     */
+    pub fn set_wrap(&mut self, wrap: bool) {
+        self.view.get_flex_node_mut().get_style_mut().wrap = match wrap {
+            true => FlexWrap::Wrap,
+            false => FlexWrap::NoWrap,
+        };
+    }
+
+    /**
+     * Sets the space between the children: the row gap separates rows
+     * (children of a column box or wrapped lines of a row box), the column gap columns.
+     */
+    pub fn set_gaps(&mut self, row_gap: f32, column_gap: f32) {
+        let style = self.view.get_flex_node_mut().get_style_mut();
+        style.row_gap = row_gap;
+        style.column_gap = column_gap;
+    }
+
+    /**
+     * Returns the frame of the box computed by the last layout, relative to its parent.
+     */
+    pub fn get_layout(&self) -> FlexLayout {
+        *self.view.get_flex_node().get_layout()
+    }
+
+    fn get_children<'a>(&self, tree: &'a ViewTree) -> &'a [ViewId] {
+        match self.view.get_view_id() {
+            Some(id) => tree.get_children(id),
+            None => &[],
+        }
     }
 }
 
 impl View for BoxView {
+    /**
+     * Draws the box, then its children with the theme override of the box applied.
+     */
     fn frame(&self, ctx: &FrameContext) {
-        todo!()
+        // Invisible and gone boxes hide their children with them
+        if self.view.get_visibility() != Visibility::Visible {
+            return;
+        }
+
+        match ctx.layer {
+            DrawLayer::Debug => self.view.draw_debug(ctx, self.get_children(ctx.tree).is_empty()),
+            _ => self.view.frame(ctx, |_| {}),
+        }
+
+        self.view.with_frame_context(ctx, |ctx| frame_children(ctx, self.get_children(ctx.tree)));
+    }
+
+    /**
+     * Moves the focus to the next child with a default focus along the axis of the box.
+     */
+    fn get_next_focus(&self, tree: &ViewTree, direction: FocusDirection, current: ViewId) -> Option<ViewId> {
+        let direction = self.view.get_layout_focus_direction(direction);
+        let offset: isize = match (self.axis, direction) {
+            (Axis::Row, FocusDirection::Right) | (Axis::Column, FocusDirection::Down) => 1,
+            (Axis::Row, FocusDirection::Left) | (Axis::Column, FocusDirection::Up) => -1,
+            _ => return None,
+        };
+
+        let children = self.get_children(tree);
+        let mut index = children.iter().position(|child| *child == current)? as isize + offset;
+
+        while index >= 0 && (index as usize) < children.len() {
+            if let Some(focus) = tree.get(children[index as usize]).and_then(|child| child.get_default_focus(tree)) {
+                return Some(focus);
+            }
+
+            index += offset;
+        }
+
+        None
     }
 
-    fn get_default_focus(&self) -> Box<dyn View> {
-        todo!()
+    fn to_json(&self, tree: &ViewTree) -> serde_json::Value {
+        let mut json = self.view.to_json("brls:Box");
+        json["axis"] = format!("{:?}", self.axis).into();
+        json["children"] = self.get_children(tree).iter()
+            .filter_map(|child| tree.get(*child))
+            .map(|child| child.to_json(tree))
+            .collect();
+        json
     }
 
-    fn get_next_focus(&self, direction: FocusDirection, current_view: &dyn View) -> Box<dyn View> {
-        todo!()
+    /**
+     * Lays out the box in the given size, then culls the children outside of it.
+     * Nothing is done if the box was already laid out in this size and no node changed since.
+     */
+    fn layout(&mut self, tree: &mut ViewTree, width: f32, height: f32) {
+        if self.laid_out_size == Some((width, height)) && !self.is_layout_dirty(tree) {
+            return;
+        }
+
+        self.calculate_layout(tree, Some(width), Some(height));
+        self.laid_out_size = Some((width, height));
+
+        if let Some(id) = self.view.get_view_id() {
+            tree.cull_children(id, 0.0, 0.0, width, height);
+        }
     }
 
-    fn on_focus_lost(&self) {
-        todo!()
+    fn get_base_view(&self) -> &BaseView {
+        &self.view
     }
 
-    fn on_focus_gained(&self) {
-        todo!()
+    fn get_base_view_mut(&mut self) -> &mut BaseView {
+        &mut self.view
     }
 
-    fn describe(&self) -> String {
-        todo!()
+    fn is_layout_dirty(&self, tree: &ViewTree) -> bool {
+        self.view.get_flex_node().is_dirty()
+            || self.get_children(tree).iter().any(|child| tree.get(*child).is_some_and(|child| child.is_layout_dirty(tree)))
     }
 
-    fn get_view(&self, id: &str) -> Rc<RefCell<Option<Box<dyn View>>>> {
-        todo!()
+    /**
+     * Lends the node of the box with the nodes of its children attached,
+     * the box is then laid out with its parent in a single pass. The nodes of the children,
+     * and of their own children for nested boxes, are lent for the duration of the layout:
+     * only the dirty branches are laid out again.
+     */
+    fn lend_flex_node(&mut self, tree: &mut ViewTree) -> FlexNode {
+        let mut nodes: Vec<FlexNode> = self.get_children(tree).to_vec().into_iter()
+            .filter_map(|child| tree.with_view_mut(child, |child, tree| child.lend_flex_node(tree)))
+            .collect();
+
+        let mut node = take(self.view.get_flex_node_mut());
+        node.swap_children(&mut nodes);
+        node
     }
 
-    fn get_parent(&self) -> Rc<RefCell<Option<Box<dyn View>>>> {
-        todo!()
+    fn give_back_flex_node(&mut self, tree: &mut ViewTree, mut node: FlexNode) {
+        let mut nodes = vec![];
+        node.swap_children(&mut nodes);
+
+        for (child, node) in self.get_children(tree).to_vec().into_iter().zip(nodes) {
+            tree.with_view_mut(child, |child, tree| child.give_back_flex_node(tree, node));
+        }
+
+        *self.view.get_flex_node_mut() = node;
+
+        if self.view.get_flex_node().has_new_layout() {
+            self.view.on_layout();
+        }
     }
 }
 
+/**
+ * Draws the given children of a container in its viewport, skipping the culled ones.
+ * Children are drawn at their position in the container, detached ones at their detached position
+ * with the layer given by BaseView::get_frame_layer().
+ */
+pub(crate) fn frame_children(ctx: &FrameContext, children: &[ViewId]) {
+    let (drawn, culled) = get_drawn_children(ctx.tree, children, ctx.layer);
+
+    for _ in 0..culled {
+        ctx.count_culled_view();
+    }
+
+    for child in drawn.into_iter().filter_map(|child| ctx.tree.get(child)) {
+        let base = child.get_base_view();
+        let layer_context;
+        let child_context = match base.get_frame_layer(ctx.layer) {
+            Some(layer) if layer != ctx.layer => {
+                layer_context = ctx.with_layer(layer);
+                &layer_context
+            }
+            _ => ctx,
+        };
+        let (x, y) = match base.is_detached() {
+            true => base.get_detached_position(),
+            false => (base.get_flex_node().get_layout().x, base.get_flex_node().get_layout().y),
+        };
+
+        unsafe {
+            let vg = ctx.vg.borrow();
+            nanovg_sys::nvgSave(vg.raw());
+            nanovg_sys::nvgTranslate(vg.raw(), x, y);
+        }
+
+        child.frame(child_context);
+
+        unsafe {
+            nanovg_sys::nvgRestore(ctx.vg.borrow().raw());
+        }
+    }
+}
+
+/**
+ * Returns the visible children in the viewport of their container drawn on the given pass of the frame
+ * (see BaseView::get_frame_layer()), with the number of children culled out of it
+ * that would have been drawn on it.
+ */
+pub(crate) fn get_drawn_children(tree: &ViewTree, children: &[ViewId], layer: DrawLayer) -> (Vec<ViewId>, usize) {
+    let mut drawn = vec![];
+    let mut culled = 0;
+
+    for child in children {
+        let base = match tree.get(*child) {
+            Some(view) => view.get_base_view(),
+            None => continue,
+        };
+
+        if base.get_frame_layer(layer).is_none() || base.get_visibility() != Visibility::Visible {
+            continue;
+        }
+
+        if base.is_in_viewport() {
+            drawn.push(*child);
+        } else if base.is_drawn_on_layer(layer) {
+            culled += 1;
+        }
+    }
+
+    (drawn, culled)
+}
+
 // An empty view that has auto x auto and grow=1.0 to push
 // all the next views in its box to the right (or to the bottom)
 pub struct Padding {
     view: BaseView,
 }
 
+impl Default for Padding {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Padding {
-    pub(crate) fn create() -> Rc<RefCell<Box<dyn View>>> {
-        todo!()
+    pub fn new() -> Self {
+        let mut view = BaseView::new();
+        view.set_grow(1.0);
+
+        Self {
+            view,
+        }
+    }
+
+    pub(crate) fn create() -> Box<dyn View> {
+        Box::new(Padding::new())
     }
 }
 
 impl View for Padding {
-    fn frame(&self, ctx: &FrameContext) {
-        todo!()
+    fn get_base_view(&self) -> &BaseView {
+        &self.view
+    }
+
+    fn get_base_view_mut(&mut self) -> &mut BaseView {
+        &mut self.view
     }
+}
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use nanovg::Color;
+    use crate::lib::core::theme::{colors, Theme, ThemeValues};
+    use crate::lib::views::label::Label;
+    use super::*;
 
-    fn get_default_focus(&self) -> Box<dyn View> {
-        todo!()
+    // Counters of a row box holding a measured leaf
+    struct Branch {
+        id: ViewId,
+        measures: Rc<Cell<i32>>,
+        layouts: Rc<Cell<i32>>,
     }
 
-    fn get_next_focus(&self, direction: FocusDirection, current_view: &dyn View) -> Box<dyn View> {
-        todo!()
+    fn add_branch(tree: &mut ViewTree, root: ViewId) -> Branch {
+        let measures = Rc::new(Cell::new(0));
+        let layouts = Rc::new(Cell::new(0));
+
+        let mut leaf = Padding::new();
+        let counter = Rc::clone(&measures);
+        leaf.get_base_view_mut().get_flex_node_mut().set_measure_func(Some(Box::new(move |_, _| {
+            counter.set(counter.get() + 1);
+            (50.0, 20.0)
+        })));
+
+        let mut branch = BoxView::new(Axis::Row);
+        let counter = Rc::clone(&layouts);
+        branch.get_base_view_mut().get_layout_event().subscribe(move |_| counter.set(counter.get() + 1));
+
+        let id = tree.add_view(root, Box::new(branch));
+        tree.add_view(id, Box::new(leaf));
+        Branch {
+            id,
+            measures,
+            layouts,
+        }
     }
 
-    fn on_focus_lost(&self) {
-        todo!()
+    fn reset(branches: &[Branch]) {
+        for branch in branches {
+            branch.measures.set(0);
+            branch.layouts.set(0);
+        }
     }
 
-    fn on_focus_gained(&self) {
-        todo!()
+    fn get_layout(tree: &ViewTree, id: ViewId) -> FlexLayout {
+        *tree.get(id).unwrap().get_base_view().get_flex_node().get_layout()
     }
 
-    fn describe(&self) -> String {
-        todo!()
+    // The views drawing themselves on the given pass of the frame, going down the tree like frame_children()
+    fn get_drawn_views(tree: &ViewTree, id: ViewId, layer: DrawLayer, drawn: &mut Vec<ViewId>) {
+        if layer == DrawLayer::Content {
+            drawn.push(id);
+        }
+
+        for child in get_drawn_children(tree, tree.get_children(id), layer).0 {
+            let layer = tree.get(child).unwrap().get_base_view().get_frame_layer(layer).unwrap();
+            get_drawn_views(tree, child, layer, drawn);
+        }
     }
 
-    fn get_view(&self, id: &str) -> Rc<RefCell<Option<Box<dyn View>>>> {
-        todo!()
+    #[test]
+    fn relays_out_only_the_changed_branch() {
+        let mut tree = ViewTree::new();
+        let root = tree.insert(Box::new(BoxView::new(Axis::Column)));
+        let branches = [add_branch(&mut tree, root), add_branch(&mut tree, root)];
+
+        tree.layout(root, 400.0, 300.0);
+        assert!(branches.iter().all(|branch| branch.measures.get() > 0 && branch.layouts.get() == 1));
+        assert_eq!(get_layout(&tree, branches[1].id).y, 20.0);
+
+        // Nothing changed
+        reset(&branches);
+        assert!(!tree.get(root).unwrap().is_layout_dirty(&tree));
+        tree.layout(root, 400.0, 300.0);
+        assert!(branches.iter().all(|branch| branch.measures.get() == 0 && branch.layouts.get() == 0));
+
+        // Only the changed branch is measured and laid out again
+        tree.get_mut(branches[1].id).unwrap().get_base_view_mut().set_height(40.0);
+        assert!(tree.get(root).unwrap().is_layout_dirty(&tree));
+        tree.layout(root, 400.0, 300.0);
+        assert_eq!(branches[0].measures.get(), 0);
+        assert_eq!(branches[0].layouts.get(), 0);
+        assert_eq!(branches[1].layouts.get(), 1);
+        assert_eq!(get_layout(&tree, branches[1].id).height, 40.0);
+        assert!(!tree.get(root).unwrap().get_base_view().get_flex_node().is_dirty());
+    }
+
+    #[test]
+    fn nested_boxes_are_laid_out_in_one_pass() {
+        let mut tree = ViewTree::new();
+        let root = tree.insert(Box::new(BoxView::new(Axis::Column)));
+
+        let mut inner = BoxView::new(Axis::Row);
+        inner.set_padding(5.0, 5.0, 5.0, 5.0);
+        let inner = tree.add_view(root, Box::new(inner));
+
+        let mut leaf = Padding::new();
+        leaf.get_base_view_mut().set_height(30.0);
+        tree.add_view(inner, Box::new(leaf));
+
+        tree.layout(root, 200.0, 100.0);
+
+        // The inner box is sized after its own child
+        let inner = get_layout(&tree, inner);
+        assert_eq!(inner.width, 200.0);
+        assert_eq!(inner.height, 40.0);
+    }
+
+    #[test]
+    fn detached_views_are_drawn_with_their_subtree_on_the_overlay() {
+        let mut tree = ViewTree::new();
+        let root = tree.insert(Box::new(BoxView::new(Axis::Column)));
+        let new_label = || {
+            let mut label = Label::new();
+            label.set_text("Label");
+            label.get_base_view_mut().set_height(20.0);
+            Box::new(label)
+        };
+        let label = tree.add_view(root, new_label());
+
+        let mut popup = BoxView::new(Axis::Column);
+        popup.get_base_view_mut().detach();
+        popup.get_base_view_mut().set_detached_position(50.0, 50.0);
+        let popup = tree.add_view(root, Box::new(popup));
+        let popup_label = tree.add_view(popup, new_label());
+
+        tree.layout(root, 400.0, 300.0);
+
+        let drawn = |layer: DrawLayer| {
+            let mut drawn = vec![];
+            get_drawn_views(&tree, root, layer, &mut drawn);
+            drawn
+        };
+        assert_eq!(drawn(DrawLayer::Content), vec![root, label]);
+        assert_eq!(drawn(DrawLayer::Overlay), vec![popup, popup_label]);
     }
 
-    fn get_parent(&self) -> Rc<RefCell<Option<Box<dyn View>>>> {
-        todo!()
+    #[test]
+    fn hidden_boxes_are_not_drawn_with_their_children() {
+        let mut tree = ViewTree::new();
+        let root = tree.insert(Box::new(BoxView::new(Axis::Column)));
+        let boxes: Vec<ViewId> = [Visibility::Visible, Visibility::Invisible, Visibility::Gone].into_iter()
+            .map(|visibility| {
+                let mut view = BoxView::new(Axis::Row);
+                view.get_base_view_mut().set_visibility(visibility);
+                let id = tree.add_view(root, Box::new(view));
+
+                let mut leaf = Padding::new();
+                leaf.get_base_view_mut().set_height(20.0);
+                tree.add_view(id, Box::new(leaf));
+                id
+            })
+            .collect();
+
+        tree.layout(root, 400.0, 300.0);
+
+        let leaf = tree.get_children(boxes[0])[0];
+        for layer in [DrawLayer::Content, DrawLayer::Debug] {
+            assert_eq!(get_drawn_children(&tree, tree.get_children(root), layer), (vec![boxes[0]], 0));
+        }
+
+        let mut drawn = vec![];
+        get_drawn_views(&tree, root, DrawLayer::Content, &mut drawn);
+        assert_eq!(drawn, vec![root, boxes[0], leaf]);
+
+        // The invisible box keeps its space, the gone one doesn't
+        assert_eq!(get_layout(&tree, boxes[1]).height, 20.0);
+        assert_eq!(get_layout(&tree, boxes[2]).height, 0.0);
+    }
+
+    #[test]
+    fn moves_the_focus_along_the_axis() {
+        let mut tree = ViewTree::new();
+        let root = tree.insert(Box::new(BoxView::new(Axis::Row)));
+        let children: Vec<ViewId> = (0..3)
+            .map(|_| tree.add_view(root, Box::new(Padding::new())))
+            .collect();
+
+        for child in [children[0], children[2]] {
+            tree.get_mut(child).unwrap().get_base_view_mut().set_focusable(true);
+        }
+
+        let root_view = tree.get(root).unwrap();
+        assert_eq!(root_view.get_default_focus(&tree), Some(children[0]));
+
+        // The unfocusable child is skipped, the box is left at its ends and on the other axis
+        assert_eq!(root_view.get_next_focus(&tree, FocusDirection::Right, children[0]), Some(children[2]));
+        assert_eq!(root_view.get_next_focus(&tree, FocusDirection::Right, children[2]), None);
+        assert_eq!(root_view.get_next_focus(&tree, FocusDirection::Left, children[2]), Some(children[0]));
+        assert_eq!(root_view.get_next_focus(&tree, FocusDirection::Down, children[0]), None);
+
+        // The focus state read by the wireframes follows the focus callbacks
+        tree.get_mut(children[2]).unwrap().on_focus_gained();
+        assert!(tree.get(children[2]).unwrap().get_base_view().is_focused());
+        tree.get_mut(children[2]).unwrap().on_focus_lost();
+        assert!(!tree.get(children[2]).unwrap().get_base_view().is_focused());
+    }
+
+    #[test]
+    fn collapsed_views_move_their_siblings_over_time() {
+        let mut tree = ViewTree::new();
+        let root = tree.insert(Box::new(BoxView::new(Axis::Column)));
+        let children: Vec<ViewId> = (0..3)
+            .map(|_| {
+                let mut child = Padding::new();
+                child.get_base_view_mut().set_grow(0.0);
+                child.get_base_view_mut().set_height(100.0);
+                tree.add_view(root, Box::new(child))
+            })
+            .collect();
+
+        // Collapsed before the first layout, the fixed height is still known
+        tree.get_mut(children[1]).unwrap().get_base_view_mut().collapse(false);
+        tree.layout(root, 400.0, 1000.0);
+        assert_eq!(get_layout(&tree, children[1]).height, 0.0);
+        assert_eq!(get_layout(&tree, children[2]).y, 100.0);
+
+        tree.get_mut(children[1]).unwrap().get_base_view_mut().expand(true);
+        let mut height = 0.0;
+        for _ in 0..3 {
+            tree.update(root, 50);
+            tree.layout(root, 400.0, 1000.0);
+            let middle = get_layout(&tree, children[1]).height;
+            assert!(middle > height && middle < 100.0);
+            assert_eq!(get_layout(&tree, children[2]).y, 100.0 + middle);
+            height = middle;
+        }
+
+        tree.update(root, 50);
+        tree.layout(root, 400.0, 1000.0);
+        assert_eq!(get_layout(&tree, children[1]).height, 100.0);
+        assert_eq!(get_layout(&tree, children[2]).y, 200.0);
+
+        // And back down
+        tree.get_mut(children[1]).unwrap().get_base_view_mut().collapse(true);
+        tree.update(root, 100);
+        tree.layout(root, 400.0, 1000.0);
+        let middle = get_layout(&tree, children[1]).height;
+        assert!(middle > 0.0 && middle < 100.0);
+        assert_eq!(get_layout(&tree, children[2]).y, 100.0 + middle);
+
+        tree.update(root, 100);
+        tree.layout(root, 400.0, 1000.0);
+        assert_eq!(get_layout(&tree, children[1]).height, 0.0);
+        assert_eq!(get_layout(&tree, children[2]).y, 100.0);
+        assert!(!tree.get(children[1]).unwrap().get_base_view().is_focusable());
+    }
+
+    #[test]
+    fn children_draw_with_the_merged_theme_overrides() {
+        let theme = Theme::new(ThemeValues::new(vec![
+            (colors::BACKGROUND.name(), Color::from_rgb(255, 255, 255)),
+            (colors::TEXT.name(), Color::from_rgb(0, 0, 0)),
+            (colors::BACKDROP.name(), Color::from_rgb(0, 0, 0)),
+        ]));
+
+        let mut root = BoxView::new(Axis::Column);
+        root.get_base_view_mut().override_theme_color(colors::BACKGROUND.name(), Color::from_rgb(0, 0, 255));
+        root.get_base_view_mut().override_theme_color(colors::TEXT.name(), Color::from_rgb(0, 255, 0));
+
+        let mut leaf = Padding::new();
+        leaf.get_base_view_mut().override_theme_color(colors::TEXT.name(), Color::from_rgb(255, 0, 0));
+
+        let mut tree = ViewTree::new();
+        let root = tree.insert(Box::new(root));
+        let leaf = tree.add_view(root, Box::new(leaf));
+        let other = tree.add_view(root, Box::new(Padding::new()));
+
+        let root_theme = tree.get(root).unwrap().get_base_view().resolve_theme(&theme);
+        let leaf_theme = tree.get(leaf).unwrap().get_base_view().resolve_theme(&root_theme);
+
+        // Inherited from the box, overridden by the leaf, untouched
+        assert_eq!(leaf_theme.get(colors::BACKGROUND).blue(), 1.0);
+        assert_eq!(leaf_theme.get(colors::TEXT).red(), 1.0);
+        assert_eq!(leaf_theme.get(colors::TEXT).green(), 0.0);
+        assert_eq!(leaf_theme.get(colors::BACKDROP).red(), 0.0);
+
+        // Views without override draw with the theme of their parent as is
+        assert!(matches!(tree.get(other).unwrap().get_base_view().resolve_theme(&root_theme), Cow::Borrowed(_)));
+        assert_eq!(theme.get(colors::TEXT).green(), 0.0);
     }
 }
\ No newline at end of file
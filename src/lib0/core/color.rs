@@ -0,0 +1,354 @@
+use log::warn;
+use nanovg::Color;
+use crate::lib::core::theme::{colors, ColorKey, Theme};
+
+// Minimum contrast ratios of the WCAG 2 success criteria
+pub const WCAG_AA_CONTRAST: f32 = 4.5;
+pub const WCAG_AA_LARGE_TEXT_CONTRAST: f32 = 3.0;
+pub const WCAG_AAA_CONTRAST: f32 = 7.0;
+
+// Text / background pairs of the built-in theme colors checked by audit_theme(),
+// with the minimum contrast each one requires
+const AUDITED_PAIRS: &[(ColorKey, ColorKey, f32)] = &[
+    (colors::TEXT, colors::BACKGROUND, WCAG_AA_CONTRAST),
+    (colors::TEXT, colors::HIGHLIGHT_BACKGROUND, WCAG_AA_CONTRAST),
+    (colors::HEADER_SUBTITLE, colors::BACKGROUND, WCAG_AA_CONTRAST),
+    (colors::SIDEBAR_ACTIVE_ITEM, colors::SIDEBAR_BACKGROUND, WCAG_AA_LARGE_TEXT_CONTRAST),
+    (colors::BUTTON_PRIMARY_ENABLED_TEXT, colors::BUTTON_PRIMARY_ENABLED_BACKGROUND, WCAG_AA_CONTRAST),
+    (colors::BUTTON_DEFAULT_ENABLED_TEXT, colors::BUTTON_DEFAULT_ENABLED_BACKGROUND, WCAG_AA_CONTRAST),
+    (colors::BUTTON_HIGHLIGHT_ENABLED_TEXT, colors::HIGHLIGHT_BACKGROUND, WCAG_AA_CONTRAST),
+];
+
+// A color in the HSL space, every component between 0.0 and 1.0
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hsl {
+    pub hue: f32,
+    pub saturation: f32,
+    pub lightness: f32,
+}
+
+// A color in the HSV space, every component between 0.0 and 1.0
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hsv {
+    pub hue: f32,
+    pub saturation: f32,
+    pub value: f32,
+}
+
+/**
+ * Parses a "#RGB", "#RGBA", "#RRGGBB" or "#RRGGBBAA" color.
+ */
+pub fn parse_hex(value: &str) -> Option<Color> {
+    let hex = value.trim().strip_prefix('#')?;
+
+    if !hex.chars().all(|character| character.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let digits: Vec<u8> = match hex.len() {
+        3 | 4 => hex.chars().map(|digit| u8::from_str_radix(&digit.to_string().repeat(2), 16).unwrap()).collect(),
+        6 | 8 => (0..hex.len()).step_by(2).map(|index| u8::from_str_radix(&hex[index..index + 2], 16).unwrap()).collect(),
+        _ => return None,
+    };
+
+    match digits.as_slice() {
+        [r, g, b] => Some(Color::from_rgb(*r, *g, *b)),
+        [r, g, b, a] => Some(Color::from_rgba(*r, *g, *b, *a)),
+        _ => None,
+    }
+}
+
+/**
+ * Formats the color as "#RRGGBB", or "#RRGGBBAA" if it is not opaque.
+ */
+pub fn to_hex(color: &Color) -> String {
+    let (r, g, b, a) = to_rgba8(color);
+
+    if a == 255 {
+        format!("#{:02X}{:02X}{:02X}", r, g, b)
+    } else {
+        format!("#{:02X}{:02X}{:02X}{:02X}", r, g, b, a)
+    }
+}
+
+/**
+ * Returns the components of the color between 0 and 255.
+ */
+pub fn to_rgba8(color: &Color) -> (u8, u8, u8, u8) {
+    let to_u8 = |component: f32| (component.clamp(0.0, 1.0) * 255.0).round() as u8;
+    (to_u8(color.red()), to_u8(color.green()), to_u8(color.blue()), to_u8(color.alpha()))
+}
+
+/**
+ * Creates a color from components between 0.0 and 1.0.
+ */
+pub fn from_rgbaf(r: f32, g: f32, b: f32, a: f32) -> Color {
+    Color::new(r.clamp(0.0, 1.0), g.clamp(0.0, 1.0), b.clamp(0.0, 1.0), a.clamp(0.0, 1.0))
+}
+
+pub fn to_hsl(color: &Color) -> Hsl {
+    let (r, g, b) = (color.red(), color.green(), color.blue());
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let lightness = (max + min) / 2.0;
+    let delta = max - min;
+
+    if delta == 0.0 {
+        return Hsl { hue: 0.0, saturation: 0.0, lightness };
+    }
+
+    let saturation = delta / (1.0 - (2.0 * lightness - 1.0).abs());
+
+    Hsl {
+        hue: get_hue(r, g, b, max, delta),
+        saturation: saturation.clamp(0.0, 1.0),
+        lightness,
+    }
+}
+
+pub fn from_hsl(hsl: Hsl, alpha: f32) -> Color {
+    let chroma = (1.0 - (2.0 * hsl.lightness - 1.0).abs()) * hsl.saturation;
+    let m = hsl.lightness - chroma / 2.0;
+    let (r, g, b) = get_hue_components(hsl.hue, chroma);
+    from_rgbaf(r + m, g + m, b + m, alpha)
+}
+
+pub fn to_hsv(color: &Color) -> Hsv {
+    let (r, g, b) = (color.red(), color.green(), color.blue());
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+
+    Hsv {
+        hue: if delta == 0.0 { 0.0 } else { get_hue(r, g, b, max, delta) },
+        saturation: if max == 0.0 { 0.0 } else { delta / max },
+        value: max,
+    }
+}
+
+pub fn from_hsv(hsv: Hsv, alpha: f32) -> Color {
+    let chroma = hsv.value * hsv.saturation;
+    let m = hsv.value - chroma;
+    let (r, g, b) = get_hue_components(hsv.hue, chroma);
+    from_rgbaf(r + m, g + m, b + m, alpha)
+}
+
+// Hue between 0.0 and 1.0 of the given RGB components
+fn get_hue(r: f32, g: f32, b: f32, max: f32, delta: f32) -> f32 {
+    let sector = if max == r {
+        ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / delta + 2.0
+    } else {
+        (r - g) / delta + 4.0
+    };
+
+    sector / 6.0
+}
+
+// RGB components of the given hue for the given chroma, before adding the lightness / value offset
+fn get_hue_components(hue: f32, chroma: f32) -> (f32, f32, f32) {
+    let sector = hue.rem_euclid(1.0) * 6.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+
+    match sector as i32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    }
+}
+
+/**
+ * Adds the given amount (-1.0 to 1.0) to the HSL lightness of the color.
+ */
+pub fn lighten(color: &Color, amount: f32) -> Color {
+    let mut hsl = to_hsl(color);
+    hsl.lightness = (hsl.lightness + amount).clamp(0.0, 1.0);
+    from_hsl(hsl, color.alpha())
+}
+
+pub fn darken(color: &Color, amount: f32) -> Color {
+    lighten(color, -amount)
+}
+
+/**
+ * Adds the given amount (-1.0 to 1.0) to the HSL saturation of the color.
+ */
+pub fn saturate(color: &Color, amount: f32) -> Color {
+    let mut hsl = to_hsl(color);
+    hsl.saturation = (hsl.saturation + amount).clamp(0.0, 1.0);
+    from_hsl(hsl, color.alpha())
+}
+
+/**
+ * Rotates the hue of the color by the given amount, 1.0 being a full turn.
+ */
+pub fn rotate_hue(color: &Color, amount: f32) -> Color {
+    let mut hsl = to_hsl(color);
+    hsl.hue = (hsl.hue + amount).rem_euclid(1.0);
+    from_hsl(hsl, color.alpha())
+}
+
+/**
+ * Returns the color with its alpha multiplied by the given factor (view alpha, fade animations...).
+ */
+pub fn multiply_alpha(color: &Color, alpha: f32) -> Color {
+    from_rgbaf(color.red(), color.green(), color.blue(), color.alpha() * alpha)
+}
+
+/**
+ * Interpolates between the two colors, progress going from 0.0 (from) to 1.0 (to).
+ * Every component is interpolated, alpha included.
+ */
+pub fn mix(from: &Color, to: &Color, progress: f32) -> Color {
+    let progress = progress.clamp(0.0, 1.0);
+    let lerp = |a: f32, b: f32| a + (b - a) * progress;
+
+    from_rgbaf(
+        lerp(from.red(), to.red()),
+        lerp(from.green(), to.green()),
+        lerp(from.blue(), to.blue()),
+        lerp(from.alpha(), to.alpha()),
+    )
+}
+
+/**
+ * Returns the color with its components multiplied by its alpha,
+ * for blending with premultiplied alpha.
+ */
+pub fn premultiply(color: &Color) -> Color {
+    let alpha = color.alpha();
+    from_rgbaf(color.red() * alpha, color.green() * alpha, color.blue() * alpha, alpha)
+}
+
+/**
+ * Returns the opaque color seen when drawing the given color over an opaque background.
+ */
+pub fn composite_over(color: &Color, background: &Color) -> Color {
+    let mut composited = mix(background, color, color.alpha());
+    composited.set_alpha(1.0);
+    composited
+}
+
+/**
+ * Returns the WCAG relative luminance of the color, between 0.0 (black) and 1.0 (white).
+ */
+pub fn relative_luminance(color: &Color) -> f32 {
+    let linearize = |component: f32| {
+        if component <= 0.03928 {
+            component / 12.92
+        } else {
+            ((component + 0.055) / 1.055).powf(2.4)
+        }
+    };
+
+    0.2126 * linearize(color.red()) + 0.7152 * linearize(color.green()) + 0.0722 * linearize(color.blue())
+}
+
+/**
+ * Returns the WCAG contrast ratio between the two colors, from 1.0 to 21.0.
+ * The foreground is composited over the background if it is translucent.
+ */
+pub fn contrast_ratio(foreground: &Color, background: &Color) -> f32 {
+    let foreground = composite_over(foreground, background);
+
+    let a = relative_luminance(&foreground);
+    let b = relative_luminance(background);
+    let (lighter, darker) = if a > b { (a, b) } else { (b, a) };
+
+    (lighter + 0.05) / (darker + 0.05)
+}
+
+// A text / background pair of a theme not meeting its minimum contrast
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContrastFailure {
+    pub foreground: String,
+    pub background: String,
+    pub ratio: f32,
+    pub required: f32,
+}
+
+/**
+ * Checks the contrast of the text / background pairs of the built-in colors,
+ * and of the given app pairs (foreground, background, minimum ratio).
+ * Returns the pairs failing their minimum contrast, missing colors are skipped.
+ */
+pub fn audit_theme(theme: &Theme, app_pairs: &[(&str, &str, f32)]) -> Vec<ContrastFailure> {
+    let pairs = AUDITED_PAIRS.iter()
+        .map(|(foreground, background, required)| (foreground.name(), background.name(), *required))
+        .chain(app_pairs.iter().copied());
+
+    let mut failures = vec![];
+
+    for (foreground, background, required) in pairs {
+        let (foreground_color, background_color) = match (theme.get_color(foreground), theme.get_color(background)) {
+            (Some(foreground_color), Some(background_color)) => (foreground_color, background_color),
+            _ => continue,
+        };
+
+        let ratio = contrast_ratio(foreground_color, background_color);
+        if ratio < required {
+            warn!("Low contrast between \"{}\" and \"{}\": {:.2}:1, {:.1}:1 required", foreground, background, ratio, required);
+            failures.push(ContrastFailure {
+                foreground: foreground.into(),
+                background: background.into(),
+                ratio,
+                required,
+            });
+        }
+    }
+
+    failures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_formats_hex() {
+        let color = parse_hex("#0DB6D5").unwrap();
+        assert_eq!(to_rgba8(&color), (13, 182, 213, 255));
+        assert_eq!(to_hex(&color), "#0DB6D5");
+
+        assert_eq!(to_hex(&parse_hex("#f0a").unwrap()), "#FF00AA");
+        assert_eq!(to_hex(&parse_hex("#000000B2").unwrap()), "#000000B2");
+
+        assert!(parse_hex("0DB6D5").is_none());
+        assert!(parse_hex("#0DB6D").is_none());
+        assert!(parse_hex("#GGGGGG").is_none());
+    }
+
+    #[test]
+    fn round_trips_hsl_and_hsv() {
+        let color = Color::from_rgb(50, 79, 241);
+
+        assert_eq!(to_rgba8(&from_hsl(to_hsl(&color), 1.0)), to_rgba8(&color));
+        assert_eq!(to_rgba8(&from_hsv(to_hsv(&color), 1.0)), to_rgba8(&color));
+    }
+
+    #[test]
+    fn computes_wcag_contrast() {
+        let black = Color::from_rgb(0, 0, 0);
+        let white = Color::from_rgb(255, 255, 255);
+
+        assert!((contrast_ratio(&black, &white) - 21.0).abs() < 0.01);
+        assert!((contrast_ratio(&white, &white) - 1.0).abs() < 0.01);
+
+        // #767676 is the lightest grey passing AA on white
+        let grey = parse_hex("#767676").unwrap();
+        assert!(contrast_ratio(&grey, &white) >= WCAG_AA_CONTRAST);
+    }
+
+    #[test]
+    fn mixes_and_premultiplies() {
+        let black = Color::from_rgb(0, 0, 0);
+        let white = Color::from_rgb(255, 255, 255);
+
+        assert_eq!(to_rgba8(&mix(&black, &white, 0.5)), (128, 128, 128, 255));
+        assert_eq!(to_rgba8(&premultiply(&Color::from_rgba(255, 255, 255, 128))), (128, 128, 128, 128));
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use log::{error, warn};
+
+// Simple persistent key / value store used by the settings modules (audio, accessibility...)
+// Values are stored as "key=value" lines in a file inside the user config directory
+pub struct ConfigFile {
+    path: PathBuf,
+    values: HashMap<String, String>,
+}
+
+/**
+ * Returns the directory where borealis stores its settings files,
+ * following the XDG base directory spec ($XDG_CONFIG_HOME/borealis, ~/.config/borealis).
+ */
+pub fn get_config_dir() -> PathBuf {
+    if let Ok(dir) = std::env::var("XDG_CONFIG_HOME") {
+        if !dir.is_empty() {
+            return PathBuf::from(dir).join("borealis");
+        }
+    }
+
+    if let Ok(home) = std::env::var("HOME") {
+        return PathBuf::from(home).join(".config").join("borealis");
+    }
+
+    PathBuf::from(".borealis")
+}
+
+impl ConfigFile {
+    /**
+     * Opens the config file with the given name in the config directory.
+     * A missing or unreadable file results in an empty config.
+     */
+    pub fn open(name: &str) -> ConfigFile {
+        Self::open_path(get_config_dir().join(name))
+    }
+
+    /**
+     * Opens the config file at the given path.
+     */
+    pub fn open_path(path: PathBuf) -> ConfigFile {
+        let mut values = HashMap::new();
+
+        if let Ok(content) = fs::read_to_string(&path) {
+            for (index, line) in content.lines().enumerate() {
+                let line = line.trim();
+
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+
+                match line.split_once('=') {
+                    Some((key, value)) => {
+                        values.insert(key.trim().to_string(), value.trim().to_string());
+                    }
+                    None => {
+                        warn!("Ignoring malformed line {} in config file {:?}", index + 1, path);
+                    }
+                }
+            }
+        }
+
+        ConfigFile {
+            path,
+            values,
+        }
+    }
+
+    pub fn get_string(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(|value| value.as_str())
+    }
+
+    pub fn get_float(&self, key: &str, default: f32) -> f32 {
+        self.get_string(key)
+            .and_then(|value| value.parse::<f32>().ok())
+            .unwrap_or(default)
+    }
+
+    pub fn get_bool(&self, key: &str, default: bool) -> bool {
+        match self.get_string(key) {
+            Some("true") => true,
+            Some("false") => false,
+            _ => default,
+        }
+    }
+
+    pub fn set_string(&mut self, key: &str, value: &str) {
+        self.values.insert(key.into(), value.into());
+    }
+
+    pub fn set_float(&mut self, key: &str, value: f32) {
+        self.set_string(key, &value.to_string());
+    }
+
+    pub fn set_bool(&mut self, key: &str, value: bool) {
+        self.set_string(key, if value { "true" } else { "false" });
+    }
+
+    /**
+     * Writes the config back to disk.
+     * Returns true if the operation succeeds.
+     */
+    pub fn save(&self) -> bool {
+        if let Some(parent) = self.path.parent() {
+            if let Err(err) = fs::create_dir_all(parent) {
+                error!("Could not create config directory {:?}: {}", parent, err);
+                return false;
+            }
+        }
+
+        let mut keys: Vec<&String> = self.values.keys().collect();
+        keys.sort();
+
+        let mut content = String::new();
+        for key in keys {
+            content.push_str(&format!("{}={}\n", key, self.values[key]));
+        }
+
+        match fs::write(&self.path, content) {
+            Ok(_) => true,
+            Err(err) => {
+                error!("Could not save config file {:?}: {}", self.path, err);
+                false
+            }
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+use crate::lib::core::view_tree::{ViewId, ViewTree};
+
+// What the wireframe debug overlay shows, above everything else
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WireframeMode {
+    Off,
+    All, // the box model of every view
+    Focused, // the box model of the focused view only
+}
+
+// Env variable enabling the wireframe at startup: "all" (or "1") or "focused"
+pub const WIREFRAME_ENV_VARIABLE: &str = "BOREALIS_WIREFRAME";
+
+// File written in the config directory by the view tree dump hotkey
+pub const VIEW_TREE_DUMP_FILE: &str = "view_tree.json";
+
+static WIREFRAME_MODE: AtomicU8 = AtomicU8::new(0);
+
+pub fn set_wireframe_mode(mode: WireframeMode) {
+    WIREFRAME_MODE.store(mode as u8, Ordering::Relaxed);
+}
+
+pub fn get_wireframe_mode() -> WireframeMode {
+    match WIREFRAME_MODE.load(Ordering::Relaxed) {
+        1 => WireframeMode::All,
+        2 => WireframeMode::Focused,
+        _ => WireframeMode::Off,
+    }
+}
+
+/**
+ * Goes to the next wireframe mode: off, all views, focused view only, then off again.
+ * Returns the new mode.
+ */
+pub fn toggle_wireframe_mode() -> WireframeMode {
+    let mode = match get_wireframe_mode() {
+        WireframeMode::Off => WireframeMode::All,
+        WireframeMode::All => WireframeMode::Focused,
+        WireframeMode::Focused => WireframeMode::Off,
+    };
+
+    set_wireframe_mode(mode);
+    mode
+}
+
+/**
+ * Sets the wireframe mode from the BOREALIS_WIREFRAME env variable, if set.
+ */
+pub fn load_wireframe_mode_from_env() {
+    if let Ok(value) = std::env::var(WIREFRAME_ENV_VARIABLE) {
+        set_wireframe_mode(match value.to_lowercase().as_str() {
+            "1" | "all" => WireframeMode::All,
+            "focused" => WireframeMode::Focused,
+            _ => WireframeMode::Off,
+        });
+    }
+}
+
+/**
+ * Returns the views of the given activity content views, bottom activity first,
+ * and the focused view as JSON. See Application::dump_view_tree().
+ */
+pub fn dump_view_tree(tree: &ViewTree, content_views: &[Option<ViewId>], focus: Option<ViewId>) -> serde_json::Value {
+    let activities: Vec<serde_json::Value> = content_views.iter()
+        .map(|content_view| {
+            let content = content_view
+                .and_then(|content_view| tree.get(content_view))
+                .map(|view| view.to_json(tree));
+
+            serde_json::json!({ "content": content })
+        })
+        .collect();
+
+    serde_json::json!({
+        "focus": focus.and_then(|focus| tree.get(focus)).map(|view| view.describe()),
+        "activities": activities,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lib::core::r#box::{Axis, BoxView, Padding};
+    use crate::lib::core::view::View;
+    use crate::lib::views::label::Label;
+    use super::*;
+
+    #[test]
+    fn dumps_the_views_of_every_activity() {
+        let mut tree = ViewTree::new();
+        let root = tree.insert(Box::new(BoxView::new(Axis::Column)));
+
+        let mut label = Label::new();
+        label.set_text("Hello");
+        label.get_base_view_mut().set_id("title");
+        tree.add_view(root, Box::new(label));
+
+        let padding = tree.add_view(root, Box::new(Padding::new()));
+        tree.get_mut(padding).unwrap().on_focus_gained();
+
+        let dump = dump_view_tree(&tree, &[Some(root), None], Some(padding));
+        let children = &dump["activities"][0]["content"]["children"];
+
+        assert_eq!(dump["activities"][0]["content"]["type"], "brls:Box");
+        assert!(dump["activities"][1]["content"].is_null());
+        assert_eq!(dump["focus"], "Padding (id=\"\")");
+
+        // Every view has the fields of its BaseView, and its own fields
+        assert_eq!(children[0]["type"], "brls:Label");
+        assert_eq!(children[0]["id"], "title");
+        assert_eq!(children[0]["text"], "Hello");
+        assert_eq!(children[0]["focused"], false);
+        assert_eq!(children[1]["type"], "brls:Padding");
+        assert_eq!(children[1]["focused"], true);
+        assert!(children[1]["frame"].is_object());
+    }
+}
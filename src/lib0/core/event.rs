@@ -1,12 +1,20 @@
 use std::sync::{Arc, Mutex};
 
+type Callbacks<T> = Arc<Mutex<Vec<Box<dyn Fn(&T)>>>>;
+
 pub struct Event<T> {
-    callbacks: Arc<Mutex<Vec<Box<dyn Fn(&T)>>>>,
+    callbacks: Callbacks<T>,
 }
 
 pub struct Subscription<T> {
     index: usize,
-    callbacks: Arc<Mutex<Vec<Box<dyn Fn(&T)>>>>,
+    callbacks: Callbacks<T>,
+}
+
+impl<T> Default for Event<T> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<T> Event<T> {
@@ -31,7 +39,7 @@ impl<T> Event<T> {
 
     pub fn unsubscribe(&mut self, subscription: Subscription<T>) {
         let mut callbacks = self.callbacks.lock().unwrap();
-        callbacks.remove(subscription.index);
+        let _ = callbacks.remove(subscription.index);
     }
 
     pub fn fire(&self, args: &T) -> bool {
@@ -0,0 +1,1541 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::lib::core::base_view::{AlignSelf, PositionType};
+use crate::lib::core::r#box::{AlignItems, Axis, Direction, JustifyContent};
+
+// Direction of the nodes inheriting it up to the root, set from the locale
+static RIGHT_TO_LEFT: AtomicBool = AtomicBool::new(false);
+
+/**
+ * Sets the direction of the whole UI, used by the nodes with an inherited direction.
+ * Right to left mirrors the layout: rows go from right to left, and the left and right
+ * margins, padding and positions are swapped. Inherit is the same as left to right.
+ */
+pub fn set_layout_direction(direction: Direction) {
+    RIGHT_TO_LEFT.store(direction == Direction::RightToLeft, Ordering::Relaxed);
+}
+
+pub fn get_layout_direction() -> Direction {
+    match RIGHT_TO_LEFT.load(Ordering::Relaxed) {
+        true => Direction::RightToLeft,
+        false => Direction::LeftToRight,
+    }
+}
+
+// A length of the flex style
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dimension
+{
+    Auto,
+    Points(f32),
+    Percent(f32), // percentage of the parent inner size, between 0.0 and 100.0
+}
+
+impl Dimension {
+    /**
+     * Returns the length in pixels, or None if it's auto or a percentage
+     * of an unknown size.
+     */
+    pub fn resolve(&self, reference: Option<f32>) -> Option<f32> {
+        match self {
+            Dimension::Auto => None,
+            Dimension::Points(points) => Some(*points),
+            Dimension::Percent(percent) => reference.map(|reference| reference * percent / 100.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FlexWrap
+{
+    NoWrap, // all the children are on a single line
+    Wrap,   // children go on a new line when the current one is full
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Display
+{
+    Flex, // the node is laid out
+    None, // the node and its children are removed from the layout, with an empty frame
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Edges<T> {
+    pub top: T,
+    pub right: T,
+    pub bottom: T,
+    pub left: T,
+}
+
+impl<T: Copy> Edges<T> {
+    pub fn all(value: T) -> Self {
+        Self {
+            top: value,
+            right: value,
+            bottom: value,
+            left: value,
+        }
+    }
+
+    // Left and right edges swapped, for right to left layouts
+    fn mirrored(&self) -> Self {
+        Self {
+            left: self.right,
+            right: self.left,
+            ..*self
+        }
+    }
+
+    // Start and end edges in the given axis
+    fn get_axis_edges(&self, axis: Axis) -> (T, T) {
+        match axis {
+            Axis::Row => (self.left, self.right),
+            Axis::Column => (self.top, self.bottom),
+        }
+    }
+}
+
+impl Edges<f32> {
+    fn get_axis_sum(&self, axis: Axis) -> f32 {
+        let (start, end) = self.get_axis_edges(axis);
+        start + end
+    }
+}
+
+// Layout properties of a node, following the CSS flexbox properties
+// Defaults are the CSS ones (row, shrink 1, stretch) and not the yoga ones
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlexStyle {
+    pub axis: Axis,
+    pub wrap: FlexWrap,
+    pub justify_content: JustifyContent,
+    pub align_items: AlignItems,
+    pub align_content: AlignItems,
+    pub align_self: AlignSelf,
+
+    pub grow: f32,
+    pub shrink: f32,
+    pub basis: Dimension,
+
+    pub width: Dimension,
+    pub height: Dimension,
+    pub min_width: Dimension, // auto is 0, there is no automatic minimum size like in CSS
+    pub min_height: Dimension,
+    pub max_width: Dimension, // auto is no maximum
+    pub max_height: Dimension,
+
+    pub margin: Edges<Dimension>, // percentages are relative to the parent inner width, like in CSS
+    pub padding: Edges<f32>,
+
+    pub row_gap: f32, // between lines of a column box, between wrapped lines of a row box
+    pub column_gap: f32,
+
+    // Relative: offsets the node from its place in the flow
+    // Absolute: takes the node out of the flow, the position is relative to the parent padding box
+    pub position_type: PositionType,
+    pub position: Edges<Dimension>, // auto edges are not set
+
+    pub display: Display,
+    pub direction: Direction, // left and right edges are start and end edges when right to left
+}
+
+impl Default for FlexStyle {
+    fn default() -> Self {
+        Self {
+            axis: Axis::Row,
+            wrap: FlexWrap::NoWrap,
+            justify_content: JustifyContent::FlexStart,
+            align_items: AlignItems::Stretch,
+            align_content: AlignItems::Stretch,
+            align_self: AlignSelf::Auto,
+            grow: 0.0,
+            shrink: 1.0,
+            basis: Dimension::Auto,
+            width: Dimension::Auto,
+            height: Dimension::Auto,
+            min_width: Dimension::Auto,
+            min_height: Dimension::Auto,
+            max_width: Dimension::Auto,
+            max_height: Dimension::Auto,
+            margin: Edges::all(Dimension::Points(0.0)),
+            padding: Edges::all(0.0),
+            row_gap: 0.0,
+            column_gap: 0.0,
+            position_type: PositionType::Relative,
+            position: Edges::all(Dimension::Auto),
+            display: Display::Flex,
+            direction: Direction::Inherit,
+        }
+    }
+}
+
+// Result of the layout of a node, relative to the top left corner of its parent
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct FlexLayout {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub margin: Edges<f32>,
+    pub padding: Edges<f32>,
+}
+
+impl FlexLayout {
+    /**
+     * Returns true if the node overlaps the given rectangle, in the coordinates of its parent.
+     * Touching edges don't count.
+     */
+    pub fn intersects(&self, x: f32, y: f32, width: f32, height: f32) -> bool {
+        self.x < x + width && x < self.x + self.width && self.y < y + height && y < self.y + self.height
+    }
+}
+
+// Number of sizes remembered per node for the measurement passes of its parent
+const MAX_CACHED_MEASURES: usize = 8;
+
+/**
+ * Returns the size of the content of a leaf node (text, image...), given the maximum
+ * width and height it can take (None if unconstrained).
+ */
+pub type MeasureFunc = Box<dyn Fn(Option<f32>, Option<f32>) -> (f32, f32)>;
+
+// A node of the layout tree, owning its children
+// Replaces the yoga nodes of borealis: views hold a node, boxes compute the layout of theirs
+//
+// Changing a node marks it dirty. Children can only be reached mutably through their parent,
+// which is then marked dirty too, so that every dirty node has dirty ancestors up to the root.
+// Clean nodes laid out again in the same constraints return their previous result without
+// visiting their children: a layout pass only goes through the dirty subtrees.
+pub struct FlexNode {
+    style: FlexStyle,
+    children: Vec<FlexNode>,
+    measure: Option<MeasureFunc>,
+    layout: FlexLayout,
+    detached: bool,
+    right_to_left: bool, // resolved direction of the last layout
+    dirty: bool,
+    last_layout: Option<(Constraints, (f32, f32))>, // constraints of the current layout of the children
+    measure_cache: Vec<(Constraints, (f32, f32))>,
+    reported_layout: Option<FlexLayout>, // see mark_layout_seen()
+}
+
+impl Default for FlexNode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FlexNode {
+    pub fn new() -> Self {
+        Self::with_style(FlexStyle::default())
+    }
+
+    pub fn with_style(style: FlexStyle) -> Self {
+        Self {
+            style,
+            children: vec![],
+            measure: None,
+            layout: FlexLayout::default(),
+            detached: false,
+            right_to_left: false,
+            dirty: true,
+            last_layout: None,
+            measure_cache: vec![],
+            reported_layout: None,
+        }
+    }
+
+    pub fn get_style(&self) -> &FlexStyle {
+        &self.style
+    }
+
+    /**
+     * Returns the style of the node to change it, marking the node dirty.
+     */
+    pub fn get_style_mut(&mut self) -> &mut FlexStyle {
+        self.dirty = true;
+        &mut self.style
+    }
+
+    /**
+     * Adds a child at the end of the node, returns its index.
+     */
+    pub fn add_child(&mut self, child: FlexNode) -> usize {
+        self.dirty = true;
+        self.children.push(child);
+        self.children.len() - 1
+    }
+
+    pub fn insert_child(&mut self, index: usize, child: FlexNode) {
+        self.dirty = true;
+        self.children.insert(index, child);
+    }
+
+    pub fn remove_child(&mut self, index: usize) -> FlexNode {
+        self.dirty = true;
+        self.children.remove(index)
+    }
+
+    pub fn get_child(&self, index: usize) -> Option<&FlexNode> {
+        self.children.get(index)
+    }
+
+    /**
+     * Returns the child to change it, marking this node dirty.
+     */
+    pub fn get_child_mut(&mut self, index: usize) -> Option<&mut FlexNode> {
+        self.dirty = true;
+        self.children.get_mut(index)
+    }
+
+    /**
+     * Exchanges the children of the node with the given nodes without invalidating
+     * the layout, for containers lending the nodes of their views during a layout pass.
+     * The node is marked dirty if one of the new children is dirty.
+     */
+    pub fn swap_children(&mut self, children: &mut Vec<FlexNode>) {
+        std::mem::swap(&mut self.children, children);
+
+        if self.children.iter().any(|child| child.dirty) {
+            self.dirty = true;
+        }
+    }
+
+    pub fn get_child_count(&self) -> usize {
+        self.children.len()
+    }
+
+    /**
+     * Sets the function measuring the content of the node.
+     * Only used if the node has no children.
+     */
+    pub fn set_measure_func(&mut self, measure: Option<MeasureFunc>) {
+        self.dirty = true;
+        self.measure = measure;
+    }
+
+    pub fn has_measure_func(&self) -> bool {
+        self.measure.is_some()
+    }
+
+    pub fn get_layout(&self) -> &FlexLayout {
+        &self.layout
+    }
+
+    /**
+     * Takes the node out of the layout of its parent: it only gets the size of its content
+     * and its position is left to the parent view (detached views are drawn at an origin of their own).
+     */
+    pub fn set_detached(&mut self, detached: bool) {
+        self.dirty = true;
+        self.detached = detached;
+    }
+
+    pub fn is_detached(&self) -> bool {
+        self.detached
+    }
+
+    /**
+     * Returns true if the node was laid out right to left, its own direction
+     * or the one it inherited. The computed layout is already mirrored.
+     */
+    pub fn is_right_to_left(&self) -> bool {
+        self.right_to_left
+    }
+
+    // Absolute, detached and hidden nodes don't take part in the flex layout of their parent
+    fn is_out_of_flow(&self) -> bool {
+        self.detached || self.style.position_type == PositionType::Absolute || self.style.display == Display::None
+    }
+
+    /**
+     * Moves the computed layout of the node, for containers placing
+     * their children themselves (grids...).
+     */
+    pub fn offset_layout(&mut self, x: f32, y: f32) {
+        self.layout.x += x;
+        self.layout.y += y;
+    }
+
+    /**
+     * Marks the node to be laid out again at the next layout pass, for changes
+     * the node cannot see by itself (content of a measured node...).
+     */
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /**
+     * Returns true if the layout of the node changed since the last call to mark_layout_seen().
+     */
+    pub fn has_new_layout(&self) -> bool {
+        self.reported_layout != Some(self.layout)
+    }
+
+    pub fn mark_layout_seen(&mut self) {
+        self.reported_layout = Some(self.layout);
+    }
+
+    /**
+     * Computes the layout of the node and its children in the given space.
+     * An auto sized root takes the whole space in the axis where it's given.
+     * Does nothing if the tree is clean and the space did not change.
+     */
+    pub fn calculate_layout(&mut self, width: Option<f32>, height: Option<f32>) {
+        let margin = resolve_margins(&self.style.margin, width);
+
+        let constraints = Constraints {
+            width: self.style.width.resolve(width).or(width.map(|width| width - margin.left - margin.right)),
+            height: self.style.height.resolve(height).or(height.map(|height| height - margin.top - margin.bottom)),
+            available_width: width,
+            available_height: height,
+            parent_width: width,
+            parent_height: height,
+            direction: get_layout_direction(),
+        };
+
+        let (node_width, node_height) = compute(self, &constraints, true);
+        let margin = if self.right_to_left { margin.mirrored() } else { margin };
+
+        self.layout.x = margin.left;
+        self.layout.y = margin.top;
+        self.layout.width = node_width;
+        self.layout.height = node_height;
+        self.layout.margin = margin;
+    }
+}
+
+// Sizes given by the parent to a node
+#[derive(Clone, Copy, PartialEq)]
+struct Constraints {
+    width: Option<f32>, // border box size forced by the parent (flexed or stretched)
+    height: Option<f32>,
+    available_width: Option<f32>, // largest size an auto sized node can take
+    available_height: Option<f32>,
+    parent_width: Option<f32>, // reference of the percentages
+    parent_height: Option<f32>,
+    direction: Direction, // inherited from the parent, never Inherit
+}
+
+// A child during the layout of its parent, sizes in main / cross axis terms
+struct FlexItem {
+    index: usize,
+    margin: Edges<f32>,
+    auto_margins: Edges<bool>,
+    align: AlignItems,
+    stretched: bool,
+    definite_cross: Option<f32>,
+    grow: f32,
+    shrink: f32,
+    base_size: f32,
+    hypothetical_size: f32,
+    min_main: f32,
+    max_main: f32,
+    min_cross: f32,
+    max_cross: f32,
+    main_size: f32,
+    cross_size: f32,
+    frozen: bool,
+    main_position: f32,
+    cross_position: f32,
+}
+
+impl FlexItem {
+    fn get_flex_factor(&self, growing: bool) -> f32 {
+        if growing { self.grow } else { self.shrink }
+    }
+
+    fn get_outer_main(&self, axis: Axis, size: f32) -> f32 {
+        size + self.margin.get_axis_sum(axis)
+    }
+
+    fn get_outer_cross(&self, axis: Axis) -> f32 {
+        self.cross_size + self.margin.get_axis_sum(get_cross_axis(axis))
+    }
+}
+
+struct FlexLine {
+    items: Vec<FlexItem>,
+    cross_size: f32,
+}
+
+fn get_cross_axis(axis: Axis) -> Axis {
+    match axis {
+        Axis::Row => Axis::Column,
+        Axis::Column => Axis::Row,
+    }
+}
+
+fn clamp_size(size: f32, min: f32, max: f32) -> f32 {
+    // The min constraint wins over the max one
+    size.min(max).max(min)
+}
+
+fn resolve_margins(margin: &Edges<Dimension>, parent_width: Option<f32>) -> Edges<f32> {
+    let resolve = |margin: Dimension| margin.resolve(parent_width).unwrap_or(0.0);
+
+    Edges {
+        top: resolve(margin.top),
+        right: resolve(margin.right),
+        bottom: resolve(margin.bottom),
+        left: resolve(margin.left),
+    }
+}
+
+fn get_auto_margins(margin: &Edges<Dimension>) -> Edges<bool> {
+    Edges {
+        top: margin.top == Dimension::Auto,
+        right: margin.right == Dimension::Auto,
+        bottom: margin.bottom == Dimension::Auto,
+        left: margin.left == Dimension::Auto,
+    }
+}
+
+fn get_align(align_items: AlignItems, align_self: AlignSelf) -> AlignItems {
+    match align_self {
+        AlignSelf::Auto => align_items,
+        AlignSelf::FlexStart => AlignItems::FlexStart,
+        AlignSelf::Center => AlignItems::Center,
+        AlignSelf::FlexEnd => AlignItems::FlexEnd,
+        AlignSelf::Stretch => AlignItems::Stretch,
+        AlignSelf::Baseline => AlignItems::Baseline,
+        AlignSelf::SpaceBetween => AlignItems::SpaceBetween,
+        AlignSelf::SpaceAround => AlignItems::SpaceAround,
+    }
+}
+
+// Main or cross value of a (width, height) pair
+fn get_axis_value<T>(axis: Axis, width: T, height: T) -> T {
+    match axis {
+        Axis::Row => width,
+        Axis::Column => height,
+    }
+}
+
+// (width, height) pair of main and cross values
+fn from_axis_values<T>(axis: Axis, main: T, cross: T) -> (T, T) {
+    match axis {
+        Axis::Row => (main, cross),
+        Axis::Column => (cross, main),
+    }
+}
+
+/**
+ * Returns the border box size of the node in the given constraints, from the cache
+ * if the node is clean. If perform_layout is true, the children must also be laid out
+ * for these constraints, otherwise only the size is needed (measurement passes).
+ */
+fn compute(node: &mut FlexNode, constraints: &Constraints, perform_layout: bool) -> (f32, f32) {
+    if node.dirty {
+        node.last_layout = None;
+        node.measure_cache.clear();
+    } else {
+        if let Some((_, size)) = node.last_layout.filter(|(last, _)| last == constraints) {
+            return size;
+        }
+
+        if !perform_layout {
+            if let Some((_, size)) = node.measure_cache.iter().find(|(cached, _)| cached == constraints) {
+                return *size;
+            }
+        }
+    }
+
+    let size = compute_node(node, constraints);
+
+    node.dirty = false;
+    node.last_layout = Some((*constraints, size));
+    if node.measure_cache.len() >= MAX_CACHED_MEASURES {
+        node.measure_cache.remove(0);
+    }
+    node.measure_cache.push((*constraints, size));
+
+    size
+}
+
+/**
+ * Lays out the node in the given constraints, positions its children
+ * and returns its border box size.
+ */
+fn compute_node(node: &mut FlexNode, constraints: &Constraints) -> (f32, f32) {
+    let style = node.style;
+    let padding = style.padding;
+    let padding_width = padding.left + padding.right;
+    let padding_height = padding.top + padding.bottom;
+
+    // Never smaller than the padding
+    let min_width = style.min_width.resolve(constraints.parent_width).unwrap_or(0.0).max(padding_width);
+    let max_width = style.max_width.resolve(constraints.parent_width).unwrap_or(f32::INFINITY);
+    let min_height = style.min_height.resolve(constraints.parent_height).unwrap_or(0.0).max(padding_height);
+    let max_height = style.max_height.resolve(constraints.parent_height).unwrap_or(f32::INFINITY);
+
+    let width = constraints.width
+        .or(style.width.resolve(constraints.parent_width))
+        .map(|width| clamp_size(width, min_width, max_width));
+    let height = constraints.height
+        .or(style.height.resolve(constraints.parent_height))
+        .map(|height| clamp_size(height, min_height, max_height));
+
+    let direction = match style.direction {
+        Direction::Inherit => constraints.direction,
+        direction => direction,
+    };
+    node.right_to_left = direction == Direction::RightToLeft;
+    node.layout.padding = if node.right_to_left { padding.mirrored() } else { padding };
+
+    if node.children.is_empty() {
+        let (content_width, content_height) = match (&node.measure, width, height) {
+            (Some(measure), _, _) if width.is_none() || height.is_none() => {
+                let max_width = width.or(constraints.available_width).map(|width| (width - padding_width).max(0.0));
+                let max_height = height.or(constraints.available_height).map(|height| (height - padding_height).max(0.0));
+                measure(max_width, max_height)
+            }
+            _ => (0.0, 0.0),
+        };
+
+        return (
+            width.unwrap_or_else(|| clamp_size(content_width + padding_width, min_width, max_width)),
+            height.unwrap_or_else(|| clamp_size(content_height + padding_height, min_height, max_height)),
+        );
+    }
+
+    // Nothing in the flow: sized like an empty leaf
+    if node.children.iter().all(FlexNode::is_out_of_flow) {
+        let size = (
+            width.unwrap_or_else(|| clamp_size(padding_width, min_width, max_width)),
+            height.unwrap_or_else(|| clamp_size(padding_height, min_height, max_height)),
+        );
+        layout_out_of_flow_children(node, size, direction);
+        mirror_children(node, size.0);
+        return size;
+    }
+
+    let axis = style.axis;
+    let cross_axis = get_cross_axis(axis);
+
+    let padding_main = padding.get_axis_sum(axis);
+    let padding_cross = padding.get_axis_sum(cross_axis);
+    let (padding_main_start, _) = padding.get_axis_edges(axis);
+    let (padding_cross_start, _) = padding.get_axis_edges(cross_axis);
+
+    let (min_main, max_main) = get_axis_value(axis, (min_width, max_width), (min_height, max_height));
+    let (min_cross, max_cross) = get_axis_value(axis, (min_height, max_height), (min_width, max_width));
+
+    let inner_width = width.map(|width| (width - padding_width).max(0.0));
+    let inner_height = height.map(|height| (height - padding_height).max(0.0));
+    let inner_main = get_axis_value(axis, inner_width, inner_height);
+    let inner_cross = get_axis_value(axis, inner_height, inner_width);
+
+    let available_main = inner_main.or(get_axis_value(axis, constraints.available_width, constraints.available_height)
+        .map(|available| (available - padding_main).max(0.0)));
+    let available_cross = inner_cross.or(get_axis_value(axis, constraints.available_height, constraints.available_width)
+        .map(|available| (available - padding_cross).max(0.0)));
+
+    let main_gap = get_axis_value(axis, style.column_gap, style.row_gap);
+    let cross_gap = get_axis_value(axis, style.row_gap, style.column_gap);
+
+    // Flex base and hypothetical main sizes (9.2)
+    let mut items = vec![];
+    for index in 0..node.children.len() {
+        let child = &mut node.children[index];
+        let child_style = child.style;
+
+        if child.is_out_of_flow() {
+            continue;
+        }
+
+        let margin = resolve_margins(&child_style.margin, inner_width);
+        let auto_margins = get_auto_margins(&child_style.margin);
+        let align = get_align(style.align_items, child_style.align_self);
+
+        let child_padding_main = child_style.padding.get_axis_sum(axis);
+        let child_padding_cross = child_style.padding.get_axis_sum(cross_axis);
+
+        let child_min_main = get_axis_value(axis, child_style.min_width, child_style.min_height)
+            .resolve(inner_main).unwrap_or(0.0).max(child_padding_main);
+        let child_max_main = get_axis_value(axis, child_style.max_width, child_style.max_height)
+            .resolve(inner_main).unwrap_or(f32::INFINITY);
+        let child_min_cross = get_axis_value(axis, child_style.min_height, child_style.min_width)
+            .resolve(inner_cross).unwrap_or(0.0).max(child_padding_cross);
+        let child_max_cross = get_axis_value(axis, child_style.max_height, child_style.max_width)
+            .resolve(inner_cross).unwrap_or(f32::INFINITY);
+
+        let cross_margins = margin.get_axis_sum(cross_axis);
+        let (cross_start_auto, cross_end_auto) = auto_margins.get_axis_edges(cross_axis);
+        let child_cross_style = get_axis_value(axis, child_style.height, child_style.width);
+
+        // Auto cross size, no auto cross margin: stretched to the line
+        let stretched = align == AlignItems::Stretch
+            && child_cross_style == Dimension::Auto
+            && !cross_start_auto
+            && !cross_end_auto;
+
+        // A single line container with a definite cross size gives it to its stretched items right away
+        let definite_cross = match (stretched, style.wrap, inner_cross) {
+            (true, FlexWrap::NoWrap, Some(inner_cross)) => Some(clamp_size(inner_cross - cross_margins, child_min_cross, child_max_cross)),
+            _ => None,
+        };
+
+        let base_size = match child_style.basis.resolve(inner_main)
+            .or(get_axis_value(axis, child_style.width, child_style.height).resolve(inner_main))
+        {
+            Some(size) => size,
+            None => {
+                // Content size: max-content in the main axis
+                let available_cross = available_cross.map(|available| (available - cross_margins).max(0.0));
+                let (child_width, child_height) = from_axis_values(axis, None, definite_cross);
+                let (available_width, available_height) = from_axis_values(axis, None, available_cross);
+
+                let (content_width, content_height) = compute(child, &Constraints {
+                    width: child_width,
+                    height: child_height,
+                    available_width,
+                    available_height,
+                    parent_width: inner_width,
+                    parent_height: inner_height,
+                    direction,
+                }, false);
+
+                get_axis_value(axis, content_width, content_height)
+            }
+        }.max(child_padding_main);
+
+        items.push(FlexItem {
+            index,
+            margin,
+            auto_margins,
+            align,
+            stretched,
+            definite_cross,
+            grow: child_style.grow,
+            shrink: child_style.shrink,
+            base_size,
+            hypothetical_size: clamp_size(base_size, child_min_main, child_max_main),
+            min_main: child_min_main,
+            max_main: child_max_main,
+            min_cross: child_min_cross,
+            max_cross: child_max_cross,
+            main_size: 0.0,
+            cross_size: definite_cross.unwrap_or(0.0),
+            frozen: false,
+            main_position: 0.0,
+            cross_position: 0.0,
+        });
+    }
+
+    // Collect the items into lines (9.3)
+    let mut lines: Vec<FlexLine> = vec![];
+    let mut line_main = 0.0;
+    for item in items {
+        let outer = item.get_outer_main(axis, item.hypothetical_size);
+
+        let new_line = match (lines.last(), style.wrap, available_main) {
+            (None, _, _) => true,
+            (Some(line), FlexWrap::Wrap, Some(available)) => !line.items.is_empty() && line_main + main_gap + outer > available,
+            _ => false,
+        };
+
+        if new_line {
+            lines.push(FlexLine {
+                items: vec![],
+                cross_size: 0.0,
+            });
+            line_main = outer;
+        } else {
+            line_main += main_gap + outer;
+        }
+
+        lines.last_mut().unwrap().items.push(item);
+    }
+
+    // Main size of an auto sized container: its longest line
+    let main_size = match get_axis_value(axis, width, height) {
+        Some(size) => size,
+        None => {
+            let content = lines.iter()
+                .map(|line| get_hypothetical_line_main(line, axis, main_gap))
+                .fold(0.0f32, f32::max);
+            clamp_size(content + padding_main, min_main, max_main)
+        }
+    };
+    let inner_main = (main_size - padding_main).max(0.0);
+    let (inner_width, inner_height) = match axis {
+        Axis::Row => (Some(inner_main), inner_height),
+        Axis::Column => (inner_width, Some(inner_main)),
+    };
+
+    // Resolve the flexible lengths (9.7) then the hypothetical cross sizes (9.4)
+    for line in &mut lines {
+        resolve_flexible_lengths(line, axis, inner_main, main_gap);
+
+        for item in &mut line.items {
+            let child = &mut node.children[item.index];
+            let cross_margins = item.margin.get_axis_sum(cross_axis);
+            let available_cross = available_cross.map(|available| (available - cross_margins).max(0.0));
+
+            let (child_width, child_height) = from_axis_values(axis, Some(item.main_size), item.definite_cross);
+            let (available_width, available_height) = from_axis_values(axis, Some(item.main_size), available_cross);
+
+            let (child_width, child_height) = compute(child, &Constraints {
+                width: child_width,
+                height: child_height,
+                available_width,
+                available_height,
+                parent_width: inner_width,
+                parent_height: inner_height,
+                direction,
+            }, true);
+
+            item.cross_size = get_axis_value(axis, child_height, child_width);
+        }
+
+        line.cross_size = line.items.iter()
+            .map(|item| item.get_outer_cross(axis))
+            .fold(0.0f32, f32::max);
+    }
+
+    // Cross size of the container (9.4)
+    let lines_cross: f32 = lines.iter().map(|line| line.cross_size).sum::<f32>()
+        + cross_gap * (lines.len().saturating_sub(1)) as f32;
+    let cross_size = match get_axis_value(axis, height, width) {
+        Some(size) => size,
+        None => clamp_size(lines_cross + padding_cross, min_cross, max_cross),
+    };
+    let inner_cross = (cross_size - padding_cross).max(0.0);
+
+    // A single line fills the container, multiple lines are distributed by align-content
+    let mut cross_offset = padding_cross_start;
+    let mut cross_between = cross_gap;
+    if style.wrap == FlexWrap::NoWrap {
+        lines[0].cross_size = inner_cross;
+    } else {
+        let free_space = inner_cross - lines_cross;
+        let count = lines.len() as f32;
+
+        match style.align_content {
+            AlignItems::Stretch if free_space > 0.0 => {
+                for line in &mut lines {
+                    line.cross_size += free_space / count;
+                }
+            }
+            AlignItems::Center => cross_offset += free_space / 2.0,
+            AlignItems::FlexEnd => cross_offset += free_space,
+            AlignItems::SpaceBetween if free_space > 0.0 && lines.len() > 1 => {
+                cross_between += free_space / (count - 1.0);
+            }
+            AlignItems::SpaceAround if free_space > 0.0 => {
+                cross_offset += free_space / count / 2.0;
+                cross_between += free_space / count;
+            }
+            AlignItems::SpaceAround => cross_offset += free_space / 2.0,
+            _ => {}
+        }
+    }
+
+    // Stretch the items (9.4) and align them in both axes (9.5 and 9.6)
+    for line in &mut lines {
+        for item in &mut line.items {
+            if item.stretched {
+                let cross_margins = item.margin.get_axis_sum(cross_axis);
+                let stretched_size = clamp_size(line.cross_size - cross_margins, item.min_cross, item.max_cross);
+
+                if stretched_size != item.cross_size {
+                    item.cross_size = stretched_size;
+
+                    let (child_width, child_height) = from_axis_values(axis, item.main_size, item.cross_size);
+                    compute(&mut node.children[item.index], &Constraints {
+                        width: Some(child_width),
+                        height: Some(child_height),
+                        available_width: Some(child_width),
+                        available_height: Some(child_height),
+                        parent_width: inner_width,
+                        parent_height: inner_height,
+                        direction,
+                    }, true);
+                }
+            }
+        }
+
+        justify_line(line, axis, style.justify_content, inner_main, main_gap, padding_main_start);
+        align_line(line, axis, cross_offset);
+
+        cross_offset += line.cross_size + cross_between;
+    }
+
+    for item in lines.iter().flat_map(|line| line.items.iter()) {
+        let child = &mut node.children[item.index];
+        let (x, y) = from_axis_values(axis, item.main_position, item.cross_position);
+        let (child_width, child_height) = from_axis_values(axis, item.main_size, item.cross_size);
+        let (offset_x, offset_y) = get_relative_offset(&child.style.position, inner_width, inner_height);
+
+        child.layout.x = x + offset_x;
+        child.layout.y = y + offset_y;
+        child.layout.width = child_width;
+        child.layout.height = child_height;
+        child.layout.margin = item.margin;
+    }
+
+    let size = from_axis_values(axis, main_size, cross_size);
+    layout_out_of_flow_children(node, size, direction);
+    mirror_children(node, size.0);
+    size
+}
+
+/**
+ * Mirrors the layout of the children of a right to left node horizontally:
+ * they are laid out left to right, the start edges being the left ones, then flipped.
+ * Detached children keep their position.
+ */
+fn mirror_children(node: &mut FlexNode, width: f32) {
+    if !node.right_to_left {
+        return;
+    }
+
+    for child in node.children.iter_mut().filter(|child| !child.detached) {
+        child.layout.x = width - child.layout.x - child.layout.width;
+        child.layout.margin = child.layout.margin.mirrored();
+    }
+}
+
+/**
+ * Returns the offset of a relative node: left or minus right, top or minus bottom.
+ */
+fn get_relative_offset(position: &Edges<Dimension>, parent_width: Option<f32>, parent_height: Option<f32>) -> (f32, f32) {
+    let x = match (position.left.resolve(parent_width), position.right.resolve(parent_width)) {
+        (Some(left), _) => left,
+        (None, Some(right)) => -right,
+        (None, None) => 0.0,
+    };
+    let y = match (position.top.resolve(parent_height), position.bottom.resolve(parent_height)) {
+        (Some(top), _) => top,
+        (None, Some(bottom)) => -bottom,
+        (None, None) => 0.0,
+    };
+
+    (x, y)
+}
+
+/**
+ * Lays out the absolute and detached children of the node once its size is known.
+ * Absolute children are placed in the padding box of the node: an edge set on both sides
+ * gives the size, a single edge the position, and no edge leaves them at the start of the content box.
+ * Detached children get the size of their content, at the origin.
+ */
+fn layout_out_of_flow_children(node: &mut FlexNode, (width, height): (f32, f32), direction: Direction) {
+    let padding = node.style.padding;
+
+    for child in node.children.iter_mut().filter(|child| child.is_out_of_flow()) {
+        let style = child.style;
+
+        if style.display == Display::None {
+            child.layout = FlexLayout::default();
+            continue;
+        }
+        let margin = resolve_margins(&style.margin, Some(width));
+
+        let left = style.position.left.resolve(Some(width));
+        let right = style.position.right.resolve(Some(width));
+        let top = style.position.top.resolve(Some(height));
+        let bottom = style.position.bottom.resolve(Some(height));
+
+        let (child_width, child_height) = match child.detached {
+            true => (None, None),
+            false => (
+                match (left, right) {
+                    (Some(left), Some(right)) if style.width == Dimension::Auto => Some((width - left - right - margin.left - margin.right).max(0.0)),
+                    _ => None,
+                },
+                match (top, bottom) {
+                    (Some(top), Some(bottom)) if style.height == Dimension::Auto => Some((height - top - bottom - margin.top - margin.bottom).max(0.0)),
+                    _ => None,
+                },
+            ),
+        };
+
+        let (child_width, child_height) = compute(child, &Constraints {
+            width: child_width,
+            height: child_height,
+            available_width: Some((width - margin.left - margin.right).max(0.0)),
+            available_height: Some((height - margin.top - margin.bottom).max(0.0)),
+            parent_width: Some(width),
+            parent_height: Some(height),
+            direction,
+        }, true);
+
+        let (x, y) = match child.detached {
+            true => (margin.left, margin.top),
+            false => (
+                match (left, right) {
+                    (Some(left), _) => left + margin.left,
+                    (None, Some(right)) => width - right - margin.right - child_width,
+                    (None, None) => padding.left + margin.left,
+                },
+                match (top, bottom) {
+                    (Some(top), _) => top + margin.top,
+                    (None, Some(bottom)) => height - bottom - margin.bottom - child_height,
+                    (None, None) => padding.top + margin.top,
+                },
+            ),
+        };
+
+        child.layout.x = x;
+        child.layout.y = y;
+        child.layout.width = child_width;
+        child.layout.height = child_height;
+        child.layout.margin = margin;
+    }
+}
+
+fn get_hypothetical_line_main(line: &FlexLine, axis: Axis, gap: f32) -> f32 {
+    line.items.iter().map(|item| item.get_outer_main(axis, item.hypothetical_size)).sum::<f32>()
+        + gap * (line.items.len().saturating_sub(1)) as f32
+}
+
+/**
+ * Grows or shrinks the items of the line to fill its main size,
+ * following the "Resolving Flexible Lengths" algorithm of the spec.
+ */
+fn resolve_flexible_lengths(line: &mut FlexLine, axis: Axis, inner_main: f32, gap: f32) {
+    let gaps = gap * (line.items.len().saturating_sub(1)) as f32;
+    let growing = get_hypothetical_line_main(line, axis, gap) < inner_main;
+
+    // Size inflexible items
+    for item in &mut line.items {
+        item.main_size = item.hypothetical_size;
+        item.frozen = item.get_flex_factor(growing) == 0.0
+            || (growing && item.base_size > item.hypothetical_size)
+            || (!growing && item.base_size < item.hypothetical_size);
+    }
+
+    let get_free_space = |items: &[FlexItem]| {
+        inner_main - gaps - items.iter()
+            .map(|item| item.get_outer_main(axis, if item.frozen { item.main_size } else { item.base_size }))
+            .sum::<f32>()
+    };
+
+    let initial_free_space = get_free_space(&line.items);
+
+    loop {
+        if line.items.iter().all(|item| item.frozen) {
+            break;
+        }
+
+        let mut free_space = get_free_space(&line.items);
+
+        let factor_sum: f32 = line.items.iter()
+            .filter(|item| !item.frozen)
+            .map(|item| item.get_flex_factor(growing))
+            .sum();
+
+        // Factors summing under 1 only take that fraction of the free space
+        if factor_sum < 1.0 && (initial_free_space * factor_sum).abs() < free_space.abs() {
+            free_space = initial_free_space * factor_sum;
+        }
+
+        if growing {
+            for item in line.items.iter_mut().filter(|item| !item.frozen) {
+                item.main_size = item.base_size + free_space * item.grow / factor_sum;
+            }
+        } else {
+            // Shrinking is weighted by the base size, so that small items don't vanish first
+            let scaled_sum: f32 = line.items.iter()
+                .filter(|item| !item.frozen)
+                .map(|item| item.shrink * item.base_size)
+                .sum();
+
+            for item in line.items.iter_mut().filter(|item| !item.frozen) {
+                item.main_size = match scaled_sum > 0.0 {
+                    true => item.base_size + free_space * item.shrink * item.base_size / scaled_sum,
+                    false => item.base_size,
+                };
+            }
+        }
+
+        // Fix min / max violations, then freeze the items violating in the direction of the total
+        let mut total_violation = 0.0;
+        let mut violations = vec![0.0; line.items.len()];
+        for (index, item) in line.items.iter_mut().enumerate().filter(|(_, item)| !item.frozen) {
+            let clamped = clamp_size(item.main_size, item.min_main, item.max_main).max(0.0);
+            violations[index] = clamped - item.main_size;
+            total_violation += violations[index];
+            item.main_size = clamped;
+        }
+
+        for (item, violation) in line.items.iter_mut().zip(&violations).filter(|(item, _)| !item.frozen) {
+            item.frozen = total_violation == 0.0
+                || (total_violation > 0.0 && *violation > 0.0)
+                || (total_violation < 0.0 && *violation < 0.0);
+        }
+    }
+}
+
+/**
+ * Places the items of the line in the main axis, with auto margins or justify-content (9.5).
+ */
+fn justify_line(line: &mut FlexLine, axis: Axis, justify: JustifyContent, inner_main: f32, gap: f32, padding_start: f32) {
+    let count = line.items.len() as f32;
+    let used: f32 = line.items.iter().map(|item| item.get_outer_main(axis, item.main_size)).sum::<f32>()
+        + gap * (count - 1.0);
+    let free_space = inner_main - used;
+
+    let auto_margins: usize = line.items.iter()
+        .map(|item| {
+            let (start, end) = item.auto_margins.get_axis_edges(axis);
+            start as usize + end as usize
+        })
+        .sum();
+
+    // Auto margins take the free space over justify-content
+    let auto_margin = match auto_margins > 0 && free_space > 0.0 {
+        true => free_space / auto_margins as f32,
+        false => 0.0,
+    };
+
+    let (mut position, between) = match (auto_margins > 0, justify) {
+        (true, _) => (0.0, 0.0),
+        (false, JustifyContent::FlexStart) => (0.0, 0.0),
+        (false, JustifyContent::FlexEnd) => (free_space, 0.0),
+        (false, JustifyContent::Center) => (free_space / 2.0, 0.0),
+        (false, JustifyContent::SpaceBetween) if free_space > 0.0 && count > 1.0 => (0.0, free_space / (count - 1.0)),
+        (false, JustifyContent::SpaceBetween) => (0.0, 0.0),
+        (false, JustifyContent::SpaceAround) if free_space > 0.0 => (free_space / count / 2.0, free_space / count),
+        (false, JustifyContent::SpaceEvenly) if free_space > 0.0 => (free_space / (count + 1.0), free_space / (count + 1.0)),
+        // Overflowing space-around and space-evenly fall back to center
+        (false, JustifyContent::SpaceAround | JustifyContent::SpaceEvenly) => (free_space / 2.0, 0.0),
+    };
+    position += padding_start;
+
+    for item in &mut line.items {
+        let (margin_start, margin_end) = item.margin.get_axis_edges(axis);
+        let (auto_start, auto_end) = item.auto_margins.get_axis_edges(axis);
+
+        position += margin_start + if auto_start { auto_margin } else { 0.0 };
+        item.main_position = position;
+        position += item.main_size + margin_end + if auto_end { auto_margin } else { 0.0 } + gap + between;
+    }
+}
+
+/**
+ * Places the items of the line in the cross axis, with auto margins or align-self (9.6).
+ */
+fn align_line(line: &mut FlexLine, axis: Axis, line_position: f32) {
+    let cross_axis = get_cross_axis(axis);
+
+    for item in &mut line.items {
+        let (margin_start, _) = item.margin.get_axis_edges(cross_axis);
+        let (auto_start, auto_end) = item.auto_margins.get_axis_edges(cross_axis);
+        let free_space = line.cross_size - item.get_outer_cross(axis);
+
+        let offset = match (auto_start, auto_end) {
+            (true, true) => free_space.max(0.0) / 2.0,
+            (true, false) => free_space.max(0.0),
+            (false, true) => 0.0,
+            (false, false) => match item.align {
+                AlignItems::Center => free_space / 2.0,
+                AlignItems::FlexEnd => free_space,
+                // Baseline alignment is not supported and behaves like flex-start
+                _ => 0.0,
+            },
+        };
+
+        item.cross_position = line_position + margin_start + offset;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use super::*;
+
+    fn node(style: FlexStyle, children: Vec<FlexNode>) -> FlexNode {
+        let mut node = FlexNode::with_style(style);
+        for child in children {
+            node.add_child(child);
+        }
+        node
+    }
+
+    fn sized(width: f32, height: f32) -> FlexStyle {
+        FlexStyle {
+            width: Dimension::Points(width),
+            height: Dimension::Points(height),
+            ..FlexStyle::default()
+        }
+    }
+
+    fn get_frame(node: &FlexNode, index: usize) -> (f32, f32, f32, f32) {
+        let layout = node.get_child(index).unwrap().get_layout();
+        (layout.x, layout.y, layout.width, layout.height)
+    }
+
+    fn get_widths(node: &FlexNode) -> Vec<f32> {
+        (0..node.get_child_count()).map(|index| get_frame(node, index).2).collect()
+    }
+
+    // 9.7: free space is distributed in proportion to the grow factors
+    #[test]
+    fn grow_distributes_free_space() {
+        let item = |grow| FlexNode::with_style(FlexStyle { basis: Dimension::Points(100.0), grow, ..FlexStyle::default() });
+        let mut root = node(sized(600.0, 100.0), vec![item(1.0), item(2.0), item(3.0)]);
+        root.calculate_layout(None, None);
+
+        assert_eq!(get_widths(&root), vec![150.0, 200.0, 250.0]);
+        assert_eq!(get_frame(&root, 2).0, 350.0);
+    }
+
+    // 9.7: negative free space is distributed in proportion to shrink * base size
+    #[test]
+    fn shrink_is_weighted_by_base_size() {
+        let item = |basis| FlexNode::with_style(FlexStyle { basis: Dimension::Points(basis), ..FlexStyle::default() });
+        let mut root = node(sized(400.0, 100.0), vec![item(300.0), item(200.0)]);
+        root.calculate_layout(None, None);
+
+        assert_eq!(get_widths(&root), vec![240.0, 160.0]);
+    }
+
+    // 9.7: an item clamped by its min size is frozen and the others share the rest
+    #[test]
+    fn min_violation_freezes_item() {
+        let mut root = node(sized(300.0, 100.0), vec![
+            FlexNode::with_style(FlexStyle { basis: Dimension::Points(200.0), min_width: Dimension::Points(180.0), ..FlexStyle::default() }),
+            FlexNode::with_style(FlexStyle { basis: Dimension::Points(200.0), ..FlexStyle::default() }),
+        ]);
+        root.calculate_layout(None, None);
+
+        assert_eq!(get_widths(&root), vec![180.0, 120.0]);
+    }
+
+    #[test]
+    fn max_violation_freezes_item() {
+        let item = |max_width| FlexNode::with_style(FlexStyle { basis: Dimension::Points(0.0), grow: 1.0, max_width, ..FlexStyle::default() });
+        let mut root = node(sized(600.0, 100.0), vec![item(Dimension::Points(100.0)), item(Dimension::Auto)]);
+        root.calculate_layout(None, None);
+
+        assert_eq!(get_widths(&root), vec![100.0, 500.0]);
+    }
+
+    // Grow factors summing under 1 only take that fraction of the free space
+    #[test]
+    fn fractional_grow_leaves_space() {
+        let item = || FlexNode::with_style(FlexStyle { basis: Dimension::Points(100.0), grow: 0.25, ..FlexStyle::default() });
+        let mut root = node(sized(600.0, 100.0), vec![item(), item()]);
+        root.calculate_layout(None, None);
+
+        // Half of the 400 free pixels
+        assert_eq!(get_widths(&root), vec![200.0, 200.0]);
+    }
+
+    #[test]
+    fn justify_content() {
+        let positions = |justify_content| {
+            let mut root = node(
+                FlexStyle { justify_content, ..sized(500.0, 100.0) },
+                vec![FlexNode::with_style(sized(100.0, 10.0)), FlexNode::with_style(sized(100.0, 10.0))],
+            );
+            root.calculate_layout(None, None);
+            (get_frame(&root, 0).0, get_frame(&root, 1).0)
+        };
+
+        assert_eq!(positions(JustifyContent::FlexStart), (0.0, 100.0));
+        assert_eq!(positions(JustifyContent::FlexEnd), (300.0, 400.0));
+        assert_eq!(positions(JustifyContent::Center), (150.0, 250.0));
+        assert_eq!(positions(JustifyContent::SpaceBetween), (0.0, 400.0));
+        assert_eq!(positions(JustifyContent::SpaceAround), (75.0, 325.0));
+        assert_eq!(positions(JustifyContent::SpaceEvenly), (100.0, 300.0));
+    }
+
+    #[test]
+    fn align_items() {
+        let frame = |align_items| {
+            let mut root = node(
+                FlexStyle { align_items, ..sized(500.0, 100.0) },
+                vec![FlexNode::with_style(FlexStyle {
+                    width: Dimension::Points(50.0),
+                    margin: Edges { top: Dimension::Points(10.0), ..Edges::all(Dimension::Points(0.0)) },
+                    padding: Edges::all(20.0),
+                    ..FlexStyle::default()
+                })],
+            );
+            root.calculate_layout(None, None);
+            get_frame(&root, 0)
+        };
+
+        // Auto height: the padding
+        assert_eq!(frame(AlignItems::FlexStart), (0.0, 10.0, 50.0, 40.0));
+        assert_eq!(frame(AlignItems::Center), (0.0, 35.0, 50.0, 40.0));
+        assert_eq!(frame(AlignItems::FlexEnd), (0.0, 60.0, 50.0, 40.0));
+        assert_eq!(frame(AlignItems::Stretch), (0.0, 10.0, 50.0, 90.0));
+    }
+
+    // 8.1: auto margins absorb the free space before justify-content
+    #[test]
+    fn auto_margin_pushes_items() {
+        let mut root = node(
+            FlexStyle { justify_content: JustifyContent::Center, ..sized(500.0, 100.0) },
+            vec![
+                FlexNode::with_style(sized(100.0, 10.0)),
+                FlexNode::with_style(sized(100.0, 10.0)),
+                FlexNode::with_style(FlexStyle {
+                    margin: Edges { left: Dimension::Auto, ..Edges::all(Dimension::Points(0.0)) },
+                    ..sized(100.0, 10.0)
+                }),
+            ],
+        );
+        root.calculate_layout(None, None);
+
+        assert_eq!(get_frame(&root, 1).0, 100.0);
+        assert_eq!(get_frame(&root, 2).0, 400.0);
+    }
+
+    #[test]
+    fn auto_cross_margins_center() {
+        let mut root = node(sized(500.0, 100.0), vec![FlexNode::with_style(FlexStyle {
+            margin: Edges::all(Dimension::Auto),
+            ..sized(100.0, 20.0)
+        })]);
+        root.calculate_layout(None, None);
+
+        assert_eq!(get_frame(&root, 0), (200.0, 40.0, 100.0, 20.0));
+    }
+
+    #[test]
+    fn wrap_with_gaps() {
+        let mut root = node(
+            FlexStyle {
+                width: Dimension::Points(300.0),
+                wrap: FlexWrap::Wrap,
+                column_gap: 10.0,
+                row_gap: 20.0,
+                ..FlexStyle::default()
+            },
+            (0..4).map(|_| FlexNode::with_style(sized(100.0, 50.0))).collect(),
+        );
+        root.calculate_layout(None, None);
+
+        assert_eq!(get_frame(&root, 0), (0.0, 0.0, 100.0, 50.0));
+        assert_eq!(get_frame(&root, 1), (110.0, 0.0, 100.0, 50.0));
+        assert_eq!(get_frame(&root, 2), (0.0, 70.0, 100.0, 50.0));
+        assert_eq!(get_frame(&root, 3), (110.0, 70.0, 100.0, 50.0));
+        assert_eq!(root.get_layout().height, 120.0);
+    }
+
+    #[test]
+    fn align_content_distributes_lines() {
+        let lines = |align_content| {
+            let mut root = node(
+                FlexStyle { wrap: FlexWrap::Wrap, align_content, ..sized(100.0, 300.0) },
+                vec![FlexNode::with_style(FlexStyle { width: Dimension::Points(100.0), ..FlexStyle::default() }), FlexNode::with_style(sized(100.0, 50.0))],
+            );
+            root.calculate_layout(None, None);
+            (get_frame(&root, 0), get_frame(&root, 1))
+        };
+
+        assert_eq!(lines(AlignItems::FlexStart), ((0.0, 0.0, 100.0, 0.0), (0.0, 0.0, 100.0, 50.0)));
+        assert_eq!(lines(AlignItems::Center), ((0.0, 125.0, 100.0, 0.0), (0.0, 125.0, 100.0, 50.0)));
+        assert_eq!(lines(AlignItems::SpaceBetween), ((0.0, 0.0, 100.0, 0.0), (0.0, 250.0, 100.0, 50.0)));
+        // Free space shared by the two lines, the auto height item stretches to its line
+        assert_eq!(lines(AlignItems::Stretch), ((0.0, 0.0, 100.0, 125.0), (0.0, 125.0, 100.0, 50.0)));
+    }
+
+    #[test]
+    fn padding_and_percentages() {
+        let mut root = node(
+            FlexStyle { padding: Edges::all(10.0), axis: Axis::Column, ..sized(220.0, 220.0) },
+            vec![FlexNode::with_style(FlexStyle {
+                width: Dimension::Percent(50.0),
+                height: Dimension::Percent(25.0),
+                margin: Edges { left: Dimension::Percent(10.0), ..Edges::all(Dimension::Points(0.0)) },
+                ..FlexStyle::default()
+            })],
+        );
+        root.calculate_layout(None, None);
+
+        assert_eq!(get_frame(&root, 0), (30.0, 10.0, 100.0, 50.0));
+    }
+
+    #[test]
+    fn auto_sized_container_fits_content() {
+        let mut root = node(FlexStyle::default(), vec![node(
+            FlexStyle { padding: Edges::all(5.0), column_gap: 10.0, ..FlexStyle::default() },
+            vec![FlexNode::with_style(sized(100.0, 40.0)), FlexNode::with_style(sized(50.0, 60.0))],
+        )]);
+        root.calculate_layout(None, None);
+
+        assert_eq!(get_frame(&root, 0), (0.0, 0.0, 170.0, 70.0));
+    }
+
+    // A stretched text wraps in the width of its column
+    #[test]
+    fn measured_leaf_wraps_in_column() {
+        let mut text = FlexNode::new();
+        text.set_measure_func(Some(Box::new(|max_width, _| {
+            let width = 300.0;
+            match max_width {
+                Some(max_width) if max_width < width => (max_width, (width / max_width).ceil() * 20.0),
+                _ => (width, 20.0),
+            }
+        })));
+
+        let mut root = node(FlexStyle { axis: Axis::Column, ..sized(100.0, 500.0) }, vec![text]);
+        root.calculate_layout(None, None);
+
+        assert_eq!(get_frame(&root, 0), (0.0, 0.0, 100.0, 60.0));
+    }
+
+    fn positioned(position: Edges<Dimension>, style: FlexStyle) -> FlexNode {
+        FlexNode::with_style(FlexStyle { position_type: PositionType::Absolute, position, ..style })
+    }
+
+    #[test]
+    fn absolute_children_leave_the_flow() {
+        let mut root = node(
+            FlexStyle { padding: Edges::all(10.0), ..sized(400.0, 300.0) },
+            vec![
+                positioned(Edges { right: Dimension::Points(20.0), bottom: Dimension::Points(30.0), ..Edges::all(Dimension::Auto) }, sized(50.0, 40.0)),
+                FlexNode::with_style(sized(100.0, 100.0)),
+                // Both horizontal edges: the width is given by the parent
+                positioned(Edges { left: Dimension::Points(0.0), right: Dimension::Percent(50.0), top: Dimension::Points(5.0), ..Edges::all(Dimension::Auto) }, FlexStyle::default()),
+            ],
+        );
+        root.calculate_layout(None, None);
+
+        assert_eq!(get_frame(&root, 0), (330.0, 230.0, 50.0, 40.0));
+        assert_eq!(get_frame(&root, 1), (10.0, 10.0, 100.0, 100.0));
+        assert_eq!(get_frame(&root, 2), (0.0, 5.0, 200.0, 0.0));
+    }
+
+    #[test]
+    fn relative_position_offsets_the_node() {
+        let mut root = node(sized(400.0, 300.0), vec![
+            FlexNode::with_style(sized(100.0, 100.0)),
+            FlexNode::with_style(FlexStyle {
+                position: Edges { right: Dimension::Points(10.0), top: Dimension::Points(5.0), ..Edges::all(Dimension::Auto) },
+                ..sized(100.0, 100.0)
+            }),
+        ]);
+        root.calculate_layout(None, None);
+
+        assert_eq!(get_frame(&root, 1), (90.0, 5.0, 100.0, 100.0));
+    }
+
+    #[test]
+    fn detached_children_only_get_their_content_size() {
+        let mut detached = FlexNode::with_style(sized(60.0, 30.0));
+        detached.set_detached(true);
+
+        let mut root = node(FlexStyle { justify_content: JustifyContent::Center, ..sized(400.0, 300.0) }, vec![detached, FlexNode::with_style(sized(100.0, 100.0))]);
+        root.calculate_layout(None, None);
+
+        assert_eq!(get_frame(&root, 0), (0.0, 0.0, 60.0, 30.0));
+        assert_eq!(get_frame(&root, 1).0, 150.0);
+    }
+
+    #[test]
+    fn intersects_ignores_touching_edges() {
+        let layout = FlexLayout { x: 0.0, y: 100.0, width: 50.0, height: 50.0, ..FlexLayout::default() };
+
+        assert!(layout.intersects(0.0, 120.0, 300.0, 200.0));
+        assert!(!layout.intersects(0.0, 0.0, 300.0, 100.0));
+        assert!(!layout.intersects(0.0, 150.0, 300.0, 100.0));
+    }
+
+    #[test]
+    fn hidden_children_take_no_space() {
+        let mut root = node(FlexStyle { axis: Axis::Column, ..sized(400.0, 300.0) }, vec![
+            FlexNode::with_style(FlexStyle { display: Display::None, ..sized(100.0, 100.0) }),
+            FlexNode::with_style(sized(100.0, 100.0)),
+        ]);
+        root.calculate_layout(None, None);
+
+        assert_eq!(get_frame(&root, 0), (0.0, 0.0, 0.0, 0.0));
+        assert_eq!(get_frame(&root, 1), (0.0, 0.0, 100.0, 100.0));
+
+        root.get_child_mut(0).unwrap().get_style_mut().display = Display::Flex;
+        root.calculate_layout(None, None);
+
+        assert_eq!(get_frame(&root, 1), (0.0, 100.0, 100.0, 100.0));
+    }
+
+    #[test]
+    fn right_to_left_mirrors_the_layout() {
+        let mut root = node(
+            FlexStyle { direction: Direction::RightToLeft, padding: Edges { left: 10.0, ..Edges::all(0.0) }, ..sized(400.0, 300.0) },
+            vec![
+                FlexNode::with_style(FlexStyle { margin: Edges { left: Dimension::Points(5.0), ..Edges::all(Dimension::Points(0.0)) }, ..sized(100.0, 100.0) }),
+                FlexNode::with_style(sized(50.0, 50.0)),
+                positioned(Edges { left: Dimension::Points(20.0), top: Dimension::Points(0.0), ..Edges::all(Dimension::Auto) }, sized(30.0, 30.0)),
+                // Overrides the direction for its subtree
+                node(FlexStyle { direction: Direction::LeftToRight, ..sized(100.0, 100.0) }, vec![FlexNode::with_style(sized(10.0, 10.0))]),
+            ],
+        );
+        root.calculate_layout(None, None);
+
+        assert!(root.is_right_to_left());
+        assert_eq!(root.get_layout().padding.right, 10.0);
+        assert_eq!(get_frame(&root, 0), (285.0, 0.0, 100.0, 100.0));
+        assert_eq!(root.get_child(0).unwrap().get_layout().margin.right, 5.0);
+        assert_eq!(get_frame(&root, 1), (235.0, 0.0, 50.0, 50.0));
+        assert_eq!(get_frame(&root, 2), (350.0, 0.0, 30.0, 30.0));
+        assert_eq!(get_frame(&root, 3), (135.0, 0.0, 100.0, 100.0));
+        assert_eq!(get_frame(root.get_child(3).unwrap(), 0), (0.0, 0.0, 10.0, 10.0));
+    }
+
+    #[test]
+    fn clean_subtrees_are_not_laid_out_again() {
+        let measures = Rc::new(Cell::new(0));
+        let measured = |measures: &Rc<Cell<i32>>| {
+            let measures = Rc::clone(measures);
+            let mut text = FlexNode::new();
+            text.set_measure_func(Some(Box::new(move |_, _| {
+                measures.set(measures.get() + 1);
+                (50.0, 20.0)
+            })));
+            node(FlexStyle::default(), vec![text])
+        };
+
+        let mut root = node(FlexStyle { axis: Axis::Column, ..FlexStyle::default() }, vec![measured(&measures), measured(&measures)]);
+        root.calculate_layout(Some(500.0), Some(500.0));
+        assert!(!root.is_dirty());
+
+        // Nothing changed
+        let first_pass = measures.get();
+        root.calculate_layout(Some(500.0), Some(500.0));
+        assert_eq!(measures.get(), first_pass);
+
+        // Only the dirty branch is measured again
+        measures.set(0);
+        root.get_child_mut(0).unwrap().get_child_mut(0).unwrap().mark_dirty();
+        root.calculate_layout(Some(500.0), Some(500.0));
+        assert!(measures.get() > 0);
+        assert!(measures.get() <= first_pass / 2);
+    }
+
+    #[test]
+    fn reports_changed_layouts_only() {
+        let mut root = node(FlexStyle::default(), vec![FlexNode::with_style(sized(100.0, 10.0)), FlexNode::with_style(sized(100.0, 10.0))]);
+        root.calculate_layout(Some(500.0), Some(500.0));
+        for index in 0..2 {
+            assert!(root.get_child(index).unwrap().has_new_layout());
+            root.get_child_mut(index).unwrap().mark_layout_seen();
+        }
+
+        root.get_child_mut(1).unwrap().get_style_mut().width = Dimension::Points(200.0);
+        root.calculate_layout(Some(500.0), Some(500.0));
+
+        assert!(!root.get_child(0).unwrap().has_new_layout());
+        assert!(root.get_child(1).unwrap().has_new_layout());
+    }
+
+    #[test]
+    fn root_fills_given_space() {
+        let mut root = node(FlexStyle::default(), vec![FlexNode::with_style(FlexStyle { grow: 1.0, ..FlexStyle::default() })]);
+        root.calculate_layout(Some(1280.0), Some(720.0));
+
+        assert_eq!(root.get_layout().width, 1280.0);
+        assert_eq!(get_frame(&root, 0), (0.0, 0.0, 1280.0, 720.0));
+    }
+}
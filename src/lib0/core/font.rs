@@ -1,21 +1,27 @@
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::rc::Rc;
 
-pub const FONT_INVALID: i32 = -1;
-pub const FONT_REGULAR: &str = "regular"; // regular Latin font
-pub const FONT_KOREAN_REGULAR: &str = "korean"; // regular Korean font
-pub const FONT_MATERIAL_ICONS: &str = "material"; // Material icons font
-pub const FONT_SWITCH_ICONS: &str = "switch"; // Switch icons font (see the HOS shared symbols font for an example)
+// The stash is shared with the winit platform, it owns the buffers of the fonts loaded from memory
+pub use crate::core::font::{FontStash, FONT_INVALID, FONT_KOREAN_REGULAR, FONT_MATERIAL_ICONS, FONT_REGULAR, FONT_SWITCH_ICONS};
 
-pub struct FontStash(HashMap<String, i32>);
+thread_local! {
+    // Font stash of the application, for the views measuring their text during layout
+    static LAYOUT_FONT_STASH: RefCell<Option<Rc<RefCell<FontStash>>>> = const { RefCell::new(None) };
+}
 
-impl FontStash {
-    pub fn insert(&mut self, k: &str, v: i32) {
-        self.0.insert(k.into(), v);
-    }
+/**
+ * Sets the font stash used to measure text during layout, done by the application once the fonts are loaded.
+ */
+pub fn set_layout_font_stash(font_stash: Option<Rc<RefCell<FontStash>>>) {
+    LAYOUT_FONT_STASH.with(|stash| *stash.borrow_mut() = font_stash);
+}
 
-    pub fn get(&mut self, k: &str) -> Option<&i32> {
-        self.0.get(k)
-    }
+/**
+ * Calls the given function with the font stash used to measure text during layout.
+ * Returns None if the application did not set it.
+ */
+pub fn with_layout_font_stash<R, F: FnOnce(&FontStash) -> R>(f: F) -> Option<R> {
+    LAYOUT_FONT_STASH.with(|stash| stash.borrow().as_ref().map(|font_stash| f(&font_stash.borrow())))
 }
 
 
@@ -28,8 +34,11 @@ pub trait FontLoader {
      * The implementation must use the Application::loadFont and
      * Application::loadFontFromMemory methods to load as much as possible
      * of the "built-in" fonts defined in the FONT_* constants above.
+     *
+     * The fallback fonts of the regular font are ordered after the given locale,
+     * see Application::get_locale().
      */
-    fn load_fonts(&self);
+    fn load_fonts(&self, locale: &str);
 
     /**
      * Convenience method to load a font from a file path
@@ -42,4 +51,9 @@ pub trait FontLoader {
      * Returns true if the operation succeeds.
      */
     fn load_material_from_resources(&self) -> bool;
+
+    /**
+     * Returns the font stash filled by load_fonts().
+     */
+    fn get_font_stash(&self) -> Rc<RefCell<FontStash>>;
 }
\ No newline at end of file
@@ -0,0 +1,390 @@
+use log::warn;
+use ttf_parser::{Face, GlyphId};
+use crate::lib::core::font::FontStash;
+
+// A line of a wrapped paragraph, as byte offsets in the text
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextLine {
+    pub start: usize,
+    pub end: usize,
+    pub width: f32,
+}
+
+// Text measurement on the CPU, from the TTF files of the font stash
+// Follows the fontstash rules used by nanovg (pixel height scale, advances rounded
+// to the pixel at a tenth of pixel font size, kerning) so that layout matches rendering
+// without needing a GL context
+pub struct FontMetrics<'a> {
+    faces: Vec<Face<'a>>,
+}
+
+impl<'a> FontMetrics<'a> {
+    /**
+     * Creates the metrics of the given font of the font stash, followed by its fallback fonts.
+     * Fonts that were not loaded from memory are skipped.
+     */
+    pub fn new(font_stash: &'a FontStash, fonts: &[&str]) -> Self {
+        let faces = fonts.iter()
+            .filter_map(|font| {
+                let face = font_stash.get_font_data(font).and_then(|data| Face::parse(data, 0).ok());
+
+                if face.is_none() {
+                    warn!("Font \"{}\" is not available for text measurement", font);
+                }
+
+                face
+            })
+            .collect();
+
+        Self {
+            faces,
+        }
+    }
+
+    /**
+     * Creates the metrics of the given TTF data, for tests and tools.
+     */
+    pub fn from_data(data: &'a [u8]) -> Option<Self> {
+        Face::parse(data, 0).ok().map(|face| Self {
+            faces: vec![face],
+        })
+    }
+
+    pub fn is_valid(&self) -> bool {
+        !self.faces.is_empty()
+    }
+
+    /**
+     * Returns the height of a line at the given font size, like nvgTextMetrics(),
+     * multiplied by the given line height (see nvgTextLineHeight()).
+     */
+    pub fn get_line_height(&self, font_size: f32, line_height: f32) -> f32 {
+        match self.faces.first() {
+            Some(face) => {
+                let font_height = (face.ascender() - face.descender()) as f32;
+                let line_gap = face.line_gap() as f32;
+                (font_height + line_gap) / font_height * font_size * line_height
+            }
+            None => 0.0,
+        }
+    }
+
+    /**
+     * Returns the ascender of the first font at the given font size.
+     */
+    pub fn get_ascender(&self, font_size: f32) -> f32 {
+        match self.faces.first() {
+            Some(face) => face.ascender() as f32 / (face.ascender() - face.descender()) as f32 * font_size,
+            None => 0.0,
+        }
+    }
+
+    /**
+     * Returns the advance width of the given single line text, like nvgTextBounds().
+     */
+    pub fn measure_width(&self, text: &str, font_size: f32) -> f32 {
+        // fontstash works with font sizes in tenths of pixels
+        let size = (font_size * 10.0) as i32 as f32 / 10.0;
+
+        let mut width = 0.0;
+        let mut previous: Option<(usize, GlyphId)> = None;
+
+        for character in text.chars() {
+            let (font, glyph) = match self.find_glyph(character) {
+                Some(found) => found,
+                None => {
+                    previous = None;
+                    continue;
+                }
+            };
+
+            let face = &self.faces[font];
+            let scale = get_pixel_height_scale(face, size);
+
+            // Kerning only applies between two glyphs of the same font
+            if let Some((previous_font, previous_glyph)) = previous {
+                if previous_font == font {
+                    width += round_advance(get_kerning(face, previous_glyph, glyph) as f32 * scale);
+                }
+            }
+
+            let advance = face.glyph_hor_advance(glyph).unwrap_or(0) as f32 * scale;
+            width += round_advance(truncate_tenth(advance));
+
+            previous = Some((font, glyph));
+        }
+
+        width
+    }
+
+    /**
+     * Breaks the given text in lines no wider than the given width, like nvgTextBreakLines().
+     */
+    pub fn break_lines(&self, text: &str, font_size: f32, max_width: f32) -> Vec<TextLine> {
+        break_lines_with(text, max_width, |part| self.measure_width(part, font_size))
+    }
+
+    /**
+     * Returns the size of the given paragraph wrapped in the given width, like nvgTextBoxBounds().
+     */
+    pub fn measure_paragraph(&self, text: &str, font_size: f32, line_height: f32, max_width: f32) -> (f32, f32) {
+        let lines = self.break_lines(text, font_size, max_width);
+        let width = lines.iter().fold(0.0f32, |width, line| width.max(line.width));
+        let height = lines.len() as f32 * self.get_line_height(font_size, line_height);
+
+        (width, height)
+    }
+
+    /**
+     * Returns the font (index in the fonts list) and glyph of the given character,
+     * going through the fallback fonts like fontstash does.
+     */
+    fn find_glyph(&self, character: char) -> Option<(usize, GlyphId)> {
+        self.faces.iter()
+            .enumerate()
+            .find_map(|(index, face)| face.glyph_index(character).map(|glyph| (index, glyph)))
+            .or_else(|| self.faces.first().map(|_| (0, GlyphId(0))))
+    }
+}
+
+// stbtt_ScaleForPixelHeight()
+fn get_pixel_height_scale(face: &Face, size: f32) -> f32 {
+    size / (face.ascender() - face.descender()) as f32
+}
+
+// fontstash stores advances as tenths of pixels in a short
+fn truncate_tenth(advance: f32) -> f32 {
+    (advance * 10.0) as i16 as f32 / 10.0
+}
+
+// fontstash moves the pen by whole pixels
+fn round_advance(advance: f32) -> f32 {
+    (advance + 0.5) as i32 as f32
+}
+
+fn get_kerning(face: &Face, left: GlyphId, right: GlyphId) -> i16 {
+    face.tables().kern
+        .and_then(|kern| {
+            kern.subtables.into_iter()
+                .filter(|subtable| subtable.horizontal && !subtable.variable)
+                .find_map(|subtable| subtable.glyphs_kerning(left, right))
+        })
+        .unwrap_or(0)
+}
+
+// Line breaking classes of the characters, see nvgTextBreakLines()
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BreakClass {
+    Space,
+    NewLine,
+    CjkChar, // can be broken before and after, like the spaces
+    Char,
+}
+
+fn get_break_class(character: char) -> BreakClass {
+    match character {
+        '\t' | '\u{0B}' | '\u{0C}' | ' ' | '\u{A0}' => BreakClass::Space,
+        '\n' | '\r' | '\u{85}' => BreakClass::NewLine,
+        '\u{4E00}'..='\u{9FFF}'
+        | '\u{3000}'..='\u{30FF}'
+        | '\u{FF00}'..='\u{FFEF}'
+        | '\u{1100}'..='\u{11FF}'
+        | '\u{3130}'..='\u{318F}'
+        | '\u{AC00}'..='\u{D7AF}' => BreakClass::CjkChar,
+        _ => BreakClass::Char,
+    }
+}
+
+fn is_space(character: char) -> bool {
+    get_break_class(character) == BreakClass::Space
+}
+
+/**
+ * Breaks the given text in lines no wider than the given width, measuring
+ * the parts of the text with the given function. Follows nvgTextBreakLines():
+ * lines break on spaces (tabs and no-break spaces included) and around CJK characters,
+ * words wider than the line are broken on characters, trailing spaces do not count
+ * in the line width and new lines (LF, CR, CRLF, NEL) always break.
+ */
+pub fn break_lines_with<F: Fn(&str) -> f32>(text: &str, max_width: f32, measure: F) -> Vec<TextLine> {
+    let mut lines = vec![];
+
+    let mut paragraph_start = 0;
+    let mut characters = text.char_indices().peekable();
+    while let Some((offset, character)) = characters.next() {
+        if get_break_class(character) != BreakClass::NewLine {
+            continue;
+        }
+
+        break_paragraph(text, paragraph_start, offset, max_width, &measure, &mut lines);
+        paragraph_start = offset + character.len_utf8();
+
+        if character == '\r' && characters.peek().map(|(_, next)| *next) == Some('\n') {
+            characters.next();
+            paragraph_start += 1;
+        }
+    }
+
+    break_paragraph(text, paragraph_start, text.len(), max_width, &measure, &mut lines);
+    lines
+}
+
+fn break_paragraph<F: Fn(&str) -> f32>(text: &str, start: usize, end: usize, max_width: f32, measure: &F, lines: &mut Vec<TextLine>) {
+    if start == end {
+        lines.push(TextLine {
+            start,
+            end,
+            width: 0.0,
+        });
+        return;
+    }
+
+    let mut line_start = start;
+
+    while line_start < end {
+        // Skip the spaces at the beginning of a wrapped line
+        if line_start > start {
+            line_start += text[line_start..end].len() - text[line_start..end].trim_start_matches(is_space).len();
+            if line_start >= end {
+                break;
+            }
+        }
+
+        let mut line_end = line_start;
+        let mut last_break: Option<usize> = None;
+        let mut previous_class = None;
+
+        for (offset, character) in text[line_start..end].char_indices() {
+            let position = line_start + offset;
+            let next = position + character.len_utf8();
+            let class = get_break_class(character);
+
+            // Breaks before spaces, and before and after CJK characters
+            if class == BreakClass::Space || (offset > 0 && (class == BreakClass::CjkChar || previous_class == Some(BreakClass::CjkChar))) {
+                last_break = Some(position);
+            }
+            previous_class = Some(class);
+
+            if measure(text[line_start..next].trim_end_matches(is_space)) > max_width && line_end > line_start {
+                break;
+            }
+
+            line_end = next;
+        }
+
+        // The line does not fit: break on the last opportunity if any, on the last character otherwise
+        if line_end < end {
+            if let Some(last_break) = last_break.filter(|last_break| *last_break > line_start) {
+                line_end = last_break;
+            }
+        }
+
+        let line = text[line_start..line_end].trim_end_matches(is_space);
+        lines.push(TextLine {
+            start: line_start,
+            end: line_start + line.len(),
+            width: measure(line),
+        });
+
+        line_start = line_end;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Monospace font, 10 pixels per character
+    fn measure(text: &str) -> f32 {
+        text.chars().count() as f32 * 10.0
+    }
+
+    fn get_lines<'a>(text: &'a str, lines: &[TextLine]) -> Vec<&'a str> {
+        lines.iter().map(|line| &text[line.start..line.end]).collect()
+    }
+
+    #[test]
+    fn single_line_fits() {
+        let text = "hello world";
+        let lines = break_lines_with(text, 200.0, measure);
+
+        assert_eq!(get_lines(text, &lines), vec!["hello world"]);
+        assert_eq!(lines[0].width, 110.0);
+    }
+
+    #[test]
+    fn wraps_on_spaces() {
+        let text = "the quick brown fox";
+        let lines = break_lines_with(text, 100.0, measure);
+
+        assert_eq!(get_lines(text, &lines), vec!["the quick", "brown fox"]);
+        assert_eq!(lines[0].width, 90.0);
+    }
+
+    #[test]
+    fn breaks_long_words() {
+        let text = "abcdefghij";
+        let lines = break_lines_with(text, 40.0, measure);
+
+        assert_eq!(get_lines(text, &lines), vec!["abcd", "efgh", "ij"]);
+    }
+
+    #[test]
+    fn keeps_new_lines_and_empty_lines() {
+        let text = "one\n\ntwo";
+        let lines = break_lines_with(text, 100.0, measure);
+
+        assert_eq!(get_lines(text, &lines), vec!["one", "", "two"]);
+    }
+
+    #[test]
+    fn breaks_around_cjk_characters_and_on_every_space() {
+        let text = "日本語のテキスト";
+        let lines = break_lines_with(text, 30.0, measure);
+        assert_eq!(get_lines(text, &lines), vec!["日本語", "のテキ", "スト"]);
+
+        // A latin word after CJK text moves to the next line as a whole
+        let text = "中文abc";
+        let lines = break_lines_with(text, 40.0, measure);
+        assert_eq!(get_lines(text, &lines), vec!["中文", "abc"]);
+
+        let text = "one\ttwo\u{A0}three";
+        let lines = break_lines_with(text, 50.0, measure);
+        assert_eq!(get_lines(text, &lines), vec!["one", "two", "three"]);
+    }
+
+    #[test]
+    fn breaks_on_every_new_line() {
+        let text = "one\r\ntwo\rthree\u{85}four";
+        let lines = break_lines_with(text, 100.0, measure);
+
+        assert_eq!(get_lines(text, &lines), vec!["one", "two", "three", "four"]);
+    }
+
+    #[test]
+    fn measures_with_the_font_file() {
+        static FONT: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fonts/DejaVuSans.ttf"));
+        let metrics = FontMetrics::from_data(FONT).unwrap();
+
+        // 1540 units advance, 2384 units from descender to ascender: 15.5 pixels rounded up
+        assert_eq!(metrics.measure_width("H", 24.0), 16.0);
+        assert_eq!(metrics.measure_width("HH", 24.0), 32.0);
+
+        let word = metrics.measure_width("world", 24.0);
+        let lines = metrics.break_lines("world hello", 24.0, word + 1.0);
+        assert_eq!(get_lines("world hello", &lines), vec!["world", "hello"]);
+        assert_eq!(lines[0].width, word);
+        assert_eq!(lines[1].width, metrics.measure_width("hello", 24.0));
+
+        let (width, height) = metrics.measure_paragraph("world hello", 24.0, 1.0, word + 1.0);
+        assert_eq!(width, word);
+        assert_eq!(height, 2.0 * metrics.get_line_height(24.0, 1.0));
+    }
+
+    #[test]
+    fn rounds_advances_like_fontstash() {
+        assert_eq!(truncate_tenth(12.349), 12.3);
+        assert_eq!(round_advance(12.3), 12.0);
+        assert_eq!(round_advance(12.5), 13.0);
+    }
+}
@@ -1,22 +1,96 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 use nanovg::Context as NVGcontext;
 use crate::lib::core::font::FontStash;
 use crate::lib::core::theme::Theme;
+use crate::lib::core::view_tree::ViewTree;
+
+// The layers of a frame, drawn one after the other
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DrawLayer {
+    Content, // the views in the layout
+    Overlay, // the detached views, above the content
+    Debug, // the wireframes of the views, see set_wireframe_mode()
+}
+
+// Counters of a frame, shared by all the contexts of the frame
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FrameStats {
+    pub drawn_views: usize,
+    pub culled_views: usize, // skipped by their parent for being out of its viewport
+}
+
 pub struct FrameContext<'a> {
     pub vg: Rc<RefCell<NVGcontext>>,
     pub pixel_ratio: f32,
     pub font_stash: &'a FontStash,
     pub theme: &'a Theme,
+    pub tree: &'a ViewTree, // the drawn views, containers draw their children from it
+    pub layer: DrawLayer,
+    stats: Rc<Cell<FrameStats>>,
 }
 
-impl FrameContext<'_> {
-    pub fn new<'a>(vg: Rc<RefCell<NVGcontext>>, pixel_ratio: f32, font_stash: &'a FontStash, theme: &'a Theme) -> FrameContext<'a> {
+impl<'a> FrameContext<'a> {
+    pub fn new(vg: Rc<RefCell<NVGcontext>>, pixel_ratio: f32, font_stash: &'a FontStash, theme: &'a Theme, tree: &'a ViewTree) -> FrameContext<'a> {
         FrameContext {
             vg,
             pixel_ratio,
             font_stash,
             theme,
+            tree,
+            layer: DrawLayer::Content,
+            stats: Rc::new(Cell::new(FrameStats::default())),
         }
     }
-}
\ No newline at end of file
+
+    /**
+     * Returns a copy of this context drawing with the given theme,
+     * given to the children of a view overriding the theme.
+     */
+    pub fn with_theme<'b>(&self, theme: &'b Theme) -> FrameContext<'b> where 'a: 'b {
+        FrameContext {
+            vg: Rc::clone(&self.vg),
+            pixel_ratio: self.pixel_ratio,
+            font_stash: self.font_stash,
+            theme,
+            tree: self.tree,
+            layer: self.layer,
+            stats: Rc::clone(&self.stats),
+        }
+    }
+
+    /**
+     * Returns a copy of this context drawing the given layer.
+     * On the overlay pass, detached views are drawn with their whole subtree with the content layer.
+     */
+    pub fn with_layer(&self, layer: DrawLayer) -> FrameContext<'a> {
+        FrameContext {
+            vg: Rc::clone(&self.vg),
+            pixel_ratio: self.pixel_ratio,
+            font_stash: self.font_stash,
+            theme: self.theme,
+            tree: self.tree,
+            layer,
+            stats: Rc::clone(&self.stats),
+        }
+    }
+
+    pub fn count_drawn_view(&self) {
+        let mut stats = self.stats.get();
+        stats.drawn_views += 1;
+        self.stats.set(stats);
+    }
+
+    pub fn count_culled_view(&self) {
+        let mut stats = self.stats.get();
+        stats.culled_views += 1;
+        self.stats.set(stats);
+    }
+
+    /**
+     * Returns the counters of the frame so far, all layers included.
+     */
+    pub fn get_stats(&self) -> FrameStats {
+        self.stats.get()
+    }
+}
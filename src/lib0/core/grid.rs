@@ -0,0 +1,423 @@
+use crate::lib::core::base_view::FocusDirection;
+
+// Size of a grid row or column
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrackSize
+{
+    Fixed(f32),    // size in pixels
+    Fraction(f32), // share of the space left by the other tracks ("1fr", "2fr"...)
+    Auto,          // size of the largest item in the track
+}
+
+impl TrackSize {
+    /**
+     * Parses a track size: "120" (pixels), "1fr", "2.5fr" or "auto".
+     */
+    pub fn parse(value: &str) -> Option<TrackSize> {
+        let value = value.trim();
+
+        if value == "auto" {
+            Some(TrackSize::Auto)
+        } else if let Some(fraction) = value.strip_suffix("fr") {
+            fraction.trim().parse().ok().filter(|fraction: &f32| *fraction > 0.0).map(TrackSize::Fraction)
+        } else {
+            value.strip_suffix("px").unwrap_or(value).trim().parse().ok().map(TrackSize::Fixed)
+        }
+    }
+
+    /**
+     * Parses a space separated list of track sizes, like the "columns" XML attribute:
+     * "200 1fr auto". Returns None if one of the sizes is invalid.
+     */
+    pub fn parse_list(value: &str) -> Option<Vec<TrackSize>> {
+        value.split_whitespace().map(TrackSize::parse).collect()
+    }
+}
+
+// Area covered by an item, in tracks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridArea {
+    pub row: usize,
+    pub column: usize,
+    pub row_span: usize,
+    pub column_span: usize,
+}
+
+impl GridArea {
+    pub fn new(row: usize, column: usize, row_span: usize, column_span: usize) -> Self {
+        Self {
+            row,
+            column,
+            row_span: row_span.max(1),
+            column_span: column_span.max(1),
+        }
+    }
+
+    fn get_range(&self, rows: bool) -> (usize, usize) {
+        match rows {
+            true => (self.row, self.row + self.row_span),
+            false => (self.column, self.column + self.column_span),
+        }
+    }
+}
+
+// Position of an item requested by the app, the missing coordinates are found by auto placement
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridPlacement {
+    pub row: Option<usize>,
+    pub column: Option<usize>,
+    pub row_span: usize,
+    pub column_span: usize,
+}
+
+impl Default for GridPlacement {
+    fn default() -> Self {
+        Self {
+            row: None,
+            column: None,
+            row_span: 1,
+            column_span: 1,
+        }
+    }
+}
+
+// Position and size of a track after sizing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Track {
+    pub offset: f32,
+    pub size: f32,
+}
+
+/**
+ * Places the items in a grid of the given number of columns, rows being added as needed.
+ * Items with a row and a column are placed first, the other ones fill the free cells
+ * in reading order, like CSS grid auto placement with a row flow.
+ */
+pub fn place_items(placements: &[GridPlacement], columns: usize) -> Vec<GridArea> {
+    let columns = columns.max(1);
+    let mut occupied: Vec<Vec<bool>> = vec![];
+    let mut areas = vec![None; placements.len()];
+
+    let occupy = |occupied: &mut Vec<Vec<bool>>, area: &GridArea| {
+        while occupied.len() < area.row + area.row_span {
+            occupied.push(vec![false; columns]);
+        }
+
+        for row in &mut occupied[area.row..area.row + area.row_span] {
+            for cell in &mut row[area.column..(area.column + area.column_span).min(columns)] {
+                *cell = true;
+            }
+        }
+    };
+
+    let is_free = |occupied: &Vec<Vec<bool>>, area: &GridArea| {
+        area.column + area.column_span <= columns
+            && (area.row..area.row + area.row_span).all(|row| {
+                occupied.get(row).is_none_or(|cells| cells[area.column..area.column + area.column_span].iter().all(|cell| !cell))
+            })
+    };
+
+    for (index, placement) in placements.iter().enumerate() {
+        if let (Some(row), Some(column)) = (placement.row, placement.column) {
+            let column = column.min(columns - 1);
+            let area = GridArea::new(row, column, placement.row_span, placement.column_span.min(columns - column));
+            occupy(&mut occupied, &area);
+            areas[index] = Some(area);
+        }
+    }
+
+    let mut cursor = (0, 0);
+    for (index, placement) in placements.iter().enumerate() {
+        if areas[index].is_some() {
+            continue;
+        }
+
+        let column_span = placement.column_span.clamp(1, columns);
+        let (mut row, mut column) = match (placement.row, placement.column) {
+            (Some(row), None) => (row, 0),
+            (None, Some(column)) => (cursor.0, column.min(columns - column_span)),
+            _ => cursor,
+        };
+
+        let area = loop {
+            let area = GridArea::new(row, column, placement.row_span, column_span);
+            if is_free(&occupied, &area) {
+                break area;
+            }
+
+            // Fixed column: next row, otherwise next cell
+            if placement.column.is_some() || column + 1 + column_span > columns {
+                row += 1;
+                column = placement.column.map_or(0, |_| column);
+            } else {
+                column += 1;
+            }
+        };
+
+        occupy(&mut occupied, &area);
+        if placement.row.is_none() {
+            cursor = (area.row, area.column + area.column_span);
+        }
+        areas[index] = Some(area);
+    }
+
+    areas.into_iter().map(|area| area.unwrap()).collect()
+}
+
+/**
+ * Sizes the tracks of one axis of the grid.
+ *
+ * items gives the area of each item and its content size in this axis, margins included.
+ * Fixed tracks take their size, auto tracks the size of their largest item (items spanning
+ * several tracks grow the auto tracks they span if they don't fit), and fraction tracks share
+ * what is left of the available space. Without available space, fraction tracks are sized
+ * after their content, keeping their ratios.
+ */
+pub fn size_tracks(tracks: &[TrackSize], items: &[(GridArea, f32)], rows: bool, available: Option<f32>, gap: f32) -> Vec<Track> {
+    let mut sizes: Vec<f32> = tracks.iter()
+        .map(|track| match track {
+            TrackSize::Fixed(size) => *size,
+            _ => 0.0,
+        })
+        .collect();
+
+    let is_auto = |track: &TrackSize, available: Option<f32>| match track {
+        TrackSize::Auto => true,
+        TrackSize::Fraction(_) => available.is_none(),
+        TrackSize::Fixed(_) => false,
+    };
+
+    // Items spanning a single track first, then the spanning ones
+    let mut sorted: Vec<&(GridArea, f32)> = items.iter().collect();
+    sorted.sort_by_key(|(area, _)| {
+        let (start, end) = area.get_range(rows);
+        end - start
+    });
+
+    let mut fraction_unit: f32 = 0.0;
+    for (area, content) in sorted {
+        let (start, end) = area.get_range(rows);
+        let end = end.min(tracks.len());
+        if start >= end {
+            continue;
+        }
+
+        // Indefinite fractions: the size of one fraction is given by the largest item
+        if end - start == 1 {
+            if let (TrackSize::Fraction(fraction), None) = (tracks[start], available) {
+                fraction_unit = fraction_unit.max(content / fraction);
+                continue;
+            }
+        }
+
+        let spanned: f32 = sizes[start..end].iter().sum::<f32>() + gap * (end - start - 1) as f32;
+        let auto_tracks: Vec<usize> = (start..end).filter(|track| is_auto(&tracks[*track], available)).collect();
+
+        if *content > spanned && !auto_tracks.is_empty() {
+            let extra = (content - spanned) / auto_tracks.len() as f32;
+            for track in auto_tracks {
+                sizes[track] += extra;
+            }
+        }
+    }
+
+    let fraction_sum: f32 = tracks.iter()
+        .map(|track| match track {
+            TrackSize::Fraction(fraction) => *fraction,
+            _ => 0.0,
+        })
+        .sum();
+
+    if fraction_sum > 0.0 {
+        let unit = match available {
+            Some(available) => {
+                let used: f32 = tracks.iter().zip(&sizes)
+                    .filter(|(track, _)| !matches!(track, TrackSize::Fraction(_)))
+                    .map(|(_, size)| size)
+                    .sum();
+                let gaps = gap * tracks.len().saturating_sub(1) as f32;
+
+                // Fractions summing under 1 take that share of the space, like in CSS
+                (available - used - gaps).max(0.0) / fraction_sum.max(1.0)
+            }
+            None => fraction_unit,
+        };
+
+        for (track, size) in tracks.iter().zip(&mut sizes) {
+            if let TrackSize::Fraction(fraction) = track {
+                *size = size.max(unit * fraction);
+            }
+        }
+    }
+
+    let mut offset = 0.0;
+    sizes.into_iter()
+        .map(|size| {
+            let track = Track {
+                offset,
+                size,
+            };
+            offset += size + gap;
+            track
+        })
+        .collect()
+}
+
+/**
+ * Returns the (offset, size) of the given span of tracks, gaps included.
+ */
+pub fn get_span(tracks: &[Track], start: usize, span: usize) -> (f32, f32) {
+    let end = (start + span).min(tracks.len());
+
+    match (tracks.get(start), end.checked_sub(1).and_then(|last| tracks.get(last))) {
+        (Some(first), Some(last)) if end > start => (first.offset, last.offset + last.size - first.offset),
+        _ => (0.0, 0.0),
+    }
+}
+
+/**
+ * Returns the index of the item to focus when moving in the given direction from
+ * the current item, in two dimensions: the closest item in the direction, preferring
+ * items overlapping the current one on the other axis, then the closest to its center.
+ * Only items accepted by can_focus are considered.
+ */
+pub fn find_next_item<F: Fn(usize) -> bool>(areas: &[GridArea], current: usize, direction: FocusDirection, can_focus: F) -> Option<usize> {
+    let from = areas.get(current)?;
+
+    // Main axis of the move: rows for up / down
+    let (rows, forward) = match direction {
+        FocusDirection::Up => (true, false),
+        FocusDirection::Down => (true, true),
+        FocusDirection::Left => (false, false),
+        FocusDirection::Right => (false, true),
+    };
+
+    let (from_start, from_end) = from.get_range(rows);
+    let (from_cross_start, from_cross_end) = from.get_range(!rows);
+    let from_cross_center = (from_cross_start + from_cross_end) as f32 / 2.0;
+
+    areas.iter()
+        .enumerate()
+        .filter(|(index, _)| *index != current && can_focus(*index))
+        .filter_map(|(index, area)| {
+            let (start, end) = area.get_range(rows);
+            let distance = match forward {
+                true if start >= from_end => start - from_end,
+                false if end <= from_start => from_start - end,
+                _ => return None,
+            };
+
+            let (cross_start, cross_end) = area.get_range(!rows);
+            let overlaps = cross_start < from_cross_end && cross_end > from_cross_start;
+            let cross_distance = ((cross_start + cross_end) as f32 / 2.0 - from_cross_center).abs();
+
+            Some((index, (distance, !overlaps, cross_distance)))
+        })
+        .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(index, _)| index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_sizes(tracks: &[Track]) -> Vec<f32> {
+        tracks.iter().map(|track| track.size).collect()
+    }
+
+    #[test]
+    fn parses_tracks() {
+        assert_eq!(
+            TrackSize::parse_list("200 1fr auto 2.5fr"),
+            Some(vec![TrackSize::Fixed(200.0), TrackSize::Fraction(1.0), TrackSize::Auto, TrackSize::Fraction(2.5)])
+        );
+        assert_eq!(TrackSize::parse_list("200 wide"), None);
+    }
+
+    #[test]
+    fn fractions_share_remaining_space() {
+        let tracks = [TrackSize::Fixed(100.0), TrackSize::Fraction(1.0), TrackSize::Auto, TrackSize::Fraction(2.0)];
+        let items = [(GridArea::new(0, 2, 1, 1), 80.0)];
+        let sized = size_tracks(&tracks, &items, false, Some(730.0), 10.0);
+
+        // 730 - 100 - 80 - 3 gaps = 520, shared 1:2
+        assert_eq!(get_sizes(&sized), vec![100.0, 520.0 / 3.0, 80.0, 1040.0 / 3.0]);
+        assert_eq!(sized[2].offset, 100.0 + 10.0 + 520.0 / 3.0 + 10.0);
+    }
+
+    #[test]
+    fn spanning_item_grows_auto_tracks() {
+        let tracks = [TrackSize::Fixed(100.0), TrackSize::Auto, TrackSize::Auto];
+        let items = [
+            (GridArea::new(0, 1, 1, 1), 50.0),
+            (GridArea::new(1, 0, 1, 3), 300.0),
+        ];
+        let sized = size_tracks(&tracks, &items, false, None, 10.0);
+
+        // 300 - (100 + 50 + 0 + 2 gaps) = 130 shared by the two auto tracks
+        assert_eq!(get_sizes(&sized), vec![100.0, 115.0, 65.0]);
+    }
+
+    #[test]
+    fn indefinite_fractions_follow_content() {
+        let tracks = [TrackSize::Fraction(1.0), TrackSize::Fraction(2.0)];
+        let items = [(GridArea::new(0, 0, 1, 1), 60.0), (GridArea::new(1, 0, 1, 1), 40.0)];
+        let sized = size_tracks(&tracks, &items, true, None, 0.0);
+
+        // One fraction is 60 pixels to fit the first item
+        assert_eq!(get_sizes(&sized), vec![60.0, 120.0]);
+    }
+
+    #[test]
+    fn auto_placement_skips_occupied_cells() {
+        let placements = [
+            GridPlacement { row: Some(0), column: Some(1), ..GridPlacement::default() },
+            GridPlacement::default(),
+            GridPlacement { column_span: 2, ..GridPlacement::default() },
+            GridPlacement::default(),
+            GridPlacement { row_span: 2, ..GridPlacement::default() },
+        ];
+        let areas = place_items(&placements, 3);
+
+        assert_eq!(areas, vec![
+            GridArea::new(0, 1, 1, 1),
+            GridArea::new(0, 0, 1, 1),
+            GridArea::new(1, 0, 1, 2),
+            GridArea::new(1, 2, 1, 1),
+            GridArea::new(2, 0, 2, 1),
+        ]);
+    }
+
+    // 3x3 grid with a wide item in the middle row:
+    //   0 1 2
+    //   3 3 4
+    //   5 6 7
+    fn get_areas() -> Vec<GridArea> {
+        vec![
+            GridArea::new(0, 0, 1, 1), GridArea::new(0, 1, 1, 1), GridArea::new(0, 2, 1, 1),
+            GridArea::new(1, 0, 1, 2), GridArea::new(1, 2, 1, 1),
+            GridArea::new(2, 0, 1, 1), GridArea::new(2, 1, 1, 1), GridArea::new(2, 2, 1, 1),
+        ]
+    }
+
+    #[test]
+    fn navigates_in_two_dimensions() {
+        let areas = get_areas();
+        let all = |_| true;
+
+        assert_eq!(find_next_item(&areas, 1, FocusDirection::Down, all), Some(3));
+        assert_eq!(find_next_item(&areas, 2, FocusDirection::Down, all), Some(4));
+        assert_eq!(find_next_item(&areas, 4, FocusDirection::Left, all), Some(3));
+        assert_eq!(find_next_item(&areas, 3, FocusDirection::Down, all), Some(5));
+        assert_eq!(find_next_item(&areas, 7, FocusDirection::Up, all), Some(4));
+        assert_eq!(find_next_item(&areas, 2, FocusDirection::Right, all), None);
+    }
+
+    #[test]
+    fn navigation_skips_unfocusable_cells() {
+        let areas = get_areas();
+
+        // Column 2 of the middle row can't be focused: go to the closest cell below
+        assert_eq!(find_next_item(&areas, 2, FocusDirection::Down, |index| index != 4), Some(3));
+    }
+}
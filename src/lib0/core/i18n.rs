@@ -1,5 +1,116 @@
-use dyn_fmt::AsStrFormatExt;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use log::{error, info};
+use once_cell::sync::Lazy;
+use crate::lib::core::flex::set_layout_direction;
+use crate::lib::core::r#box::Direction;
+use crate::lib::core::resources::{list_resources, read_resource_string};
 
-pub fn get_str(string_name: String, _args: Vec<String>) -> String {
-    string_name
-}
\ No newline at end of file
+// Translations of the current locale, flattened as "file/key/subkey" -> string
+static TRANSLATIONS: Lazy<RwLock<HashMap<String, String>>> = Lazy::new(|| {
+    RwLock::new(HashMap::new())
+});
+
+// Languages written right to left, the UI is mirrored for them
+// Kurdish is only listed as Sorani (ckb), Kurmanji (ku) being written in the Latin script
+const RIGHT_TO_LEFT_LANGUAGES: &[&str] = &["ar", "he", "fa", "ur", "yi", "ps", "sd", "ug", "dv", "ckb"];
+
+/**
+ * Returns true if the given locale ("ar", "he-IL", "fa_IR"...) is written right to left.
+ */
+pub fn is_right_to_left_locale(locale: &str) -> bool {
+    let language = locale.split(['-', '_']).next().unwrap_or_default().to_lowercase();
+    RIGHT_TO_LEFT_LANGUAGES.contains(&language.as_str())
+}
+
+/**
+ * Loads every translation file of the given locale from "@res/i18n/<locale>/".
+ * The layout direction of the UI follows the locale.
+ * Returns the number of loaded strings.
+ */
+pub fn load_translations(locale: &str) -> usize {
+    let mut translations = HashMap::new();
+
+    for path in list_resources(&format!("@res/i18n/{}", locale)) {
+        let file_name = match path.rsplit('/').next().and_then(|name| name.strip_suffix(".json")) {
+            Some(file_name) => file_name.to_string(),
+            None => continue,
+        };
+
+        let content = match read_resource_string(&path) {
+            Some(content) => content,
+            None => continue,
+        };
+
+        match serde_json::from_str::<serde_json::Value>(&content) {
+            Ok(value) => flatten_translations(&file_name, &value, &mut translations),
+            Err(err) => error!("Invalid translation file \"{}\": {}", path, err),
+        }
+    }
+
+    info!("Loaded {} strings for locale {}", translations.len(), locale);
+
+    set_layout_direction(match is_right_to_left_locale(locale) {
+        true => Direction::RightToLeft,
+        false => Direction::LeftToRight,
+    });
+
+    let count = translations.len();
+    *TRANSLATIONS.write().unwrap() = translations;
+    count
+}
+
+fn flatten_translations(prefix: &str, value: &serde_json::Value, translations: &mut HashMap<String, String>) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, child) in map {
+                flatten_translations(&format!("{}/{}", prefix, key), child, translations);
+            }
+        }
+        serde_json::Value::String(string) => {
+            translations.insert(prefix.into(), string.clone());
+        }
+        _ => {}
+    }
+}
+
+pub fn get_str(string_name: String, args: Vec<String>) -> String {
+    match TRANSLATIONS.read().unwrap().get(&string_name) {
+        Some(string) => format_str(string, &args),
+        None => string_name,
+    }
+}
+
+/**
+ * Replaces the "{}" placeholders of the given string by the given arguments, in order.
+ * Placeholders without argument are left empty.
+ */
+fn format_str(string: &str, args: &[String]) -> String {
+    let mut parts = string.split("{}");
+    let mut formatted = parts.next().unwrap_or_default().to_string();
+
+    for (index, part) in parts.enumerate() {
+        if let Some(arg) = args.get(index) {
+            formatted.push_str(arg);
+        }
+        formatted.push_str(part);
+    }
+
+    formatted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_right_to_left_locales() {
+        for locale in ["ar", "he-IL", "fa_IR", "AR-eg", "ckb", "ckb-IQ"] {
+            assert!(is_right_to_left_locale(locale), "{}", locale);
+        }
+
+        for locale in ["en-US", "ku", "ku-TR", "zh_CN", "arn", ""] {
+            assert!(!is_right_to_left_locale(locale), "{}", locale);
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+use log::{info, warn};
+use once_cell::sync::Lazy;
+use crate::lib::core::font::{FONT_MATERIAL_ICONS, FONT_SWITCH_ICONS};
+use crate::lib::core::input::ControllerButton;
+use crate::lib::core::resources::read_resource_string;
+
+// Prefix of the XML attribute values resolved through the icon registry ("@icon/settings")
+pub const ICON_PREFIX: &str = "@icon/";
+
+// Codepoints file shipped next to the Material icons font
+pub const MATERIAL_ICONS_CODEPOINTS_PATH: &str = "@res/material/MaterialIcons-Regular.codepoints";
+
+// Fonts searched when an icon name is not qualified with its font, by order of priority
+const ICON_FONTS_LOOKUP_ORDER: [&str; 2] = [FONT_SWITCH_ICONS, FONT_MATERIAL_ICONS];
+
+// Glyphs of the Switch icons font, see the HOS shared symbols font
+const SWITCH_ICONS: &[(&str, u32)] = &[
+    ("button_a", 0xE0E0),
+    ("button_b", 0xE0E1),
+    ("button_x", 0xE0E2),
+    ("button_y", 0xE0E3),
+    ("button_l", 0xE0E4),
+    ("button_r", 0xE0E5),
+    ("button_zl", 0xE0E6),
+    ("button_zr", 0xE0E7),
+    ("dpad_up", 0xE0EB),
+    ("dpad_down", 0xE0EC),
+    ("dpad_left", 0xE0ED),
+    ("dpad_right", 0xE0EE),
+    ("button_plus", 0xE0EF),
+    ("button_minus", 0xE0F0),
+    ("button_home", 0xE0F4),
+    ("stick_l_press", 0xE104),
+    ("stick_r_press", 0xE105),
+];
+
+// A glyph of an icon font, ready to be drawn as text with the given font
+#[derive(Debug, Clone, PartialEq)]
+pub struct Icon {
+    pub font: String,
+    pub codepoint: char,
+}
+
+impl Icon {
+    /**
+     * Returns the glyph as a string, to be given to the text drawing functions.
+     */
+    pub fn get_text(&self) -> String {
+        self.codepoint.to_string()
+    }
+}
+
+// Maps icon names to codepoints, for each icon font of the font stash
+pub struct IconRegistry {
+    fonts: HashMap<String, HashMap<String, char>>,
+}
+
+impl Default for IconRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IconRegistry {
+    pub fn new() -> Self {
+        let mut registry = Self {
+            fonts: HashMap::new(),
+        };
+
+        for (name, codepoint) in SWITCH_ICONS {
+            registry.register(FONT_SWITCH_ICONS, name, *codepoint);
+        }
+
+        registry
+    }
+
+    /**
+     * Registers an icon of the given font.
+     * Returns false if the codepoint is not a valid character.
+     */
+    pub fn register(&mut self, font: &str, name: &str, codepoint: u32) -> bool {
+        match char::from_u32(codepoint) {
+            Some(codepoint) => {
+                self.fonts.entry(font.into()).or_default().insert(name.into(), codepoint);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /**
+     * Registers every icon of a codepoints file, made of "name hexcodepoint" lines
+     * (the format of the file shipped with the Material icons font).
+     * Returns the number of registered icons.
+     */
+    pub fn register_codepoints(&mut self, font: &str, content: &str) -> usize {
+        let mut count = 0;
+
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let registered = line.split_once(char::is_whitespace)
+                .and_then(|(name, codepoint)| {
+                    u32::from_str_radix(codepoint.trim().trim_start_matches("0x"), 16).ok().map(|codepoint| (name, codepoint))
+                })
+                .map(|(name, codepoint)| self.register(font, name, codepoint))
+                .unwrap_or(false);
+
+            if registered {
+                count += 1;
+            } else {
+                warn!("Ignoring malformed line {} in the codepoints of font \"{}\"", index + 1, font);
+            }
+        }
+
+        count
+    }
+
+    /**
+     * Returns the icon with the given name. The name can be qualified with its font
+     * ("material/home"), otherwise the Switch icons then the Material icons are searched.
+     */
+    pub fn get(&self, name: &str) -> Option<Icon> {
+        if let Some((font, icon)) = name.split_once('/') {
+            return self.get_in_font(font, icon);
+        }
+
+        ICON_FONTS_LOOKUP_ORDER.iter().find_map(|font| self.get_in_font(font, name))
+    }
+
+    fn get_in_font(&self, font: &str, name: &str) -> Option<Icon> {
+        self.fonts.get(font)
+            .and_then(|icons| icons.get(name))
+            .map(|codepoint| Icon {
+                font: font.into(),
+                codepoint: *codepoint,
+            })
+    }
+}
+
+// The icons of every icon font loaded by the platform
+static ICONS: Lazy<RwLock<IconRegistry>> = Lazy::new(|| {
+    RwLock::new(IconRegistry::new())
+});
+
+/**
+ * Loads the codepoints file at the given path for the given icon font,
+ * "@res/..." paths being resolved through the mounted resources.
+ * Returns true if the file could be read.
+ */
+pub fn load_codepoints(font: &str, path: &str) -> bool {
+    match read_resource_string(path) {
+        Some(content) => {
+            let count = ICONS.write().unwrap().register_codepoints(font, &content);
+            info!("Loaded {} icons for font \"{}\" from \"{}\"", count, font, path);
+            true
+        }
+        None => {
+            warn!("Could not load the codepoints of font \"{}\" from \"{}\"", font, path);
+            false
+        }
+    }
+}
+
+/**
+ * Registers an icon of the given font, for apps using their own icon fonts.
+ */
+pub fn register_icon(font: &str, name: &str, codepoint: u32) -> bool {
+    ICONS.write().unwrap().register(font, name, codepoint)
+}
+
+/**
+ * Returns the icon with the given name, see IconRegistry::get().
+ */
+pub fn get_icon(name: &str) -> Option<Icon> {
+    let icon = ICONS.read().unwrap().get(name);
+
+    if icon.is_none() {
+        warn!("Unknown icon \"{}\"", name);
+    }
+
+    icon
+}
+
+/**
+ * Resolves an "@icon/name" XML attribute value.
+ */
+pub fn get_icon_xml_attribute_value(value: &str) -> Option<Icon> {
+    value.strip_prefix(ICON_PREFIX).and_then(get_icon)
+}
+
+/**
+ * Returns the name of the icon representing the given button in the action hints.
+ */
+pub fn get_button_icon_name(button: &ControllerButton) -> &'static str {
+    match button {
+        ControllerButton::ButtonA => "button_a",
+        ControllerButton::ButtonB => "button_b",
+        ControllerButton::ButtonX => "button_x",
+        ControllerButton::ButtonY => "button_y",
+        ControllerButton::ButtonLb => "button_l",
+        ControllerButton::ButtonRb => "button_r",
+        ControllerButton::ButtonLt => "button_zl",
+        ControllerButton::ButtonRt => "button_zr",
+        ControllerButton::ButtonUp => "dpad_up",
+        ControllerButton::ButtonDown => "dpad_down",
+        ControllerButton::ButtonLeft => "dpad_left",
+        ControllerButton::ButtonRight => "dpad_right",
+        ControllerButton::ButtonStart => "button_plus",
+        ControllerButton::ButtonBack => "button_minus",
+        ControllerButton::ButtonGuide => "button_home",
+        ControllerButton::ButtonLsb => "stick_l_press",
+        ControllerButton::ButtonRsb => "stick_r_press",
+    }
+}
+
+/**
+ * Returns the icon representing the given button in the action hints.
+ */
+pub fn get_button_icon(button: &ControllerButton) -> Option<Icon> {
+    get_icon(&format!("{}/{}", FONT_SWITCH_ICONS, get_button_icon_name(button)))
+}
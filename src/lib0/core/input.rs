@@ -1,11 +1,10 @@
-use std::convert::Into;
-use strum::{EnumCount, IntoEnumIterator};
+use strum::EnumCount;
 use strum_macros::{EnumCount as EnumCountMacro, EnumIter};
 
 // Abstract buttons enum - names correspond to a generic Xbox controller
 // LT and RT should not be buttons but for the sake of simplicity we'll assume they are.
 // Similarly, DPAD (also called HAT) is assumed to be buttons here.
-#[derive(Debug, PartialEq, EnumCountMacro, EnumIter)]
+#[derive(Debug, Clone, Copy, PartialEq, EnumCountMacro, EnumIter)]
 pub enum ControllerButton {
     ButtonLt = 0,
     ButtonLb,
@@ -50,6 +49,12 @@ pub struct ControllerState{
     pub axes: [f64; ControllerAxis::COUNT],
 }
 
+impl Default for ControllerState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ControllerState {
     pub fn new() -> Self {
         ControllerState {
@@ -1,23 +1,36 @@
-pub(crate) mod video;
-pub(crate) mod application;
-pub(crate) mod platform;
-pub(crate) mod font;
-pub(crate) mod actions;
-pub(crate) mod audio;
-pub(crate) mod input;
-pub(crate) mod animation;
-pub(crate) mod bind;
-pub(crate) mod r#box;
-pub(crate) mod event;
-pub(crate) mod frame_context;
-pub(crate) mod i18n;
-pub(crate) mod style;
-pub(crate) mod task;
-pub(crate) mod theme;
-pub(crate) mod time;
-pub(crate) mod timer;
-pub(crate) mod util;
-pub(crate) mod base_view;
-pub(crate) mod activity;
-pub(crate) mod xml;
-pub(crate) mod view;
\ No newline at end of file
+pub mod video;
+pub mod accessibility;
+pub mod application;
+pub mod config;
+pub mod platform;
+pub mod font;
+pub mod font_metrics;
+pub mod icons;
+pub mod actions;
+pub mod audio;
+pub mod input;
+pub mod music;
+pub mod animation;
+pub mod bind;
+pub mod color;
+pub mod r#box;
+pub mod debug;
+pub mod event;
+pub mod flex;
+pub mod frame_context;
+pub mod grid;
+pub mod i18n;
+pub mod resources;
+pub mod style;
+pub mod task;
+pub mod text_shaping;
+pub mod theme;
+pub mod theme_file;
+pub mod time;
+pub mod timer;
+pub mod util;
+pub mod base_view;
+pub mod activity;
+pub mod xml;
+pub mod view;
+pub mod view_tree;
\ No newline at end of file
@@ -0,0 +1,471 @@
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use log::{error, info};
+use crate::lib::core::animation::{Animatable, EasingFunction};
+use crate::lib::core::audio::AudioPlayer;
+use crate::lib::core::time::Ticking;
+
+// Default duration of the crossfade between two activities tracks, in ms
+const DEFAULT_CROSSFADE_DURATION: i32 = 1000;
+
+// Size of the chunks read from disk by FileStreamSource
+const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+// A background music track, declared by an activity
+#[derive(Debug, Clone, PartialEq)]
+pub struct MusicTrack {
+    pub path: String,
+    pub volume: f32,
+    pub looping: bool,
+}
+
+impl MusicTrack {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.into(),
+            volume: 1.0,
+            looping: true,
+        }
+    }
+}
+
+// A music file streamed from disk, created by the platform AudioPlayer
+// The stream must decode the file progressively instead of loading it whole (see FileStreamSource)
+pub trait MusicStream {
+    /**
+     * Starts or resumes the playback.
+     * Returns a boolean indicating if the stream is playing or not.
+     */
+    fn play(&mut self) -> bool;
+
+    /**
+     * Pauses the playback, keeping the current position.
+     */
+    fn pause(&mut self);
+
+    /**
+     * Stops the playback and releases the file.
+     */
+    fn stop(&mut self);
+
+    /**
+     * Sets the stream volume between 0.0 and 1.0, applied immediately.
+     */
+    fn set_volume(&mut self, volume: f32);
+
+    /**
+     * Feeds the decoder with the next part of the file, called every frame while playing.
+     */
+    fn update(&mut self) {}
+
+    /**
+     * Returns true once the stream cannot play anymore: a track that does not loop reached its end,
+     * or the stream was stopped.
+     */
+    fn is_finished(&self) -> bool {
+        false
+    }
+}
+
+// Receives the chunks of a FileMusicStream with the stream volume,
+// returns the number of bytes taken by the decoder
+pub type MusicSink = Box<dyn FnMut(&[u8], f32) -> usize>;
+
+// Reads a music file from disk chunk by chunk, for platforms that
+// feed their decoder progressively
+pub struct FileStreamSource {
+    reader: BufReader<File>,
+}
+
+impl FileStreamSource {
+    pub fn open(path: &str) -> Option<Self> {
+        match File::open(path) {
+            Ok(file) => Some(Self {
+                reader: BufReader::with_capacity(STREAM_CHUNK_SIZE, file),
+            }),
+            Err(err) => {
+                error!("Could not open music file \"{}\": {}", path, err);
+                None
+            }
+        }
+    }
+
+    /**
+     * Reads the next chunk of the file into the given buffer.
+     * Returns the number of bytes read, 0 meaning the end of the file has been reached.
+     */
+    pub fn read_chunk(&mut self, buffer: &mut [u8]) -> usize {
+        self.reader.read(buffer).unwrap_or(0)
+    }
+
+    /**
+     * Goes back to the beginning of the file, to loop the track.
+     */
+    pub fn rewind(&mut self) -> bool {
+        self.reader.seek(SeekFrom::Start(0)).is_ok()
+    }
+}
+
+// A MusicStream reading its file with a FileStreamSource and giving the chunks
+// to the platform decoder as it takes them, rewinding the file to loop the track
+pub struct FileMusicStream {
+    source: Option<FileStreamSource>,
+    sink: MusicSink,
+    chunk: Vec<u8>,
+    pending: usize, // bytes of the chunk not taken by the decoder yet
+    looping: bool,
+    playing: bool,
+    volume: f32,
+}
+
+impl FileMusicStream {
+    /**
+     * Opens the given file, created paused.
+     * Returns None if the file could not be opened.
+     */
+    pub fn open(path: &str, looping: bool, sink: MusicSink) -> Option<Self> {
+        Some(Self {
+            source: Some(FileStreamSource::open(path)?),
+            sink,
+            chunk: vec![0; STREAM_CHUNK_SIZE],
+            pending: 0,
+            looping,
+            playing: false,
+            volume: 1.0,
+        })
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+}
+
+impl MusicStream for FileMusicStream {
+    fn play(&mut self) -> bool {
+        self.playing = self.source.is_some();
+        self.playing
+    }
+
+    fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    fn stop(&mut self) {
+        self.playing = false;
+        self.source = None;
+        self.pending = 0;
+    }
+
+    fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+    }
+
+    fn is_finished(&self) -> bool {
+        self.source.is_none()
+    }
+
+    fn update(&mut self) {
+        if !self.playing {
+            return;
+        }
+
+        let source = match self.source.as_mut() {
+            Some(source) => source,
+            None => return,
+        };
+
+        // Read the next chunk once the decoder took the previous one
+        if self.pending == 0 {
+            let mut read = source.read_chunk(&mut self.chunk);
+
+            if read == 0 && self.looping && source.rewind() {
+                read = source.read_chunk(&mut self.chunk);
+            }
+
+            if read == 0 {
+                self.stop();
+                return;
+            }
+
+            self.chunk.copy_within(0..read, STREAM_CHUNK_SIZE - read);
+            self.pending = read;
+        }
+
+        let start = STREAM_CHUNK_SIZE - self.pending;
+        let taken = (self.sink)(&self.chunk[start..], self.volume);
+        self.pending -= taken.min(self.pending);
+    }
+}
+
+// A track currently alive in the player, either playing, fading or paused
+struct PlayingTrack {
+    track: MusicTrack,
+    stream: Box<dyn MusicStream>,
+    fade: Animatable,
+    paused: bool,
+}
+
+// Plays the background music of the activities stack, crossfading
+// between tracks when activities are pushed and popped
+pub struct MusicPlayer {
+    tracks_stack: Vec<Option<MusicTrack>>,
+    streams: Vec<PlayingTrack>,
+    crossfade_duration: i32,
+    crossfade_easing: EasingFunction,
+    gain: f32,
+}
+
+impl Default for MusicPlayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MusicPlayer {
+    pub fn new() -> Self {
+        Self {
+            tracks_stack: vec![],
+            streams: vec![],
+            crossfade_duration: DEFAULT_CROSSFADE_DURATION,
+            crossfade_easing: EasingFunction::QuadraticInOut,
+            gain: 1.0,
+        }
+    }
+
+    /**
+     * Sets the duration in ms and the easing curve of the crossfade between tracks.
+     */
+    pub fn set_crossfade(&mut self, duration: i32, easing: EasingFunction) {
+        self.crossfade_duration = duration;
+        self.crossfade_easing = easing;
+    }
+
+    /**
+     * Sets the gain applied on top of every track volume (master volume, app category gain...).
+     */
+    pub fn set_gain(&mut self, gain: f32) {
+        self.gain = gain;
+        self.apply_volumes();
+    }
+
+    /**
+     * Called when an activity is pushed, with the track it declares.
+     * An activity without track keeps the music of the activity under it.
+     */
+    pub fn on_activity_pushed(&mut self, track: Option<MusicTrack>, audio_player: &dyn AudioPlayer) {
+        let previous = self.get_current_track();
+        self.tracks_stack.push(track);
+        self.on_current_track_changed(previous, audio_player);
+    }
+
+    /**
+     * Called when an activity is popped, resumes the track of the activity under it.
+     */
+    pub fn on_activity_popped(&mut self, audio_player: &dyn AudioPlayer) {
+        let previous = self.get_current_track();
+        self.tracks_stack.pop();
+        self.on_current_track_changed(previous, audio_player);
+    }
+
+    /**
+     * Returns the track that should be playing for the current activities stack.
+     */
+    pub fn get_current_track(&self) -> Option<MusicTrack> {
+        self.tracks_stack.iter().rev().find_map(|track| track.clone())
+    }
+
+    /**
+     * Advances the crossfades, to be called every frame with the time elapsed since the last one.
+     */
+    pub fn update(&mut self, delta: i64) {
+        for playing in self.streams.iter_mut() {
+            playing.fade.update(delta);
+
+            if !playing.paused {
+                playing.stream.update();
+            }
+        }
+
+        // Faded out tracks are paused if an activity of the stack still uses them, stopped otherwise
+        // Ended tracks are dropped, to be opened again when their activity comes back
+        let current = self.get_current_track();
+        let tracks_stack = &self.tracks_stack;
+
+        self.streams.retain_mut(|playing| {
+            if playing.stream.is_finished() {
+                info!("Background music \"{}\" ended", playing.track.path);
+                return false;
+            }
+
+            let faded_out = !playing.fade.is_running() && playing.fade.get_value() <= 0.0;
+            if !faded_out || Some(&playing.track) == current.as_ref() {
+                return true;
+            }
+
+            if tracks_stack.iter().any(|track| track.as_ref() == Some(&playing.track)) {
+                if !playing.paused {
+                    playing.stream.pause();
+                    playing.paused = true;
+                }
+                true
+            } else {
+                playing.stream.stop();
+                false
+            }
+        });
+
+        self.apply_volumes();
+    }
+
+    fn on_current_track_changed(&mut self, previous: Option<MusicTrack>, audio_player: &dyn AudioPlayer) {
+        let current = self.get_current_track();
+
+        if previous == current {
+            return;
+        }
+
+        let duration = self.crossfade_duration;
+        let easing = self.crossfade_easing;
+
+        // Fade out the previous track
+        if let Some(previous) = &previous {
+            if let Some(playing) = self.streams.iter_mut().find(|playing| &playing.track == previous) {
+                Self::start_fade(&mut playing.fade, 0.0, duration, easing);
+            }
+        }
+
+        // Fade in the new one, resuming it if it was paused
+        if let Some(current) = current {
+            match self.streams.iter_mut().find(|playing| playing.track == current) {
+                Some(playing) => {
+                    if playing.paused {
+                        playing.stream.play();
+                        playing.paused = false;
+                    }
+                    Self::start_fade(&mut playing.fade, 1.0, duration, easing);
+                }
+                None => {
+                    match audio_player.open_music_stream(&current.path, current.looping) {
+                        Some(mut stream) => {
+                            info!("Starting background music \"{}\"", current.path);
+                            stream.set_volume(0.0);
+                            stream.play();
+
+                            let mut fade = Animatable::new(0.0);
+                            fade.set_ignore_reduced_motion(true);
+                            Self::start_fade(&mut fade, 1.0, duration, easing);
+
+                            self.streams.push(PlayingTrack {
+                                track: current,
+                                stream,
+                                fade,
+                                paused: false,
+                            });
+                        }
+                        None => {
+                            error!("Could not stream background music \"{}\"", current.path);
+                        }
+                    }
+                }
+            }
+        }
+
+        self.apply_volumes();
+    }
+
+    fn start_fade(fade: &mut Animatable, target: f32, duration: i32, easing: EasingFunction) {
+        let value = fade.get_value();
+        fade.reset_with_value(value);
+        fade.add_step(target, duration, easing);
+        fade.start_self_updated();
+    }
+
+    fn apply_volumes(&mut self) {
+        for playing in self.streams.iter_mut() {
+            if !playing.paused {
+                let volume = playing.track.volume * playing.fade.get_value() * self.gain;
+                playing.stream.set_volume(volume);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::env::temp_dir;
+    use std::fs;
+    use std::rc::Rc;
+    use crate::lib::core::audio::NullAudioPlayer;
+    use super::*;
+
+    // Opens a stream on a file with the given content, its sink taking
+    // at most the given number of bytes per frame
+    fn open_stream(name: &str, content: &[u8], looping: bool, taken_per_frame: usize) -> (FileMusicStream, Rc<RefCell<Vec<u8>>>) {
+        let path = temp_dir().join(name);
+        fs::write(&path, content).unwrap();
+
+        let received = Rc::new(RefCell::new(vec![]));
+        let sink_received = Rc::clone(&received);
+        let sink: MusicSink = Box::new(move |chunk, _volume| {
+            let taken = chunk.len().min(taken_per_frame);
+            sink_received.borrow_mut().extend_from_slice(&chunk[..taken]);
+            taken
+        });
+
+        (FileMusicStream::open(path.to_str().unwrap(), looping, sink).unwrap(), received)
+    }
+
+    #[test]
+    fn streams_the_file_as_the_decoder_takes_it() {
+        let (mut stream, received) = open_stream("borealis-music-stream.ogg", b"abcdef", false, 4);
+
+        stream.update();
+        assert!(received.borrow().is_empty(), "a paused stream must not be read");
+
+        stream.play();
+        stream.update();
+        assert_eq!(received.borrow().as_slice(), b"abcd");
+
+        stream.update();
+        stream.update();
+        assert_eq!(received.borrow().as_slice(), b"abcdef");
+        assert!(!stream.is_playing(), "the stream must stop at the end of a track that does not loop");
+    }
+
+    #[test]
+    fn reopens_ended_tracks_when_their_activity_comes_back() {
+        let path = temp_dir().join("borealis-music-ended.ogg");
+        fs::write(&path, b"abc").unwrap();
+
+        let mut track = MusicTrack::new(path.to_str().unwrap());
+        track.looping = false;
+
+        let audio_player = NullAudioPlayer::new();
+        let mut player = MusicPlayer::new();
+        player.on_activity_pushed(Some(track.clone()), &audio_player);
+
+        player.update(16);
+        player.update(16);
+        assert!(player.streams.is_empty(), "the ended track must be dropped");
+
+        player.on_activity_pushed(Some(MusicTrack::new("borealis-missing.ogg")), &audio_player);
+        player.on_activity_popped(&audio_player);
+
+        assert_eq!(player.streams.len(), 1);
+        assert_eq!(player.streams[0].track, track);
+        assert!(!player.streams[0].stream.is_finished());
+    }
+
+    #[test]
+    fn rewinds_looping_tracks() {
+        let (mut stream, received) = open_stream("borealis-music-loop.ogg", b"abc", true, usize::MAX);
+
+        stream.play();
+        stream.update();
+        stream.update();
+
+        assert_eq!(received.borrow().as_slice(), b"abcabc");
+        assert!(stream.is_playing());
+    }
+}
\ No newline at end of file
@@ -25,13 +25,37 @@ pub trait Platform {
     /**
      * Can be called at anytime to get the current system theme variant.
      *
-     * For now, the variant is assumed to stay the same during the whole time
-     * the app is running (no variant hot swap).
+     * This is the variant the app starts with, the app can then switch
+     * variants at runtime with Application::set_theme_variant().
      *
      * As such, the result should be cached by the platform code.
      */
     fn get_theme_variant(&self) -> ThemeVariant;
 
+    /**
+     * Called at every iteration of the main loop to follow the system theme.
+     * Returns the new system theme variant if it changed since the last call.
+     */
+    fn poll_theme_variant_change(&mut self) -> Option<ThemeVariant> {
+        None
+    }
+
+    /**
+     * Called at every iteration of the main loop.
+     * Returns true once each time the debug wireframe hotkey is pressed.
+     */
+    fn poll_wireframe_hotkey(&mut self) -> bool {
+        false
+    }
+
+    /**
+     * Called at every iteration of the main loop.
+     * Returns true once each time the debug view tree dump hotkey is pressed.
+     */
+    fn poll_view_tree_dump_hotkey(&mut self) -> bool {
+        false
+    }
+
 
     /**
      * Can be called at anytime to get the current locale
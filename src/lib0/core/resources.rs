@@ -0,0 +1,411 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::RwLock;
+use log::{error, info, warn};
+use once_cell::sync::Lazy;
+
+// Prefix of the paths resolved through the resources, equivalent to "romfs:/" upstream
+pub const RESOURCES_PREFIX: &str = "@res/";
+
+// Directory mounted by default, next to the executable
+pub const DEFAULT_RESOURCES_DIRECTORY: &str = "resources";
+
+const TAR_BLOCK_SIZE: usize = 512;
+
+// A tree of resource files, addressed by paths relative to its root ("font/regular.ttf")
+pub trait ResourceSource {
+    /**
+     * Returns the human-readable name of the source, for logging.
+     */
+    fn get_name(&self) -> String;
+
+    /**
+     * Returns the content of the file at the given relative path, if it exists in this source.
+     */
+    fn read(&self, path: &str) -> Option<Vec<u8>>;
+
+    /**
+     * Returns the relative paths of the files directly inside the given directory.
+     */
+    fn list(&self, dir: &str) -> Vec<String>;
+}
+
+// Resources embedded in the binary at build time, see include_resources!()
+pub struct EmbeddedResources {
+    files: &'static [(&'static str, &'static [u8])],
+}
+
+impl EmbeddedResources {
+    pub fn new(files: &'static [(&'static str, &'static [u8])]) -> Self {
+        Self {
+            files,
+        }
+    }
+}
+
+impl ResourceSource for EmbeddedResources {
+    fn get_name(&self) -> String {
+        "embedded".into()
+    }
+
+    fn read(&self, path: &str) -> Option<Vec<u8>> {
+        self.files.iter()
+            .find(|(name, _)| *name == path)
+            .map(|(_, data)| data.to_vec())
+    }
+
+    fn list(&self, dir: &str) -> Vec<String> {
+        list_files_in(self.files.iter().map(|(name, _)| *name), dir)
+    }
+}
+
+// Resources read from a directory at runtime
+pub struct DirectoryResources {
+    root: PathBuf,
+}
+
+impl DirectoryResources {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+        }
+    }
+
+    /**
+     * Returns the path of the given relative path in the root directory.
+     * Returns None for paths escaping the root: absolute paths, drive prefixes and "..".
+     */
+    fn resolve(&self, path: &str) -> Option<PathBuf> {
+        let relative = Path::new(path);
+        let inside_root = relative.components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+
+        if inside_root { Some(self.root.join(relative)) } else { None }
+    }
+}
+
+impl ResourceSource for DirectoryResources {
+    fn get_name(&self) -> String {
+        self.root.to_string_lossy().into()
+    }
+
+    fn read(&self, path: &str) -> Option<Vec<u8>> {
+        fs::read(self.resolve(path)?).ok()
+    }
+
+    fn list(&self, dir: &str) -> Vec<String> {
+        let entries = match self.resolve(dir).map(fs::read_dir) {
+            Some(Ok(entries)) => entries,
+            _ => return vec![],
+        };
+
+        let dir = dir.trim_end_matches('/');
+        let mut files: Vec<String> = entries.flatten()
+            .filter(|entry| entry.path().is_file())
+            .map(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if dir.is_empty() { name } else { format!("{}/{}", dir, name) }
+            })
+            .collect();
+
+        files.sort();
+        files
+    }
+}
+
+// Resources read from a tar archive at runtime, fully loaded in memory
+pub struct ArchiveResources {
+    name: String,
+    files: HashMap<String, Vec<u8>>,
+}
+
+impl ArchiveResources {
+    /**
+     * Opens the tar archive at the given path.
+     * Returns None if the file could not be read or is not a valid tar archive.
+     */
+    pub fn open(path: &Path) -> Option<Self> {
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(err) => {
+                error!("Could not read resources archive {:?}: {}", path, err);
+                return None;
+            }
+        };
+
+        match parse_tar(&data) {
+            Some(files) => Some(Self {
+                name: path.to_string_lossy().into(),
+                files,
+            }),
+            None => {
+                error!("Resources archive {:?} is not a valid tar archive", path);
+                None
+            }
+        }
+    }
+}
+
+impl ResourceSource for ArchiveResources {
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn read(&self, path: &str) -> Option<Vec<u8>> {
+        self.files.get(path).cloned()
+    }
+
+    fn list(&self, dir: &str) -> Vec<String> {
+        list_files_in(self.files.keys().map(|name| name.as_str()), dir)
+    }
+}
+
+fn list_files_in<'a>(names: impl Iterator<Item = &'a str>, dir: &str) -> Vec<String> {
+    let dir = dir.trim_end_matches('/');
+    let prefix = if dir.is_empty() { String::new() } else { format!("{}/", dir) };
+
+    let mut files: Vec<String> = names
+        .filter(|name| name.starts_with(&prefix) && !name[prefix.len()..].contains('/'))
+        .map(|name| name.to_string())
+        .collect();
+
+    files.sort();
+    files
+}
+
+/**
+ * Parses an uncompressed ustar archive, returning its regular files by path.
+ */
+fn parse_tar(data: &[u8]) -> Option<HashMap<String, Vec<u8>>> {
+    let mut files = HashMap::new();
+    let mut offset = 0;
+
+    while offset + TAR_BLOCK_SIZE <= data.len() {
+        let header = &data[offset..offset + TAR_BLOCK_SIZE];
+
+        // Two empty blocks mark the end of the archive
+        if header.iter().all(|byte| *byte == 0) {
+            break;
+        }
+
+        let name = read_tar_string(&header[0..100]);
+        let size = usize::from_str_radix(read_tar_string(&header[124..136]).trim(), 8).ok()?;
+        let type_flag = header[156];
+        let prefix = if &header[257..262] == b"ustar" { read_tar_string(&header[345..500]) } else { String::new() };
+
+        let data_start = offset + TAR_BLOCK_SIZE;
+        let data_end = data_start + size;
+
+        if data_end > data.len() {
+            return None;
+        }
+
+        // Regular files only
+        if type_flag == b'0' || type_flag == 0 {
+            let path = if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) };
+            let path = path.trim_start_matches("./").to_string();
+            files.insert(path, data[data_start..data_end].to_vec());
+        }
+
+        offset = data_start + size.div_ceil(TAR_BLOCK_SIZE) * TAR_BLOCK_SIZE;
+    }
+
+    Some(files)
+}
+
+fn read_tar_string(field: &[u8]) -> String {
+    let end = field.iter().position(|byte| *byte == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into()
+}
+
+// Mounted resource sources, the last mounted source has the priority
+static RESOURCES: Lazy<RwLock<Vec<Box<dyn ResourceSource + Send + Sync>>>> = Lazy::new(|| {
+    RwLock::new(vec![])
+});
+
+/**
+ * Mounts the given source. Sources mounted later override the files of the previous ones,
+ * so an app can mount its embedded resources first and then a directory of user overrides.
+ */
+pub fn mount(source: Box<dyn ResourceSource + Send + Sync>) {
+    info!("Mounting resources from {}", source.get_name());
+    RESOURCES.write().unwrap().push(source);
+}
+
+/**
+ * Mounts resources embedded at build time with include_resources!().
+ */
+pub fn mount_embedded(files: &'static [(&'static str, &'static [u8])]) {
+    mount(Box::new(EmbeddedResources::new(files)));
+}
+
+/**
+ * Mounts a resources directory.
+ * Returns true if the directory exists.
+ */
+pub fn mount_directory(path: &Path) -> bool {
+    if !path.is_dir() {
+        error!("Cannot mount resources directory {:?}: not a directory", path);
+        return false;
+    }
+
+    mount(Box::new(DirectoryResources::new(path)));
+    true
+}
+
+/**
+ * Mounts a resources tar archive.
+ * Returns true if the archive could be opened.
+ */
+pub fn mount_archive(path: &Path) -> bool {
+    match ArchiveResources::open(path) {
+        Some(archive) => {
+            mount(Box::new(archive));
+            true
+        }
+        None => false,
+    }
+}
+
+/**
+ * Mounts the "resources" directory next to the executable, if there is one.
+ * Returns true if the directory was mounted.
+ */
+pub fn mount_default_directory() -> bool {
+    let dir = match std::env::current_exe() {
+        Ok(exe) => exe.with_file_name(DEFAULT_RESOURCES_DIRECTORY),
+        Err(_) => return false,
+    };
+
+    dir.is_dir() && mount_directory(&dir)
+}
+
+/**
+ * Unmounts every resource source.
+ */
+pub fn unmount_all() {
+    RESOURCES.write().unwrap().clear();
+}
+
+pub fn is_resource_path(path: &str) -> bool {
+    path.starts_with(RESOURCES_PREFIX)
+}
+
+/**
+ * Reads the file at the given path: "@res/..." paths are resolved through the
+ * mounted resources, any other path is read from the filesystem.
+ */
+pub fn read_resource(path: &str) -> Option<Vec<u8>> {
+    match path.strip_prefix(RESOURCES_PREFIX) {
+        Some(relative) => {
+            let sources = RESOURCES.read().unwrap();
+            let found = sources.iter().rev().find_map(|source| source.read(relative));
+
+            if found.is_none() {
+                warn!("Resource \"{}\" not found in any mounted resources", path);
+            }
+
+            found
+        }
+        None => fs::read(path).ok(),
+    }
+}
+
+/**
+ * Reads the file at the given path as UTF-8 text, see read_resource().
+ */
+pub fn read_resource_string(path: &str) -> Option<String> {
+    read_resource(path).and_then(|data| String::from_utf8(data).ok())
+}
+
+/**
+ * Lists the files directly inside the given "@res/..." directory, across every mounted source.
+ * Returned paths keep the "@res/" prefix.
+ */
+pub fn list_resources(dir: &str) -> Vec<String> {
+    let relative = dir.strip_prefix(RESOURCES_PREFIX).unwrap_or(dir);
+    let sources = RESOURCES.read().unwrap();
+
+    let mut files: Vec<String> = sources.iter()
+        .flat_map(|source| source.list(relative))
+        .map(|file| format!("{}{}", RESOURCES_PREFIX, file))
+        .collect();
+
+    files.sort();
+    files.dedup();
+    files
+}
+
+/**
+ * Expands to the table generated by borealis_build::generate_embedded_resources()
+ * in the build script of the app, to be given to mount_embedded().
+ */
+#[macro_export]
+macro_rules! include_resources {
+    () => {
+        include!(concat!(env!("OUT_DIR"), "/borealis_resources.rs"))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env::temp_dir;
+    use super::*;
+
+    // Builds a ustar header block for the given file
+    fn tar_header(name: &str, size: usize, type_flag: u8) -> Vec<u8> {
+        let mut header = vec![0; TAR_BLOCK_SIZE];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[124..135].copy_from_slice(format!("{:011o}", size).as_bytes());
+        header[156] = type_flag;
+        header[257..262].copy_from_slice(b"ustar");
+        header
+    }
+
+    fn tar_file(name: &str, content: &[u8]) -> Vec<u8> {
+        let mut data = tar_header(name, content.len(), b'0');
+        data.extend_from_slice(content);
+        data.resize(data.len().div_ceil(TAR_BLOCK_SIZE) * TAR_BLOCK_SIZE, 0);
+        data
+    }
+
+    #[test]
+    fn parses_regular_files_of_tar_archives() {
+        let mut data = tar_header("./xml/", 0, b'5');
+        data.extend(tar_file("./xml/main.xml", b"<brls:Box/>"));
+        data.extend(tar_file("font/regular.ttf", &[1; TAR_BLOCK_SIZE + 1]));
+        data.extend(vec![0; TAR_BLOCK_SIZE * 2]);
+
+        let files = parse_tar(&data).unwrap();
+
+        assert_eq!(files.len(), 2, "directories must be skipped");
+        assert_eq!(files["xml/main.xml"], b"<brls:Box/>");
+        assert_eq!(files["font/regular.ttf"].len(), TAR_BLOCK_SIZE + 1);
+    }
+
+    #[test]
+    fn rejects_truncated_tar_archives() {
+        let mut data = tar_file("xml/main.xml", &[1; 100]);
+        data.truncate(TAR_BLOCK_SIZE + 10);
+
+        assert!(parse_tar(&data).is_none());
+        assert!(parse_tar(&tar_header("xml/main.xml", 0, b'0')[..TAR_BLOCK_SIZE - 1]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn directory_resources_stay_inside_their_root() {
+        let root = temp_dir().join("borealis-resources-root");
+        fs::create_dir_all(root.join("xml")).unwrap();
+        fs::write(root.join("xml/main.xml"), "<brls:Box/>").unwrap();
+
+        let resources = DirectoryResources::new(&root);
+        let secret = root.join("xml/main.xml").to_string_lossy().to_string();
+
+        assert_eq!(resources.read("xml/main.xml").unwrap(), b"<brls:Box/>");
+        assert_eq!(resources.list("xml"), vec!["xml/main.xml".to_string()]);
+        assert!(resources.read(&secret).is_none(), "absolute paths must be rejected");
+        assert!(resources.read("../borealis-resources-root/xml/main.xml").is_none());
+        assert!(resources.list("/").is_empty());
+    }
+}
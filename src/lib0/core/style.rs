@@ -1,6 +1,96 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use log::error;
 use once_cell::sync::Lazy;
 
+// Typed name of a style metric, to look metrics up without typos
+// Built-in keys are in the metrics module, apps can create their own with MetricKey::new()
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MetricKey(&'static str);
+
+impl MetricKey {
+    pub const fn new(name: &'static str) -> Self {
+        MetricKey(name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.0
+    }
+}
+
+macro_rules! declare_metric_keys {
+    ($($constant:ident => $name:literal,)*) => {
+        $(pub const $constant: MetricKey = MetricKey::new($name);)*
+
+        // Every built-in metric, all required to be defined in the style
+        pub const BUILTIN_METRICS: &[MetricKey] = &[$($constant,)*];
+    };
+}
+
+// Keys of the built-in style metrics
+pub mod metrics {
+    use super::MetricKey;
+
+    declare_metric_keys! {
+        ANIMATIONS_SHOW => "brls/animations/show",
+        ANIMATIONS_SHOW_SLIDE => "brls/animations/show_slide",
+        ANIMATIONS_HIGHLIGHT => "brls/animations/highlight",
+        ANIMATIONS_HIGHLIGHT_SHAKE => "brls/animations/highlight_shake",
+        ANIMATIONS_LABEL_SCROLLING_TIMER => "brls/animations/label_scrolling_timer",
+        ANIMATIONS_LABEL_SCROLLING_SPEED => "brls/animations/label_scrolling_speed",
+        HIGHLIGHT_STROKE_WIDTH => "brls/highlight/stroke_width",
+        HIGHLIGHT_CORNER_RADIUS => "brls/highlight/corner_radius",
+        HIGHLIGHT_SHADOW_WIDTH => "brls/highlight/shadow_width",
+        HIGHLIGHT_SHADOW_OFFSET => "brls/highlight/shadow_offset",
+        HIGHLIGHT_SHADOW_FEATHER => "brls/highlight/shadow_feather",
+        HIGHLIGHT_SHADOW_OPACITY => "brls/highlight/shadow_opacity",
+        APPLET_FRAME_PADDING_SIDES => "brls/applet_frame/padding_sides",
+        APPLET_FRAME_HEADER_HEIGHT => "brls/applet_frame/header_height",
+        APPLET_FRAME_HEADER_PADDING_TOP_BOTTOM => "brls/applet_frame/header_padding_top_bottom",
+        APPLET_FRAME_HEADER_PADDING_SIDES => "brls/applet_frame/header_padding_sides",
+        APPLET_FRAME_HEADER_IMAGE_TITLE_SPACING => "brls/applet_frame/header_image_title_spacing",
+        APPLET_FRAME_HEADER_TITLE_FONT_SIZE => "brls/applet_frame/header_title_font_size",
+        APPLET_FRAME_HEADER_TITLE_TOP_OFFSET => "brls/applet_frame/header_title_top_offset",
+        APPLET_FRAME_FOOTER_HEIGHT => "brls/applet_frame/footer_height",
+        APPLET_FRAME_FOOTER_PADDING_TOP_BOTTOM => "brls/applet_frame/footer_padding_top_bottom",
+        APPLET_FRAME_FOOTER_PADDING_SIDES => "brls/applet_frame/footer_padding_sides",
+        TAB_FRAME_SIDEBAR_WIDTH => "brls/tab_frame/sidebar_width",
+        TAB_FRAME_CONTENT_PADDING_TOP_BOTTOM => "brls/tab_frame/content_padding_top_bottom",
+        TAB_FRAME_CONTENT_PADDING_SIDES => "brls/tab_frame/content_padding_sides",
+        SIDEBAR_BORDER_HEIGHT => "brls/sidebar/border_height",
+        SIDEBAR_PADDING_TOP => "brls/sidebar/padding_top",
+        SIDEBAR_PADDING_BOTTOM => "brls/sidebar/padding_bottom",
+        SIDEBAR_PADDING_LEFT => "brls/sidebar/padding_left",
+        SIDEBAR_PADDING_RIGHT => "brls/sidebar/padding_right",
+        SIDEBAR_ITEM_HEIGHT => "brls/sidebar/item_height",
+        SIDEBAR_ITEM_ACCENT_MARGIN_TOP_BOTTOM => "brls/sidebar/item_accent_margin_top_bottom",
+        SIDEBAR_ITEM_ACCENT_MARGIN_SIDES => "brls/sidebar/item_accent_margin_sides",
+        SIDEBAR_ITEM_ACCENT_RECT_WIDTH => "brls/sidebar/item_accent_rect_width",
+        SIDEBAR_ITEM_FONT_SIZE => "brls/sidebar/item_font_size",
+        SIDEBAR_SEPARATOR_HEIGHT => "brls/sidebar/separator_height",
+        LABEL_DEFAULT_FONT_SIZE => "brls/label/default_font_size",
+        LABEL_DEFAULT_LINE_HEIGHT => "brls/label/default_line_height",
+        LABEL_SCROLLING_ANIMATION_SPACING => "brls/label/scrolling_animation_spacing",
+        LABEL_HIGHLIGHT_PADDING => "brls/label/highlight_padding",
+        HEADER_PADDING_TOP_BOTTOM => "brls/header/padding_top_bottom",
+        HEADER_PADDING_RIGHT => "brls/header/padding_right",
+        HEADER_RECTANGLE_WIDTH => "brls/header/rectangle_width",
+        HEADER_RECTANGLE_HEIGHT => "brls/header/rectangle_height",
+        HEADER_RECTANGLE_MARGIN => "brls/header/rectangle_margin",
+        HEADER_FONT_SIZE => "brls/header/font_size",
+        BUTTON_PADDING_TOP_BOTTOM => "brls/button/padding_top_bottom",
+        BUTTON_PADDING_SIDES => "brls/button/padding_sides",
+        BUTTON_CORNER_RADIUS => "brls/button/corner_radius",
+        BUTTON_TEXT_SIZE => "brls/button/text_size",
+        BUTTON_PRIMARY_HIGHLIGHT_PADDING => "brls/button/primary_highlight_padding",
+        BUTTON_BORDER_THICKNESS => "brls/button/border_thickness",
+        SHADOW_WIDTH => "brls/shadow/width",
+        SHADOW_FEATHER => "brls/shadow/feather",
+        SHADOW_OPACITY => "brls/shadow/opacity",
+        SHADOW_OFFSET => "brls/shadow/offset",
+    }
+}
+
 pub struct StyleValues {
     values: HashMap<String, f32>
 }
@@ -23,17 +113,23 @@ impl StyleValues {
     pub fn get_metric(&self, name: &'_ str) -> Option<&f32> {
         self.values.get(name)
     }
+
+    pub fn has_metric(&self, name: &str) -> bool {
+        self.values.contains_key(name)
+    }
 }
 
 // Simple wrapper around StyleValues for the array operator
 pub struct Style  {
-    style_values: StyleValues
+    style_values: StyleValues,
+    reported_metrics: Mutex<HashSet<&'static str>>, // missing metrics already logged
 }
 
 impl Style {
     pub fn new(values: StyleValues) -> Style {
         Style {
-            style_values: values
+            style_values: values,
+            reported_metrics: Mutex::new(HashSet::new()),
         }
     }
 
@@ -44,10 +140,42 @@ impl Style {
     pub fn get_metric(&self, name: &'_ str) -> Option<&f32> {
         self.style_values.get_metric(name)
     }
+
+    pub fn has_metric(&self, name: &str) -> bool {
+        self.style_values.has_metric(name)
+    }
+
+    /**
+     * Returns the value of the given metric. Missing metrics are reported
+     * by validate_style() on startup, here they are logged once and read as 0.0.
+     */
+    pub fn get(&self, key: MetricKey) -> f32 {
+        match self.get_metric(key.name()) {
+            Some(value) => *value,
+            None => {
+                if self.report_missing(key) {
+                    error!("Missing style metric \"{}\"", key.name());
+                }
+                0.0
+            }
+        }
+    }
+
+    // Returns true the first time the given metric is reported missing
+    fn report_missing(&self, key: MetricKey) -> bool {
+        self.reported_metrics.lock().unwrap().insert(key.name())
+    }
+
+    /**
+     * Returns the value of the given metric, or the given default if it is not defined.
+     */
+    pub fn get_metric_or(&self, name: &str, default: f32) -> f32 {
+        self.get_metric(name).copied().unwrap_or(default)
+    }
 }
 
-pub static STYLE: Lazy<Style> = Lazy::new(||{
-    Style::new(StyleValues::new(
+static STYLE: Lazy<RwLock<Style>> = Lazy::new(||{
+    RwLock::new(Style::new(StyleValues::new(
         vec![
             // Animations
             ( "brls/animations/show", 250.0),
@@ -127,5 +255,73 @@ pub static STYLE: Lazy<Style> = Lazy::new(||{
             ( "brls/shadow/opacity", 63.75),
             ( "brls/shadow/offset", 10.0),
         ]
-    ))
-});
\ No newline at end of file
+    )))
+});
+
+pub fn get_style() -> RwLockReadGuard<'static, Style> {
+    STYLE.read().unwrap()
+}
+
+/**
+ * Gives write access to the style, to merge loaded metrics into it.
+ */
+pub fn get_style_mut() -> RwLockWriteGuard<'static, Style> {
+    STYLE.write().unwrap()
+}
+
+// Metrics declared by the app as required, checked by validate_style() with the built-in ones
+static REQUIRED_METRICS: Lazy<RwLock<Vec<String>>> = Lazy::new(|| {
+    RwLock::new(vec![])
+});
+
+/**
+ * Declares a metric of the app with its default value, used unless a theme file defines it.
+ */
+pub fn declare_metric(key: MetricKey, default: f32) {
+    let mut style = get_style_mut();
+
+    if !style.has_metric(key.name()) {
+        style.add_metric(key.name(), default);
+    }
+}
+
+/**
+ * Declares a metric the app needs but does not provide a default for:
+ * it must be defined by a theme file, validate_style() reports it otherwise.
+ */
+pub fn require_metric(key: MetricKey) {
+    REQUIRED_METRICS.write().unwrap().push(key.name().into());
+}
+
+/**
+ * Checks that every built-in and required metric is defined.
+ * Returns the names of all the missing metrics.
+ */
+pub fn validate_style() -> Vec<String> {
+    let style = get_style();
+    let required = REQUIRED_METRICS.read().unwrap();
+
+    let missing: Vec<String> = metrics::BUILTIN_METRICS.iter()
+        .map(|key| key.name().to_string())
+        .chain(required.iter().cloned())
+        .filter(|name| !style.has_metric(name))
+        .collect();
+
+    missing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_metrics_are_reported_once() {
+        let key = MetricKey::new("app/missing");
+        let style = Style::new(StyleValues::new(vec![("app/defined", 4.0)]));
+
+        assert_eq!(style.get(MetricKey::new("app/defined")), 4.0);
+        assert_eq!(style.get(key), 0.0);
+        assert!(!style.report_missing(key));
+        assert!(style.report_missing(MetricKey::new("app/other")));
+    }
+}
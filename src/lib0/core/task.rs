@@ -1,4 +1,4 @@
-use crate::lib::core::time::{empty_end_fn, empty_fn, Ticking, Timestamp};
+use crate::lib::core::time::{empty_end_fn, Ticking, Timestamp};
 use crate::lib::core::timer::RepeatingTimer;
 
 // A RepeatingTask is a task executed repeatedly on the main thread at a given period in ms
@@ -15,7 +15,7 @@ impl RepeatingTask {
      * handler runs, but it can wait for slightly longer (usually less than a ms late)
      */
     pub fn new(period: Timestamp) -> Self {
-        let mut repeat_timer = RepeatingTimer::new(period);
+        let repeat_timer = RepeatingTimer::new(period);
 
         let mut repeating_task = RepeatingTask {
             repeat_timer,
@@ -0,0 +1,485 @@
+use std::ops::Range;
+use log::warn;
+use nanovg::Color;
+use rustybuzz::{Face, UnicodeBuffer};
+use ttf_parser::{GlyphId, OutlineBuilder};
+use unicode_bidi::{BidiInfo, Level};
+use crate::lib::core::font::FontStash;
+use crate::lib::core::frame_context::FrameContext;
+use crate::lib::core::r#box::Direction;
+
+// A glyph positioned on the line, in pixels relative to the start of the text on the baseline
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShapedGlyph {
+    pub font: usize, // index of the font in the shaper fonts list
+    pub glyph_id: u16,
+    pub x: f32,
+    pub y: f32,
+    pub advance: f32,
+    pub cluster: usize, // byte offset of the source character in the text
+}
+
+// A line of text shaped and reordered in visual order, ready to be drawn
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShapedText {
+    pub glyphs: Vec<ShapedGlyph>,
+    pub width: f32,
+    pub font_size: f32,
+}
+
+// Shapes text with HarfBuzz rules (ligatures, Arabic joining, Indic reordering...) after
+// splitting it in bidi runs, using the fonts registered in the font stash.
+// The first font is the primary one, the next ones are used for the characters it does not cover.
+pub struct TextShaper<'a> {
+    faces: Vec<Face<'a>>,
+}
+
+impl<'a> TextShaper<'a> {
+    /**
+     * Creates a shaper for the given fonts of the font stash, by order of preference.
+     * Fonts that were not loaded from memory are skipped.
+     */
+    pub fn new(font_stash: &'a FontStash, fonts: &[&str]) -> Self {
+        let faces = fonts.iter()
+            .filter_map(|font| {
+                let face = font_stash.get_font_data(font).and_then(|data| Face::from_slice(data, 0));
+
+                if face.is_none() {
+                    warn!("Font \"{}\" is not available for text shaping", font);
+                }
+
+                face
+            })
+            .collect();
+
+        Self {
+            faces,
+        }
+    }
+
+    /**
+     * Returns true if the shaper has at least one usable font.
+     */
+    pub fn is_valid(&self) -> bool {
+        !self.faces.is_empty()
+    }
+
+    /**
+     * Shapes a single line of text at the given font size. The base direction decides
+     * the order of the runs when the text mixes directions, Inherit detecting it from the text.
+     */
+    pub fn shape(&self, text: &str, font_size: f32, base_direction: &Direction) -> ShapedText {
+        let mut shaped = ShapedText {
+            glyphs: vec![],
+            width: 0.0,
+            font_size,
+        };
+
+        if text.is_empty() || !self.is_valid() {
+            return shaped;
+        }
+
+        let base_level = match base_direction {
+            Direction::Inherit => None,
+            Direction::LeftToRight => Some(Level::ltr()),
+            Direction::RightToLeft => Some(Level::rtl()),
+        };
+
+        let bidi = BidiInfo::new(text, base_level);
+
+        for paragraph in &bidi.paragraphs {
+            let (levels, runs) = bidi.visual_runs(paragraph, paragraph.range.clone());
+
+            for run in runs {
+                let rtl = levels[run.start].is_rtl();
+                let mut segments = self.split_by_font(text, run);
+
+                // Segments are in logical order, RTL runs are drawn from their end
+                if rtl {
+                    segments.reverse();
+                }
+
+                for (font, range) in segments {
+                    self.shape_segment(text, range, font, rtl, &mut shaped);
+                }
+            }
+        }
+
+        shaped
+    }
+
+    /**
+     * Splits the given range of the text in segments drawn with the same font, in logical order.
+     * Characters without glyph of their own (spaces, combining marks) stay with the previous segment.
+     */
+    fn split_by_font(&self, text: &str, range: Range<usize>) -> Vec<(usize, Range<usize>)> {
+        let mut segments: Vec<(usize, Range<usize>)> = vec![];
+
+        for (offset, character) in text[range.clone()].char_indices() {
+            let start = range.start + offset;
+            let end = start + character.len_utf8();
+
+            let inherits = character.is_whitespace() || is_combining_mark(character);
+            let font = match (inherits, segments.last()) {
+                (true, Some((font, _))) => *font,
+                _ => self.faces.iter()
+                    .position(|face| face.glyph_index(character).is_some())
+                    .unwrap_or(0),
+            };
+
+            match segments.last_mut() {
+                Some((last_font, last_range)) if *last_font == font => last_range.end = end,
+                _ => segments.push((font, start..end)),
+            }
+        }
+
+        segments
+    }
+
+    fn shape_segment(&self, text: &str, range: Range<usize>, font: usize, rtl: bool, shaped: &mut ShapedText) {
+        let face = &self.faces[font];
+        let scale = shaped.font_size / face.units_per_em() as f32;
+
+        let mut buffer = UnicodeBuffer::new();
+        buffer.push_str(&text[range.clone()]);
+        buffer.set_direction(if rtl { rustybuzz::Direction::RightToLeft } else { rustybuzz::Direction::LeftToRight });
+        buffer.guess_segment_properties();
+
+        let output = rustybuzz::shape(face, &[], buffer);
+
+        for (info, position) in output.glyph_infos().iter().zip(output.glyph_positions()) {
+            let advance = position.x_advance as f32 * scale;
+
+            shaped.glyphs.push(ShapedGlyph {
+                font,
+                glyph_id: info.glyph_id as u16,
+                x: shaped.width + position.x_offset as f32 * scale,
+                y: -position.y_offset as f32 * scale,
+                advance,
+                cluster: range.start + info.cluster as usize,
+            });
+
+            shaped.width += advance;
+        }
+    }
+
+    /**
+     * Draws the shaped text with its baseline starting at the given position,
+     * filling the glyph outlines with the given color. Counters (the inside of "o", "a"...)
+     * are drawn as holes.
+     */
+    pub fn draw(&self, ctx: &FrameContext, shaped: &ShapedText, x: f32, y: f32, color: Color) {
+        let vg = ctx.vg.borrow();
+
+        unsafe {
+            nanovg_sys::nvgBeginPath(vg.raw());
+        }
+
+        for glyph in &shaped.glyphs {
+            for contour in self.outline_glyph(shaped, glyph, x, y) {
+                contour.add_to_path(vg.raw());
+            }
+        }
+
+        unsafe {
+            nanovg_sys::nvgFillColor(vg.raw(), nanovg_sys::nvgRGBAf(color.red(), color.green(), color.blue(), color.alpha()));
+            nanovg_sys::nvgFill(vg.raw());
+        }
+    }
+
+    /**
+     * Returns the contours of the given glyph, in pixels with the text baseline starting at the given position.
+     */
+    fn outline_glyph(&self, shaped: &ShapedText, glyph: &ShapedGlyph, x: f32, y: f32) -> Vec<Contour> {
+        let face = match self.faces.get(glyph.font) {
+            Some(face) => face,
+            None => return vec![],
+        };
+
+        let mut builder = ContourBuilder {
+            scale: shaped.font_size / face.units_per_em() as f32,
+            x: x + glyph.x,
+            y: y + glyph.y,
+            contours: vec![],
+        };
+
+        face.outline_glyph(GlyphId(glyph.glyph_id), &mut builder);
+        builder.finish()
+    }
+}
+
+/**
+ * Returns true if the text needs to go through the shaper, false if nanovg
+ * can draw it directly (left to right text without joining or reordering).
+ */
+pub fn needs_shaping(text: &str) -> bool {
+    text.chars().any(|character| {
+        matches!(character as u32,
+            0x0590..=0x08FF     // Hebrew, Arabic, Syriac, Thaana, NKo...
+            | 0x0900..=0x0DFF   // Indic scripts
+            | 0x0E00..=0x0EFF   // Thai, Lao
+            | 0x0F00..=0x109F   // Tibetan, Myanmar
+            | 0x1780..=0x17FF   // Khmer
+            | 0x200C..=0x200F   // joiners and direction marks
+            | 0x202A..=0x202E   // bidi embeddings
+            | 0x2066..=0x2069   // bidi isolates
+            | 0xFB1D..=0xFDFF   // Hebrew and Arabic presentation forms
+            | 0xFE70..=0xFEFF
+        ) || is_combining_mark(character)
+    })
+}
+
+/**
+ * Returns true if the paragraph of the text containing the given byte offset goes right to left:
+ * the base direction if there is one, the direction of its first strong character for Inherit.
+ */
+pub fn is_right_to_left(text: &str, offset: usize, base_direction: &Direction) -> bool {
+    match base_direction {
+        Direction::LeftToRight => false,
+        Direction::RightToLeft => true,
+        Direction::Inherit => BidiInfo::new(text, None).paragraphs.iter()
+            .find(|paragraph| paragraph.range.contains(&offset))
+            .is_some_and(|paragraph| paragraph.level.is_rtl()),
+    }
+}
+
+fn is_combining_mark(character: char) -> bool {
+    matches!(character as u32, 0x0300..=0x036F | 0x1AB0..=0x1AFF | 0x1DC0..=0x1DFF | 0x20D0..=0x20FF | 0xFE20..=0xFE2F)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PathCommand {
+    Move(f32, f32),
+    Line(f32, f32),
+    Quad(f32, f32, f32, f32),
+    Bezier(f32, f32, f32, f32, f32, f32),
+}
+
+// A closed contour of a glyph outline, in nanovg coordinates
+//
+// nanovg fills every sub-path as solid unless told otherwise, so the contours
+// turning the other way than the outer contour of their glyph must be marked as holes.
+#[derive(Debug, Clone, PartialEq)]
+struct Contour {
+    commands: Vec<PathCommand>,
+    area: f32, // signed area of the polygon of the points, its sign gives the direction
+    hole: bool,
+}
+
+impl Contour {
+    fn add_to_path(&self, vg: *mut nanovg_sys::NVGcontext) {
+        for command in &self.commands {
+            unsafe {
+                match *command {
+                    PathCommand::Move(x, y) => nanovg_sys::nvgMoveTo(vg, x, y),
+                    PathCommand::Line(x, y) => nanovg_sys::nvgLineTo(vg, x, y),
+                    PathCommand::Quad(x1, y1, x, y) => nanovg_sys::nvgQuadTo(vg, x1, y1, x, y),
+                    PathCommand::Bezier(x1, y1, x2, y2, x, y) => nanovg_sys::nvgBezierTo(vg, x1, y1, x2, y2, x, y),
+                }
+            }
+        }
+
+        unsafe {
+            nanovg_sys::nvgClosePath(vg);
+
+            // Applies to the sub-path started by the last move
+            if self.hole {
+                nanovg_sys::nvgPathWinding(vg, nanovg_sys::NVGsolidity_NVG_HOLE);
+            }
+        }
+    }
+
+    fn get_last_point(&self) -> Option<(f32, f32)> {
+        match self.commands.last()? {
+            PathCommand::Move(x, y)
+            | PathCommand::Line(x, y)
+            | PathCommand::Quad(_, _, x, y)
+            | PathCommand::Bezier(_, _, _, _, x, y) => Some((*x, *y)),
+        }
+    }
+
+    fn push(&mut self, command: PathCommand) {
+        if let (Some((x0, y0)), PathCommand::Line(x1, y1) | PathCommand::Quad(_, _, x1, y1) | PathCommand::Bezier(_, _, _, _, x1, y1)) = (self.get_last_point(), command) {
+            self.area += x0 * y1 - x1 * y0;
+        }
+
+        self.commands.push(command);
+    }
+
+    fn close(&mut self) {
+        let first = match self.commands.first() {
+            Some(PathCommand::Move(x, y)) => (*x, *y),
+            _ => return,
+        };
+
+        if let Some((x, y)) = self.get_last_point() {
+            self.area += x * first.1 - first.0 * y;
+        }
+    }
+}
+
+// Converts glyph outlines from font units (Y up) to contours in nanovg coordinates (Y down)
+struct ContourBuilder {
+    scale: f32,
+    x: f32,
+    y: f32,
+    contours: Vec<Contour>,
+}
+
+impl ContourBuilder {
+    fn map(&self, x: f32, y: f32) -> (f32, f32) {
+        (self.x + x * self.scale, self.y - y * self.scale)
+    }
+
+    fn push(&mut self, command: PathCommand) {
+        if let Some(contour) = self.contours.last_mut() {
+            contour.push(command);
+        }
+    }
+
+    /**
+     * Returns the contours, the ones turning against the largest one being holes.
+     * TrueType and CFF outlines turn in opposite directions, so the direction of
+     * solid contours is taken from the glyph itself.
+     */
+    fn finish(mut self) -> Vec<Contour> {
+        let solid_area = self.contours.iter()
+            .map(|contour| contour.area)
+            .fold(0.0f32, |largest, area| if area.abs() > largest.abs() { area } else { largest });
+
+        for contour in &mut self.contours {
+            contour.hole = contour.area * solid_area < 0.0;
+        }
+
+        self.contours
+    }
+}
+
+impl OutlineBuilder for ContourBuilder {
+    fn move_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.map(x, y);
+        self.contours.push(Contour {
+            commands: vec![PathCommand::Move(x, y)],
+            area: 0.0,
+            hole: false,
+        });
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.map(x, y);
+        self.push(PathCommand::Line(x, y));
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (x1, y1) = self.map(x1, y1);
+        let (x, y) = self.map(x, y);
+        self.push(PathCommand::Quad(x1, y1, x, y));
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (x1, y1) = self.map(x1, y1);
+        let (x2, y2) = self.map(x2, y2);
+        let (x, y) = self.map(x, y);
+        self.push(PathCommand::Bezier(x1, y1, x2, y2, x, y));
+    }
+
+    fn close(&mut self) {
+        if let Some(contour) = self.contours.last_mut() {
+            contour.close();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lib::core::font::FONT_REGULAR;
+    use super::*;
+
+    static FONT: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fonts/DejaVuSans.ttf"));
+
+    fn get_font_stash() -> FontStash {
+        let mut font_stash = FontStash::new();
+        font_stash.keep_buffer(FONT_REGULAR, FONT.to_vec());
+        font_stash
+    }
+
+    fn get_clusters(shaped: &ShapedText) -> Vec<usize> {
+        shaped.glyphs.iter().map(|glyph| glyph.cluster).collect()
+    }
+
+    #[test]
+    fn detects_text_needing_shaping() {
+        assert!(!needs_shaping("Hello, world"));
+        assert!(needs_shaping("שלום"));
+        assert!(needs_shaping("مرحبا"));
+        assert!(needs_shaping("e\u{301}"));
+    }
+
+    #[test]
+    fn right_to_left_runs_are_reversed() {
+        let font_stash = get_font_stash();
+        let shaper = TextShaper::new(&font_stash, &[FONT_REGULAR]);
+
+        let shaped = shaper.shape("abc", 20.0, &Direction::Inherit);
+        assert_eq!(get_clusters(&shaped), vec![0, 1, 2]);
+        assert!(shaped.width > 0.0);
+
+        // Hebrew letters are two bytes long
+        let shaped = shaper.shape("אבג", 20.0, &Direction::Inherit);
+        assert_eq!(get_clusters(&shaped), vec![4, 2, 0]);
+    }
+
+    #[test]
+    fn mixed_direction_runs_follow_the_base_direction() {
+        let font_stash = get_font_stash();
+        let shaper = TextShaper::new(&font_stash, &[FONT_REGULAR]);
+
+        let shaped = shaper.shape("ab אב", 20.0, &Direction::LeftToRight);
+        assert_eq!(get_clusters(&shaped), vec![0, 1, 2, 5, 3]);
+
+        let shaped = shaper.shape("ab אב", 20.0, &Direction::RightToLeft);
+        assert_eq!(get_clusters(&shaped), vec![5, 3, 2, 0, 1]);
+
+        // Glyphs are laid out one after the other
+        assert!(shaped.glyphs.windows(2).all(|glyphs| glyphs[1].x >= glyphs[0].x));
+    }
+
+    #[test]
+    fn paragraphs_take_the_direction_of_their_first_strong_character() {
+        let text = "אב ab\nab אב";
+
+        assert!(is_right_to_left(text, 0, &Direction::Inherit));
+        assert!(!is_right_to_left(text, text.find('\n').unwrap() + 1, &Direction::Inherit));
+        assert!(!is_right_to_left(text, 0, &Direction::LeftToRight));
+        assert!(is_right_to_left("ab", 0, &Direction::RightToLeft));
+    }
+
+    #[test]
+    fn arabic_letters_are_joined() {
+        let font_stash = get_font_stash();
+        let shaper = TextShaper::new(&font_stash, &[FONT_REGULAR]);
+
+        let isolated = shaper.shape("ب", 20.0, &Direction::Inherit);
+        let joined = shaper.shape("ببب", 20.0, &Direction::Inherit);
+
+        assert_eq!(joined.glyphs.len(), 3);
+        assert!(joined.glyphs.iter().all(|glyph| glyph.glyph_id != isolated.glyphs[0].glyph_id));
+    }
+
+    #[test]
+    fn counters_are_drawn_as_holes() {
+        let font_stash = get_font_stash();
+        let shaper = TextShaper::new(&font_stash, &[FONT_REGULAR]);
+
+        let shaped = shaper.shape("ol", 20.0, &Direction::Inherit);
+        let o = shaper.outline_glyph(&shaped, &shaped.glyphs[0], 0.0, 0.0);
+        let l = shaper.outline_glyph(&shaped, &shaped.glyphs[1], 0.0, 0.0);
+
+        assert_eq!(o.len(), 2);
+        assert_eq!(o.iter().filter(|contour| contour.hole).count(), 1);
+        assert!(o.iter().all(|contour| contour.hole == (contour.area.abs() < o[0].area.abs().max(o[1].area.abs()))));
+
+        assert_eq!(l.len(), 1);
+        assert!(!l[0].hole);
+    }
+}
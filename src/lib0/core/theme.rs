@@ -1,13 +1,97 @@
 use std::collections::HashMap;
+use std::sync::RwLock;
+use log::{error, info, warn};
 use nanovg::Color;
 use once_cell::sync::Lazy;
+use crate::lib::core::color::mix;
 
-#[derive(PartialEq)]
+// Typed name of a theme color, to look colors up without typos
+// Built-in keys are in the colors module, apps can create their own with ColorKey::new()
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ColorKey(&'static str);
+
+impl ColorKey {
+    pub const fn new(name: &'static str) -> Self {
+        ColorKey(name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.0
+    }
+}
+
+macro_rules! declare_color_keys {
+    ($($constant:ident => $name:literal,)*) => {
+        $(pub const $constant: ColorKey = ColorKey::new($name);)*
+
+        // Every built-in color, all required to be defined in every theme
+        pub const BUILTIN_COLORS: &[ColorKey] = &[$($constant,)*];
+    };
+}
+
+// Keys of the built-in theme colors
+pub mod colors {
+    use super::ColorKey;
+
+    declare_color_keys! {
+        BACKGROUND => "brls/background",
+        TEXT => "brls/text",
+        BACKDROP => "brls/backdrop",
+        CLICK_PULSE => "brls/click_pulse",
+        HIGHLIGHT_BACKGROUND => "brls/highlight/background",
+        HIGHLIGHT_COLOR1 => "brls/highlight/color1",
+        HIGHLIGHT_COLOR2 => "brls/highlight/color2",
+        APPLET_FRAME_SEPARATOR => "brls/applet_frame/separator",
+        SIDEBAR_BACKGROUND => "brls/sidebar/background",
+        SIDEBAR_ACTIVE_ITEM => "brls/sidebar/active_item",
+        SIDEBAR_SEPARATOR => "brls/sidebar/separator",
+        HEADER_BORDER => "brls/header/border",
+        HEADER_RECTANGLE => "brls/header/rectangle",
+        HEADER_SUBTITLE => "brls/header/subtitle",
+        BUTTON_PRIMARY_ENABLED_BACKGROUND => "brls/button/primary_enabled_background",
+        BUTTON_PRIMARY_DISABLED_BACKGROUND => "brls/button/primary_disabled_background",
+        BUTTON_PRIMARY_ENABLED_TEXT => "brls/button/primary_enabled_text",
+        BUTTON_PRIMARY_DISABLED_TEXT => "brls/button/primary_disabled_text",
+        BUTTON_DEFAULT_ENABLED_BACKGROUND => "brls/button/default_enabled_background",
+        BUTTON_DEFAULT_DISABLED_BACKGROUND => "brls/button/default_disabled_background",
+        BUTTON_DEFAULT_ENABLED_TEXT => "brls/button/default_enabled_text",
+        BUTTON_DEFAULT_DISABLED_TEXT => "brls/button/default_disabled_text",
+        BUTTON_HIGHLIGHT_ENABLED_TEXT => "brls/button/highlight_enabled_text",
+        BUTTON_HIGHLIGHT_DISABLED_TEXT => "brls/button/highlight_disabled_text",
+        BUTTON_ENABLED_BORDER_COLOR => "brls/button/enabled_border_color",
+        BUTTON_DISABLED_BORDER_COLOR => "brls/button/disabled_border_color",
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum ThemeVariant {
     Light,
-    Dark
+    Dark,
+    Custom(String), // registered by the app with register_theme()
 }
 
+impl ThemeVariant {
+    /**
+     * Returns the name of the variant, as used in theme files and in the themes registry.
+     */
+    pub fn get_name(&self) -> &str {
+        match self {
+            ThemeVariant::Light => "light",
+            ThemeVariant::Dark => "dark",
+            ThemeVariant::Custom(name) => name,
+        }
+    }
+
+    pub fn from_name(name: &str) -> ThemeVariant {
+        match name {
+            "light" => ThemeVariant::Light,
+            "dark" => ThemeVariant::Dark,
+            _ => ThemeVariant::Custom(name.into()),
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct ThemeValues {
     values: HashMap<String, Color>
 }
@@ -24,6 +108,7 @@ impl ThemeValues {
     }
 }
 
+#[derive(Clone)]
 pub struct Theme  {
     theme_values: ThemeValues
 }
@@ -42,9 +127,86 @@ impl Theme{
     pub fn get_color(&self, name: &'_ str) -> Option<&Color> {
         self.theme_values.values.get(name)
     }
+
+    pub fn has_color(&self, name: &str) -> bool {
+        self.theme_values.values.contains_key(name)
+    }
+
+    /**
+     * Returns the given color. Missing colors are reported by validate_themes()
+     * on startup, here they are logged and drawn transparent.
+     */
+    pub fn get(&self, key: ColorKey) -> Color {
+        match self.get_color(key.name()) {
+            Some(color) => *color,
+            None => {
+                error!("Missing theme color \"{}\"", key.name());
+                Color::from_rgba(0, 0, 0, 0)
+            }
+        }
+    }
+
+    /**
+     * Returns the given color, or the given default if it is not defined.
+     */
+    pub fn get_color_or(&self, name: &str, default: Color) -> Color {
+        self.get_color(name).cloned().unwrap_or(default)
+    }
+
+    /**
+     * Returns a copy of this theme with the colors of the given overrides applied on top.
+     * The overrides can be a partial theme, only defining the colors to replace.
+     */
+    pub fn overlay(&self, overrides: &Theme) -> Theme {
+        let mut theme = self.clone();
+
+        for (name, color) in &overrides.theme_values.values {
+            theme.add_color(name, *color);
+        }
+
+        theme
+    }
+
+    /**
+     * Returns the theme interpolated between this one (progress = 0.0) and the given one (progress = 1.0),
+     * used to crossfade between variants. Colors missing from one of the themes are taken from the other.
+     */
+    pub fn blend(&self, other: &Theme, progress: f32) -> Theme {
+        let mut blended = other.clone();
+        self.blend_into(other, progress, &mut blended);
+        blended
+    }
+
+    /**
+     * Writes the theme interpolated between this one and the given one into the given theme,
+     * see blend(). The colors are updated in place, so crossfading does not allocate every frame.
+     */
+    pub fn blend_into(&self, other: &Theme, progress: f32, target: &mut Theme) {
+        for (name, to) in &other.theme_values.values {
+            let color = match self.get_color(name) {
+                Some(from) => mix(from, to, progress),
+                None => *to,
+            };
+            target.set_color(name, color);
+        }
+
+        for (name, from) in &self.theme_values.values {
+            if !other.has_color(name) {
+                target.set_color(name, *from);
+            }
+        }
+    }
+
+    // Same as add_color(), without allocating the name when the color exists
+    fn set_color(&mut self, name: &str, color: Color) {
+        match self.theme_values.values.get_mut(name) {
+            Some(existing) => *existing = color,
+            None => self.add_color(name, color),
+        }
+    }
 }
 
-static LIGHT_THEME: Lazy<Theme> = Lazy::new(|| {
+fn create_light_theme() -> Theme {
     let light_theme_values = ThemeValues::new(
         vec![
             // Generic values
@@ -90,9 +252,9 @@ static LIGHT_THEME: Lazy<Theme> = Lazy::new(|| {
         ]
     );
     Theme::new(light_theme_values)
-});
+}
 
-static DARK_THEME: Lazy<Theme> = Lazy::new(||{
+fn create_dark_theme() -> Theme {
     let dark_theme_value = ThemeValues::new(
         vec![
             // Generic values
@@ -138,12 +300,108 @@ static DARK_THEME: Lazy<Theme> = Lazy::new(||{
         ]
     );
     Theme::new(dark_theme_value)
+}
+
+// Every theme variant available to the app, by variant name
+static THEMES: Lazy<RwLock<HashMap<String, Theme>>> = Lazy::new(|| {
+    let mut themes = HashMap::new();
+    themes.insert(ThemeVariant::Light.get_name().into(), create_light_theme());
+    themes.insert(ThemeVariant::Dark.get_name().into(), create_dark_theme());
+    RwLock::new(themes)
+});
+
+/**
+ * Registers a custom theme variant, or replaces the theme of an existing one.
+ * Custom themes should define every "brls/" color, starting from a copy of
+ * the light or dark theme with get_theme() is the easiest.
+ */
+pub fn register_theme(variant: &ThemeVariant, theme: Theme) {
+    info!("Registering theme variant \"{}\"", variant.get_name());
+    THEMES.write().unwrap().insert(variant.get_name().into(), theme);
+}
+
+pub fn has_theme(variant: &ThemeVariant) -> bool {
+    THEMES.read().unwrap().contains_key(variant.get_name())
+}
+
+/**
+ * Returns a copy of the theme of the given variant, if registered.
+ */
+pub fn get_theme(variant: &ThemeVariant) -> Option<Theme> {
+    let theme = THEMES.read().unwrap().get(variant.get_name()).cloned();
+
+    if theme.is_none() {
+        warn!("Unknown theme variant \"{}\"", variant.get_name());
+    }
+
+    theme
+}
+
+/**
+ * Runs the given closure with write access to the theme of the given variant,
+ * to merge loaded values into it. Returns false if the variant is not registered.
+ */
+pub fn with_theme_mut<F: FnOnce(&mut Theme)>(variant: &ThemeVariant, f: F) -> bool {
+    match THEMES.write().unwrap().get_mut(variant.get_name()) {
+        Some(theme) => {
+            f(theme);
+            true
+        }
+        None => false,
+    }
+}
+
+// Colors declared by the app as required, checked by validate_themes() with the built-in ones
+static REQUIRED_COLORS: Lazy<RwLock<Vec<String>>> = Lazy::new(|| {
+    RwLock::new(vec![])
 });
 
-pub fn get_light_theme() -> &'static Theme {
-    &LIGHT_THEME
+/**
+ * Declares a color of the app with its default value for the light and dark variants,
+ * used unless a theme file defines it. Custom variants get the light color.
+ */
+pub fn declare_color(key: ColorKey, light: Color, dark: Color) {
+    for (name, theme) in THEMES.write().unwrap().iter_mut() {
+        if !theme.has_color(key.name()) {
+            let color = if name == ThemeVariant::Dark.get_name() { dark } else { light };
+            theme.add_color(key.name(), color);
+        }
+    }
+}
+
+/**
+ * Declares a color the app needs but does not provide a default for:
+ * it must be defined by a theme file, validate_themes() reports it otherwise.
+ */
+pub fn require_color(key: ColorKey) {
+    REQUIRED_COLORS.write().unwrap().push(key.name().into());
 }
 
-pub fn get_dark_theme() -> &'static Theme {
-    &DARK_THEME
-}
\ No newline at end of file
+/**
+ * Checks that every built-in and required color is defined in every registered theme.
+ * Returns all the missing colors, as "variant: name".
+ */
+pub fn validate_themes() -> Vec<String> {
+    let themes = THEMES.read().unwrap();
+    let required = REQUIRED_COLORS.read().unwrap();
+
+    let mut variants: Vec<&String> = themes.keys().collect();
+    variants.sort();
+
+    let mut missing = vec![];
+    for variant in variants {
+        let theme = &themes[variant];
+
+        let names = colors::BUILTIN_COLORS.iter()
+            .map(|key| key.name().to_string())
+            .chain(required.iter().cloned());
+
+        for name in names {
+            if !theme.has_color(&name) {
+                missing.push(format!("{}: {}", variant, name));
+            }
+        }
+    }
+
+    missing
+}
@@ -0,0 +1,333 @@
+use log::{error, info, warn};
+use nanovg::Color;
+use crate::lib::core::color::parse_hex;
+use crate::lib::core::config::get_config_dir;
+use crate::lib::core::resources::read_resource_string;
+use crate::lib::core::style::get_style_mut;
+use crate::lib::core::theme::{get_theme, has_theme, register_theme, with_theme_mut, ThemeVariant};
+use crate::lib::core::xml::wrap_xml_document;
+
+// Namespace of the built-in keys, only keys already known in this namespace are accepted
+const BRLS_NAMESPACE: &str = "brls/";
+
+// Name of the user overrides file in the config directory, loaded after the app files
+const USER_THEME_FILE: &str = "theme.toml";
+
+// Style metrics and theme colors read from a file, before being merged
+// into the global style and themes
+//
+// TOML and JSON files use the same layout, keys can be written flat or nested:
+//
+//     [style]
+//     "brls/button/corner_radius" = 5.0
+//
+//     [theme.light]
+//     "brls/background" = "#EBEBEB"
+//
+//     [theme.dark.brls.sidebar]
+//     background = "#323232"
+//
+// Other variants than light and dark are custom variants, created from
+// a copy of the light theme if the app did not register them
+//
+// XML files use one element per value:
+//
+//     <brls:Stylesheet>
+//         <brls:Metric name="brls/button/corner_radius" value="5.0"/>
+//         <brls:Color variant="light" name="brls/background" value="#EBEBEB"/>
+//     </brls:Stylesheet>
+#[derive(Default)]
+pub struct ThemeFile {
+    pub metrics: Vec<(String, f32)>,
+    pub colors: Vec<(ThemeVariant, String, Color)>,
+}
+
+impl ThemeFile {
+    /**
+     * Reads the file at the given path, "@res/..." paths being resolved through the mounted resources.
+     * The format is guessed from the extension (.toml, .json or .xml).
+     */
+    pub fn load(path: &str) -> Option<ThemeFile> {
+        let content = match read_resource_string(path) {
+            Some(content) => content,
+            None => {
+                error!("Could not read theme file \"{}\"", path);
+                return None;
+            }
+        };
+
+        let extension = path.rsplit('.').next().unwrap_or("").to_lowercase();
+        let parsed = match extension.as_str() {
+            "toml" => Self::parse_toml(&content),
+            "json" => Self::parse_json(&content),
+            "xml" => Self::parse_xml(&content),
+            _ => Err(format!("unknown theme file format \".{}\"", extension)),
+        };
+
+        match parsed {
+            Ok(file) => Some(file),
+            Err(err) => {
+                error!("Invalid theme file \"{}\": {}", path, err);
+                None
+            }
+        }
+    }
+
+    pub fn parse_toml(content: &str) -> Result<ThemeFile, String> {
+        let value: toml::Value = toml::from_str(content).map_err(|err| err.to_string())?;
+        let json = serde_json::to_value(value).map_err(|err| err.to_string())?;
+        Self::from_json_value(&json)
+    }
+
+    pub fn parse_json(content: &str) -> Result<ThemeFile, String> {
+        let value: serde_json::Value = serde_json::from_str(content).map_err(|err| err.to_string())?;
+        Self::from_json_value(&value)
+    }
+
+    fn from_json_value(value: &serde_json::Value) -> Result<ThemeFile, String> {
+        let root = value.as_object().ok_or("the root must be a table")?;
+        let mut file = ThemeFile::default();
+
+        for (section, content) in root {
+            match section.as_str() {
+                "style" => {
+                    for (key, value) in flatten("", content) {
+                        match value.as_f64() {
+                            Some(metric) => file.metrics.push((key, metric as f32)),
+                            None => warn!("Ignoring style metric \"{}\": not a number", key),
+                        }
+                    }
+                }
+                "theme" => {
+                    let variants = content.as_object().ok_or("the \"theme\" section must be a table")?;
+
+                    for (variant, colors) in variants {
+                        let variant = ThemeVariant::from_name(variant);
+
+                        for (key, value) in flatten("", colors) {
+                            match value.as_str().and_then(parse_hex) {
+                                Some(color) => file.colors.push((variant.clone(), key, color)),
+                                None => warn!("Ignoring theme color \"{}\": not a \"#RGB[A]\" or \"#RRGGBB[AA]\" color", key),
+                            }
+                        }
+                    }
+                }
+                _ => warn!("Ignoring unknown theme file section \"{}\"", section),
+            }
+        }
+
+        Ok(file)
+    }
+
+    pub fn parse_xml(content: &str) -> Result<ThemeFile, String> {
+        let content = wrap_xml_document(content).ok_or("unclosed XML declaration")?;
+        let document = roxmltree::Document::parse(&content).map_err(|err| err.to_string())?;
+        let stylesheet = document.root_element().children()
+            .find(|node| node.is_element())
+            .ok_or("no <brls:Stylesheet> element")?;
+        let mut file = ThemeFile::default();
+
+        for node in stylesheet.children().filter(|node| node.is_element()) {
+            let name = match node.attribute("name") {
+                Some(name) => name.to_string(),
+                None => {
+                    warn!("Ignoring <{}> without \"name\" attribute", node.tag_name().name());
+                    continue;
+                }
+            };
+            let value = node.attribute("value").unwrap_or("");
+
+            match node.tag_name().name() {
+                "Metric" => match value.parse::<f32>() {
+                    Ok(metric) => file.metrics.push((name, metric)),
+                    Err(_) => warn!("Ignoring style metric \"{}\": not a number", name),
+                },
+                "Color" => {
+                    let color = match parse_hex(value) {
+                        Some(color) => color,
+                        None => {
+                            warn!("Ignoring theme color \"{}\": not a \"#RGB[A]\" or \"#RRGGBB[AA]\" color", name);
+                            continue;
+                        }
+                    };
+
+                    match node.attribute("variant") {
+                        Some(variant) => file.colors.push((ThemeVariant::from_name(variant), name, color)),
+                        // No variant: same color for light and dark
+                        None => {
+                            file.colors.push((ThemeVariant::Light, name.clone(), color));
+                            file.colors.push((ThemeVariant::Dark, name, color));
+                        }
+                    }
+                }
+                tag => warn!("Ignoring unknown theme file element <{}>", tag),
+            }
+        }
+
+        Ok(file)
+    }
+
+    /**
+     * Merges the values into the global style and themes, replacing existing keys.
+     * Unknown "brls/" keys are most likely typos and are reported, other keys are custom app keys.
+     */
+    pub fn apply(&self) {
+        let mut style = get_style_mut();
+        for (key, metric) in &self.metrics {
+            if key.starts_with(BRLS_NAMESPACE) && !style.has_metric(key) {
+                warn!("Unknown style metric \"{}\", check for typos or use your own namespace", key);
+                continue;
+            }
+            style.add_metric(key, *metric);
+        }
+        drop(style);
+
+        for (variant, key, color) in &self.colors {
+            if !has_theme(variant) {
+                if let Some(light) = get_theme(&ThemeVariant::Light) {
+                    register_theme(variant, light);
+                }
+            }
+
+            with_theme_mut(variant, |theme| {
+                if key.starts_with(BRLS_NAMESPACE) && !theme.has_color(key) {
+                    warn!("Unknown theme color \"{}\", check for typos or use your own namespace", key);
+                    return;
+                }
+                theme.add_color(key, *color);
+            });
+        }
+    }
+}
+
+/**
+ * Loads and merges the given theme files in order (app defaults first),
+ * then the user overrides file of the config directory if it exists.
+ * Returns the number of files loaded.
+ */
+pub fn load_theme_files(paths: &[&str]) -> usize {
+    let mut loaded = 0;
+
+    for path in paths {
+        if let Some(file) = ThemeFile::load(path) {
+            file.apply();
+            loaded += 1;
+        }
+    }
+
+    let user_file = get_config_dir().join(USER_THEME_FILE);
+    if user_file.is_file() {
+        if let Some(file) = ThemeFile::load(&user_file.to_string_lossy()) {
+            info!("Applying user theme overrides from {:?}", user_file);
+            file.apply();
+            loaded += 1;
+        }
+    }
+
+    loaded
+}
+
+/**
+ * Flattens nested tables in "parent/child" keys.
+ */
+fn flatten(prefix: &str, value: &serde_json::Value) -> Vec<(String, serde_json::Value)> {
+    match value.as_object() {
+        Some(table) => table.iter()
+            .flat_map(|(key, child)| {
+                let key = if prefix.is_empty() { key.clone() } else { format!("{}/{}", prefix, key) };
+                flatten(&key, child)
+            })
+            .collect(),
+        None => vec![(prefix.to_string(), value.clone())],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lib::core::color::to_hex;
+    use super::*;
+
+    fn get_colors(file: &ThemeFile) -> Vec<(ThemeVariant, &str, String)> {
+        file.colors.iter().map(|(variant, key, color)| (variant.clone(), key.as_str(), to_hex(color))).collect()
+    }
+
+    #[test]
+    fn parses_flat_and_nested_toml_keys() {
+        let file = ThemeFile::parse_toml(r##"
+            [style]
+            "brls/button/corner_radius" = 5.0
+            app = { margin = 12 }
+
+            [theme.dark.brls.sidebar]
+            background = "#323232"
+
+            [theme.sepia]
+            "app/paper" = "#F4ECD880"
+        "##).unwrap();
+
+        let mut metrics = file.metrics.clone();
+        metrics.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(metrics, vec![("app/margin".to_string(), 12.0), ("brls/button/corner_radius".to_string(), 5.0)]);
+
+        let mut colors = get_colors(&file);
+        colors.sort_by(|a, b| a.1.cmp(b.1));
+        assert_eq!(colors, vec![
+            (ThemeVariant::Custom("sepia".into()), "app/paper", "#F4ECD880".to_string()),
+            (ThemeVariant::Dark, "brls/sidebar/background", "#323232".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn skips_bad_values_and_unknown_sections() {
+        // Partial files are fine, bad values are left out without failing the file
+        let file = ThemeFile::parse_json(r##"{
+            "theme": { "light": { "brls/text": "#FFF", "brls/background": "white", "brls/backdrop": 3 } },
+            "style": { "brls/label/font_size": "big" },
+            "fonts": { "regular": "font.ttf" }
+        }"##).unwrap();
+
+        assert!(file.metrics.is_empty());
+        assert_eq!(get_colors(&file), vec![(ThemeVariant::Light, "brls/text", "#FFFFFF".to_string())]);
+
+        assert!(ThemeFile::parse_json("[1, 2]").is_err());
+        assert!(ThemeFile::parse_json("{ \"style\": ").is_err());
+        assert!(ThemeFile::parse_toml("theme = 3").is_err());
+        assert!(ThemeFile::parse_toml("[style").is_err());
+    }
+
+    #[test]
+    fn parses_xml_elements() {
+        // As documented on ThemeFile, without namespace declaration
+        let file = ThemeFile::parse_xml(r##"
+            <brls:Stylesheet>
+                <brls:Metric name="brls/button/corner_radius" value="5.0"/>
+                <brls:Color variant="light" name="brls/background" value="#EBEBEB"/>
+            </brls:Stylesheet>
+        "##).unwrap();
+
+        assert_eq!(file.metrics, vec![("brls/button/corner_radius".to_string(), 5.0)]);
+        assert_eq!(get_colors(&file), vec![(ThemeVariant::Light, "brls/background", "#EBEBEB".to_string())]);
+
+        let file = ThemeFile::parse_xml(r##"<?xml version="1.0" encoding="UTF-8"?>
+            <brls:Stylesheet>
+                <brls:Metric name="brls/button/corner_radius" value="5.0"/>
+                <brls:Metric name="brls/header/padding" value="wide"/>
+                <brls:Color variant="dark" name="brls/background" value="#2D2D2D"/>
+                <brls:Color name="app/accent" value="#00C0FF"/>
+                <brls:Color name="app/broken" value="#00C0F"/>
+                <brls:Color value="#000000"/>
+                <brls:Font name="regular" value="font.ttf"/>
+            </brls:Stylesheet>
+        "##).unwrap();
+
+        assert_eq!(file.metrics, vec![("brls/button/corner_radius".to_string(), 5.0)]);
+        assert_eq!(get_colors(&file), vec![
+            (ThemeVariant::Dark, "brls/background", "#2D2D2D".to_string()),
+            (ThemeVariant::Light, "app/accent", "#00C0FF".to_string()),
+            (ThemeVariant::Dark, "app/accent", "#00C0FF".to_string()),
+        ]);
+
+        assert!(ThemeFile::parse_xml("<brls:Stylesheet>").is_err());
+        assert!(ThemeFile::parse_xml("").is_err());
+    }
+}
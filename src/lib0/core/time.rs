@@ -1,4 +1,4 @@
-use once_cell::sync::Lazy;
+use std::cell::Cell;
 use std::sync::{Arc, Mutex};
 
 pub type Timestamp = i64;
@@ -22,16 +22,26 @@ pub type TickingTickCallback = fn();
 pub type TickingEndCallback = fn(bool);
 
 pub fn empty_fn(){}
-pub fn empty_end_fn(t: bool) {}
+pub fn empty_end_fn(_t: bool) {}
+
+type RunningTickings = Arc<Mutex<Vec<Arc<Mutex<dyn Ticking + Send + Sync>>>>>;
 
 pub struct TickManager {
-    running_tickings: Arc<Mutex<Vec<Arc<Mutex<dyn Ticking + Send + Sync>>>>>
+    running_tickings: RunningTickings,
+    previous_time: Cell<Timestamp>,
+}
+
+impl Default for TickManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl TickManager {
     pub fn new() -> TickManager {
         TickManager {
-            running_tickings: Arc::new(Mutex::new(vec![]))
+            running_tickings: Arc::new(Mutex::new(vec![])),
+            previous_time: Cell::new(0),
         }
     }
 
@@ -39,9 +49,9 @@ impl TickManager {
      * Called internally by the main loop. Takes all running tickings
      * and updates them.
      */
-    fn update_tickings(&self) {
+    pub fn update_tickings(&self) {
         // Update time
-        let mut previous_time: Timestamp = 0;
+        let previous_time = self.previous_time.get();
 
         let current_time = get_cpu_time_msec();
         let delta = match previous_time == 0 {
@@ -49,14 +59,14 @@ impl TickManager {
             false => {current_time - previous_time}
         };
 
-        previous_time = current_time;
+        self.previous_time.set(current_time);
 
         // Update every running ticking, kill them and execute cb if they are finished
         // We have to clone the running tickings list to avoid altering it while
         // in the for loop (so if another ticking is started in a callback or during onUpdate())
         let binding = self.running_tickings.lock().unwrap();
 
-        for mut ticking in binding.clone().into_iter() {
+        for ticking in binding.clone().into_iter() {
             let mut ticking_mutex = ticking.lock().unwrap();
             let run = ticking_mutex.on_update(delta);
 
@@ -115,7 +125,7 @@ pub trait Ticking {
      * If the ticking is finished, it will be restarted.
      * If the ticking is already running, this method will have no effect.
      */
-    fn start(&mut self, tick_manager: &mut TickManager) where Self: Send + Sync, Self: Sized {
+    fn start(&mut self, _tick_manager: &mut TickManager) where Self: Send + Sync, Self: Sized {
         if self.is_running() {
             return;
         }
@@ -1,4 +1,3 @@
-use glm::ext::e;
 use crate::lib::core::time::{empty_end_fn, empty_fn, FiniteTicking, Ticking, TickingEndCallback, TickingGenericCallback, TickingTickCallback, TickManager, Timestamp};
 
 // A Timer allows to run a callback once after a given period of time, in ms
@@ -65,7 +64,7 @@ impl Timer {
     /**
      * Starts the timer directly with a given duration, in ms.
      */
-    fn start_with_duration(&mut self, duration: Timestamp, tick_manager: &mut TickManager) {
+    pub fn start_with_duration(&mut self, duration: Timestamp, tick_manager: &mut TickManager) {
         self.duration = duration;
         self.start(tick_manager);
     }
@@ -74,7 +73,7 @@ impl Timer {
      * Sets the duration of the timer, in ms.
      * Does not stop or reset it.
      */
-    fn set_duration(&mut self, duration: Timestamp) {
+    pub fn set_duration(&mut self, duration: Timestamp) {
         self.duration = duration;
     }
 }
@@ -129,7 +128,7 @@ impl Ticking for RepeatingTimer {
             self.progress = 0;
         }
 
-        return true; // never stop
+        true // never stop
     }
 
     fn on_start(&mut self) {
@@ -157,7 +156,7 @@ impl RepeatingTimer {
     /**
      * Starts the timer directly with a given period, in ms.
      */
-    fn start_with_period(&mut self, period: Timestamp, tick_manager: &mut TickManager) {
+    pub fn start_with_period(&mut self, period: Timestamp, tick_manager: &mut TickManager) {
         self.period = period;
         self.start(tick_manager);
     }
@@ -166,7 +165,7 @@ impl RepeatingTimer {
      * Sets the period of the timer, in ms.
      * Does not stop or reset it.
      */
-    fn set_period(&mut self, period: Timestamp) {
+    pub fn set_period(&mut self, period: Timestamp) {
         self.period = period;
     }
 
@@ -175,7 +174,7 @@ impl RepeatingTimer {
      * Tick callback is still executed every tick.
      * End callback is executed when the timer is stopped.
      */
-    fn set_callback(&mut self, callback: TickingGenericCallback) {
+    pub fn set_callback(&mut self, callback: TickingGenericCallback) {
         self.callback = callback;
     }
 }
@@ -1,4 +1,3 @@
-use std::f32::consts::PI;
 
 pub fn shake_animation(t: f32, a: f32) -> i32 {
     // Damped sine wave
@@ -1,24 +1,211 @@
-use std::cell::RefCell;
-use std::rc::Rc;
-use crate::lib::core::base_view::FocusDirection;
+use std::any::{type_name, Any};
+use std::mem::take;
+use crate::lib::core::base_view::{BaseView, FocusDirection, Visibility};
 use crate::lib::core::event::Event;
+use crate::lib::core::flex::FlexNode;
 use crate::lib::core::frame_context::FrameContext;
+use crate::lib::core::theme::ThemeVariant;
+use crate::lib::core::time::Timestamp;
+use crate::lib::core::view_tree::{ViewId, ViewTree};
 
-pub type GenericEvent = Event<Rc<RefCell<Option<Box<dyn View>>>>> ;
+pub type GenericEvent = Event<Option<ViewId>>;
 pub type VoidEvent = Event<()>;
 
-pub trait View {
+// Gives the concrete type of a view back, see ViewTree::get_as()
+pub trait AsAny {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
 
-    fn frame(&self, ctx: &FrameContext);
-    fn get_default_focus(&self) -> Box<dyn View>;
-    fn get_next_focus(&self, direction: FocusDirection, current_view: &dyn View) -> Box<dyn View>;
-    fn on_focus_lost(&self);
+impl<T: Any> AsAny for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
 
-    fn on_focus_gained(&self);
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
 
-    fn describe(&self) -> String;
+// A view of the tree. Views are owned by a ViewTree, which holds the links between them:
+// containers find their children with the ViewId of their BaseView.
+pub trait View: AsAny {
 
-    fn get_view(&self, id: &str) -> Rc<RefCell<Option<Box<dyn View>>>>;
+    /**
+     * Draws the view. Called once per layer of the frame (see FrameContext::layer):
+     * views only draw themselves on the content layer, given to the detached ones on the overlay pass
+     * (see BaseView::get_frame_layer()),
+     * but containers go through their children (see FrameContext::tree) on every layer.
+     * Leaves don't override it, they draw their content in draw().
+     */
+    fn frame(&self, ctx: &FrameContext) {
+        self.get_base_view().frame(ctx, |ctx| self.draw(ctx));
+    }
 
-    fn get_parent(&self) -> Rc<RefCell<Option<Box<dyn View>>>>;
+    /**
+     * Draws the content of the view in its frame, between its background and its border.
+     */
+    fn draw(&self, _ctx: &FrameContext) {}
+
+    /**
+     * Returns the view to focus when focusing this view: the view itself if it is focusable,
+     * otherwise the default focus of its first child having one.
+     * None means that neither the view nor its children can be focused.
+     */
+    fn get_default_focus(&self, tree: &ViewTree) -> Option<ViewId> {
+        let view = self.get_base_view();
+        if view.is_focusable() {
+            return view.get_view_id();
+        }
+
+        if view.get_visibility() != Visibility::Visible || view.is_collapsed() {
+            return None;
+        }
+
+        tree.get_children(view.get_view_id()?).iter()
+            .find_map(|child| tree.get(*child)?.get_default_focus(tree))
+    }
+
+    /**
+     * Returns the next view to focus when moving in the given direction from the given child
+     * of this view (holding the focused view). None means that there is no next view
+     * in that direction, the parent of this view is then asked.
+     */
+    fn get_next_focus(&self, _tree: &ViewTree, _direction: FocusDirection, _current: ViewId) -> Option<ViewId> {
+        None
+    }
+
+    /**
+     * Called when the view loses the focus. Views overriding it must call
+     * BaseView::on_focus_lost(), which keeps BaseView::is_focused() up to date.
+     */
+    fn on_focus_lost(&mut self) {
+        self.get_base_view_mut().on_focus_lost();
+    }
+
+    /**
+     * Called when the view gets the focus, see on_focus_lost().
+     */
+    fn on_focus_gained(&mut self) {
+        self.get_base_view_mut().on_focus_gained();
+    }
+
+    /**
+     * Called on the parents of the view getting the focus, with their child holding it.
+     */
+    fn on_child_focus_gained(&mut self, _child: ViewId) {}
+
+    fn describe(&self) -> String {
+        let name = type_name::<Self>().rsplit("::").next().unwrap_or_default();
+        format!("{} (id=\"{}\")", name, self.get_base_view().get_id())
+    }
+
+    /**
+     * Returns the base of the view: layout node, visibility, focus state...
+     */
+    fn get_base_view(&self) -> &BaseView;
+
+    fn get_base_view_mut(&mut self) -> &mut BaseView;
+
+    /**
+     * Returns true if the node of the view, or one of the nodes laid out with it
+     * (its children for containers), changed since the last layout.
+     */
+    fn is_layout_dirty(&self, _tree: &ViewTree) -> bool {
+        self.get_base_view().get_flex_node().is_dirty()
+    }
+
+    /**
+     * Takes the layout node of the view for the layout pass of its parent box.
+     * Containers laid out by their parent attach the nodes of their children to it,
+     * so that a dirty child makes the whole branch dirty.
+     * The node is given back with give_back_flex_node() once the parent is laid out.
+     */
+    fn lend_flex_node(&mut self, _tree: &mut ViewTree) -> FlexNode {
+        take(self.get_base_view_mut().get_flex_node_mut())
+    }
+
+    /**
+     * Gives back the node taken by lend_flex_node(), calling on_layout()
+     * on the views whose frame changed.
+     */
+    fn give_back_flex_node(&mut self, _tree: &mut ViewTree, node: FlexNode) {
+        let view = self.get_base_view_mut();
+        *view.get_flex_node_mut() = node;
+
+        if view.get_flex_node().has_new_layout() {
+            view.on_layout();
+        }
+    }
+
+    /**
+     * Lays out the view and the children attached to its node in the given size,
+     * None being the size of the content. Only the dirty branches are laid out again.
+     */
+    fn calculate_layout(&mut self, tree: &mut ViewTree, width: Option<f32>, height: Option<f32>) {
+        let mut node = self.lend_flex_node(tree);
+        node.calculate_layout(width, height);
+        self.give_back_flex_node(tree, node);
+    }
+
+    /**
+     * Lays out the view and its children in the given size if anything changed since
+     * the last layout, and calls on_layout() on the views whose frame changed.
+     * Called once per frame by the application on the content views of the visible activities,
+     * lent by the tree (see ViewTree::layout()).
+     */
+    fn layout(&mut self, _tree: &mut ViewTree, _width: f32, _height: f32) {}
+
+    /**
+     * Advances the animations of the view (collapse...) by the given delta in ms.
+     * Called once per frame by the application on every view of the tree, before the layout pass.
+     * Views overriding it must call BaseView::update().
+     */
+    fn update(&mut self, delta: Timestamp) {
+        self.get_base_view_mut().update(delta);
+    }
+
+    /**
+     * Applies the given XML attribute to the view, see create_from_xml_string().
+     * Returns false if the attribute is unknown or invalid.
+     */
+    fn apply_xml_attribute(&mut self, name: &str, value: &str) -> bool {
+        self.get_base_view_mut().apply_xml_attribute(name, value)
+    }
+
+    /**
+     * Applies an XML attribute of the given child that the child did not handle itself,
+     * for the attributes placing the child in its parent (gridRow...).
+     * Returns false if the attribute is unknown or invalid.
+     */
+    fn apply_child_xml_attribute(&mut self, _child: ViewId, _name: &str, _value: &str) -> bool {
+        false
+    }
+
+    /**
+     * Returns the state of the view and its children as JSON, see Application::dump_view_tree().
+     * Views return BaseView::to_json() with their own fields added, the default being
+     * the type name of the view ("brls:Padding") and its children.
+     */
+    fn to_json(&self, tree: &ViewTree) -> serde_json::Value {
+        let name = type_name::<Self>().rsplit("::").next().unwrap_or_default();
+        let view = self.get_base_view();
+        let mut json = view.to_json(&format!("brls:{}", name));
+
+        let children = view.get_view_id().map_or(&[][..], |id| tree.get_children(id));
+        if !children.is_empty() {
+            json["children"] = children.iter()
+                .filter_map(|child| tree.get(*child))
+                .map(|child| child.to_json(tree))
+                .collect();
+        }
+
+        json
+    }
+
+    /**
+     * Called when the app switches theme variant, before the next frame is drawn
+     * with the new theme. Views caching theme colors must refresh them here.
+     */
+    fn on_theme_changed(&self, _variant: &ThemeVariant) {}
 }
\ No newline at end of file
@@ -0,0 +1,470 @@
+use slotmap::{new_key_type, SlotMap};
+use crate::lib::core::time::Timestamp;
+use crate::lib::core::view::View;
+
+new_key_type! {
+    /**
+     * Handle of a view in a ViewTree. Handles are never reused:
+     * once the view is removed, its handle just doesn't resolve anymore.
+     */
+    pub struct ViewId;
+}
+
+// A view of the tree and its links
+struct ViewNode {
+    view: Option<Box<dyn View>>, // None while lent by ViewTree::with_view_mut()
+    parent: Option<ViewId>,
+    children: Vec<ViewId>,
+}
+
+// Owns the views in an arena, linked to their parent and children by ViewId handles
+//
+// Links are plain handles, so there are no reference cycles, and views are only borrowed
+// through the tree, so there are no borrow panics. Views without a parent are roots
+// (content views of the activities). Containers go through their children with the tree,
+// lending them out of it to lay them out. Event dispatch lends a view out of the tree,
+// leaving the rest of the tree mutable.
+pub struct ViewTree {
+    nodes: SlotMap<ViewId, ViewNode>,
+}
+
+impl Default for ViewTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ViewTree {
+    pub fn new() -> Self {
+        Self {
+            nodes: SlotMap::with_key(),
+        }
+    }
+
+    /**
+     * Adds a view to the tree, as a root. Returns its handle, also given to its BaseView.
+     */
+    pub fn insert(&mut self, view: Box<dyn View>) -> ViewId {
+        let id = self.nodes.insert(ViewNode {
+            view: Some(view),
+            parent: None,
+            children: vec![],
+        });
+
+        if let Some(view) = self.get_mut(id) {
+            view.get_base_view_mut().set_view_id(id);
+        }
+
+        id
+    }
+
+    /**
+     * Adds a view to the tree, at the end of the children of the given parent.
+     * The view is added as a root if the parent is not in the tree. Returns its handle.
+     */
+    pub fn add_view(&mut self, parent: ViewId, view: Box<dyn View>) -> ViewId {
+        let id = self.insert(view);
+        self.add_child(parent, id);
+        id
+    }
+
+    /**
+     * Moves a view to the end of the children of the given parent, taking it from its previous parent.
+     * Returns false if one of the views is not in the tree, or if the parent is the view itself
+     * or one of its descendants.
+     */
+    pub fn add_child(&mut self, parent: ViewId, child: ViewId) -> bool {
+        let index = match self.nodes.get(parent) {
+            Some(node) => node.children.len(),
+            None => return false,
+        };
+
+        self.insert_child(parent, index, child)
+    }
+
+    /**
+     * Moves a view to the given index in the children of the given parent (clamped to the
+     * number of children), see add_child().
+     */
+    pub fn insert_child(&mut self, parent: ViewId, index: usize, child: ViewId) -> bool {
+        if !self.nodes.contains_key(parent) || !self.nodes.contains_key(child) {
+            return false;
+        }
+
+        if parent == child || self.ancestors(parent).any(|ancestor| ancestor == child) {
+            return false;
+        }
+
+        self.detach(child);
+
+        let children = &mut self.nodes[parent].children;
+        children.insert(index.min(children.len()), child);
+        self.nodes[child].parent = Some(parent);
+        self.invalidate(parent);
+
+        true
+    }
+
+    /**
+     * Takes a view from its parent, making it a root with its children.
+     * Returns false if the view is not in the tree.
+     */
+    pub fn detach(&mut self, id: ViewId) -> bool {
+        let parent = match self.nodes.get_mut(id) {
+            Some(node) => node.parent.take(),
+            None => return false,
+        };
+
+        if let Some(parent) = parent {
+            self.nodes[parent].children.retain(|child| *child != id);
+            self.invalidate(parent);
+        }
+
+        true
+    }
+
+    // The children of the view changed, it must be laid out again.
+    // A lent view is not invalidated, it must do it itself.
+    fn invalidate(&mut self, id: ViewId) {
+        if let Some(view) = self.get_mut(id) {
+            view.get_base_view_mut().invalidate();
+        }
+    }
+
+    /**
+     * Removes a view and its descendants from the tree, returning the view.
+     * The descendants are dropped. Returns None if the view is not in the tree,
+     * or is being lent by with_view_mut() (it is then dropped when given back).
+     */
+    pub fn remove(&mut self, id: ViewId) -> Option<Box<dyn View>> {
+        if !self.detach(id) {
+            return None;
+        }
+
+        let descendants = self.descendants(id);
+        for descendant in descendants.into_iter().skip(1) {
+            self.nodes.remove(descendant);
+        }
+
+        self.nodes.remove(id)?.view
+    }
+
+    pub fn contains(&self, id: ViewId) -> bool {
+        self.nodes.contains_key(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /**
+     * Returns the view with the given handle, None if it is not in the tree or is being lent.
+     */
+    pub fn get(&self, id: ViewId) -> Option<&dyn View> {
+        self.nodes.get(id)?.view.as_deref()
+    }
+
+    pub fn get_mut(&mut self, id: ViewId) -> Option<&mut (dyn View + 'static)> {
+        self.nodes.get_mut(id)?.view.as_deref_mut()
+    }
+
+    /**
+     * Returns the view with the given handle as its concrete type,
+     * None if it is not in the tree, is being lent or is of another type.
+     */
+    pub fn get_as<T: View + 'static>(&self, id: ViewId) -> Option<&T> {
+        self.get(id)?.as_any().downcast_ref()
+    }
+
+    pub fn get_as_mut<T: View + 'static>(&mut self, id: ViewId) -> Option<&mut T> {
+        self.get_mut(id)?.as_any_mut().downcast_mut()
+    }
+
+    pub fn get_parent(&self, id: ViewId) -> Option<ViewId> {
+        self.nodes.get(id)?.parent
+    }
+
+    /**
+     * Returns the children of a view, in order. Empty if the view is not in the tree.
+     */
+    pub fn get_children(&self, id: ViewId) -> &[ViewId] {
+        match self.nodes.get(id) {
+            Some(node) => &node.children,
+            None => &[],
+        }
+    }
+
+    /**
+     * Returns the parent of the view, then the parent of the parent... up to the root.
+     */
+    pub fn ancestors(&self, id: ViewId) -> impl Iterator<Item = ViewId> + '_ {
+        std::iter::successors(self.get_parent(id), move |id| self.get_parent(*id))
+    }
+
+    /**
+     * Returns the view and all its descendants, parents before their children
+     * and children in order (depth first).
+     */
+    pub fn descendants(&self, id: ViewId) -> Vec<ViewId> {
+        let mut descendants = vec![];
+        let mut stack = vec![id];
+
+        while let Some(id) = stack.pop() {
+            let node = match self.nodes.get(id) {
+                Some(node) => node,
+                None => continue,
+            };
+
+            descendants.push(id);
+            stack.extend(node.children.iter().rev());
+        }
+
+        descendants
+    }
+
+    /**
+     * Returns the first view with the given id (see BaseView::set_id()) among
+     * the given view and its descendants, depth first.
+     */
+    pub fn find_view(&self, root: ViewId, id: &str) -> Option<ViewId> {
+        self.descendants(root).into_iter()
+            .find(|view| self.get(*view).is_some_and(|view| view.get_base_view().get_id() == id))
+    }
+
+    /**
+     * Lends a view to the given closure along with the tree, which can then be changed
+     * while the view is mutably borrowed (adding children, focusing another view...).
+     * The view is given back to the tree afterwards, or dropped if it was removed meanwhile.
+     * Returns None if the view is not in the tree or is already lent.
+     */
+    pub fn with_view_mut<R, F: FnOnce(&mut dyn View, &mut ViewTree) -> R>(&mut self, id: ViewId, f: F) -> Option<R> {
+        let mut view = self.nodes.get_mut(id)?.view.take()?;
+        let result = f(view.as_mut(), self);
+
+        if let Some(node) = self.nodes.get_mut(id) {
+            node.view = Some(view);
+        }
+
+        Some(result)
+    }
+
+    /**
+     * Dispatches an event from a view up to its ancestors, until the given closure
+     * returns true (the event is consumed). The closure gets each view lent as with with_view_mut().
+     * Returns the view that consumed the event.
+     */
+    pub fn dispatch<F: FnMut(&mut dyn View, &mut ViewTree) -> bool>(&mut self, id: ViewId, mut f: F) -> Option<ViewId> {
+        let mut current = Some(id);
+
+        while let Some(id) = current {
+            // Read before calling, the closure may move the view
+            let parent = self.get_parent(id);
+
+            if self.with_view_mut(id, &mut f) == Some(true) {
+                return Some(id);
+            }
+
+            current = parent;
+        }
+
+        None
+    }
+
+    /**
+     * Lays out the given view in the given size, see View::layout().
+     * Its children are laid out by the view, through the tree.
+     */
+    pub fn layout(&mut self, id: ViewId, width: f32, height: f32) {
+        self.with_view_mut(id, |view, tree| view.layout(tree, width, height));
+    }
+
+    /**
+     * Advances the animations of the given view and its descendants, see View::update().
+     * Subtrees culled out of the viewport of their parent are skipped until they come back.
+     */
+    pub fn update(&mut self, id: ViewId, delta: Timestamp) {
+        let mut stack = vec![id];
+
+        while let Some(id) = stack.pop() {
+            let view = match self.get_mut(id) {
+                Some(view) => view,
+                None => continue,
+            };
+
+            if !view.get_base_view().is_in_viewport() {
+                continue;
+            }
+
+            view.update(delta);
+            stack.extend(self.nodes[id].children.iter().rev());
+        }
+    }
+
+    /**
+     * Marks the children of the given view intersecting the given rectangle, in the coordinates
+     * of the view, as in the viewport and the other ones as out of it. Detached children are never culled.
+     */
+    pub(crate) fn cull_children(&mut self, id: ViewId, x: f32, y: f32, width: f32, height: f32) {
+        for child in self.get_children(id).to_vec() {
+            if let Some(child) = self.get_mut(child) {
+                let child = child.get_base_view_mut();
+                let visible = child.is_detached() || child.get_flex_node().get_layout().intersects(x, y, width, height);
+                child.set_in_viewport(visible);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use crate::lib::core::base_view::BaseView;
+    use crate::lib::core::r#box::{Axis, BoxView, Padding};
+    use super::*;
+
+    struct TestView {
+        view: BaseView,
+        name: &'static str,
+        drops: Rc<RefCell<Vec<&'static str>>>,
+    }
+
+    impl Drop for TestView {
+        fn drop(&mut self) {
+            self.drops.borrow_mut().push(self.name);
+        }
+    }
+
+    impl View for TestView {
+        fn describe(&self) -> String {
+            self.name.into()
+        }
+
+        fn get_base_view(&self) -> &BaseView {
+            &self.view
+        }
+
+        fn get_base_view_mut(&mut self) -> &mut BaseView {
+            &mut self.view
+        }
+    }
+
+    fn tree(names: &[&'static str]) -> (ViewTree, Vec<ViewId>, Rc<RefCell<Vec<&'static str>>>) {
+        let drops = Rc::new(RefCell::new(vec![]));
+        let mut tree = ViewTree::new();
+        let ids = names.iter()
+            .map(|name| tree.insert(Box::new(TestView { view: BaseView::new(), name, drops: Rc::clone(&drops) })))
+            .collect();
+
+        (tree, ids, drops)
+    }
+
+    #[test]
+    fn links_parents_and_children() {
+        let (mut tree, ids, _) = tree(&["root", "a", "b", "c"]);
+
+        assert!(tree.add_child(ids[0], ids[1]));
+        assert!(tree.add_child(ids[0], ids[3]));
+        assert!(tree.insert_child(ids[0], 1, ids[2]));
+        assert_eq!(tree.get_children(ids[0]), &[ids[1], ids[2], ids[3]]);
+
+        // Moving a view takes it from its previous parent
+        assert!(tree.add_child(ids[1], ids[3]));
+        assert_eq!(tree.get_children(ids[0]), &[ids[1], ids[2]]);
+        assert_eq!(tree.get_parent(ids[3]), Some(ids[1]));
+        assert_eq!(tree.ancestors(ids[3]).collect::<Vec<_>>(), vec![ids[1], ids[0]]);
+        assert_eq!(tree.descendants(ids[0]), vec![ids[0], ids[1], ids[3], ids[2]]);
+
+        // No cycles
+        assert!(!tree.add_child(ids[3], ids[0]));
+        assert!(!tree.add_child(ids[1], ids[1]));
+    }
+
+    #[test]
+    fn removes_subtrees() {
+        let (mut tree, ids, drops) = tree(&["root", "a", "b", "c"]);
+        tree.add_child(ids[0], ids[1]);
+        tree.add_child(ids[1], ids[2]);
+        tree.add_child(ids[0], ids[3]);
+
+        let removed = tree.remove(ids[1]).unwrap();
+        assert_eq!(removed.describe(), "a");
+        assert_eq!(*drops.borrow(), vec!["b"]);
+        assert_eq!(tree.get_children(ids[0]), &[ids[3]]);
+        assert!(tree.get(ids[2]).is_none());
+
+        // Handles are not reused
+        let id = tree.insert(removed);
+        assert_ne!(id, ids[1]);
+        assert!(!tree.contains(ids[1]));
+        assert_eq!(tree.len(), 3);
+    }
+
+    #[test]
+    fn lends_views_with_the_tree_mutable() {
+        let (mut tree, ids, drops) = tree(&["root", "a", "b"]);
+        tree.add_child(ids[0], ids[1]);
+
+        // The view changes the tree while borrowed
+        let name = tree.with_view_mut(ids[1], |view, tree| {
+            assert!(tree.get(ids[1]).is_none());
+            assert!(tree.add_child(ids[1], ids[2]));
+            view.describe()
+        });
+        assert_eq!(name.as_deref(), Some("a"));
+        assert_eq!(tree.get_children(ids[1]), &[ids[2]]);
+
+        // Bubbles up until consumed
+        let mut visited = vec![];
+        let consumer = tree.dispatch(ids[2], |view, _| {
+            visited.push(view.describe());
+            view.describe() == "a"
+        });
+        assert_eq!(consumer, Some(ids[1]));
+        assert_eq!(visited, vec!["b", "a"]);
+
+        // Removed while lent: dropped when given back
+        tree.with_view_mut(ids[2], |_, tree| tree.remove(ids[2]));
+        assert!(!tree.contains(ids[2]));
+        assert_eq!(*drops.borrow(), vec!["b"]);
+    }
+
+    #[test]
+    fn finds_views_by_id_and_type() {
+        let (mut tree, ids, _) = tree(&["root", "a"]);
+        tree.add_child(ids[0], ids[1]);
+        tree.get_mut(ids[1]).unwrap().get_base_view_mut().set_id("child");
+        let column = tree.add_view(ids[1], Box::new(BoxView::new(Axis::Column)));
+
+        assert_eq!(tree.get(column).unwrap().get_base_view().get_view_id(), Some(column));
+        assert_eq!(tree.find_view(ids[0], "child"), Some(ids[1]));
+        assert_eq!(tree.find_view(column, "child"), None);
+        assert!(tree.get_as::<BoxView>(column).is_some());
+        assert!(tree.get_as::<BoxView>(ids[1]).is_none());
+    }
+
+    #[test]
+    fn lays_out_children_through_their_links() {
+        let mut tree = ViewTree::new();
+        let root = tree.insert(Box::new(BoxView::new(Axis::Column)));
+        let row = tree.add_view(root, Box::new(BoxView::new(Axis::Row)));
+        let leaf = tree.add_view(row, Box::new(Padding::new()));
+        tree.get_mut(leaf).unwrap().get_base_view_mut().set_height(30.0);
+
+        tree.layout(root, 200.0, 100.0);
+        let layout = |tree: &ViewTree, id| *tree.get(id).unwrap().get_base_view().get_flex_node().get_layout();
+        assert_eq!((layout(&tree, row).width, layout(&tree, row).height), (200.0, 30.0));
+        assert_eq!(layout(&tree, leaf).width, 200.0);
+
+        // Moving the leaf to the root lays the root out again
+        tree.add_child(root, leaf);
+        tree.layout(root, 200.0, 100.0);
+        assert_eq!(layout(&tree, row).height, 0.0);
+        assert_eq!(layout(&tree, leaf).y, 0.0);
+        assert!(!tree.get(root).unwrap().get_base_view().get_flex_node().is_dirty());
+    }
+}
\ No newline at end of file
@@ -1,4 +1,144 @@
-use crate::lib::core::base_view::{AutoAttributeHandler, BoolAttributeHandler, ColorAttributeHandler, FilePathAttributeHandler, FloatAttributeHandler, StringAttributeHandler, BaseView};
+use log::error;
+use roxmltree::{Document, Node};
+use crate::lib::core::i18n::get_str;
+use crate::lib::core::icons::{get_icon_xml_attribute_value, ICON_PREFIX};
+use crate::lib::core::resources::{read_resource_string, RESOURCES_PREFIX};
+use crate::lib::core::view::View;
+use crate::lib::core::view_tree::{ViewId, ViewTree};
+
+const I18N_PREFIX: &str = "@i18n/";
+
+// Prefix of the library views ("brls:Box"). XML files don't declare it,
+// the document is wrapped in an element declaring it before being parsed.
+const VIEWS_NAMESPACE: &str = "brls";
+
+/**
+ * Resolves the value of the given XML string attribute:
+ * "@i18n/..." values are translated and "@icon/..." values are replaced by the icon glyph.
+ */
+pub fn get_string_xml_attribute_value(value: &str) -> String {
+    if let Some(string_name) = value.strip_prefix(I18N_PREFIX) {
+        return get_str(string_name.into(), vec![]);
+    }
+
+    if value.starts_with(ICON_PREFIX) {
+        if let Some(icon) = get_icon_xml_attribute_value(value) {
+            return icon.get_text();
+        }
+    }
+
+    value.to_string()
+}
+
+/**
+ * Returns the content of the given XML file path, "@res/..." paths
+ * being resolved through the mounted resources.
+ */
+pub fn load_xml_file(path: &str) -> Option<String> {
+    read_resource_string(path)
+}
+
+/**
+ * Returns the content of the XML resource with the given name, in the "@res/xml" directory.
+ */
+pub fn load_xml_resource(name: &str) -> Option<String> {
+    read_resource_string(&format!("{}xml/{}", RESOURCES_PREFIX, name))
+}
+
+/**
+ * Returns the content of the given XML document, without its prolog, in a <document> element
+ * declaring the brls namespace: documents use "brls:" tags without declaring it.
+ * Returns None if the prolog is not closed.
+ */
+pub(crate) fn wrap_xml_document(xml: &str) -> Option<String> {
+    let content = match xml.trim_start().strip_prefix("<?xml") {
+        Some(prolog) => &prolog[prolog.find("?>")? + 2..],
+        None => xml,
+    };
+
+    Some(format!("<document xmlns:{0}=\"{0}\">{1}</document>", VIEWS_NAMESPACE, content))
+}
+
+/**
+ * Creates the views of the given XML string in the given tree, the root view being
+ * added as a root of the tree. Views are created from their tag ("brls:Box") by the given
+ * function, see Application::register_xml_view().
+ *
+ * Attributes are applied with View::apply_xml_attribute(), and the ones unknown to
+ * a child are given to its parent with View::apply_child_xml_attribute() (gridRow...).
+ * Returns None if the XML is invalid or has an unknown tag.
+ */
+pub fn create_from_xml_string<F: Fn(&str) -> Option<Box<dyn View>>>(tree: &mut ViewTree, xml: &str, create_view: F) -> Option<ViewId> {
+    let content = wrap_xml_document(xml)?;
+
+    let document = match Document::parse(&content) {
+        Ok(document) => document,
+        Err(err) => {
+            error!("Invalid XML: {}", err);
+            return None;
+        }
+    };
+
+    let element = document.root_element().children().find(|node| node.is_element())?;
+    let (view, unknown_attributes) = create_from_xml_element(tree, element, &create_view)?;
+
+    for (name, _) in unknown_attributes {
+        error!("Unknown XML attribute \"{}\" on \"{}\"", name, get_tag(element));
+    }
+
+    Some(view)
+}
+
+// Creates the view of the element and of its children. Returns the view
+// with the attributes it didn't handle, for its parent.
+fn create_from_xml_element<'a, F: Fn(&str) -> Option<Box<dyn View>>>(tree: &mut ViewTree, element: Node<'a, '_>, create_view: &F) -> Option<(ViewId, Vec<(&'a str, &'a str)>)> {
+    let tag = get_tag(element);
+    let view = match create_view(&tag) {
+        Some(view) => view,
+        None => {
+            error!("Unknown XML tag \"{}\"", tag);
+            return None;
+        }
+    };
+
+    let id = tree.insert(view);
+    let view = tree.get_mut(id)?;
+    let unknown_attributes = element.attributes()
+        .filter(|attribute| !view.apply_xml_attribute(attribute.name(), attribute.value()))
+        .map(|attribute| (attribute.name(), attribute.value()))
+        .collect();
+
+    for child_element in element.children().filter(|node| node.is_element()) {
+        let (child, child_attributes) = match create_from_xml_element(tree, child_element, create_view) {
+            Some(child) => child,
+            None => {
+                tree.remove(id);
+                return None;
+            }
+        };
+
+        tree.add_child(id, child);
+
+        for (name, value) in child_attributes {
+            let applied = tree.get_mut(id).is_some_and(|view| view.apply_child_xml_attribute(child, name, value));
+
+            if !applied {
+                error!("Unknown XML attribute \"{}\" on \"{}\"", name, get_tag(child_element));
+            }
+        }
+    }
+
+    Some((id, unknown_attributes))
+}
+
+fn get_tag(element: Node) -> String {
+    let name = element.tag_name().name();
+
+    match element.tag_name().namespace() {
+        Some(namespace) => format!("{}:{}", namespace, name),
+        None => name.to_string(),
+    }
+}
 
 // pub trait XmlReader {
 //     /**
@@ -258,4 +398,78 @@ use crate::lib::core::base_view::{AutoAttributeHandler, BoolAttributeHandler, Co
 //
 //     // Gets the maximum number of allowed children XML elements
 //     fn get_maximum_allowed_xml_elements(&self) -> u32;
-// }
\ No newline at end of file
+// }
+
+#[cfg(test)]
+mod tests {
+    use crate::lib::core::base_view::FocusDirection;
+    use crate::lib::core::grid::GridArea;
+    use crate::lib::core::r#box::{BoxView, Padding};
+    use crate::lib::views::grid::Grid;
+    use super::*;
+
+    fn create_view(tag: &str) -> Option<Box<dyn View>> {
+        match tag {
+            "brls:Box" => Some(BoxView::create()),
+            "brls:Grid" => Some(Grid::create()),
+            "brls:Padding" => Some(Padding::create()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn places_grid_children_from_their_attributes() {
+        let mut tree = ViewTree::new();
+        let grid = create_from_xml_string(&mut tree, r#"<?xml version="1.0" encoding="UTF-8"?>
+            <brls:Grid id="grid" columns="1fr 1fr">
+                <brls:Padding id="first"/>
+                <brls:Padding id="wide" gridRow="1" gridColumnSpan="2"/>
+                <brls:Padding id="second"/>
+            </brls:Grid>"#, create_view).unwrap();
+
+        let area = |id| {
+            let child = tree.find_view(grid, id).unwrap();
+            tree.get_as::<Grid>(grid).unwrap().get_child_area(&tree, child)
+        };
+
+        assert_eq!(tree.get(grid).unwrap().get_base_view().get_id(), "grid");
+        assert_eq!(area("first"), Some(GridArea::new(0, 0, 1, 1)));
+        assert_eq!(area("wide"), Some(GridArea::new(1, 0, 1, 2)));
+        assert_eq!(area("second"), Some(GridArea::new(0, 1, 1, 1)));
+    }
+
+    #[test]
+    fn moves_the_focus_between_grid_cells() {
+        let mut tree = ViewTree::new();
+        let grid = create_from_xml_string(&mut tree, r#"
+            <brls:Grid columns="1fr 1fr">
+                <brls:Padding id="first"/>
+                <brls:Padding id="second"/>
+                <brls:Padding id="wide" gridColumnSpan="2"/>
+            </brls:Grid>"#, create_view).unwrap();
+
+        let children = tree.get_children(grid).to_vec();
+        for child in &children {
+            tree.get_mut(*child).unwrap().get_base_view_mut().set_focusable(true);
+        }
+
+        let view = tree.get(grid).unwrap();
+        assert_eq!(view.get_default_focus(&tree), Some(children[0]));
+        assert_eq!(view.get_next_focus(&tree, FocusDirection::Right, children[0]), Some(children[1]));
+        assert_eq!(view.get_next_focus(&tree, FocusDirection::Down, children[1]), Some(children[2]));
+        assert_eq!(view.get_next_focus(&tree, FocusDirection::Right, children[1]), None);
+
+        // The grid gives the focus back to the last focused cell
+        tree.get_mut(grid).unwrap().on_child_focus_gained(children[1]);
+        assert_eq!(tree.get(grid).unwrap().get_default_focus(&tree), Some(children[1]));
+    }
+
+    #[test]
+    fn rejects_unknown_tags() {
+        let mut tree = ViewTree::new();
+        let view = create_from_xml_string(&mut tree, "<brls:Box><brls:Unknown/></brls:Box>", create_view);
+
+        assert!(view.is_none());
+        assert!(tree.is_empty());
+    }
+}
\ No newline at end of file
@@ -1,3 +1,3 @@
-pub(crate) mod core;
-pub(crate) mod platforms;
-pub(crate) mod views;
\ No newline at end of file
+pub mod core;
+pub mod platforms;
+pub mod views;
@@ -0,0 +1,285 @@
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+use log::{debug, info, warn};
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::{OwnedValue, Value};
+use crate::lib::core::theme::ThemeVariant;
+
+const PORTAL_DESTINATION: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const PORTAL_SETTINGS_INTERFACE: &str = "org.freedesktop.portal.Settings";
+
+const APPEARANCE_NAMESPACE: &str = "org.freedesktop.appearance";
+const COLOR_SCHEME_KEY: &str = "color-scheme";
+
+// Values of the portal color-scheme setting
+const COLOR_SCHEME_NO_PREFERENCE: u32 = 0;
+const COLOR_SCHEME_PREFER_DARK: u32 = 1;
+const COLOR_SCHEME_PREFER_LIGHT: u32 = 2;
+
+/**
+ * Returns the theme variant preferred by the desktop, in order:
+ * the BOREALIS_THEME env variable ("DARK" or "LIGHT"), the freedesktop settings portal
+ * and the GTK theme name. Defaults to the light variant.
+ */
+pub fn get_desktop_theme_variant() -> ThemeVariant {
+    if let Ok(value) = std::env::var("BOREALIS_THEME") {
+        return match value.as_str() {
+            "DARK" => ThemeVariant::Dark,
+            _ => ThemeVariant::Light,
+        };
+    }
+
+    match Connection::session() {
+        Ok(connection) => {
+            if let Some(variant) = read_portal_theme_variant(&connection) {
+                return variant;
+            }
+        }
+        Err(err) => debug!("No session D-Bus to query the desktop theme: {}", err),
+    }
+
+    get_gtk_theme_variant().unwrap_or(ThemeVariant::Light)
+}
+
+/**
+ * Reads the color-scheme setting from the settings portal.
+ * Returns None if the portal is not available or the user has no preference.
+ */
+pub fn read_portal_theme_variant(connection: &Connection) -> Option<ThemeVariant> {
+    let proxy = Proxy::new(connection, PORTAL_DESTINATION, PORTAL_PATH, PORTAL_SETTINGS_INTERFACE).ok()?;
+
+    // ReadOne is only available since version 2 of the interface, Read wraps the value in one more variant
+    let value: OwnedValue = proxy.call("ReadOne", &(APPEARANCE_NAMESPACE, COLOR_SCHEME_KEY))
+        .or_else(|_| proxy.call("Read", &(APPEARANCE_NAMESPACE, COLOR_SCHEME_KEY)))
+        .map_err(|err| debug!("Could not read the portal color scheme: {}", err))
+        .ok()?;
+
+    get_color_scheme_variant(&value)
+}
+
+fn get_color_scheme_variant(value: &Value) -> Option<ThemeVariant> {
+    match value {
+        Value::Value(inner) => get_color_scheme_variant(inner),
+        Value::U32(COLOR_SCHEME_PREFER_DARK) => Some(ThemeVariant::Dark),
+        Value::U32(COLOR_SCHEME_PREFER_LIGHT) => Some(ThemeVariant::Light),
+        Value::U32(COLOR_SCHEME_NO_PREFERENCE) => None,
+        _ => {
+            warn!("Unexpected portal color scheme value {:?}", value);
+            None
+        }
+    }
+}
+
+/**
+ * Guesses the variant from the GTK theme name ("Adwaita-dark"...) or the GTK dark theme preference,
+ * from the GTK_THEME env variable, the GTK 3 settings file or gsettings.
+ */
+pub fn get_gtk_theme_variant() -> Option<ThemeVariant> {
+    if let Ok(theme) = std::env::var("GTK_THEME") {
+        return Some(get_gtk_theme_name_variant(&theme));
+    }
+
+    if let Some(variant) = read_gtk_settings_file() {
+        return Some(variant);
+    }
+
+    let output = Command::new("gsettings")
+        .args(["get", "org.gnome.desktop.interface", "gtk-theme"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+
+    let theme = String::from_utf8_lossy(&output.stdout).trim().trim_matches('\'').to_string();
+    if theme.is_empty() {
+        return None;
+    }
+
+    Some(get_gtk_theme_name_variant(&theme))
+}
+
+fn get_gtk_theme_name_variant(theme: &str) -> ThemeVariant {
+    if theme.to_lowercase().contains("dark") {
+        ThemeVariant::Dark
+    } else {
+        ThemeVariant::Light
+    }
+}
+
+fn read_gtk_settings_file() -> Option<ThemeVariant> {
+    let config_dir = match std::env::var("XDG_CONFIG_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var("HOME").ok()?).join(".config"),
+    };
+
+    let content = std::fs::read_to_string(config_dir.join("gtk-3.0").join("settings.ini")).ok()?;
+    let mut variant = None;
+
+    for line in content.lines() {
+        match line.split_once('=').map(|(key, value)| (key.trim(), value.trim())) {
+            Some(("gtk-application-prefer-dark-theme", value)) if value == "1" || value == "true" => {
+                return Some(ThemeVariant::Dark);
+            }
+            Some(("gtk-theme-name", value)) => {
+                variant = Some(get_gtk_theme_name_variant(value));
+            }
+            _ => {}
+        }
+    }
+
+    variant
+}
+
+// Listens to the portal SettingChanged signal on a background thread
+// and forwards the color scheme changes to the main loop
+pub struct DesktopThemeWatcher {
+    receiver: Receiver<ThemeVariant>,
+}
+
+impl DesktopThemeWatcher {
+    /**
+     * Subscribes to the color scheme changes of the session bus portal.
+     * Returns None if there is no session bus.
+     */
+    pub fn new() -> Option<Self> {
+        match Connection::session() {
+            Ok(connection) => Self::with_connection(connection),
+            Err(err) => {
+                debug!("No session D-Bus to follow the desktop theme: {}", err);
+                None
+            }
+        }
+    }
+
+    /**
+     * Subscribes to the color scheme changes of the portal reachable through the given connection.
+     */
+    pub fn with_connection(connection: Connection) -> Option<Self> {
+        let (sender, receiver) = channel();
+
+        let proxy = Proxy::new(&connection, PORTAL_DESTINATION, PORTAL_PATH, PORTAL_SETTINGS_INTERFACE)
+            .map_err(|err| warn!("Could not create the settings portal proxy: {}", err))
+            .ok()?;
+
+        // Subscribe before returning so that no change is missed
+        let signals = proxy.receive_signal("SettingChanged")
+            .map_err(|err| warn!("Could not subscribe to the settings portal: {}", err))
+            .ok()?;
+
+        let spawned = thread::Builder::new()
+            .name("desktop-theme".into())
+            .spawn(move || Self::watch(signals, sender));
+
+        match spawned {
+            Ok(_) => {
+                info!("Following the desktop color scheme");
+                Some(Self {
+                    receiver,
+                })
+            }
+            Err(err) => {
+                warn!("Could not start the desktop theme watcher: {}", err);
+                None
+            }
+        }
+    }
+
+    fn watch(signals: zbus::blocking::SignalIterator, sender: Sender<ThemeVariant>) {
+        for message in signals {
+            let (namespace, key, value): (String, String, OwnedValue) = match message.body().deserialize() {
+                Ok(body) => body,
+                Err(err) => {
+                    warn!("Invalid SettingChanged signal: {}", err);
+                    continue;
+                }
+            };
+
+            if namespace != APPEARANCE_NAMESPACE || key != COLOR_SCHEME_KEY {
+                continue;
+            }
+
+            // "No preference" goes back to the GTK theme, like at startup
+            let variant = get_color_scheme_variant(&value)
+                .or_else(get_gtk_theme_variant)
+                .unwrap_or(ThemeVariant::Light);
+
+            // The main loop is gone, stop listening
+            if sender.send(variant).is_err() {
+                break;
+            }
+        }
+    }
+
+    /**
+     * Returns the latest variant received since the last call, if any.
+     */
+    pub fn poll(&self) -> Option<ThemeVariant> {
+        self.receiver.try_iter().last()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+    use zbus::blocking::connection::Builder;
+    use zbus::object_server::SignalContext;
+    use super::*;
+
+    // Stand-in for the settings portal, only serving the color scheme
+    struct StandInPortal {
+        color_scheme: Arc<Mutex<u32>>,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.portal.Settings")]
+    impl StandInPortal {
+        fn read_one(&self, namespace: &str, key: &str) -> zbus::fdo::Result<OwnedValue> {
+            if namespace != APPEARANCE_NAMESPACE || key != COLOR_SCHEME_KEY {
+                return Err(zbus::fdo::Error::Failed("unknown setting".into()));
+            }
+
+            Ok(OwnedValue::from(*self.color_scheme.lock().unwrap()))
+        }
+
+        #[zbus(signal)]
+        async fn setting_changed(context: &SignalContext<'_>, namespace: &str, key: &str, value: Value<'_>) -> zbus::Result<()>;
+    }
+
+    // Needs a session bus where the real portal is not running:
+    // dbus-run-session -- cargo test -- --ignored desktop_theme
+    #[test]
+    #[ignore]
+    fn follows_stand_in_portal() {
+        let color_scheme = Arc::new(Mutex::new(COLOR_SCHEME_PREFER_DARK));
+
+        let server = Builder::session().unwrap()
+            .name(PORTAL_DESTINATION).unwrap()
+            .serve_at(PORTAL_PATH, StandInPortal { color_scheme: Arc::clone(&color_scheme) }).unwrap()
+            .build().unwrap();
+
+        let client = Connection::session().unwrap();
+        assert_eq!(read_portal_theme_variant(&client), Some(ThemeVariant::Dark));
+
+        let watcher = DesktopThemeWatcher::with_connection(client).unwrap();
+
+        *color_scheme.lock().unwrap() = COLOR_SCHEME_PREFER_LIGHT;
+        let portal = server.object_server().interface::<_, StandInPortal>(PORTAL_PATH).unwrap();
+        zbus::block_on(StandInPortal::setting_changed(
+            portal.signal_context(),
+            APPEARANCE_NAMESPACE,
+            COLOR_SCHEME_KEY,
+            Value::from(COLOR_SCHEME_PREFER_LIGHT),
+        )).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut received = None;
+        while received.is_none() && Instant::now() < deadline {
+            received = watcher.poll();
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        assert_eq!(received, Some(ThemeVariant::Light));
+    }
+}
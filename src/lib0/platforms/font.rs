@@ -1,44 +1,85 @@
-use crate::lib::core::font::{FONT_MATERIAL_ICONS, FONT_SWITCH_ICONS, FontLoader};
+use std::cell::RefCell;
+use std::rc::Rc;
+use log::{error, info};
+use crate::core::system_font::load_fallback_chain;
+use crate::lib::core::font::{FONT_MATERIAL_ICONS, FONT_REGULAR, FONT_SWITCH_ICONS, FontLoader, FontStash};
+use crate::lib::core::icons::{load_codepoints, MATERIAL_ICONS_CODEPOINTS_PATH};
+use crate::lib::core::resources::read_resource;
 
 // Font loader that reads everything from resources
 pub struct GLFWFontLoader {
-
+    nvg_context: Rc<RefCell<nanovg::Context>>,
+    font_stash: Rc<RefCell<FontStash>>,
 }
 
 impl GLFWFontLoader {
-    pub fn new() -> Self {
+    pub fn new(nvg_context: Rc<RefCell<nanovg::Context>>) -> Self {
         Self {
-
+            nvg_context,
+            font_stash: Rc::new(RefCell::new(FontStash::new())),
         }
     }
 }
 
-static USER_REGULAR_PATH: &str = "User-Regular.ttf";
-static INTER_FONT_PATH: &str = "inter/Inter-Switch.ttf";
-static USER_SWITCH_ICONS_PATH: &str = "User-Switch-Icons.ttf";
+pub static USER_REGULAR_PATH: &str = "User-Regular.ttf";
+pub static INTER_FONT_PATH: &str = "@res/inter/Inter-Switch.ttf";
+pub static USER_SWITCH_ICONS_PATH: &str = "User-Switch-Icons.ttf";
 
-static MATERIAL_ICONS_PATH: &str = "material/MaterialIcons-Regular.ttf";
+pub static MATERIAL_ICONS_PATH: &str = "@res/material/MaterialIcons-Regular.ttf";
 
 impl FontLoader for GLFWFontLoader {
-    fn load_fonts(&self) {
+    fn load_fonts(&self, locale: &str) {
         // Regular
         // Try to use user-provided font first, fallback to Inter
-        // todo!()
+        if !self.load_font_from_file(FONT_REGULAR, USER_REGULAR_PATH) {
+            self.load_font_from_file(FONT_REGULAR, INTER_FONT_PATH);
+        }
+
+        // CJK fallbacks from the system fonts, so that every script renders in the same label
+        if self.font_stash.borrow().contains(FONT_REGULAR) {
+            load_fallback_chain(&self.nvg_context.borrow(), &mut self.font_stash.borrow_mut(), FONT_REGULAR, locale);
+        }
 
         // Switch icons
         // Only supports user-provided font
         self.load_font_from_file(FONT_SWITCH_ICONS, USER_SWITCH_ICONS_PATH);
 
         // Material icons
-        self.load_material_from_resources();
+        if self.load_material_from_resources() {
+            load_codepoints(FONT_MATERIAL_ICONS, MATERIAL_ICONS_CODEPOINTS_PATH);
+        }
     }
 
     fn load_font_from_file(&self, font_name: &str, font_path: &str) -> bool {
-        todo!()
+        // Read through the resources so that "@res/" paths work in self-contained builds
+        let data = match read_resource(font_path) {
+            Some(data) => data,
+            None => {
+                error!("Could not load font \"{}\": \"{}\" not found", font_name, font_path);
+                return false;
+            }
+        };
+
+        let result = self.font_stash.borrow_mut().load_font_from_memory(&self.nvg_context.borrow(), font_name, data);
+
+        match result {
+            Ok(handle) => {
+                info!("Loaded font \"{}\" from \"{}\" (handle {})", font_name, font_path, handle);
+                true
+            }
+            Err(err) => {
+                error!("Could not load font \"{}\" from \"{}\": {}", font_name, font_path, err);
+                false
+            }
+        }
     }
 
 
     fn load_material_from_resources(&self) -> bool {
-        return self.load_font_from_file(FONT_MATERIAL_ICONS, MATERIAL_ICONS_PATH)
+        self.load_font_from_file(FONT_MATERIAL_ICONS, MATERIAL_ICONS_PATH)
+    }
+
+    fn get_font_stash(&self) -> Rc<RefCell<FontStash>> {
+        Rc::clone(&self.font_stash)
     }
 }
\ No newline at end of file
@@ -1,6 +1,6 @@
 use std::cell::RefCell;
 use std::rc::Rc;
-use glfw::{Action, GamepadState, Glfw, JoystickEvent};
+use glfw::{Action, Glfw, JoystickEvent};
 use log::info;
 use once_cell::sync::Lazy;
 use crate::lib::core::input::{ControllerState, InputManager};
@@ -1,5 +1,7 @@
-pub(crate) mod glfw;
-pub(crate) mod platform;
-pub(crate) mod font;
-pub(crate) mod input;
-pub(crate) mod video;
\ No newline at end of file
+pub mod glfw;
+pub mod platform;
+#[cfg(target_os = "linux")]
+pub mod desktop_theme;
+pub mod font;
+pub mod input;
+pub mod video;
\ No newline at end of file
@@ -1,6 +1,5 @@
-use std::cell::{Cell, RefCell};
+use std::cell::RefCell;
 use std::rc::Rc;
-use std::sync::Arc;
 use glfw::{Context, Glfw, PWindow};
 use crate::lib::core::audio::{AudioPlayer, NullAudioPlayer};
 use crate::lib::core::font::FontLoader;
@@ -8,10 +7,36 @@ use crate::lib::core::input::InputManager;
 use crate::lib::core::platform::Platform;
 use crate::lib::core::theme::ThemeVariant;
 use crate::lib::core::video::VideoContext;
+#[cfg(target_os = "linux")]
+use crate::lib::platforms::desktop_theme::{get_desktop_theme_variant, DesktopThemeWatcher};
 use crate::lib::platforms::font::GLFWFontLoader;
 use crate::lib::platforms::video::GLFWVideoContext;
 use crate::lib::platforms::input::GLFWInputManager;
 
+// The desktop theme is only followed on Linux, elsewhere the BOREALIS_THEME env variable
+// ("DARK" or "LIGHT") picks the variant
+#[cfg(not(target_os = "linux"))]
+fn get_desktop_theme_variant() -> ThemeVariant {
+    match std::env::var("BOREALIS_THEME").as_deref() {
+        Ok("DARK") => ThemeVariant::Dark,
+        _ => ThemeVariant::Light,
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+struct DesktopThemeWatcher;
+
+#[cfg(not(target_os = "linux"))]
+impl DesktopThemeWatcher {
+    fn new() -> Option<Self> {
+        None
+    }
+
+    fn poll(&self) -> Option<ThemeVariant> {
+        None
+    }
+}
+
 pub struct GlfwPlatform {
     audio_player: Rc<RefCell<Box<dyn AudioPlayer>>>,
     input_manager: Rc<RefCell<Box<dyn InputManager>>>,
@@ -19,6 +44,10 @@ pub struct GlfwPlatform {
     video_context: Rc<RefCell<Box<dyn VideoContext>>>,
     g: Rc<RefCell<Glfw>>,
     window: Rc<RefCell<PWindow>>,
+    theme_variant: ThemeVariant,
+    theme_watcher: Option<DesktopThemeWatcher>,
+    wireframe_key_down: bool,
+    view_tree_dump_key_down: bool,
 }
 
 impl GlfwPlatform {
@@ -27,12 +56,31 @@ impl GlfwPlatform {
         GlfwPlatform {
             audio_player: Rc::new(RefCell::new(Box::new(NullAudioPlayer::new()))),
             input_manager: Rc::new(RefCell::new(Box::new(GLFWInputManager::new(Rc::clone(&glfw_video_context.get_glfw()))))),
-            font_loader: Rc::new(RefCell::new(Box::new(GLFWFontLoader::new()))),
+            font_loader: Rc::new(RefCell::new(Box::new(GLFWFontLoader::new(glfw_video_context.get_nvg_context())))),
             g: glfw_video_context.get_glfw(),
             window: glfw_video_context.get_glfw_window(),
             video_context: Rc::new(RefCell::new(Box::new(glfw_video_context))),
+            theme_variant: get_desktop_theme_variant(),
+            // The env variable forces the variant, do not follow the system then
+            theme_watcher: match std::env::var("BOREALIS_THEME") {
+                Ok(_) => None,
+                Err(_) => DesktopThemeWatcher::new(),
+            },
+            wireframe_key_down: false,
+            view_tree_dump_key_down: false,
         }
     }
+
+    /**
+     * Returns true if the key went down since the last call, given its previous state.
+     */
+    fn poll_key(&self, key: glfw::Key, key_down: &mut bool) -> bool {
+        let down = self.window.borrow().get_key(key) == glfw::Action::Press;
+        let pressed = down && !*key_down;
+
+        *key_down = down;
+        pressed
+    }
 }
 
 impl Platform for GlfwPlatform {
@@ -59,18 +107,40 @@ impl Platform for GlfwPlatform {
     }
 
     fn get_theme_variant(&self) -> ThemeVariant {
-        if let Ok(value) = std::env::var("BOREALIS_THEME") {
-            match value.as_str() {
-                "DARK" => {
-                    ThemeVariant::Dark
-                }
-                _ => {
-                    ThemeVariant::Light
-                }
-            }
-        } else {
-            ThemeVariant::Light
+        self.theme_variant.clone()
+    }
+
+    fn poll_theme_variant_change(&mut self) -> Option<ThemeVariant> {
+        let variant = self.theme_watcher.as_ref()?.poll()?;
+
+        if variant == self.theme_variant {
+            return None;
         }
+
+        self.theme_variant = variant.clone();
+        Some(variant)
+    }
+
+    /**
+     * The wireframe hotkey is F12.
+     */
+    fn poll_wireframe_hotkey(&mut self) -> bool {
+        let mut key_down = self.wireframe_key_down;
+        let pressed = self.poll_key(glfw::Key::F12, &mut key_down);
+
+        self.wireframe_key_down = key_down;
+        pressed
+    }
+
+    /**
+     * The view tree dump hotkey is F11.
+     */
+    fn poll_view_tree_dump_hotkey(&mut self) -> bool {
+        let mut key_down = self.view_tree_dump_key_down;
+        let pressed = self.poll_key(glfw::Key::F11, &mut key_down);
+
+        self.view_tree_dump_key_down = key_down;
+        pressed
     }
 
     fn get_locale(&self) -> &str {
@@ -14,7 +14,7 @@ pub struct GLFWVideoContext {
     nvg_context: Rc<RefCell<nanovg::Context>>,
 }
 
-extern "C" fn glfw_window_framebuffer_size_callback(window: *mut GLFWwindow, width: c_int, height: c_int) {
+extern "C" fn glfw_window_framebuffer_size_callback(_window: *mut GLFWwindow, width: c_int, height: c_int) {
     if width < 0 || height < 0 {
         return;
     }
@@ -46,9 +46,8 @@ impl GLFWVideoContext {
             glfwMakeContextCurrent(window.window_ptr());
             glfwSetFramebufferSizeCallback(window.window_ptr(), Some(glfw_window_framebuffer_size_callback));
 
-            // Load OpenGL routines using glad
-            gl_loader::init_gl();
-            gl::load_with(|symbol| gl_loader::get_proc_address(symbol) as *const _);
+            // Load OpenGL routines through glfw
+            gl::load_with(|symbol| window.get_proc_address(symbol) as *const _);
             glfwSwapInterval(1);
 
             info!("glfw: GL Vendor: {:#?}", gl::GetString(gl::VENDOR));
@@ -57,7 +56,7 @@ impl GLFWVideoContext {
         }
 
         // Initialize nanovg
-        let mut context = nanovg::ContextBuilder::new()
+        let context = nanovg::ContextBuilder::new()
             .stencil_strokes()
             .antialias()
             .build()
@@ -1,6 +1,4 @@
-use std::cell::RefCell;
-use std::rc::Rc;
-use crate::lib::core::base_view::FocusDirection;
+use crate::lib::core::base_view::BaseView;
 use crate::lib::core::frame_context::FrameContext;
 use crate::lib::core::r#box::BoxView;
 use crate::lib::core::view::View;
@@ -10,41 +8,21 @@ pub struct Button {
 }
 
 impl Button {
-    pub(crate) fn create() -> Rc<RefCell<Box<dyn View>>> {
+    pub(crate) fn create() -> Box<dyn View> {
         todo!()
     }
 }
 
 impl View for Button {
-    fn frame(&self, ctx: &FrameContext) {
+    fn frame(&self, _ctx: &FrameContext) {
         todo!()
     }
 
-    fn get_default_focus(&self) -> Box<dyn View> {
-        todo!()
+    fn get_base_view(&self) -> &BaseView {
+        self.box_view.get_base_view()
     }
 
-    fn get_next_focus(&self, direction: FocusDirection, current_view: &dyn View) -> Box<dyn View> {
-        todo!()
-    }
-
-    fn on_focus_lost(&self) {
-        todo!()
-    }
-
-    fn on_focus_gained(&self) {
-        todo!()
-    }
-
-    fn describe(&self) -> String {
-        todo!()
-    }
-
-    fn get_view(&self, id: &str) -> Rc<RefCell<Option<Box<dyn View>>>> {
-        todo!()
-    }
-
-    fn get_parent(&self) -> Rc<RefCell<Option<Box<dyn View>>>> {
-        todo!()
+    fn get_base_view_mut(&mut self) -> &mut BaseView {
+        self.box_view.get_base_view_mut()
     }
 }
\ No newline at end of file
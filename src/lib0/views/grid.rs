@@ -0,0 +1,505 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::mem::take;
+use std::rc::Rc;
+use log::error;
+use crate::lib::core::base_view::{BaseView, FocusDirection, Visibility};
+use crate::lib::core::flex::{FlexLayout, FlexNode};
+use crate::lib::core::frame_context::{DrawLayer, FrameContext};
+use crate::lib::core::r#box::frame_children;
+use crate::lib::core::grid::{find_next_item, get_span, place_items, size_tracks, GridArea, GridPlacement, Track, TrackSize};
+use crate::lib::core::view::View;
+use crate::lib::core::view_tree::{ViewId, ViewTree};
+
+// Lays out its children in rows and columns
+//
+// Tracks are fixed ("200"), fractions of the remaining space ("1fr") or sized after their
+// content ("auto"). Children are placed in reading order in the free cells, or at the row
+// and column they ask for, and can span several tracks. Rows are added as needed.
+// Focus moves between cells in two dimensions.
+// The children are the children of the grid in its view tree.
+pub struct Grid {
+    view: BaseView,
+    columns: Vec<TrackSize>,
+    rows: Vec<TrackSize>, // rows after these ones are auto
+    row_gap: f32,
+    column_gap: f32,
+    placements: HashMap<ViewId, GridPlacement>, // children without one are placed in reading order
+    focused_child: Option<ViewId>,
+    dirty: bool,
+    laid_out_size: Option<(f32, f32)>,
+    laid_out_children: Vec<ViewId>,
+    content: Rc<RefCell<GridContent>>, // shared with the measure function of the node
+}
+
+// The tracks of a grid with the cells of its children, to size the tracks. During a layout
+// of the parent of the grid, the nodes of the children are lent to the measure function
+// of the node of the grid, see Grid::lend_flex_node().
+#[derive(Default)]
+struct GridContent {
+    columns: Vec<TrackSize>,
+    rows: Vec<TrackSize>,
+    row_gap: f32,
+    column_gap: f32,
+    areas: Vec<GridArea>,
+    nodes: Vec<FlexNode>,
+}
+
+impl GridContent {
+    /**
+     * Sizes the columns in the given width, then the rows in the given height, None being the size
+     * of the content. layout_child lays out the child at the given index alone in the given width,
+     * None for its natural width, and returns its layout.
+     */
+    fn size_grid<F: FnMut(usize, Option<f32>) -> FlexLayout>(&self, width: Option<f32>, height: Option<f32>, mut layout_child: F) -> (Vec<Track>, Vec<Track>) {
+        // Columns, from the natural width of the children
+        let column_items: Vec<(GridArea, f32)> = self.areas.iter().enumerate()
+            .map(|(index, area)| {
+                let child = layout_child(index, None);
+                (*area, child.width + child.margin.left + child.margin.right)
+            })
+            .collect();
+        let columns = size_tracks(&self.columns, &column_items, false, width, self.column_gap);
+
+        // Rows, from the height of the children in the width of their columns
+        let mut rows = self.rows.clone();
+        let row_count = self.areas.iter().map(|area| area.row + area.row_span).max().unwrap_or(0);
+        if rows.len() < row_count {
+            rows.resize(row_count, TrackSize::Auto);
+        }
+
+        let row_items: Vec<(GridArea, f32)> = self.areas.iter().enumerate()
+            .map(|(index, area)| {
+                let (_, cell_width) = get_span(&columns, area.column, area.column_span);
+                let child = layout_child(index, Some(cell_width));
+                (*area, child.height + child.margin.top + child.margin.bottom)
+            })
+            .collect();
+        let rows = size_tracks(&rows, &row_items, true, height, self.row_gap);
+
+        (columns, rows)
+    }
+
+    /**
+     * Returns the size of the content of the grid in the given width, None being the width of the content,
+     * from the lent nodes of the children. The rows take the height of their content.
+     */
+    fn measure(&mut self, width: Option<f32>) -> (f32, f32) {
+        let mut nodes = take(&mut self.nodes);
+        let (columns, rows) = self.size_grid(width, None, |index, width| {
+            let node = &mut nodes[index];
+            node.calculate_layout(width, None);
+            *node.get_layout()
+        });
+        self.nodes = nodes;
+
+        (get_span(&columns, 0, columns.len()).1, get_span(&rows, 0, rows.len()).1)
+    }
+}
+
+impl Default for Grid {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Grid {
+    pub fn new() -> Self {
+        let content = Rc::new(RefCell::new(GridContent::default()));
+        let measured = Rc::clone(&content);
+        let mut view = BaseView::new();
+        view.get_flex_node_mut().set_measure_func(Some(Box::new(move |width, _| measured.borrow_mut().measure(width))));
+
+        Self {
+            view,
+            columns: vec![TrackSize::Fraction(1.0)],
+            rows: vec![],
+            row_gap: 0.0,
+            column_gap: 0.0,
+            placements: HashMap::new(),
+            focused_child: None,
+            dirty: true,
+            laid_out_size: None,
+            laid_out_children: vec![],
+            content,
+        }
+    }
+
+    pub(crate) fn create() -> Box<dyn View> {
+        Box::new(Grid::new())
+    }
+
+    pub fn set_columns(&mut self, columns: Vec<TrackSize>) {
+        self.columns = columns;
+        self.dirty = true;
+    }
+
+    pub fn set_rows(&mut self, rows: Vec<TrackSize>) {
+        self.rows = rows;
+        self.dirty = true;
+    }
+
+    /**
+     * Sets the space between rows and between columns.
+     */
+    pub fn set_gaps(&mut self, row_gap: f32, column_gap: f32) {
+        self.row_gap = row_gap;
+        self.column_gap = column_gap;
+        self.dirty = true;
+    }
+
+    /**
+     * Places the given child of the grid at the given row and column.
+     */
+    pub fn set_child_placement(&mut self, child: ViewId, placement: GridPlacement) {
+        self.placements.insert(child, placement);
+        self.dirty = true;
+    }
+
+    /**
+     * Returns the cells covered by the given child.
+     */
+    pub fn get_child_area(&self, tree: &ViewTree, child: ViewId) -> Option<GridArea> {
+        self.get_cells(tree).into_iter()
+            .find(|(id, _)| *id == child)
+            .map(|(_, area)| area)
+    }
+
+    fn get_children<'a>(&self, tree: &'a ViewTree) -> &'a [ViewId] {
+        match self.view.get_view_id() {
+            Some(id) => tree.get_children(id),
+            None => &[],
+        }
+    }
+
+    /**
+     * Returns the tracks of the grid with the given cells of its children.
+     */
+    fn get_content(&self, areas: Vec<GridArea>) -> GridContent {
+        GridContent {
+            columns: self.columns.clone(),
+            rows: self.rows.clone(),
+            row_gap: self.row_gap,
+            column_gap: self.column_gap,
+            areas,
+            nodes: vec![],
+        }
+    }
+
+    /**
+     * Returns the children of the grid with the cells they cover.
+     */
+    fn get_cells(&self, tree: &ViewTree) -> Vec<(ViewId, GridArea)> {
+        let children = self.get_children(tree);
+        let placements: Vec<GridPlacement> = children.iter()
+            .map(|child| self.placements.get(child).copied().unwrap_or_default())
+            .collect();
+
+        children.iter().copied().zip(place_items(&placements, self.columns.len())).collect()
+    }
+
+    /**
+     * Returns the child to focus when moving in the given direction
+     * from the given child, or None to leave the grid.
+     * Columns go from right to left in right to left layouts.
+     */
+    pub fn get_next_focus_child(&self, tree: &ViewTree, current: ViewId, direction: FocusDirection) -> Option<ViewId> {
+        let direction = self.view.get_layout_focus_direction(direction);
+        let cells = self.get_cells(tree);
+        let current = cells.iter().position(|(id, _)| *id == current)?;
+        let areas: Vec<GridArea> = cells.iter().map(|(_, area)| *area).collect();
+
+        find_next_item(&areas, current, direction, |index| is_focusable(tree, cells[index].0))
+            .map(|index| cells[index].0)
+    }
+
+    /**
+     * Returns the child to focus when the grid gets the focus: the last focused one,
+     * one of its neighbours if it can't be focused anymore (collapsed or hidden),
+     * or the first focusable one.
+     */
+    pub fn get_default_focus_child(&self, tree: &ViewTree) -> Option<ViewId> {
+        let children = self.get_children(tree);
+
+        if let Some(focused) = self.focused_child.filter(|child| children.contains(child)) {
+            if is_focusable(tree, focused) {
+                return Some(focused);
+            }
+
+            let neighbour = [FocusDirection::Down, FocusDirection::Up, FocusDirection::Right, FocusDirection::Left]
+                .into_iter()
+                .find_map(|direction| self.get_next_focus_child(tree, focused, direction));
+
+            if neighbour.is_some() {
+                return neighbour;
+            }
+        }
+
+        children.iter().copied().find(|child| is_focusable(tree, *child))
+    }
+}
+
+// Can the given child of the grid, or one of its own children, get the focus?
+fn is_focusable(tree: &ViewTree, child: ViewId) -> bool {
+    tree.get(child).is_some_and(|view| view.get_default_focus(tree).is_some())
+}
+
+impl View for Grid {
+    /**
+     * Draws the grid, then its children in their cells with the theme override of the grid applied.
+     */
+    fn frame(&self, ctx: &FrameContext) {
+        if self.view.get_visibility() != Visibility::Visible {
+            return;
+        }
+
+        match ctx.layer {
+            DrawLayer::Debug => self.view.draw_debug(ctx, self.get_children(ctx.tree).is_empty()),
+            _ => self.view.frame(ctx, |_| {}),
+        }
+
+        self.view.with_frame_context(ctx, |ctx| frame_children(ctx, self.get_children(ctx.tree)));
+    }
+
+    /**
+     * Focuses the grid itself if it is focusable, otherwise the last focused child,
+     * see get_default_focus_child().
+     */
+    fn get_default_focus(&self, tree: &ViewTree) -> Option<ViewId> {
+        if self.view.is_focusable() {
+            return self.view.get_view_id();
+        }
+
+        if self.view.get_visibility() != Visibility::Visible || self.view.is_collapsed() {
+            return None;
+        }
+
+        tree.get(self.get_default_focus_child(tree)?)?.get_default_focus(tree)
+    }
+
+    /**
+     * Moves the focus to the nearest cell in the given direction, see get_next_focus_child().
+     */
+    fn get_next_focus(&self, tree: &ViewTree, direction: FocusDirection, current: ViewId) -> Option<ViewId> {
+        tree.get(self.get_next_focus_child(tree, current, direction)?)?.get_default_focus(tree)
+    }
+
+    /**
+     * Remembers the focused child, to give it the focus back when the grid is focused again.
+     */
+    fn on_child_focus_gained(&mut self, child: ViewId) {
+        self.focused_child = Some(child);
+    }
+
+    /**
+     * Applies the XML attributes of the grid:
+     * columns="200 1fr 1fr", rows="auto 1fr", rowGap="10", columnGap="10".
+     * Returns false if the attribute is unknown or invalid.
+     */
+    fn apply_xml_attribute(&mut self, name: &str, value: &str) -> bool {
+        match name {
+            "columns" | "rows" => match TrackSize::parse_list(value) {
+                Some(tracks) if name == "columns" && !tracks.is_empty() => {
+                    self.set_columns(tracks);
+                    self.view.record_xml_attribute(name, value);
+                    true
+                }
+                Some(tracks) if name == "rows" => {
+                    self.set_rows(tracks);
+                    self.view.record_xml_attribute(name, value);
+                    true
+                }
+                _ => {
+                    error!("Invalid grid {} \"{}\", expected sizes like \"200 1fr auto\"", name, value);
+                    false
+                }
+            },
+            "rowGap" | "columnGap" => match value.parse::<f32>() {
+                Ok(gap) if name == "rowGap" => {
+                    self.set_gaps(gap, self.column_gap);
+                    self.view.record_xml_attribute(name, value);
+                    true
+                }
+                Ok(gap) => {
+                    self.set_gaps(self.row_gap, gap);
+                    self.view.record_xml_attribute(name, value);
+                    true
+                }
+                Err(_) => false,
+            },
+            _ => self.view.apply_xml_attribute(name, value),
+        }
+    }
+
+    /**
+     * Applies the XML attributes of a child placing it in the grid:
+     * gridRow, gridColumn, gridRowSpan and gridColumnSpan (indexes start at 0).
+     * Returns false if the attribute is unknown or invalid.
+     */
+    fn apply_child_xml_attribute(&mut self, child: ViewId, name: &str, value: &str) -> bool {
+        let value = match value.parse::<usize>() {
+            Ok(value) => value,
+            Err(_) => return false,
+        };
+
+        let mut placement = self.placements.get(&child).copied().unwrap_or_default();
+
+        match name {
+            "gridRow" => placement.row = Some(value),
+            "gridColumn" => placement.column = Some(value),
+            "gridRowSpan" => placement.row_span = value.max(1),
+            "gridColumnSpan" => placement.column_span = value.max(1),
+            _ => return false,
+        }
+
+        self.set_child_placement(child, placement);
+        true
+    }
+
+    fn to_json(&self, tree: &ViewTree) -> serde_json::Value {
+        let mut json = self.view.to_json("brls:Grid");
+        json["children"] = self.get_cells(tree).into_iter()
+            .filter_map(|(child, area)| {
+                let mut child = tree.get(child)?.to_json(tree);
+                child["gridArea"] = serde_json::json!({
+                    "row": area.row,
+                    "column": area.column,
+                    "rowSpan": area.row_span,
+                    "columnSpan": area.column_span,
+                });
+                Some(child)
+            })
+            .collect();
+        json
+    }
+
+    /**
+     * Lays out the grid in the given size, then its children, see layout_cells().
+     */
+    fn layout(&mut self, tree: &mut ViewTree, width: f32, height: f32) {
+        if !self.needs_layout(tree) && self.laid_out_size == Some((width, height)) {
+            return;
+        }
+
+        self.view.get_flex_node_mut().calculate_layout(Some(width), Some(height));
+        self.layout_cells(tree);
+
+        if self.view.get_flex_node().has_new_layout() {
+            self.view.on_layout();
+        }
+
+        self.laid_out_size = Some((width, height));
+    }
+
+    fn get_base_view(&self) -> &BaseView {
+        &self.view
+    }
+
+    fn get_base_view_mut(&mut self) -> &mut BaseView {
+        &mut self.view
+    }
+
+    fn is_layout_dirty(&self, tree: &ViewTree) -> bool {
+        self.needs_layout(tree)
+    }
+
+    /**
+     * Lends the node of the grid with the nodes of its children lent to its measure function:
+     * the grid gets the size of its tracks in the containers sizing it after its content
+     * (scrolling frames...).
+     */
+    fn lend_flex_node(&mut self, tree: &mut ViewTree) -> FlexNode {
+        // The nodes of the children are laid out by the measure function, read their state first
+        self.dirty = self.needs_layout(tree);
+
+        let cells = self.get_cells(tree);
+        let nodes = cells.iter()
+            .map(|(child, _)| tree.with_view_mut(*child, |child, tree| child.lend_flex_node(tree)).unwrap_or_default())
+            .collect();
+        *self.content.borrow_mut() = GridContent {
+            nodes,
+            ..self.get_content(cells.into_iter().map(|(_, area)| area).collect())
+        };
+
+        let mut node = take(self.view.get_flex_node_mut());
+        if self.dirty {
+            node.mark_dirty();
+        }
+        node
+    }
+
+    /**
+     * The children are placed by the grid once it got its size from the parent.
+     */
+    fn give_back_flex_node(&mut self, tree: &mut ViewTree, node: FlexNode) {
+        let lent = take(&mut *self.content.borrow_mut());
+        for (child, node) in self.get_children(tree).to_vec().into_iter().zip(lent.nodes) {
+            tree.with_view_mut(child, |child, tree| child.give_back_flex_node(tree, node));
+        }
+
+        let size = (node.get_layout().width, node.get_layout().height);
+        *self.view.get_flex_node_mut() = node;
+
+        if self.laid_out_size != Some(size) || self.needs_layout(tree) {
+            self.layout_cells(tree);
+            self.laid_out_size = Some(size);
+        }
+
+        if self.view.get_flex_node().has_new_layout() {
+            self.view.on_layout();
+        }
+    }
+}
+
+impl Grid {
+    // Did the grid, its children or their order change since the last layout?
+    fn needs_layout(&self, tree: &ViewTree) -> bool {
+        let children = self.get_children(tree);
+
+        self.dirty
+            || self.view.get_flex_node().is_dirty()
+            || children != self.laid_out_children.as_slice()
+            || children.iter().any(|child| tree.get(*child).is_some_and(|child| child.is_layout_dirty(tree)))
+    }
+
+    /**
+     * Sizes the columns from the width of the grid, then the rows from the height of the
+     * children in their columns, and gives each child the size of its cells.
+     */
+    fn layout_cells(&mut self, tree: &mut ViewTree) {
+        let layout = *self.view.get_flex_node().get_layout();
+        let inner_width = (layout.width - layout.padding.left - layout.padding.right).max(0.0);
+        let inner_height = (layout.height - layout.padding.top - layout.padding.bottom).max(0.0);
+        let cells = self.get_cells(tree);
+        let content = self.get_content(cells.iter().map(|(_, area)| *area).collect());
+        let (columns, rows) = content.size_grid(Some(inner_width), Some(inner_height), |index, width| {
+            calculate_child_layout(tree, cells[index].0, width, None)
+        });
+
+        let right_to_left = self.view.is_right_to_left();
+
+        for (child, area) in &cells {
+            let (x, cell_width) = get_span(&columns, area.column, area.column_span);
+            let x = if right_to_left { inner_width - x - cell_width } else { x };
+            let (y, cell_height) = get_span(&rows, area.row, area.row_span);
+
+            tree.with_view_mut(*child, |child, tree| {
+                let mut node = child.lend_flex_node(tree);
+                node.calculate_layout(Some(cell_width), Some(cell_height));
+                node.offset_layout(layout.padding.left + x, layout.padding.top + y);
+                child.give_back_flex_node(tree, node);
+            });
+        }
+
+        self.laid_out_children = cells.into_iter().map(|(child, _)| child).collect();
+        self.dirty = false;
+    }
+}
+
+// Lays out the given child of the grid alone in the given size, to size the tracks
+fn calculate_child_layout(tree: &mut ViewTree, child: ViewId, width: Option<f32>, height: Option<f32>) -> FlexLayout {
+    tree.with_view_mut(child, |child, tree| {
+        child.calculate_layout(tree, width, height);
+        *child.get_base_view().get_flex_node().get_layout()
+    }).unwrap_or_default()
+}
\ No newline at end of file
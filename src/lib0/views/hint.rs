@@ -0,0 +1,132 @@
+use crate::lib::core::actions::Action;
+use crate::lib::core::icons::Icon;
+use crate::lib::core::r#box::{AlignItems, Axis, BoxView};
+use crate::lib::core::view::View;
+use crate::lib::core::view_tree::{ViewId, ViewTree};
+use crate::lib::views::label::Label;
+
+// Space between the icon of the action button and the hint text
+const HINT_ICON_MARGIN: f32 = 8.0;
+
+/**
+ * Adds the hint of the given action to the footer: a row made of the icon
+ * of the action button (see Action::get_hint_icon()) followed by the hint text.
+ * Returns the row.
+ */
+pub fn add_hint(tree: &mut ViewTree, footer: ViewId, action: &Action) -> ViewId {
+    add_hint_row(tree, footer, action.get_hint_icon(), &action.hint_text)
+}
+
+/**
+ * Replaces the hints of the footer by the ones of the focused view and of its parents,
+ * the nearest view winning for each button. Hidden and unavailable actions are left out.
+ * Returns the number of hints.
+ */
+pub fn fill_hints(tree: &mut ViewTree, footer: ViewId, focus: Option<ViewId>) -> usize {
+    for hint in tree.get_children(footer).to_vec() {
+        tree.remove(hint);
+    }
+
+    let mut buttons = vec![];
+    let mut hints = vec![];
+
+    let views = focus.into_iter().chain(focus.into_iter().flat_map(|focus| tree.ancestors(focus)));
+    for view in views {
+        let actions = match tree.get(view) {
+            Some(view) => view.get_base_view().get_actions(),
+            None => continue,
+        };
+
+        for action in actions {
+            if action.hidden || buttons.contains(&action.button) {
+                continue;
+            }
+
+            buttons.push(action.button);
+            if action.available {
+                hints.push((action.get_hint_icon(), action.hint_text.clone()));
+            }
+        }
+    }
+
+    for (icon, text) in &hints {
+        add_hint_row(tree, footer, icon.clone(), text);
+    }
+
+    hints.len()
+}
+
+fn add_hint_row(tree: &mut ViewTree, footer: ViewId, icon: Option<Icon>, text: &str) -> ViewId {
+    let mut row = BoxView::new(Axis::Row);
+    row.set_align_items(AlignItems::Center);
+    let row = tree.add_view(footer, Box::new(row));
+
+    if let Some(icon) = icon {
+        let mut label = Label::new();
+        label.set_icon_glyph(icon);
+        label.get_base_view_mut().set_margin_right(HINT_ICON_MARGIN);
+        tree.add_view(row, Box::new(label));
+    }
+
+    let mut label = Label::new();
+    label.set_text(text);
+    tree.add_view(row, Box::new(label));
+
+    row
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lib::core::audio::Sound;
+    use crate::lib::core::font::{FONT_REGULAR, FONT_SWITCH_ICONS};
+    use crate::lib::core::input::ControllerButton;
+    use super::*;
+
+    fn get_label(tree: &ViewTree, id: ViewId) -> &Label {
+        tree.get_as::<Label>(id).unwrap()
+    }
+
+    #[test]
+    fn fills_the_hints_of_the_focus_and_its_parents() {
+        let mut tree = ViewTree::new();
+        let footer = tree.insert(Box::new(BoxView::new(Axis::Row)));
+        let root = tree.insert(Box::new(BoxView::new(Axis::Column)));
+        let focus = tree.add_view(root, Box::new(BoxView::new(Axis::Row)));
+
+        let base = tree.get_mut(root).unwrap().get_base_view_mut();
+        base.register_action("Back", ControllerButton::ButtonB, Box::new(|_| true), false, Sound::SoundClick);
+        base.register_action("Open", ControllerButton::ButtonA, Box::new(|_| true), false, Sound::SoundClick);
+        base.register_action("Debug", ControllerButton::ButtonY, Box::new(|_| true), true, Sound::SoundClick);
+        tree.get_mut(focus).unwrap().get_base_view_mut()
+            .register_action("OK", ControllerButton::ButtonA, Box::new(|_| true), false, Sound::SoundClick);
+
+        // The focused view overrides the A hint of its parent, the hidden action is left out
+        assert_eq!(fill_hints(&mut tree, footer, Some(focus)), 2);
+        let hints = tree.get_children(footer).to_vec();
+        assert_eq!(hints.len(), 2);
+
+        let row = tree.get_children(hints[0]).to_vec();
+        assert_eq!(get_label(&tree, row[0]).get_font(), FONT_SWITCH_ICONS);
+        assert_eq!(get_label(&tree, row[0]).get_text(), "\u{E0E0}");
+        assert_eq!(get_label(&tree, row[1]).get_font(), FONT_REGULAR);
+        assert_eq!(get_label(&tree, row[1]).get_text(), "OK");
+        assert_eq!(get_label(&tree, tree.get_children(hints[1])[1]).get_text(), "Back");
+
+        // Filled again, the previous hints are removed
+        assert_eq!(fill_hints(&mut tree, footer, Some(root)), 2);
+        assert_eq!(tree.get_children(footer).len(), 2);
+        assert!(!tree.contains(hints[0]));
+        assert_eq!(fill_hints(&mut tree, footer, None), 0);
+    }
+
+    #[test]
+    fn labels_keep_their_icon_font() {
+        let mut label = Label::new();
+        label.set_text("@icon/button_b");
+        assert_eq!(label.get_font(), FONT_SWITCH_ICONS);
+        assert_eq!(label.get_text(), "\u{E0E1}");
+
+        label.set_text("\u{E0E2}");
+        assert_eq!(label.get_font(), FONT_SWITCH_ICONS);
+    }
+}
\ No newline at end of file
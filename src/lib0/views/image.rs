@@ -1,49 +1,146 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
-use crate::lib::core::base_view::FocusDirection;
+use log::error;
+use nanovg::Context as NVGcontext;
+use crate::lib::core::base_view::BaseView;
 use crate::lib::core::frame_context::FrameContext;
+use crate::lib::core::resources::read_resource;
 use crate::lib::core::view::View;
+use crate::lib::core::view_tree::ViewTree;
 
+const IMAGE_INVALID: i32 = 0;
+
+// A picture stretched in the frame of the view
+//
+// The file is read when the image is set ("@res/..." paths going through the mounted resources),
+// the nanovg image is created from it on the first frame, once a context is available.
 pub struct Image {
+    view: BaseView,
+    path: String,
+    data: RefCell<Option<Vec<u8>>>, // file waiting to be decoded by nanovg
+    handle: Cell<i32>,
+    vg: RefCell<Option<Rc<RefCell<NVGcontext>>>>, // context owning the handle, to delete it
+}
 
+impl Default for Image {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Image {
-    pub(crate) fn create() -> Rc<RefCell<Box<dyn View>>> {
-        todo!()
+    pub fn new() -> Self {
+        Self {
+            view: BaseView::new(),
+            path: String::new(),
+            data: RefCell::new(None),
+            handle: Cell::new(IMAGE_INVALID),
+            vg: RefCell::new(None),
+        }
     }
-}
 
-impl View for Image {
-    fn frame(&self, ctx: &FrameContext) {
-        todo!()
+    pub(crate) fn create() -> Box<dyn View> {
+        Box::new(Image::new())
+    }
+
+    /**
+     * Shows the image file at the given path, see read_resource().
+     * Returns false if the file could not be read.
+     */
+    pub fn set_image_from_file(&mut self, path: &str) -> bool {
+        self.delete_image();
+
+        let data = read_resource(path);
+        if data.is_none() {
+            error!("Could not load image \"{}\"", path);
+        }
+
+        self.path = path.into();
+        let loaded = data.is_some();
+        *self.data.borrow_mut() = data;
+        loaded
+    }
+
+    pub fn get_path(&self) -> &str {
+        &self.path
     }
 
-    fn get_default_focus(&self) -> Box<dyn View> {
-        todo!()
+    // Decodes the pending file with the context of the frame
+    fn get_handle(&self, ctx: &FrameContext) -> i32 {
+        if let Some(mut data) = self.data.borrow_mut().take() {
+            let handle = unsafe {
+                nanovg_sys::nvgCreateImageMem(ctx.vg.borrow().raw(), 0, data.as_mut_ptr(), data.len() as i32)
+            };
+
+            if handle == IMAGE_INVALID {
+                error!("Could not decode image \"{}\"", self.path);
+            }
+
+            self.handle.set(handle);
+            *self.vg.borrow_mut() = Some(Rc::clone(&ctx.vg));
+        }
+
+        self.handle.get()
     }
 
-    fn get_next_focus(&self, direction: FocusDirection, current_view: &dyn View) -> Box<dyn View> {
-        todo!()
+    fn delete_image(&self) {
+        let handle = self.handle.replace(IMAGE_INVALID);
+
+        if let Some(vg) = self.vg.borrow_mut().take() {
+            if handle != IMAGE_INVALID {
+                unsafe { nanovg_sys::nvgDeleteImage(vg.borrow().raw(), handle) };
+            }
+        }
     }
+}
+
+impl Drop for Image {
+    fn drop(&mut self) {
+        self.delete_image();
+    }
+}
+
+impl View for Image {
+    fn draw(&self, ctx: &FrameContext) {
+        let handle = self.get_handle(ctx);
+        if handle == IMAGE_INVALID {
+            return;
+        }
+
+        let (width, height) = (self.view.get_width(), self.view.get_height());
+        let vg = ctx.vg.borrow();
 
-    fn on_focus_lost(&self) {
-        todo!()
+        unsafe {
+            let paint = nanovg_sys::nvgImagePattern(vg.raw(), 0.0, 0.0, width, height, 0.0, handle, 1.0);
+            nanovg_sys::nvgBeginPath(vg.raw());
+            nanovg_sys::nvgRect(vg.raw(), 0.0, 0.0, width, height);
+            nanovg_sys::nvgFillPaint(vg.raw(), paint);
+            nanovg_sys::nvgFill(vg.raw());
+        }
     }
 
-    fn on_focus_gained(&self) {
-        todo!()
+    fn to_json(&self, _tree: &ViewTree) -> serde_json::Value {
+        let mut json = self.view.to_json("brls:Image");
+        json["image"] = self.path.clone().into();
+        json
     }
 
-    fn describe(&self) -> String {
-        todo!()
+    /**
+     * Applies the "image" XML attribute ("@res/img/logo.png").
+     * Returns false if the attribute is not handled by the image or the file could not be read.
+     */
+    fn apply_xml_attribute(&mut self, name: &str, value: &str) -> bool {
+        match name {
+            "image" => self.set_image_from_file(value),
+            _ => self.view.apply_xml_attribute(name, value),
+        }
     }
 
-    fn get_view(&self, id: &str) -> Rc<RefCell<Option<Box<dyn View>>>> {
-        todo!()
+    fn get_base_view(&self) -> &BaseView {
+        &self.view
     }
 
-    fn get_parent(&self) -> Rc<RefCell<Option<Box<dyn View>>>> {
-        todo!()
+    fn get_base_view_mut(&mut self) -> &mut BaseView {
+        &mut self.view
     }
 }
\ No newline at end of file
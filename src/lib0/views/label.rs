@@ -1,48 +1,408 @@
 use std::cell::RefCell;
-use std::rc::Rc;
-use crate::lib::core::base_view::FocusDirection;
+use std::ffi::CString;
+use std::ops::Range;
+use nanovg::Color;
+use crate::lib::core::base_view::BaseView;
+use crate::lib::core::font::{with_layout_font_stash, FontStash, FONT_KOREAN_REGULAR, FONT_REGULAR};
+use crate::lib::core::font_metrics::FontMetrics;
 use crate::lib::core::frame_context::FrameContext;
+use crate::lib::core::icons::{get_icon, get_icon_xml_attribute_value, Icon, ICON_PREFIX};
+use crate::lib::core::r#box::Direction;
+use crate::lib::core::style::{get_style, metrics};
+use crate::lib::core::theme::colors;
+use crate::lib::core::text_shaping::{is_right_to_left, needs_shaping, ShapedText, TextShaper};
 use crate::lib::core::view::View;
+use crate::lib::core::view_tree::ViewTree;
+use crate::lib::core::xml::get_string_xml_attribute_value;
 
+// A text, measured by the layout at the label default font size and wrapped in the width of the label
 pub struct Label {
+    view: BaseView,
+    text: String,
+    font: String,
+    direction: Direction,
+    lines: RefCell<Option<LabelLines>>,
+}
 
+// A line of the text as drawn by the label
+struct LabelLine {
+    range: Range<usize>,
+    width: f32,
+    rtl: bool,
+    shaped: Option<ShapedText>, // None for the lines drawn directly by nanovg
 }
 
-impl Label {
-    pub(crate) fn create() -> Rc<RefCell<Box<dyn View>>> {
-        todo!()
+impl LabelLine {
+    /**
+     * Returns the left of the line in a paragraph of the given width,
+     * the lines of right to left paragraphs being aligned on the right.
+     */
+    fn get_x(&self, left: f32, width: f32) -> f32 {
+        if self.rtl { left + width - self.width } else { left }
     }
 }
-impl View for Label {
-    fn frame(&self, ctx: &FrameContext) {
-        todo!()
+
+// The lines of the text wrapped and shaped for a font size and a width,
+// kept between frames until the text, the font or the font size changes
+struct LabelLines {
+    font_size: f32,
+    width: f32,
+    lines: Vec<LabelLine>,
+}
+
+impl Default for Label {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Label {
+    pub fn new() -> Self {
+        let mut label = Self {
+            view: BaseView::new(),
+            text: String::new(),
+            font: FONT_REGULAR.into(),
+            direction: Direction::Inherit,
+            lines: RefCell::new(None),
+        };
+
+        label.invalidate_text();
+        label
+    }
+
+    pub(crate) fn create() -> Box<dyn View> {
+        Box::new(Label::new())
+    }
+
+    /**
+     * Sets the text of the label, keeping its font: a label showing icons keeps its icon font
+     * (see set_font()). An "@icon/name" text is resolved through the icon registry.
+     */
+    pub fn set_text(&mut self, text: &str) {
+        if text.starts_with(ICON_PREFIX) {
+            if let Some(icon) = get_icon_xml_attribute_value(text) {
+                self.set_icon_glyph(icon);
+                return;
+            }
+        }
+
+        self.text = text.into();
+        self.invalidate_text();
+    }
+
+    /**
+     * Sets the font of the text, by its name in the font stash.
+     */
+    pub fn set_font(&mut self, font: &str) {
+        self.font = font.into();
+        self.invalidate_text();
+    }
+
+    pub fn get_text(&self) -> &str {
+        &self.text
     }
 
-    fn get_default_focus(&self) -> Box<dyn View> {
-        todo!()
+    pub fn get_font(&self) -> &str {
+        &self.font
     }
 
-    fn get_next_focus(&self, direction: FocusDirection, current_view: &dyn View) -> Box<dyn View> {
-        todo!()
+    /**
+     * Displays the icon with the given name instead of the text, see IconRegistry::get().
+     * Returns false if the icon is unknown.
+     */
+    pub fn set_icon(&mut self, name: &str) -> bool {
+        match get_icon(name) {
+            Some(icon) => {
+                self.set_icon_glyph(icon);
+                true
+            }
+            None => false,
+        }
     }
 
-    fn on_focus_lost(&self) {
-        todo!()
+    /**
+     * Sets the base direction of the text, deciding the order of the runs
+     * of mixed direction text. Inherit detects it from the text itself.
+     */
+    pub fn set_direction(&mut self, direction: Direction) {
+        self.direction = direction;
+        self.invalidate_text();
     }
 
-    fn on_focus_gained(&self) {
-        todo!()
+    /**
+     * Returns the size of the text wrapped in the given width, measured on the CPU
+     * from the font stash so that it does not need a live nanovg context.
+     */
+    pub fn measure(&self, font_stash: &FontStash, font_size: f32, line_height: f32, max_width: f32) -> (f32, f32) {
+        measure_text(font_stash, &self.font, &self.text, font_size, line_height, max_width)
     }
 
-    fn describe(&self) -> String {
-        todo!()
+    /**
+     * Gives the layout node a measure function for the current text and font,
+     * marking the label to be laid out again at the next frame.
+     */
+    fn invalidate_text(&mut self) {
+        *self.lines.get_mut() = None;
+
+        let text = self.text.clone();
+        let font = self.font.clone();
+
+        self.view.get_flex_node_mut().set_measure_func(Some(Box::new(move |max_width, _| {
+            let (font_size, line_height) = get_text_style();
+
+            with_layout_font_stash(|font_stash| {
+                measure_text(font_stash, &font, &text, font_size, line_height, max_width.unwrap_or(f32::INFINITY))
+            }).unwrap_or((0.0, 0.0))
+        })));
+        self.view.invalidate();
+    }
+
+    /**
+     * Draws the text on a single line with its baseline starting at the given position.
+     */
+    pub fn draw_text(&self, ctx: &FrameContext, x: f32, y: f32, font_size: f32, color: Color) {
+        self.draw_line(ctx, &self.text, x, y, font_size, color);
+    }
+
+    /**
+     * Draws the text wrapped in the width of the label, the way it was measured by the layout.
+     */
+    fn draw_paragraph(&self, ctx: &FrameContext) {
+        let (font_size, line_height) = get_text_style();
+        let color = ctx.theme.get(colors::TEXT);
+        let layout = *self.view.get_flex_node().get_layout();
+        let (left, top) = (layout.padding.left, layout.padding.top);
+
+        let metrics = FontMetrics::new(ctx.font_stash, &get_fonts(&self.font));
+
+        // Fonts not loaded from memory can't be measured, nanovg draws them on one line
+        if !metrics.is_valid() {
+            self.draw_text(ctx, left, top + font_size, font_size, color);
+            return;
+        }
+
+        let width = layout.width - layout.padding.left - layout.padding.right;
+        let ascender = metrics.get_ascender(font_size);
+        let line_height = metrics.get_line_height(font_size, line_height);
+
+        self.update_lines(ctx.font_stash, &metrics, font_size, width);
+        let lines = self.lines.borrow();
+        let lines = &lines.as_ref().expect("the lines were just updated").lines;
+
+        let shaper = if lines.iter().any(|line| line.shaped.is_some()) {
+            Some(TextShaper::new(ctx.font_stash, &get_fonts(&self.font)))
+        } else {
+            None
+        };
+
+        for (index, line) in lines.iter().enumerate() {
+            let x = line.get_x(left, width);
+            let y = top + ascender + index as f32 * line_height;
+
+            match (&line.shaped, &shaper) {
+                (Some(shaped), Some(shaper)) => shaper.draw(ctx, shaped, x, y, color),
+                _ => self.draw_unshaped_line(ctx, &self.text[line.range.clone()], x, y, font_size, color),
+            }
+        }
     }
 
-    fn get_view(&self, id: &str) -> Rc<RefCell<Option<Box<dyn View>>>> {
-        todo!()
+    /**
+     * Wraps the text in the given width and shapes its lines in complex scripts,
+     * unless the lines of the previous frame were made for the same font size and width.
+     */
+    fn update_lines(&self, font_stash: &FontStash, metrics: &FontMetrics, font_size: f32, width: f32) {
+        let mut lines = self.lines.borrow_mut();
+
+        if lines.as_ref().is_some_and(|lines| lines.font_size == font_size && lines.width == width) {
+            return;
+        }
+
+        let text_lines = metrics.break_lines(&self.text, font_size, width);
+        let shaper = if self.font == FONT_REGULAR && text_lines.iter().any(|line| needs_shaping(&self.text[line.start..line.end])) {
+            Some(TextShaper::new(font_stash, &get_fonts(&self.font))).filter(|shaper| shaper.is_valid())
+        } else {
+            None
+        };
+
+        *lines = Some(LabelLines {
+            font_size,
+            width,
+            lines: text_lines.into_iter()
+                .map(|line| {
+                    let text = &self.text[line.start..line.end];
+                    let shaped = shaper.as_ref()
+                        .filter(|_| needs_shaping(text))
+                        .map(|shaper| shaper.shape(text, font_size, &self.direction));
+
+                    LabelLine {
+                        width: shaped.as_ref().map_or(line.width, |shaped| shaped.width),
+                        rtl: is_right_to_left(&self.text, line.start, &self.direction),
+                        range: line.start..line.end,
+                        shaped,
+                    }
+                })
+                .collect(),
+        });
     }
 
-    fn get_parent(&self) -> Rc<RefCell<Option<Box<dyn View>>>> {
-        todo!()
+    /**
+     * Draws a line of the text with its baseline starting at the given position.
+     * Text in complex scripts (Arabic, Hebrew, Indic...) goes through the shaper,
+     * the rest is drawn directly by nanovg.
+     */
+    fn draw_line(&self, ctx: &FrameContext, text: &str, x: f32, y: f32, font_size: f32, color: Color) {
+        if self.font == FONT_REGULAR && needs_shaping(text) {
+            let shaper = TextShaper::new(ctx.font_stash, &get_fonts(&self.font));
+
+            if shaper.is_valid() {
+                let shaped = shaper.shape(text, font_size, &self.direction);
+                shaper.draw(ctx, &shaped, x, y, color);
+                return;
+            }
+        }
+
+        self.draw_unshaped_line(ctx, text, x, y, font_size, color);
+    }
+
+    /**
+     * Draws a line of the text with nanovg, without shaping.
+     */
+    fn draw_unshaped_line(&self, ctx: &FrameContext, text: &str, x: f32, y: f32, font_size: f32, color: Color) {
+        let font = match ctx.font_stash.get(&self.font) {
+            Some(font) => font,
+            None => return,
+        };
+
+        let text = match CString::new(text) {
+            Ok(text) => text,
+            Err(_) => return,
+        };
+
+        let vg = ctx.vg.borrow();
+        unsafe {
+            nanovg_sys::nvgFontFaceId(vg.raw(), font);
+            nanovg_sys::nvgFontSize(vg.raw(), font_size);
+            nanovg_sys::nvgTextAlign(vg.raw(), (nanovg_sys::NVGalign::NVG_ALIGN_LEFT | nanovg_sys::NVGalign::NVG_ALIGN_BASELINE).bits());
+            nanovg_sys::nvgFillColor(vg.raw(), nanovg_sys::nvgRGBAf(color.red(), color.green(), color.blue(), color.alpha()));
+            nanovg_sys::nvgText(vg.raw(), x, y, text.as_ptr(), std::ptr::null());
+        }
+    }
+
+    /**
+     * Displays the given glyph of an icon font instead of the text.
+     */
+    pub fn set_icon_glyph(&mut self, icon: Icon) {
+        self.text = icon.get_text();
+        self.font = icon.font;
+        self.invalidate_text();
+    }
+}
+
+// Font size and line height of the labels, from the style
+fn get_text_style() -> (f32, f32) {
+    let style = get_style();
+    (style.get(metrics::LABEL_DEFAULT_FONT_SIZE), style.get(metrics::LABEL_DEFAULT_LINE_HEIGHT))
+}
+
+// The given font followed by its fallback fonts
+fn get_fonts(font: &str) -> Vec<&str> {
+    if font == FONT_REGULAR { vec![FONT_REGULAR, FONT_KOREAN_REGULAR] } else { vec![font] }
+}
+
+fn measure_text(font_stash: &FontStash, font: &str, text: &str, font_size: f32, line_height: f32, max_width: f32) -> (f32, f32) {
+    let metrics = FontMetrics::new(font_stash, &get_fonts(font));
+
+    if !metrics.is_valid() {
+        return (0.0, 0.0);
+    }
+
+    metrics.measure_paragraph(text, font_size, line_height, max_width)
+}
+
+impl View for Label {
+    fn draw(&self, ctx: &FrameContext) {
+        self.draw_paragraph(ctx);
+    }
+
+    fn to_json(&self, _tree: &ViewTree) -> serde_json::Value {
+        let mut json = self.view.to_json("brls:Label");
+        json["text"] = self.text.clone().into();
+        json["font"] = self.font.clone().into();
+        json["direction"] = format!("{:?}", self.direction).into();
+        json
+    }
+
+    /**
+     * Applies the "text" and "icon" XML attributes ("@icon/settings").
+     * Returns false if the attribute is not handled by the label.
+     */
+    fn apply_xml_attribute(&mut self, name: &str, value: &str) -> bool {
+        match name {
+            "text" => {
+                self.set_text(&get_string_xml_attribute_value(value));
+                true
+            }
+            "icon" => {
+                let icon = if value.starts_with(ICON_PREFIX) {
+                    get_icon_xml_attribute_value(value)
+                } else {
+                    get_icon(value)
+                };
+
+                match icon {
+                    Some(icon) => {
+                        self.set_icon_glyph(icon);
+                        true
+                    }
+                    None => false,
+                }
+            }
+            _ => self.view.apply_xml_attribute(name, value),
+        }
+    }
+
+    fn get_base_view(&self) -> &BaseView {
+        &self.view
+    }
+
+    fn get_base_view_mut(&mut self) -> &mut BaseView {
+        &mut self.view
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static FONT: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fonts/DejaVuSans.ttf"));
+
+    #[test]
+    fn shaped_lines_are_kept_until_the_text_or_the_font_size_changes() {
+        let mut font_stash = FontStash::new();
+        font_stash.keep_buffer(FONT_REGULAR, FONT.to_vec());
+        let metrics = FontMetrics::new(&font_stash, &[FONT_REGULAR]);
+
+        let mut label = Label::new();
+        label.set_text("שלום");
+        label.update_lines(&font_stash, &metrics, 20.0, 200.0);
+
+        {
+            let mut lines = label.lines.borrow_mut();
+            let line = &mut lines.as_mut().unwrap().lines[0];
+            assert!(line.shaped.is_some() && line.rtl);
+            assert_eq!(line.get_x(10.0, 200.0), 10.0 + 200.0 - line.width, "right to left lines must be aligned on the right");
+
+            // Marks the cached line to see if it is shaped again
+            line.width = -1.0;
+        }
+
+        label.update_lines(&font_stash, &metrics, 20.0, 200.0);
+        assert_eq!(label.lines.borrow().as_ref().unwrap().lines[0].width, -1.0);
+
+        label.update_lines(&font_stash, &metrics, 24.0, 200.0);
+        assert!(label.lines.borrow().as_ref().unwrap().lines[0].width > 0.0, "a font size change must shape the text again");
+
+        label.set_text("abc");
+        assert!(label.lines.borrow().is_none());
     }
 }
\ No newline at end of file
@@ -1,9 +1,11 @@
-pub(crate) mod applet_frame;
-pub(crate) mod button;
-pub(crate) mod header;
-pub(crate) mod image;
-pub(crate) mod label;
-pub(crate) mod rectangle;
-pub(crate) mod scrolling_frame;
-pub(crate) mod sidebar;
-pub(crate) mod tab_frame;
\ No newline at end of file
+pub mod applet_frame;
+pub mod button;
+pub mod grid;
+pub mod header;
+pub mod hint;
+pub mod image;
+pub mod label;
+pub mod rectangle;
+pub mod scrolling_frame;
+pub mod sidebar;
+pub mod tab_frame;
\ No newline at end of file
@@ -1,50 +1,337 @@
-use std::cell::RefCell;
-use std::rc::Rc;
-use crate::lib::core::base_view::FocusDirection;
-use crate::lib::core::frame_context::FrameContext;
-use crate::lib::core::r#box::BoxView;
+use crate::lib::core::base_view::{BaseView, Visibility};
+use crate::lib::core::flex::FlexNode;
+use crate::lib::core::frame_context::{DrawLayer, FrameContext};
 use crate::lib::core::view::View;
+use crate::lib::core::view_tree::{ViewId, ViewTree};
 
+// Shows a vertical window of its content view, taller than itself
+//
+// The content view is the first child of the frame in its view tree.
+// Only the children of the content intersecting the window are drawn,
+// the others are culled until they are scrolled back into view.
 pub struct ScrollingFrame {
-    box_view: BoxView
+    view: BaseView, // the window, laid out by the parent
+    content_offset: f32, // scrolled distance from the top of the content
+    viewport: (f32, f32),
+    laid_out_content: Option<ViewId>, // content laid out in the viewport
+}
+
+impl Default for ScrollingFrame {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ScrollingFrame {
-    pub(crate) fn create() -> Rc<RefCell<Box<dyn View>>> {
-        todo!()
+    pub fn new() -> Self {
+        Self {
+            view: BaseView::new(),
+            content_offset: 0.0,
+            viewport: (0.0, 0.0),
+            laid_out_content: None,
+        }
+    }
+
+    pub(crate) fn create() -> Box<dyn View> {
+        Box::new(ScrollingFrame::new())
+    }
+
+    pub fn get_content_view(&self, tree: &ViewTree) -> Option<ViewId> {
+        tree.get_children(self.view.get_view_id()?).first().copied()
+    }
+
+    /**
+     * Scrolls the content to the given offset from its top, clamped to the content height.
+     * The children entering or leaving the window are culled again right away.
+     */
+    pub fn set_content_offset(&mut self, tree: &mut ViewTree, offset: f32) {
+        let content = match self.get_content_view(tree) {
+            Some(content) => content,
+            None => return,
+        };
+
+        let (width, height) = self.viewport;
+        let content_height = tree.get(content).map_or(0.0, |view| view.get_base_view().get_height());
+        let max_offset = (content_height - height).max(0.0);
+
+        self.content_offset = offset.clamp(0.0, max_offset);
+        tree.cull_children(content, 0.0, self.content_offset, width, height);
+    }
+
+    pub fn get_content_offset(&self) -> f32 {
+        self.content_offset
+    }
+
+    /**
+     * Lays out the content in the width of the window and at the height of its children,
+     * then culls the children outside of the window.
+     * Nothing is done if the window kept its size and the content did not change.
+     */
+    fn layout_content(&mut self, tree: &mut ViewTree, width: f32, height: f32) {
+        let content = self.get_content_view(tree);
+        if content == self.laid_out_content && self.viewport == (width, height) && !self.is_content_dirty(tree) {
+            return;
+        }
+
+        if let Some(content) = content {
+            tree.with_view_mut(content, |content, tree| content.calculate_layout(tree, Some(width), None));
+        }
+
+        self.laid_out_content = content;
+        self.viewport = (width, height);
+        self.set_content_offset(tree, self.content_offset);
+    }
+
+    fn is_content_dirty(&self, tree: &ViewTree) -> bool {
+        self.get_content_view(tree)
+            .and_then(|content| tree.get(content))
+            .is_some_and(|content| content.is_layout_dirty(tree))
     }
 }
 
 impl View for ScrollingFrame {
+    /**
+     * Draws the window, then the content scrolled by the content offset and clipped to the window,
+     * with the theme override of the window applied.
+     */
     fn frame(&self, ctx: &FrameContext) {
-        todo!()
+        if self.view.get_visibility() != Visibility::Visible {
+            return;
+        }
+
+        match ctx.layer {
+            DrawLayer::Debug => self.view.draw_debug(ctx, false),
+            _ => self.view.frame(ctx, |_| {}),
+        }
+
+        let content = match self.get_content_view(ctx.tree).and_then(|content| ctx.tree.get(content)) {
+            Some(content) => content,
+            None => return,
+        };
+
+        self.view.with_frame_context(ctx, |ctx| {
+            let layout = content.get_base_view().get_flex_node().get_layout();
+
+            // Clip the content to the window, the rows partly in it overflow it
+            unsafe {
+                let vg = ctx.vg.borrow();
+                nanovg_sys::nvgSave(vg.raw());
+                nanovg_sys::nvgIntersectScissor(vg.raw(), 0.0, 0.0, self.view.get_width(), self.view.get_height());
+                nanovg_sys::nvgTranslate(vg.raw(), layout.x, layout.y - self.content_offset);
+            }
+
+            content.frame(ctx);
+
+            unsafe {
+                nanovg_sys::nvgRestore(ctx.vg.borrow().raw());
+            }
+        });
     }
 
-    fn get_default_focus(&self) -> Box<dyn View> {
-        todo!()
+    fn to_json(&self, tree: &ViewTree) -> serde_json::Value {
+        let mut json = self.view.to_json("brls:ScrollingFrame");
+        json["contentOffset"] = self.content_offset.into();
+        json["children"] = self.get_content_view(tree)
+            .and_then(|content| tree.get(content))
+            .map(|content| content.to_json(tree))
+            .into_iter()
+            .collect();
+        json
     }
 
-    fn get_next_focus(&self, direction: FocusDirection, current_view: &dyn View) -> Box<dyn View> {
-        todo!()
+    /**
+     * Lays out the window in the given size, then its content, see layout_content().
+     */
+    fn layout(&mut self, tree: &mut ViewTree, width: f32, height: f32) {
+        let node = self.view.get_flex_node_mut();
+        node.calculate_layout(Some(width), Some(height));
+
+        if node.has_new_layout() {
+            self.view.on_layout();
+        }
+
+        let layout = *self.view.get_flex_node().get_layout();
+        self.layout_content(tree, layout.width, layout.height);
     }
 
-    fn on_focus_lost(&self) {
-        todo!()
+    fn get_base_view(&self) -> &BaseView {
+        &self.view
     }
 
-    fn on_focus_gained(&self) {
-        todo!()
+    fn get_base_view_mut(&mut self) -> &mut BaseView {
+        &mut self.view
     }
 
-    fn describe(&self) -> String {
-        todo!()
+    /**
+     * The window is laid out again for a change of its content.
+     */
+    fn is_layout_dirty(&self, tree: &ViewTree) -> bool {
+        self.view.get_flex_node().is_dirty() || self.is_content_dirty(tree)
     }
 
-    fn get_view(&self, id: &str) -> Rc<RefCell<Option<Box<dyn View>>>> {
-        todo!()
+    /**
+     * The content does not take part in the layout of the parent,
+     * it is laid out once the window got its size.
+     */
+    fn give_back_flex_node(&mut self, tree: &mut ViewTree, node: FlexNode) {
+        *self.view.get_flex_node_mut() = node;
+
+        if self.view.get_flex_node().has_new_layout() {
+            self.view.on_layout();
+        }
+
+        let layout = *self.view.get_flex_node().get_layout();
+        self.layout_content(tree, layout.width, layout.height);
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use crate::lib::core::r#box::{get_drawn_children, Axis, BoxView};
+    use crate::lib::core::grid::TrackSize;
+    use crate::lib::core::time::Timestamp;
+    use crate::lib::views::grid::Grid;
+    use super::*;
+
+    // A row of the content, counting its updates and layouts
+    struct Row {
+        view: BaseView,
+        updates: Rc<Cell<usize>>,
+        lends: Rc<Cell<usize>>,
+    }
+
+    impl Row {
+        fn new(height: f32) -> Self {
+            let mut view = BaseView::new();
+            view.set_height(height);
+            Self {
+                view,
+                updates: Rc::new(Cell::new(0)),
+                lends: Rc::new(Cell::new(0)),
+            }
+        }
+    }
+
+    impl View for Row {
+        fn frame(&self, _ctx: &FrameContext) {}
+
+        fn update(&mut self, _delta: Timestamp) {
+            self.updates.set(self.updates.get() + 1);
+        }
+
+        fn lend_flex_node(&mut self, _tree: &mut ViewTree) -> FlexNode {
+            self.lends.set(self.lends.get() + 1);
+            std::mem::take(self.view.get_flex_node_mut())
+        }
+
+        fn get_base_view(&self) -> &BaseView {
+            &self.view
+        }
+
+        fn get_base_view_mut(&mut self) -> &mut BaseView {
+            &mut self.view
+        }
+    }
+
+    #[test]
+    fn culls_the_rows_out_of_the_window() {
+        let mut tree = ViewTree::new();
+        let frame = tree.insert(ScrollingFrame::create());
+        let content = tree.add_view(frame, Box::new(BoxView::new(Axis::Column)));
+        let updates: Vec<Rc<Cell<usize>>> = (0..5)
+            .map(|_| {
+                let row = Row::new(100.0);
+                let updates = Rc::clone(&row.updates);
+                tree.add_view(content, Box::new(row));
+                updates
+            })
+            .collect();
+
+        let rows = tree.get_children(content).to_vec();
+        let drawn = |tree: &ViewTree| get_drawn_children(tree, tree.get_children(content), DrawLayer::Content);
+
+        tree.layout(frame, 300.0, 150.0);
+        assert_eq!(drawn(&tree), (rows[0..2].to_vec(), 3));
+
+        // Only the rows in the window are ticked
+        tree.update(frame, 16);
+        let counts: Vec<usize> = updates.iter().map(|updates| updates.get()).collect();
+        assert_eq!(counts, vec![1, 1, 0, 0, 0]);
+
+        // Scrolled rows come back in the viewport, the offset is clamped to the content height
+        tree.with_view_mut(frame, |frame, tree| {
+            frame.as_any_mut().downcast_mut::<ScrollingFrame>().unwrap().set_content_offset(tree, 1000.0);
+        });
+        assert_eq!(tree.get_as::<ScrollingFrame>(frame).unwrap().get_content_offset(), 350.0);
+        assert_eq!(drawn(&tree), (rows[3..5].to_vec(), 3));
+
+        // Rows with culling disabled are always drawn
+        tree.get_mut(rows[0]).unwrap().get_base_view_mut().set_culled(false);
+        assert_eq!(drawn(&tree), (vec![rows[0], rows[3], rows[4]], 2));
+    }
+
+    #[test]
+    fn lays_out_the_content_again_only_when_it_changed() {
+        let mut tree = ViewTree::new();
+        let root = tree.insert(Box::new(BoxView::new(Axis::Column)));
+        let header = tree.add_view(root, Box::new(BoxView::new(Axis::Row)));
+        let mut frame = ScrollingFrame::new();
+        frame.get_base_view_mut().set_height(100.0);
+        let frame = tree.add_view(root, Box::new(frame));
+        let content = tree.add_view(frame, Box::new(BoxView::new(Axis::Column)));
+
+        let row = Row::new(100.0);
+        let lends = Rc::clone(&row.lends);
+        let row = tree.add_view(content, Box::new(row));
+
+        tree.layout(root, 300.0, 200.0);
+        assert_eq!(lends.get(), 1);
+
+        // Clean tree
+        tree.layout(root, 300.0, 200.0);
+        assert_eq!(lends.get(), 1);
+
+        // The window keeps its size when a sibling changes
+        tree.get_mut(header).unwrap().get_base_view_mut().set_height(50.0);
+        tree.layout(root, 300.0, 200.0);
+        assert_eq!(lends.get(), 1);
+
+        // A change of the content lays out the content again
+        tree.get_mut(row).unwrap().get_base_view_mut().set_height(120.0);
+        tree.layout(root, 300.0, 200.0);
+        assert_eq!(lends.get(), 2);
+        assert_eq!(tree.get(content).unwrap().get_base_view().get_height(), 120.0);
+    }
+
+    #[test]
+    fn grid_content_takes_the_height_of_its_rows() {
+        let mut tree = ViewTree::new();
+        let frame = tree.insert(ScrollingFrame::create());
+
+        let mut grid = Grid::new();
+        grid.set_columns(vec![TrackSize::Fraction(1.0), TrackSize::Fraction(1.0)]);
+        grid.set_gaps(10.0, 10.0);
+        let grid = tree.add_view(frame, Box::new(grid));
+
+        let cells: Vec<ViewId> = (0..5)
+            .map(|_| tree.add_view(grid, Box::new(Row::new(40.0))))
+            .collect();
+
+        tree.layout(frame, 300.0, 100.0);
+
+        // 3 rows of 40 with 2 gaps, in the width of the window
+        let layout = *tree.get(grid).unwrap().get_base_view().get_flex_node().get_layout();
+        assert_eq!((layout.width, layout.height), (300.0, 140.0));
+
+        let last = *tree.get(cells[4]).unwrap().get_base_view().get_flex_node().get_layout();
+        assert_eq!((last.x, last.y, last.width), (0.0, 100.0, 145.0));
 
-    fn get_parent(&self) -> Rc<RefCell<Option<Box<dyn View>>>> {
-        todo!()
+        // The content can be scrolled to its last row
+        tree.with_view_mut(frame, |frame, tree| {
+            frame.as_any_mut().downcast_mut::<ScrollingFrame>().unwrap().set_content_offset(tree, 1000.0);
+        });
+        assert_eq!(tree.get_as::<ScrollingFrame>(frame).unwrap().get_content_offset(), 40.0);
     }
 }
\ No newline at end of file